@@ -0,0 +1,18 @@
+//! Decode arbitrary text as a websocket message from a client, as a session does.
+
+#![no_main]
+use chrono::Utc;
+use libfuzzer_sys::fuzz_target;
+
+use news_backend::protocol::ClientMessage;
+use news_backend::sync;
+
+fuzz_target!(|text: &str| {
+    match serde_json::from_str::<ClientMessage>(text) {
+        Ok(ClientMessage::MarkRead { at, sent_at, .. })
+        | Ok(ClientMessage::Star { at, sent_at, .. }) => {
+            sync::server_time(at, sent_at, Utc::now());
+        }
+        _ => {}
+    }
+});
@@ -0,0 +1,25 @@
+//! Parse arbitrary bytes as a feed and build the items sent to clients from whatever comes out.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use news_backend::news;
+use news_backend::protocol::ItemDto;
+use news_backend::settings::Settings;
+use news_backend::store::Store;
+
+use std::path::Path;
+
+const FEED: &str = "https://example.com/feed";
+
+fuzz_target!(|data: &[u8]| {
+    let items = match news::parse_feed(data, None, FEED, Path::new("/nonexistent")) {
+        Ok(items) => items,
+        Err(_) => return,
+    };
+    let (settings, store) = (Settings::default(), Store::default());
+    for item in &items {
+        item.id();
+        ItemDto::new(item, &settings, &store);
+    }
+});
@@ -0,0 +1,136 @@
+//! Append-only log of significant events, exposed through `GET /api/activity`.
+//!
+//! Events are appended as JSON lines to `activity.jsonl` in the project data directory, so that
+//! users can find out why an item disappeared or a feed stopped updating.
+
+use actix_web::{error, web, Error, HttpResponse};
+use anyhow::Result;
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use crate::news;
+
+/// Number of entries returned when no limit is given.
+const DEFAULT_LIMIT: usize = 100;
+/// Maximum number of entries returned at once.
+const MAX_LIMIT: usize = 1_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Event {
+    /// A feed was subscribed to, directly or through an import.
+    FeedAdded { feed: String },
+    /// A feed was paused and isn't fetched anymore.
+    FeedPaused { feed: String },
+    FeedResumed { feed: String },
+    FetchFailed { feed: String, error: String },
+    /// Unread items beyond the limit of their feed were marked read.
+    FeedLimitApplied { feed: String, count: usize },
+    /// New items matched a desktop notification rule.
+    Notified { count: usize },
+    /// Several items were marked read at once.
+    MarkedRead { count: usize },
+    /// A feed hasn't posted for several times its usual interval between items.
+    FeedSilent {
+        feed: String,
+        since: DateTime<Utc>,
+        usual_interval_hours: f64,
+    },
+    /// A feed posted several times its usual daily volume in the last day.
+    FeedFlooded {
+        feed: String,
+        count: usize,
+        usual_count: f64,
+    },
+    /// The cache went over its cap, so cached images then bodies of old items were evicted.
+    CacheEvicted {
+        freed_images: usize,
+        dropped_bodies: usize,
+    },
+    /// Items published before `before` were deleted.
+    Pruned { before: DateTime<Utc>, count: usize },
+}
+
+/// Append an event, logging rather than failing as the event already happened.
+pub fn record(event: Event) {
+    log::debug!("activity: {:?}", event);
+    let entry = Entry {
+        at: Utc::now(),
+        event,
+    };
+    if let Err(e) = append(&entry) {
+        log::error!("failed to record activity: {}", e);
+    }
+}
+
+fn append(entry: &Entry) -> Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path()?)?;
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+    file.write_all(&line)?;
+    Ok(())
+}
+
+fn path() -> Result<PathBuf> {
+    let data_dir = news::project_dirs()?.data_dir().to_path_buf();
+    fs::create_dir_all(&data_dir)?;
+    Ok(data_dir.join("activity.jsonl"))
+}
+
+/// Read the latest `limit` entries recorded since `since`, newest first.
+fn read(since: Option<DateTime<Utc>>, limit: usize) -> Result<Vec<Entry>> {
+    let file = match fs::File::open(path()?) {
+        Ok(file) => file,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        // A line being appended may be incomplete, skip it rather than failing.
+        let entry: Entry = match serde_json::from_str(&line?) {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        if since.map_or(true, |since| entry.at >= since) {
+            entries.push(entry);
+        }
+    }
+    entries.reverse();
+    entries.truncate(limit);
+    Ok(entries)
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ActivityQuery {
+    /// Only return entries recorded since then.
+    since: Option<DateTime<Utc>>,
+    /// Maximum number of entries returned, newest first.
+    limit: Option<usize>,
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/api/activity", web::get().to(get_activity));
+}
+
+async fn get_activity(query: web::Query<ActivityQuery>) -> Result<HttpResponse, Error> {
+    let since = query.since;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let entries = web::block(move || read(since, limit))
+        .await
+        .map_err(|e| {
+            log::error!("failed to read activity: {}", e);
+            error::ErrorInternalServerError("failed to read activity")
+        })?;
+    Ok(HttpResponse::Ok().json(entries))
+}
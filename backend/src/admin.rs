@@ -0,0 +1,190 @@
+//! Administrative HTTP routes.
+//!
+//! Every route requires `Authorization: Bearer <admin_token>` and is disabled entirely when no
+//! admin token is configured.
+
+use actix::Addr;
+use actix_web::{error, http::header, web, Error, HttpRequest, HttpResponse};
+use chrono::prelude::*;
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::import;
+use crate::server::{CancelRefresh, ListFeeds, ListJobs, NewsServer, Prune, Refresh};
+use crate::storage;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/admin")
+            .route("/refresh", web::post().to(refresh))
+            .route("/refresh/cancel", web::post().to(cancel_refresh))
+            .route("/jobs", web::get().to(jobs))
+            .route("/prune", web::post().to(prune))
+            .route("/storage", web::get().to(storage_usage))
+            .route("/import", web::post().to(import::import)),
+    );
+}
+
+/// Check the request carries the configured admin token.
+pub fn authorize(req: &HttpRequest, config: &Config) -> Result<(), Error> {
+    let expected = match &config.admin_token {
+        Some(token) => token,
+        None => return Err(error::ErrorForbidden("admin routes are disabled")),
+    };
+
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match provided {
+        Some(token) if secret_matches(token, expected) => Ok(()),
+        _ => Err(error::ErrorUnauthorized("invalid admin token")),
+    }
+}
+
+/// Whether the `provided` secret is the `expected` one, taking the same time wherever they differ
+/// so that the secret can't be guessed a byte at a time.
+pub fn secret_matches<P, E>(provided: P, expected: E) -> bool
+where
+    P: AsRef<[u8]>,
+    E: AsRef<[u8]>,
+{
+    // The hashes compare in constant time, and hide the length of the secret.
+    blake3::hash(provided.as_ref()) == blake3::hash(expected.as_ref())
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RefreshParams {
+    /// Only refresh this feed instead of every feed.
+    feed: Option<String>,
+}
+
+async fn refresh(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    params: web::Query<RefreshParams>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let params = params.into_inner();
+    if let Some(feed) = &params.feed {
+        let feeds = server
+            .send(ListFeeds)
+            .await
+            .map_err(error::ErrorInternalServerError)?;
+        if !feeds.contains(feed) {
+            return Err(error::ErrorNotFound("unknown feed"));
+        }
+    }
+
+    let queued = server
+        .send(Refresh { feed: params.feed })
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Accepted().json(queued))
+}
+
+/// Cancel the refresh in progress, reporting which feeds it fetched and which it dropped.
+async fn cancel_refresh(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let cancelled = server
+        .send(CancelRefresh)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(cancelled))
+}
+
+async fn jobs(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let jobs = server
+        .send(ListJobs)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(jobs))
+}
+
+#[derive(Debug, Deserialize)]
+struct PruneParams {
+    /// Remove items published more than this many days ago, between 1 and `MAX_PRUNE_DAYS`.
+    #[serde(default = "default_prune_days")]
+    older_than_days: i64,
+}
+
+/// Most days items may be kept for when pruning, about a century.
+const MAX_PRUNE_DAYS: i64 = 36500;
+
+fn default_prune_days() -> i64 {
+    30
+}
+
+/// Return the date items published before are pruned when keeping the last `days` days, `None`
+/// unless `days` is between 1 and `MAX_PRUNE_DAYS`.
+fn prune_before(now: DateTime<Utc>, days: i64) -> Option<DateTime<Utc>> {
+    if !(1..=MAX_PRUNE_DAYS).contains(&days) {
+        return None;
+    }
+    now.checked_sub_signed(chrono::Duration::days(days))
+}
+
+async fn prune(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    params: web::Query<PruneParams>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let before = prune_before(Utc::now(), params.older_than_days).ok_or_else(|| {
+        error::ErrorBadRequest(format!(
+            "older_than_days must be between 1 and {}",
+            MAX_PRUNE_DAYS
+        ))
+    })?;
+    let removed = server
+        .send(Prune { before })
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "removed": removed })))
+}
+
+/// Report the disk usage of the cache against its cap.
+async fn storage_usage(
+    req: HttpRequest,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let cap_bytes = config.cache_cap_mb.map(|mb| mb * 1024 * 1024);
+    let usage = web::block(move || storage::usage(cap_bytes))
+        .await
+        .map_err(|e| {
+            log::error!("failed to measure the cache: {}", e);
+            error::ErrorInternalServerError("failed to measure the cache")
+        })?;
+    Ok(HttpResponse::Ok().json(usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_prune_days() {
+        let now = Utc.ymd(2020, 5, 4).and_hms(12, 0, 0);
+        let day_before = Utc.ymd(2020, 5, 3).and_hms(12, 0, 0);
+        assert_eq!(prune_before(now, 1), Some(day_before));
+        assert!(prune_before(now, MAX_PRUNE_DAYS).is_some());
+        assert_eq!(prune_before(now, 0), None);
+        assert_eq!(prune_before(now, -30), None);
+        assert_eq!(prune_before(now, MAX_PRUNE_DAYS + 1), None);
+        assert_eq!(prune_before(now, i64::MAX), None);
+    }
+}
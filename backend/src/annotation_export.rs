@@ -0,0 +1,358 @@
+//! Export of the user's notes and highlights, as Markdown through
+//! `GET /api/annotations/export` or to Readwise through `POST /api/annotations/readwise`.
+//!
+//! The Markdown export is a gzipped tarball holding a document per feed, or per folder with
+//! `?by=folder`, which drops into a notes system as is. Pushing to Readwise needs the `readwise`
+//! config section. Readwise skips the highlights it already has, so pushing again only adds the
+//! new ones; notes on whole items have no place there and are left out.
+
+use actix::Addr;
+use actix_web::{error, web, Error, HttpResponse};
+use anyhow::Result;
+use chrono::prelude::*;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::config::{Config, ReadwiseConfig};
+use crate::folders::FeedOrder;
+use crate::protocol::ItemDto;
+use crate::server::{GetAnnotated, GetFeedOrder, NewsServer};
+
+/// Group of the items of feeds outside any folder.
+const UNFILED: &str = "Unfiled";
+
+/// How the exported items are split into documents.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    Feed,
+    Folder,
+}
+
+impl Default for GroupBy {
+    fn default() -> Self {
+        GroupBy::Feed
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ExportQuery {
+    by: GroupBy,
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/api/annotations/export", web::get().to(export))
+        .route("/api/annotations/readwise", web::post().to(push_readwise));
+}
+
+async fn export(
+    server: web::Data<Addr<NewsServer>>,
+    query: web::Query<ExportQuery>,
+) -> Result<HttpResponse, Error> {
+    let items = server
+        .send(GetAnnotated)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    let order = server
+        .send(GetFeedOrder)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let documents = group(items, query.by, &order)
+        .into_iter()
+        .map(|(name, items)| {
+            let document = markdown(&name, &items);
+            (name, document)
+        })
+        .collect::<Vec<_>>();
+    let bundle = bundle(&documents).map_err(|e| {
+        log::error!("failed to bundle annotations: {}", e);
+        error::ErrorInternalServerError("failed to bundle annotations")
+    })?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/gzip")
+        .header("Content-Disposition", "attachment; filename=\"annotations.tar.gz\"")
+        .body(bundle))
+}
+
+async fn push_readwise(
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+) -> Result<HttpResponse, Error> {
+    let readwise = config
+        .readwise
+        .as_ref()
+        .ok_or_else(|| error::ErrorNotFound("readwise is disabled"))?;
+    let items = server
+        .send(GetAnnotated)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let pushed = push(readwise, &items).await.map_err(|e| {
+        log::error!("failed to push highlights to readwise: {}", e);
+        error::ErrorBadGateway("failed to push highlights to readwise")
+    })?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "pushed": pushed })))
+}
+
+/// Split items into named groups, each listing its items in the order given.
+pub fn group(
+    items: Vec<ItemDto>,
+    by: GroupBy,
+    order: &FeedOrder,
+) -> BTreeMap<String, Vec<ItemDto>> {
+    let mut groups: BTreeMap<String, Vec<ItemDto>> = BTreeMap::new();
+    for item in items {
+        let name = match by {
+            GroupBy::Feed => item.feed_title.clone().unwrap_or_else(|| item.feed.clone()),
+            GroupBy::Folder => order
+                .folders
+                .iter()
+                .find(|folder| folder.feeds.contains(&item.feed))
+                .map_or_else(|| UNFILED.to_string(), |folder| folder.name.clone()),
+        };
+        groups.entry(name).or_default().push(item);
+    }
+    groups
+}
+
+/// Lay out the notes and highlights of items as a Markdown document titled `title`.
+pub fn markdown(title: &str, items: &[ItemDto]) -> String {
+    let mut document = format!("# {}\n", title);
+    for item in items {
+        let annotations = match &item.annotations {
+            Some(annotations) => annotations,
+            None => continue,
+        };
+        let item_title = escape(item.title.as_deref().unwrap_or("Untitled"));
+        match &item.link {
+            Some(link) => document.push_str(&format!("\n## [{}]({})\n", item_title, link)),
+            None => document.push_str(&format!("\n## {}\n", item_title)),
+        }
+        let source = item.feed_title.as_deref().unwrap_or(&item.feed);
+        match item.pub_date {
+            Some(date) => document.push_str(&format!("\n{} · {}\n", source, date.format("%F"))),
+            None => document.push_str(&format!("\n{}\n", source)),
+        }
+        if let Some(note) = &annotations.note {
+            document.push_str(&format!("\n{}\n", note.trim()));
+        }
+        for highlight in &annotations.highlights {
+            document.push('\n');
+            for line in highlight.text.trim().lines() {
+                document.push_str(&format!("> {}\n", line));
+            }
+            if let Some(note) = &highlight.note {
+                document.push_str(&format!("\n{}\n", note.trim()));
+            }
+        }
+    }
+    document
+}
+
+/// Escape the brackets of a title so that it reads right as the text of a link.
+fn escape(title: &str) -> String {
+    title.replace('[', "\\[").replace(']', "\\]")
+}
+
+/// Name the document of a group after it, keeping names unique within a bundle.
+fn file_name(name: &str, taken: &mut HashSet<String>) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let slug: Vec<&str> = slug.split('-').filter(|part| !part.is_empty()).collect();
+    let mut slug: String = slug.join("-").chars().take(80).collect();
+    if slug.is_empty() {
+        slug = "notes".to_string();
+    }
+    let mut file_name = format!("{}.md", slug);
+    let mut n = 2;
+    while !taken.insert(file_name.clone()) {
+        file_name = format!("{}-{}.md", slug, n);
+        n += 1;
+    }
+    file_name
+}
+
+/// Pack named Markdown documents into a gzipped tarball.
+pub fn bundle(documents: &[(String, String)]) -> Result<Vec<u8>> {
+    let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    let mut taken = HashSet::new();
+    for (name, document) in documents {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(document.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(Utc::now().timestamp() as u64);
+        header.set_cksum();
+        archive.append_data(&mut header, file_name(name, &mut taken), document.as_bytes())?;
+    }
+    Ok(archive.into_inner()?.finish()?)
+}
+
+/// Highlight as the Readwise API takes it.
+#[derive(Debug, PartialEq, Serialize)]
+struct ReadwiseHighlight<'a> {
+    text: &'a str,
+    title: &'a str,
+    /// Readwise has no field for the feed, which stands in for the author.
+    author: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_url: Option<&'a str>,
+    source_type: &'static str,
+    category: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<&'a str>,
+    highlighted_at: DateTime<Utc>,
+}
+
+/// Convert the highlights of items to the Readwise format.
+fn readwise_highlights(items: &[ItemDto]) -> Vec<ReadwiseHighlight<'_>> {
+    items
+        .iter()
+        .flat_map(|item| {
+            let highlights = item.annotations.iter().flat_map(|a| a.highlights.iter());
+            highlights.map(move |highlight| ReadwiseHighlight {
+                text: &highlight.text,
+                title: item.title.as_deref().unwrap_or("Untitled"),
+                author: item.feed_title.as_deref().unwrap_or(&item.feed),
+                source_url: item.link.as_deref(),
+                source_type: "news",
+                category: "articles",
+                note: highlight.note.as_deref(),
+                highlighted_at: highlight.created_at,
+            })
+        })
+        .collect()
+}
+
+/// Push the highlights of items to Readwise, returning how many there were.
+pub async fn push(config: &ReadwiseConfig, items: &[ItemDto]) -> Result<usize> {
+    #[derive(Serialize)]
+    struct Request<'a> {
+        highlights: Vec<ReadwiseHighlight<'a>>,
+    }
+
+    let highlights = readwise_highlights(items);
+    let pushed = highlights.len();
+    if pushed == 0 {
+        return Ok(0);
+    }
+    reqwest::Client::new()
+        .post(&config.endpoint)
+        .header("Authorization", format!("Token {}", config.token))
+        .json(&Request { highlights })
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(pushed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::{Annotations, Highlight};
+    use crate::folders::Folder;
+    use crate::settings::Settings;
+    use crate::store::Store;
+
+    use news_core::test_util;
+
+    /// Return the DTO of an item of `feed` annotated with `highlights`.
+    fn item(feed: &str, title: &str, highlights: &[&str]) -> ItemDto {
+        let link = format!("{}/{}", feed, title);
+        let item = test_util::item(title).feed(feed).link(&link).build();
+        let mut item = ItemDto::new(&item, &Settings::default(), &Store::default());
+        let mut start = 0;
+        let highlights = highlights.iter().map(|text| {
+            start += 100;
+            Highlight {
+                start,
+                end: start + text.chars().count(),
+                text: text.to_string(),
+                note: None,
+                created_at: Utc::now(),
+            }
+        });
+        item.annotations = Some(Annotations {
+            note: None,
+            highlights: highlights.collect(),
+        });
+        item
+    }
+
+    #[test]
+    fn groups_by_folder() {
+        let order = FeedOrder {
+            folders: vec![Folder {
+                name: "Science".to_string(),
+                feeds: vec!["https://a.example".to_string()],
+            }],
+            feeds: vec!["https://b.example".to_string()],
+        };
+        let items = vec![
+            item("https://a.example", "one", &["x"]),
+            item("https://b.example", "two", &["y"]),
+            item("https://a.example", "three", &["z"]),
+        ];
+
+        let groups = group(items.clone(), GroupBy::Folder, &order);
+        let titles = |name: &str| -> Vec<String> {
+            groups[name].iter().map(|item| item.title.clone().unwrap()).collect()
+        };
+        assert_eq!(titles("Science"), vec!["one", "three"]);
+        assert_eq!(titles(UNFILED), vec!["two"]);
+
+        let groups = group(items, GroupBy::Feed, &order);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups["https://a.example"].len(), 2);
+    }
+
+    #[test]
+    fn lays_out_highlights_as_quotes() {
+        let mut item = item("https://a.example", "A [draft] plan", &["first\nsecond"]);
+        item.link = Some("https://a.example/plan".to_string());
+        if let Some(annotations) = item.annotations.as_mut() {
+            annotations.note = Some("Worth a follow up".to_string());
+            annotations.highlights[0].note = Some("Key point".to_string());
+        }
+
+        let document = markdown("Science", &[item]);
+        assert_eq!(
+            document,
+            "# Science\n\
+             \n## [A \\[draft\\] plan](https://a.example/plan)\n\
+             \nhttps://a.example\n\
+             \nWorth a follow up\n\
+             \n> first\n> second\n\
+             \nKey point\n"
+        );
+    }
+
+    #[test]
+    fn names_documents_uniquely() {
+        let mut taken = HashSet::new();
+        assert_eq!(file_name("Science & Tech", &mut taken), "science-tech.md");
+        assert_eq!(file_name("science tech", &mut taken), "science-tech-2.md");
+        assert_eq!(file_name("???", &mut taken), "notes.md");
+    }
+
+    #[test]
+    fn converts_highlights_for_readwise() {
+        let items = vec![
+            item("https://a.example", "one", &["x", "y"]),
+            item("https://b.example", "two", &[]),
+        ];
+        let highlights = readwise_highlights(&items);
+        assert_eq!(highlights.len(), 2);
+        assert_eq!(highlights[1].text, "y");
+        assert_eq!(highlights[1].title, "one");
+        assert_eq!(highlights[1].author, "https://a.example");
+        assert_eq!(highlights[1].source_url, Some("https://a.example/one"));
+    }
+}
@@ -0,0 +1,180 @@
+//! Private notes and highlights the user attaches to items, through
+//! `GET/PUT /api/items/{id}/annotations`.
+//!
+//! Highlights are ranges of characters of the text of the body shown in the reading pane, the
+//! content or else the description, kept along with the text they covered so that they still read
+//! right in exports and once the feed edits the item. Like the queue, the annotations of an item
+//! are replaced as a whole.
+
+use actix::Addr;
+use actix_web::{error, web, Error, HttpResponse};
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::server::{GetAnnotations, NewsServer, SetAnnotations};
+
+/// Maximum number of highlights of an item.
+const MAX_HIGHLIGHTS: usize = 200;
+/// Maximum length of a note, in characters.
+const MAX_NOTE_CHARS: usize = 10_000;
+
+/// Notes and highlights of an item.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Annotations {
+    /// Note on the item as a whole.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Highlights in the order they appear in the text.
+    #[serde(default)]
+    pub highlights: Vec<Highlight>,
+}
+
+/// A range of the text of an item the user highlighted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Highlight {
+    /// Offset of the first character highlighted.
+    pub start: usize,
+    /// Offset of the character after the last one highlighted.
+    pub end: usize,
+    /// Text highlighted.
+    pub text: String,
+    /// Note on the highlighted text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl Annotations {
+    pub fn is_empty(&self) -> bool {
+        self.note.is_none() && self.highlights.is_empty()
+    }
+
+    /// Check that the annotations are sensible, dropping blank notes and ordering the highlights.
+    pub fn validate(&mut self) -> Result<(), &'static str> {
+        if self.highlights.len() > MAX_HIGHLIGHTS {
+            return Err("too many highlights");
+        }
+        let notes = std::iter::once(&mut self.note)
+            .chain(self.highlights.iter_mut().map(|highlight| &mut highlight.note));
+        for note in notes {
+            if note.as_ref().map_or(false, |note| note.chars().count() > MAX_NOTE_CHARS) {
+                return Err("note too long");
+            }
+            if note.as_ref().map_or(false, |note| note.trim().is_empty()) {
+                *note = None;
+            }
+        }
+        for highlight in &self.highlights {
+            if highlight.start >= highlight.end {
+                return Err("empty highlight");
+            }
+            if highlight.text.chars().count() != highlight.end - highlight.start {
+                return Err("highlighted text doesn't match its range");
+            }
+        }
+        self.highlights.sort_by_key(|highlight| highlight.start);
+        let overlap = self
+            .highlights
+            .windows(2)
+            .any(|pair| pair[0].end > pair[1].start);
+        if overlap {
+            return Err("overlapping highlights");
+        }
+        Ok(())
+    }
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/api/items/{id}/annotations")
+            .route(web::get().to(get_annotations))
+            .route(web::put().to(put_annotations)),
+    );
+}
+
+async fn get_annotations(
+    server: web::Data<Addr<NewsServer>>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let annotations = server
+        .send(GetAnnotations {
+            id: id.into_inner(),
+        })
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("no such item"))?;
+    Ok(HttpResponse::Ok().json(annotations))
+}
+
+async fn put_annotations(
+    server: web::Data<Addr<NewsServer>>,
+    id: web::Path<String>,
+    annotations: web::Json<Annotations>,
+) -> Result<HttpResponse, Error> {
+    let mut annotations = annotations.into_inner();
+    annotations.validate().map_err(error::ErrorBadRequest)?;
+
+    let annotations = server
+        .send(SetAnnotations {
+            id: id.into_inner(),
+            annotations,
+        })
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("no such item"))?;
+    Ok(HttpResponse::Ok().json(annotations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlight(start: usize, text: &str) -> Highlight {
+        Highlight {
+            start,
+            end: start + text.chars().count(),
+            text: text.to_string(),
+            note: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn orders_highlights() {
+        let mut annotations = Annotations {
+            note: Some("Worth a follow-up".to_string()),
+            highlights: vec![highlight(40, "harbor"), highlight(3, "storm café")],
+        };
+        annotations.validate().unwrap();
+        let starts: Vec<usize> = annotations.highlights.iter().map(|h| h.start).collect();
+        assert_eq!(starts, [3, 40]);
+        assert_eq!(annotations.highlights[0].end, 13);
+    }
+
+    #[test]
+    fn drops_blank_notes() {
+        let mut annotations = Annotations {
+            note: Some("  ".to_string()),
+            highlights: vec![highlight(0, "harbor")],
+        };
+        annotations.highlights[0].note = Some(String::new());
+        annotations.validate().unwrap();
+        assert_eq!(annotations.note, None);
+        assert_eq!(annotations.highlights[0].note, None);
+    }
+
+    #[test]
+    fn rejects_bad_ranges() {
+        let mut overlapping = Annotations {
+            note: None,
+            highlights: vec![highlight(0, "harbor reopens"), highlight(7, "reopens")],
+        };
+        assert!(overlapping.validate().is_err());
+
+        let mut mismatched = Annotations::default();
+        mismatched.highlights.push(highlight(0, "harbor"));
+        mismatched.highlights[0].end = 3;
+        assert!(mismatched.validate().is_err());
+    }
+}
@@ -0,0 +1,83 @@
+//! Detection of feeds posting at an unusual rate, from the publication dates of stored items.
+//!
+//! A feed is silent when nothing was published for several times its average interval between
+//! items, and floods when the last day brought several times its average daily volume.
+
+use chrono::prelude::*;
+use chrono::Duration;
+
+use crate::settings::AnomalySettings;
+
+/// Items needed before a feed's usual rate is trusted.
+const MIN_HISTORY: usize = 5;
+/// Shortest silence reported, so that feeds posting every few minutes don't alert on a quiet hour.
+const MIN_SILENCE_HOURS: i64 = 24;
+/// Fewest items in a day reported as a flood, so that a slow feed posting twice doesn't alert.
+const MIN_FLOOD: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Anomaly {
+    /// Nothing was published since `since`.
+    Silent { since: DateTime<Utc>, usual: Duration },
+    /// `count` items were published in the last day instead of the usual `usual`.
+    Flood { count: usize, usual: f64 },
+}
+
+impl Anomaly {
+    /// Describe the anomaly for the feed `feed`.
+    pub fn describe(&self, feed: &str) -> String {
+        match self {
+            Anomaly::Silent { since, usual } => format!(
+                "{} hasn't posted since {}, it usually posts every {} hour(s)",
+                feed,
+                since.format("%Y-%m-%d %H:%M"),
+                usual.num_hours().max(1)
+            ),
+            Anomaly::Flood { count, usual } => format!(
+                "{} posted {} item(s) in the last day, it usually posts {:.1}",
+                feed, count, usual
+            ),
+        }
+    }
+}
+
+/// Look for an anomaly in the publication `dates` of the items of a feed.
+pub fn detect(
+    mut dates: Vec<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    settings: &AnomalySettings,
+) -> Option<Anomaly> {
+    dates.retain(|date| *date <= now);
+    if dates.len() < MIN_HISTORY {
+        return None;
+    }
+    dates.sort_unstable();
+
+    let day_ago = now - Duration::days(1);
+    let split = dates
+        .iter()
+        .position(|date| *date >= day_ago)
+        .unwrap_or(dates.len());
+    let (history, recent) = dates.split_at(split);
+
+    if history.len() >= MIN_HISTORY && recent.len() >= MIN_FLOOD {
+        let days = (day_ago - history[0]).num_seconds() as f64 / 86_400.0;
+        let usual = history.len() as f64 / days.max(1.0);
+        if recent.len() as f64 >= usual * settings.flood_factor {
+            return Some(Anomaly::Flood {
+                count: recent.len(),
+                usual,
+            });
+        }
+    }
+
+    let (first, last) = (dates[0], dates[dates.len() - 1]);
+    let usual = (last - first) / (dates.len() as i32 - 1);
+    let silence = now - last;
+    let threshold = usual.num_seconds() as f64 * settings.silence_factor;
+    let threshold = Duration::seconds(threshold as i64);
+    if silence >= threshold && silence >= Duration::hours(MIN_SILENCE_HOURS) {
+        return Some(Anomaly::Silent { since: last, usual });
+    }
+    None
+}
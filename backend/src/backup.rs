@@ -0,0 +1,251 @@
+//! Scheduled backups of the subscriptions and the user's state.
+//!
+//! When a `backup` section is present in the config, the subscriptions are exported as OPML every
+//! `interval_hours`, next to a gzipped tarball holding the same OPML along with the user's state
+//! and settings, to a directory or to a bucket of an S3 compatible service. Only the newest `keep`
+//! copies of each are kept. The OPML imports into any feed reader, so the subscriptions survive
+//! losing the disk even if the backend is never restored.
+
+use actix::Addr;
+use actix_web::web;
+use anyhow::{anyhow, Result};
+use chrono::prelude::*;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::{BackupConfig, BackupTarget};
+use crate::folders::FeedOrder;
+use crate::s3::Bucket;
+use crate::sanitize::escape;
+use crate::server::{GetBackup, NewsServer};
+use crate::settings::Settings;
+
+/// Prefix of the names of the backups, which are followed by the time they were taken.
+const PREFIX: &str = "news-";
+/// Extensions of the files of a backup.
+const EXTENSIONS: [&str; 2] = [".opml", ".tar.gz"];
+
+/// What a backup holds, as returned by the server.
+pub struct BackupData {
+    /// Folders and order of the subscribed feeds.
+    pub order: FeedOrder,
+    /// Title of the feeds, keyed by URL.
+    pub titles: HashMap<String, String>,
+    /// The user's state, serialized as JSON.
+    pub state: Vec<u8>,
+    pub settings: Settings,
+}
+
+/// Back up every `interval_hours` for as long as the server runs, starting now.
+pub fn spawn(config: BackupConfig, server: Addr<NewsServer>) {
+    actix_rt::spawn(async move {
+        let period = Duration::from_secs(config.interval_hours.max(1) * 60 * 60);
+        let mut interval = actix_rt::time::interval(period);
+        loop {
+            interval.tick().await;
+            if let Err(e) = back_up(&config, &server).await {
+                log::error!("failed to back up: {}", e);
+            }
+        }
+    });
+}
+
+async fn back_up(config: &BackupConfig, server: &Addr<NewsServer>) -> Result<()> {
+    let data = server.send(GetBackup).await.map_err(|e| anyhow!("{}", e))??;
+    let opml = opml(&data.order, &data.titles);
+    let archive = archive(&data, &opml)?;
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+    let files = vec![
+        (format!("{}{}.opml", PREFIX, stamp), opml.into_bytes()),
+        (format!("{}{}.tar.gz", PREFIX, stamp), archive),
+    ];
+
+    match &config.target {
+        BackupTarget::Dir { path } => {
+            let (path, keep) = (path.clone(), config.keep);
+            web::block(move || write_dir(&path, files, keep))
+                .await
+                .map_err(|e| anyhow!("{}", e))?;
+        }
+        BackupTarget::S3(s3) => {
+            let bucket = Bucket::new(s3.clone());
+            for (name, body) in files {
+                let content_type = if name.ends_with(".opml") {
+                    "text/x-opml"
+                } else {
+                    "application/gzip"
+                };
+                bucket.put(&name, body, content_type).await?;
+            }
+            for name in stale(bucket.list(PREFIX).await?, config.keep) {
+                bucket.delete(&name).await?;
+            }
+        }
+    }
+    log::info!("backed up the subscriptions and state");
+    Ok(())
+}
+
+/// Write the files of a backup to `dir`, deleting the copies beyond the newest `keep`.
+fn write_dir(dir: &Path, files: Vec<(String, Vec<u8>)>, keep: usize) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    for (name, body) in files {
+        // Written aside then renamed, so that a crash never leaves a truncated backup behind.
+        let partial = dir.join(format!(".{}.partial", name));
+        fs::write(&partial, body)?;
+        fs::rename(&partial, dir.join(name))?;
+    }
+    let names = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    for name in stale(names, keep) {
+        fs::remove_file(dir.join(name))?;
+    }
+    Ok(())
+}
+
+/// Return the names of the backups beyond the newest `keep` of each kind among `names`.
+fn stale(mut names: Vec<String>, keep: usize) -> Vec<String> {
+    // Names end with the time the backups were taken, so they sort by age.
+    names.sort_unstable_by(|a, b| b.cmp(a));
+    EXTENSIONS
+        .iter()
+        .flat_map(|extension| {
+            names
+                .iter()
+                .filter(move |name| name.starts_with(PREFIX) && name.ends_with(extension))
+                .skip(keep)
+                .cloned()
+        })
+        .collect()
+}
+
+/// Lay out the subscriptions as OPML, the folders as outlines holding their feeds.
+pub fn opml(order: &FeedOrder, titles: &HashMap<String, String>) -> String {
+    let outline = |feed: &String| {
+        let title = escape(titles.get(feed).unwrap_or(feed));
+        format!(
+            "<outline type=\"rss\" text=\"{}\" title=\"{}\" xmlUrl=\"{}\"/>\n",
+            title,
+            title,
+            escape(feed)
+        )
+    };
+    let mut opml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n\
+                    <head><title>News subscriptions</title></head>\n<body>\n"
+        .to_string();
+    for folder in &order.folders {
+        opml.push_str(&format!("<outline text=\"{}\">\n", escape(&folder.name)));
+        for feed in &folder.feeds {
+            opml.push_str("  ");
+            opml.push_str(&outline(feed));
+        }
+        opml.push_str("</outline>\n");
+    }
+    for feed in &order.feeds {
+        opml.push_str(&outline(feed));
+    }
+    opml.push_str("</body>\n</opml>\n");
+    opml
+}
+
+/// Pack the OPML, the state and the settings into a gzipped tarball.
+fn archive(data: &BackupData, opml: &str) -> Result<Vec<u8>> {
+    let settings = serde_json::to_vec_pretty(&data.settings)?;
+    let files: [(&str, &[u8]); 3] = [
+        ("subscriptions.opml", opml.as_bytes()),
+        ("state.json", &data.state),
+        ("settings.json", &settings),
+    ];
+    let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for (name, content) in files.iter() {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o600);
+        header.set_mtime(Utc::now().timestamp() as u64);
+        header.set_cksum();
+        archive.append_data(&mut header, name, *content)?;
+    }
+    Ok(archive.into_inner()?.finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folders::Folder;
+
+    #[test]
+    fn lays_out_folders() {
+        let order = FeedOrder {
+            folders: vec![Folder {
+                name: "Science & Tech".to_string(),
+                feeds: vec!["https://a.example/rss".to_string()],
+            }],
+            feeds: vec!["https://b.example/rss?format=xml&full=1".to_string()],
+        };
+        let mut titles = HashMap::new();
+        titles.insert("https://a.example/rss".to_string(), "A".to_string());
+
+        assert_eq!(
+            opml(&order, &titles),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n\
+             <head><title>News subscriptions</title></head>\n<body>\n\
+             <outline text=\"Science &amp; Tech\">\n\
+             \x20 <outline type=\"rss\" text=\"A\" title=\"A\" xmlUrl=\"https://a.example/rss\"/>\n\
+             </outline>\n\
+             <outline type=\"rss\" text=\"https://b.example/rss?format=xml&amp;full=1\" \
+             title=\"https://b.example/rss?format=xml&amp;full=1\" \
+             xmlUrl=\"https://b.example/rss?format=xml&amp;full=1\"/>\n\
+             </body>\n</opml>\n"
+        );
+    }
+
+    #[test]
+    fn rotates_each_kind() {
+        let names = [
+            "news-20200101T000000Z.opml",
+            "news-20200103T000000Z.opml",
+            "news-20200102T000000Z.opml",
+            "news-20200101T000000Z.tar.gz",
+            "news-20200103T000000Z.tar.gz",
+            "notes.txt",
+        ];
+        let names = names.iter().map(|name| name.to_string()).collect();
+        assert_eq!(
+            stale(names, 2),
+            ["news-20200101T000000Z.opml", "news-20200101T000000Z.tar.gz"]
+        );
+    }
+
+    #[test]
+    fn writes_to_directories() {
+        let dir = std::env::temp_dir().join(format!("news-backup-{}", rand::random::<u64>()));
+        for day in 1..=3 {
+            let files = vec![
+                (format!("news-2020010{}T000000Z.opml", day), b"<opml/>".to_vec()),
+                (format!("news-2020010{}T000000Z.tar.gz", day), Vec::new()),
+            ];
+            write_dir(&dir, files, 2).unwrap();
+        }
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "news-20200102T000000Z.opml",
+                "news-20200102T000000Z.tar.gz",
+                "news-20200103T000000Z.opml",
+                "news-20200103T000000Z.tar.gz",
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
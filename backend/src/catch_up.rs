@@ -0,0 +1,116 @@
+//! Time-boxed reading sessions through `GET /api/catch_up?minutes=10`.
+//!
+//! The unread items the user is most likely to read are picked, by priority, until their estimated
+//! reading times fill the time the user has. Items too long for the time left are skipped in
+//! favor of shorter ones, so that the session ends when promised.
+
+use actix::Addr;
+use actix_web::{error, web, Error, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use std::cmp::Ordering;
+
+use crate::protocol::ItemDto;
+use crate::server::{NewsServer, ViewItems};
+use crate::views::View;
+
+/// Longest session, in minutes.
+const MAX_MINUTES: u32 = 240;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/api/catch_up", web::get().to(get_session));
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionParams {
+    /// Time the user has, in minutes.
+    minutes: u32,
+}
+
+/// Items to read in a session, in order.
+#[derive(Debug, Serialize)]
+pub struct Session {
+    /// Ids of the items.
+    pub ids: Vec<String>,
+    /// Estimated minutes it takes to read every item.
+    pub minutes: u32,
+}
+
+async fn get_session(
+    server: web::Data<Addr<NewsServer>>,
+    params: web::Query<SessionParams>,
+) -> Result<HttpResponse, Error> {
+    if params.minutes == 0 || params.minutes > MAX_MINUTES {
+        return Err(error::ErrorBadRequest("minutes must be between 1 and 240"));
+    }
+    let unread = server
+        .send(ViewItems {
+            view: View::Unread,
+            limit: usize::MAX,
+        })
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(select(unread, params.minutes)))
+}
+
+/// Pick the items of `dtos` with the highest priority whose reading times fit in `minutes`.
+pub fn select(mut dtos: Vec<ItemDto>, minutes: u32) -> Session {
+    // Stable, so that items of equal priority stay newest first.
+    dtos.sort_by(|a, b| b.priority.partial_cmp(&a.priority).unwrap_or(Ordering::Equal));
+    let mut session = Session {
+        ids: Vec::new(),
+        minutes: 0,
+    };
+    for dto in dtos {
+        if session.minutes + dto.reading_minutes > minutes {
+            continue;
+        }
+        session.minutes += dto.reading_minutes;
+        session.ids.push(dto.id);
+        if session.minutes == minutes {
+            break;
+        }
+    }
+    session
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use news_core::test_util;
+
+    use crate::settings::Settings;
+    use crate::store::Store;
+
+    /// Return the DTO of an item of `words` words whose priority is `priority`.
+    fn dto(title: &str, words: usize, priority: f64) -> ItemDto {
+        let description = vec!["word"; words].join(" ");
+        let item = test_util::item(title).description(&description).build();
+        let mut dto = ItemDto::new(&item, &Settings::default(), &Store::default());
+        dto.priority = priority;
+        dto
+    }
+
+    #[test]
+    fn estimates_reading_time() {
+        assert_eq!(dto("Brief", 0, 0.5).reading_minutes, 1);
+        assert_eq!(dto("Essay", 1_000, 0.5).reading_minutes, 5);
+    }
+
+    #[test]
+    fn fills_the_time_by_priority() {
+        let dtos = vec![
+            dto("Long and likely", 2_000, 0.9),
+            dto("Short and likely", 200, 0.8),
+            dto("Medium", 900, 0.6),
+            dto("Short and unlikely", 100, 0.1),
+        ];
+        let ids: Vec<String> = dtos.iter().map(|dto| dto.id.clone()).collect();
+
+        let session = select(dtos, 5);
+        // The long item doesn't fit, so shorter ones take its place.
+        assert_eq!(session.ids, [ids[1].clone(), ids[2].clone()]);
+        assert_eq!(session.minutes, 5);
+    }
+}
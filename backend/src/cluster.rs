@@ -0,0 +1,106 @@
+//! Grouping of items covering the same story.
+//!
+//! Titles are turned into TF-IDF vectors and an item joins the cluster of the most similar item
+//! published within a short window before it, so that coverage of the same event by several
+//! feeds ends up under a single cluster id.
+
+use chrono::Duration;
+
+use std::collections::HashMap;
+
+use crate::news::NewsItem;
+use crate::recommend;
+
+/// How far apart items can be published and still cover the same story.
+const WINDOW_HOURS: i64 = 36;
+/// Cosine similarity of titles above which two items cover the same story.
+const THRESHOLD: f64 = 0.5;
+
+type Vector = HashMap<String, f64>;
+
+/// Assign items covering the same story a shared cluster id.
+///
+/// Returns the cluster id of every item that has at least one related item, keyed by item id.
+/// The id of a cluster is the id of its earliest item, so it stays stable as newer coverage is
+/// added.
+pub fn cluster<'a, I>(items: I) -> HashMap<String, String>
+where
+    I: IntoIterator<Item = &'a NewsItem>,
+{
+    let mut dated: Vec<(&NewsItem, Vec<String>)> = items
+        .into_iter()
+        .filter(|item| item.pub_date().is_some())
+        .map(|item| (item, recommend::keywords(item.title().unwrap_or("")).collect()))
+        .collect();
+    dated.sort_by_key(|(item, _)| item.pub_date());
+
+    let vectors = tf_idf(dated.iter().map(|(_, words)| words.as_slice()));
+    let window = Duration::hours(WINDOW_HOURS);
+
+    let mut assigned: Vec<String> = Vec::with_capacity(dated.len());
+    let mut sizes: HashMap<String, usize> = HashMap::new();
+    for (idx, (item, _)) in dated.iter().enumerate() {
+        let date = item.pub_date().unwrap();
+        let best = (0..idx)
+            .rev()
+            .take_while(|&other| date - dated[other].0.pub_date().unwrap() <= window)
+            .map(|other| (other, similarity(&vectors[idx], &vectors[other])))
+            .filter(|&(_, score)| score >= THRESHOLD)
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+        let id = match best {
+            Some((other, _)) => assigned[other].clone(),
+            None => item.id(),
+        };
+        *sizes.entry(id.clone()).or_insert(0) += 1;
+        assigned.push(id);
+    }
+
+    dated
+        .iter()
+        .zip(assigned)
+        .filter(|(_, cluster)| sizes[cluster] > 1)
+        .map(|((item, _), cluster)| (item.id(), cluster))
+        .collect()
+}
+
+/// Compute normalized TF-IDF vectors of keyword lists.
+fn tf_idf<'a, I>(documents: I) -> Vec<Vector>
+where
+    I: Iterator<Item = &'a [String]> + Clone,
+{
+    let count = documents.clone().count() as f64;
+    let mut frequencies: HashMap<&str, usize> = HashMap::new();
+    for words in documents.clone() {
+        let mut seen: Vec<&str> = words.iter().map(String::as_str).collect();
+        seen.sort_unstable();
+        seen.dedup();
+        for word in seen {
+            *frequencies.entry(word).or_insert(0) += 1;
+        }
+    }
+
+    documents
+        .map(|words| {
+            let mut vector = Vector::new();
+            for word in words {
+                let idf = (count / frequencies[word.as_str()] as f64).ln();
+                *vector.entry(word.clone()).or_insert(0.0) += idf;
+            }
+            let norm = vector.values().map(|v| v * v).sum::<f64>().sqrt();
+            if norm > 0.0 {
+                vector.values_mut().for_each(|v| *v /= norm);
+            }
+            vector
+        })
+        .collect()
+}
+
+/// Cosine similarity of two normalized vectors.
+fn similarity(a: &Vector, b: &Vector) -> f64 {
+    let (small, large) = if a.len() < b.len() { (a, b) } else { (b, a) };
+    small
+        .iter()
+        .filter_map(|(word, weight)| large.get(word).map(|other| weight * other))
+        .sum()
+}
@@ -0,0 +1,100 @@
+//! Native desktop notifications for new items, for a backend running as a local desktop daemon.
+//!
+//! Notifications are sent over D-Bus through notify-rust whether or not the web UI is open. They
+//! are only enabled by a `desktop_notifications` section in the config, listing which items to
+//! notify about, in builds with the `desktop-notifications` feature.
+
+#[cfg(feature = "notify-rust")]
+use notify_rust::Notification;
+
+#[cfg(feature = "notify-rust")]
+use std::thread;
+
+use crate::config::{DesktopNotificationConfig, NotificationRule};
+use crate::news::NewsItem;
+
+/// Above this many new matching items a single summary notification is shown instead.
+const MAX_NOTIFICATIONS: usize = 3;
+
+pub struct DesktopNotifier {
+    config: DesktopNotificationConfig,
+}
+
+impl DesktopNotifier {
+    pub fn new(config: DesktopNotificationConfig) -> Self {
+        DesktopNotifier { config }
+    }
+
+    /// Whether any rule asks for notifications about `item`.
+    pub fn matches(&self, item: &NewsItem) -> bool {
+        self.config.rules.iter().any(|rule| rule_matches(rule, item))
+    }
+
+    /// Show notifications for new items, which should all match.
+    pub fn notify(&self, items: &[&NewsItem]) {
+        let notifications: Vec<(String, String)> = if items.len() > MAX_NOTIFICATIONS {
+            vec![(format!("{} new items", items.len()), String::new())]
+        } else {
+            items
+                .iter()
+                .map(|item| {
+                    let title = item.title().unwrap_or("New item").to_string();
+                    (title, feed_name(&item.feed).to_string())
+                })
+                .collect()
+        };
+        show(notifications);
+    }
+
+    /// Show a notification about something other than new items, e.g. a feed gone silent.
+    pub fn alert(&self, summary: String, body: String) {
+        show(vec![(summary, body)]);
+    }
+}
+
+/// Show notifications given as pairs of summary and body.
+#[cfg(feature = "notify-rust")]
+fn show(notifications: Vec<(String, String)>) {
+    // Sending over D-Bus blocks, so keep it away from the server actor.
+    thread::spawn(move || {
+        for (summary, body) in notifications {
+            let result = Notification::new()
+                .appname("News")
+                .summary(&summary)
+                .body(&body)
+                .show();
+            if let Err(e) = result {
+                log::warn!("failed to show desktop notification: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(not(feature = "notify-rust"))]
+fn show(notifications: Vec<(String, String)>) {
+    log::warn!(
+        "not showing {} desktop notification(s), built without them",
+        notifications.len()
+    );
+}
+
+fn rule_matches(rule: &NotificationRule, item: &NewsItem) -> bool {
+    if rule.feed.as_ref().map_or(false, |feed| feed != &item.feed) {
+        return false;
+    }
+    if rule.keywords.is_empty() {
+        return true;
+    }
+    let title = item.title().unwrap_or("").to_lowercase();
+    rule.keywords
+        .iter()
+        .any(|keyword| title.contains(&keyword.to_lowercase()))
+}
+
+/// Return the host of a feed URL.
+fn feed_name(feed: &str) -> &str {
+    feed.split("://")
+        .nth(1)
+        .and_then(|rest| rest.split('/').next())
+        .unwrap_or(feed)
+}
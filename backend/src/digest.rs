@@ -0,0 +1,211 @@
+//! Daily digest through `GET /api/digest`, for checking in once a day rather than streaming.
+//!
+//! Items are grouped by the local day they were published on, then by feed, so that each feed
+//! contributes one card per day listing its headlines.
+
+use actix::Addr;
+use actix_web::{error, web, Error, HttpResponse};
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::ItemDto;
+use crate::server::{Digest, NewsServer};
+
+/// Number of days of digest returned unless asked otherwise.
+const DEFAULT_DAYS: u32 = 7;
+/// Maximum number of days of digest.
+const MAX_DAYS: u32 = 31;
+
+/// Headlines of every feed on one day.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DigestDay {
+    pub day: NaiveDate,
+    /// Cards of the feeds with the most unread headlines first.
+    pub cards: Vec<Card>,
+}
+
+/// Headlines of one feed on one day.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Card {
+    pub feed: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed_title: Option<String>,
+    pub unread: usize,
+    /// Headlines, newest first.
+    pub headlines: Vec<Headline>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Headline {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    pub read: bool,
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/api/digest", web::get().to(get_digest));
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct DigestParams {
+    /// Number of days, today included.
+    days: Option<u32>,
+    /// Offset of the reader's time zone east of UTC in minutes, defaulting to the time zone of
+    /// the settings.
+    offset_minutes: Option<i32>,
+}
+
+async fn get_digest(
+    server: web::Data<Addr<NewsServer>>,
+    params: web::Query<DigestParams>,
+) -> Result<HttpResponse, Error> {
+    let days = params.days.unwrap_or(DEFAULT_DAYS);
+    if days == 0 || days > MAX_DAYS {
+        return Err(error::ErrorBadRequest("days must be between 1 and 31"));
+    }
+    let offset = match params.offset_minutes {
+        Some(minutes) => Some(
+            FixedOffset::east_opt(minutes.saturating_mul(60))
+                .ok_or_else(|| error::ErrorBadRequest("invalid time zone offset"))?,
+        ),
+        None => None,
+    };
+
+    let digest = server
+        .send(Digest { days, offset })
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(digest))
+}
+
+/// Group the items of `dtos` published from `since` on, in the time zone of `offset`, into daily
+/// digests, newest first. Low quality items are left out.
+pub fn group<I>(dtos: I, since: NaiveDate, offset: FixedOffset) -> Vec<DigestDay>
+where
+    I: IntoIterator<Item = ItemDto>,
+{
+    let mut days: Vec<DigestDay> = Vec::new();
+    for dto in dtos.into_iter().filter(|dto| !dto.low_quality) {
+        let day = match dto.pub_date {
+            Some(date) => date.with_timezone(&offset).naive_local().date(),
+            None => continue,
+        };
+        if day < since {
+            continue;
+        }
+
+        let idx = match days.iter().position(|digest| digest.day == day) {
+            Some(idx) => idx,
+            None => {
+                days.push(DigestDay {
+                    day,
+                    cards: Vec::new(),
+                });
+                days.len() - 1
+            }
+        };
+        let cards = &mut days[idx].cards;
+        let idx = match cards.iter().position(|card| card.feed == dto.feed) {
+            Some(idx) => idx,
+            None => {
+                cards.push(Card {
+                    feed: dto.feed.clone(),
+                    feed_title: dto.feed_title.clone(),
+                    unread: 0,
+                    headlines: Vec::new(),
+                });
+                cards.len() - 1
+            }
+        };
+        let card = &mut cards[idx];
+        if !dto.read {
+            card.unread += 1;
+        }
+        card.headlines.push(Headline {
+            id: dto.id,
+            title: dto.title,
+            link: dto.link,
+            read: dto.read,
+        });
+    }
+
+    days.sort_by(|a, b| b.day.cmp(&a.day));
+    for digest in &mut days {
+        digest
+            .cards
+            .sort_by(|a, b| b.unread.cmp(&a.unread).then_with(|| a.feed.cmp(&b.feed)));
+        for card in &mut digest.cards {
+            card.headlines.reverse();
+        }
+    }
+    days
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use news_core::test_util;
+
+    use crate::settings::Settings;
+    use crate::store::Store;
+
+    const WORLD: &str = "https://example.com/world.xml";
+    const TECH: &str = "https://example.com/tech.xml";
+
+    /// Return the DTO of an item of `feed` published at `hour` UTC on `day` of May 2020.
+    fn dto(feed: &str, title: &str, day: u32, hour: u32, store: &Store) -> ItemDto {
+        let date = Utc.ymd(2020, 5, day).and_hms(hour, 0, 0);
+        let item = test_util::item(title).feed(feed).date(date).build();
+        ItemDto::new(&item, &Settings::default(), store)
+    }
+
+    fn titles(card: &Card) -> Vec<&str> {
+        card.headlines
+            .iter()
+            .map(|headline| headline.title.as_deref().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn groups_headlines_by_day_and_feed() {
+        let mut store = Store::default();
+        let read = dto(TECH, "Kernel patch merged", 5, 9, &store);
+        store.mark_read(&read.id);
+        // Oldest first, as stored.
+        let dtos = vec![
+            dto(WORLD, "Old news", 1, 12, &store),
+            dto(WORLD, "Harbor reopens", 5, 8, &store),
+            dto(TECH, "Kernel patch merged", 5, 9, &store),
+            dto(WORLD, "Bridge vote delayed", 5, 10, &store),
+            dto(TECH, "Compiler release", 6, 7, &store),
+        ];
+
+        let since = NaiveDate::from_ymd(2020, 5, 4);
+        let digest = group(dtos, since, FixedOffset::east(0));
+        let days: Vec<NaiveDate> = digest.iter().map(|day| day.day).collect();
+        assert_eq!(
+            days,
+            [NaiveDate::from_ymd(2020, 5, 6), NaiveDate::from_ymd(2020, 5, 5)]
+        );
+        let cards = &digest[1].cards;
+        assert_eq!(cards[0].feed, WORLD);
+        assert_eq!(cards[0].unread, 2);
+        assert_eq!(titles(&cards[0]), ["Bridge vote delayed", "Harbor reopens"]);
+        assert_eq!(cards[1].unread, 0);
+        assert!(cards[1].headlines[0].read);
+    }
+
+    #[test]
+    fn days_follow_the_time_zone_of_the_reader() {
+        let store = Store::default();
+        let dtos = vec![dto(WORLD, "Late news", 5, 23, &store)];
+        let since = NaiveDate::from_ymd(2020, 5, 1);
+        let digest = group(dtos, since, FixedOffset::east(2 * 60 * 60));
+        assert_eq!(digest[0].day, NaiveDate::from_ymd(2020, 5, 6));
+    }
+}
@@ -0,0 +1,43 @@
+//! Full article content extraction.
+
+use scraper::{Html, Selector};
+
+use crate::news::NewsItem;
+
+/// Elements likely to hold the article, tried in order.
+const CONTENT_SELECTORS: &[&str] = &["article", "main", "[role=main]", "body"];
+
+/// Return the full content of an item.
+///
+/// Content provided by the feed is preferred. Otherwise the linked page is downloaded and the
+/// first element matching one of `CONTENT_SELECTORS` is used.
+pub async fn full_content(item: &NewsItem) -> Option<String> {
+    if let Some(content) = item.feed_content() {
+        return Some(content.to_string());
+    }
+
+    let link = item.link()?;
+    let page = match reqwest::get(link).await {
+        Ok(resp) => resp.text().await,
+        Err(e) => Err(e),
+    };
+    match page {
+        Ok(page) => extract_article(&page),
+        Err(e) => {
+            log::warn!("failed to download {} for extraction: {}", link, e);
+            None
+        }
+    }
+}
+
+/// Extract the article out of a HTML document.
+pub fn extract_article(page: &str) -> Option<String> {
+    let document = Html::parse_document(page);
+    CONTENT_SELECTORS.iter().find_map(|selector| {
+        let selector = Selector::parse(selector).ok()?;
+        document
+            .select(&selector)
+            .next()
+            .map(|element| element.inner_html())
+    })
+}
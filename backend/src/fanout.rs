@@ -0,0 +1,126 @@
+//! Fan-out of stored items between instances over Redis pub/sub.
+//!
+//! Instances sharing a store behind a load balancer each fetch feeds for the clients connected
+//! to them. Every instance publishes the items it stores and takes in those the others publish,
+//! so that clients are pushed new items whichever instance fetched them.
+
+use actix::prelude::*;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use std::thread;
+use std::time::Duration;
+
+use crate::config::RedisConfig;
+use crate::news::NewsItem;
+
+/// How long to wait before subscribing again after losing the connection.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// Items stored by another instance.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct RemoteItems(pub Vec<NewsItem>);
+
+/// Message published on the channel.
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    /// Instance that stored the items, which ignores its own messages.
+    instance: u64,
+    items: Vec<NewsItem>,
+}
+
+pub struct Fanout {
+    instance: u64,
+    client: redis::Client,
+    connection: redis::Connection,
+    channel: String,
+}
+
+impl Fanout {
+    /// Connect to the Redis server configured.
+    pub fn connect(config: &RedisConfig) -> Result<Fanout> {
+        let client = redis::Client::open(config.url.as_str())?;
+        let connection = client.get_connection()?;
+        Ok(Fanout {
+            instance: rand::random(),
+            client,
+            connection,
+            channel: config.channel.clone(),
+        })
+    }
+
+    /// Publish items this instance stored to the other instances.
+    pub fn publish(&mut self, items: &[NewsItem]) {
+        if items.is_empty() {
+            return;
+        }
+        let envelope = Envelope {
+            instance: self.instance,
+            items: items.to_vec(),
+        };
+        let result = bincode::serialize(&envelope)
+            .map_err(anyhow::Error::from)
+            .and_then(|payload| {
+                redis::cmd("PUBLISH")
+                    .arg(&self.channel)
+                    .arg(payload)
+                    .query::<usize>(&mut self.connection)
+                    .map_err(anyhow::Error::from)
+            });
+        if let Err(e) = result {
+            log::error!("failed to publish {} item(s): {}", items.len(), e);
+        }
+    }
+
+    /// Send the items the other instances publish to `recipient`, from a thread of its own as
+    /// waiting for them blocks.
+    pub fn subscribe(&self, recipient: Recipient<RemoteItems>) {
+        let client = self.client.clone();
+        let channel = self.channel.clone();
+        let instance = self.instance;
+        let spawned = thread::Builder::new()
+            .name("fanout".to_string())
+            .spawn(move || {
+                while let Err(e) = listen(&client, &channel, instance, &recipient) {
+                    log::error!("lost the subscription to {}: {}", channel, e);
+                    thread::sleep(RESUBSCRIBE_DELAY);
+                }
+            });
+        if let Err(e) = spawned {
+            log::error!("failed to subscribe to {}: {}", self.channel, e);
+        }
+    }
+}
+
+/// Forward the items published by other instances until the connection fails or the server
+/// stops.
+fn listen(
+    client: &redis::Client,
+    channel: &str,
+    instance: u64,
+    recipient: &Recipient<RemoteItems>,
+) -> Result<()> {
+    let mut connection = client.get_connection()?;
+    let mut pubsub = connection.as_pubsub();
+    pubsub.subscribe(channel)?;
+    log::info!("subscribed to {}", channel);
+    loop {
+        let payload: Vec<u8> = pubsub.get_message()?.get_payload()?;
+        let envelope: Envelope = match bincode::deserialize(&payload) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                log::warn!("ignoring a malformed message on {}: {}", channel, e);
+                continue;
+            }
+        };
+        if envelope.instance == instance {
+            continue;
+        }
+        log::debug!("received {} item(s) from another instance", envelope.items.len());
+        if recipient.do_send(RemoteItems(envelope.items)).is_err() {
+            // The server stopped, so does the subscription.
+            return Ok(());
+        }
+    }
+}
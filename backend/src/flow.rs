@@ -0,0 +1,408 @@
+//! Per-connection flow control for item pushes.
+//!
+//! Items are queued in an outbox and sent in fixed size batches. Only a few batches may be
+//! unacknowledged at a time so a slow client cannot make the actor mailbox grow without bound.
+//!
+//! Changes to only the read or starred state of items are queued as patches. A patch of an item
+//! still queued is folded into it, and queueing an item drops the patches it supersedes, so the
+//! items and patches of a batch never concern the same item and can be applied in any order.
+//!
+//! The stored items are sent as a snapshot a page at a time, the next page being fetched once the
+//! client took the previous one, so that however many items are stored the client keeps up. A
+//! page is newer than the updates queued before it was fetched, which it replaces. Only updates
+//! count towards the lag past which the client has to resync.
+//!
+//! Pages fetched while a refresh runs are sent right away in batches tagged stale, rather than
+//! after the refresh, whose fetched items follow in batches of their own.
+
+use std::collections::{HashSet, VecDeque};
+
+use crate::protocol::{ItemBatch, ItemDto, ItemPatch};
+
+/// Number of items sent in a single batch.
+const BATCH_SIZE: usize = 100;
+/// Number of patches sent in a single batch, more than items as they are a few bytes each.
+const PATCH_BATCH_SIZE: usize = 1_000;
+/// Number of batches that may be sent without being acknowledged.
+const MAX_IN_FLIGHT: u64 = 4;
+/// Number of queued updates after which the client is considered too far behind.
+const MAX_PENDING: usize = 5_000;
+/// Number of stored items fetched at a time for a snapshot.
+const PAGE_SIZE: usize = BATCH_SIZE * MAX_IN_FLIGHT as usize;
+
+/// Outcome of queueing items in the outbox.
+#[derive(Debug, PartialEq)]
+pub enum Queued {
+    Ok,
+    /// The client lagged too far behind; the queue was dropped and a resync is needed.
+    ResyncNeeded,
+}
+
+/// Page of the stored items to fetch next for the snapshot, newest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageRequest {
+    /// Snapshot the page is for, so that pages of an abandoned snapshot are ignored.
+    pub snapshot: u64,
+    /// Id of the item the page starts after, the newest item when unset.
+    pub after: Option<String>,
+    pub limit: usize,
+}
+
+/// Progress of the snapshot being sent.
+#[derive(Debug, Default)]
+struct Snapshot {
+    /// Id of the last item queued, which the next page starts after.
+    cursor: Option<String>,
+    /// A page was requested and hasn't been queued yet.
+    loading: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Outbox {
+    /// Updates to items, sent after the items of the snapshot queued.
+    pending: VecDeque<ItemDto>,
+    /// Items of the pages of the snapshot queued.
+    snapshot_items: VecDeque<ItemDto>,
+    /// Number of items at the front of `snapshot_items` to tag stale.
+    stale: usize,
+    /// Snapshot still being fetched, if any.
+    snapshot: Option<Snapshot>,
+    /// Number of snapshots started.
+    snapshots: u64,
+    patches: VecDeque<ItemPatch>,
+    /// Sequence number of the last batch handed out.
+    sent: u64,
+    /// Sequence number of the last batch acknowledged by the client.
+    acked: u64,
+    resync_needed: bool,
+}
+
+impl Outbox {
+    /// Queue updated items to be sent to the client.
+    pub fn push<I: IntoIterator<Item = ItemDto>>(&mut self, items: I) -> Queued {
+        if self.resync_needed {
+            return Queued::ResyncNeeded;
+        }
+
+        let start = self.pending.len();
+        self.pending.extend(items);
+        let ids: HashSet<String> = self
+            .pending
+            .range(start..)
+            .map(|item| item.id.clone())
+            .collect();
+        self.patches.retain(|patch| !ids.contains(&patch.id));
+        self.snapshot_items.retain(|item| !ids.contains(&item.id));
+        self.stale = self.stale.min(self.snapshot_items.len());
+        self.check_lag()
+    }
+
+    /// Start sending every stored item to the client, dropping the snapshot being sent if any.
+    pub fn start_snapshot(&mut self) {
+        self.snapshots += 1;
+        self.snapshot = Some(Snapshot::default());
+        self.snapshot_items.clear();
+        self.stale = 0;
+    }
+
+    /// Return the page of the snapshot to fetch next, once the client took the previous one.
+    pub fn next_page(&mut self) -> Option<PageRequest> {
+        let busy = !self.snapshot_items.is_empty() || self.in_flight() >= MAX_IN_FLIGHT;
+        if self.resync_needed || busy {
+            return None;
+        }
+        let snapshot = self
+            .snapshot
+            .as_mut()
+            .filter(|snapshot| !snapshot.loading)?;
+        snapshot.loading = true;
+        Some(PageRequest {
+            snapshot: self.snapshots,
+            after: snapshot.cursor.clone(),
+            limit: PAGE_SIZE,
+        })
+    }
+
+    /// Queue the `items` of the page `request` asked for, tagged stale if a refresh is running.
+    pub fn push_page(&mut self, request: &PageRequest, items: Vec<ItemDto>, stale: bool) {
+        if request.snapshot != self.snapshots || self.resync_needed {
+            return;
+        }
+        let snapshot = match self.snapshot.as_mut() {
+            Some(snapshot) => snapshot,
+            None => return,
+        };
+        if items.len() < request.limit {
+            self.snapshot = None;
+        } else {
+            snapshot.loading = false;
+            snapshot.cursor = items.last().map(|item| item.id.clone());
+        }
+
+        let ids: HashSet<&str> = items.iter().map(|item| item.id.as_str()).collect();
+        self.pending.retain(|item| !ids.contains(item.id.as_str()));
+        self.patches
+            .retain(|patch| !ids.contains(patch.id.as_str()));
+        self.snapshot_items.extend(items);
+        if stale {
+            self.stale = self.snapshot_items.len();
+        }
+    }
+
+    /// Queue changes to the read or starred state of items to be sent to the client.
+    pub fn patch<I: IntoIterator<Item = ItemPatch>>(&mut self, patches: I) -> Queued {
+        if self.resync_needed {
+            return Queued::ResyncNeeded;
+        }
+
+        for patch in patches {
+            let mut folded = false;
+            let queued = self
+                .snapshot_items
+                .iter_mut()
+                .chain(self.pending.iter_mut());
+            for item in queued.filter(|item| item.id == patch.id) {
+                patch.apply(item);
+                folded = true;
+            }
+            if folded {
+                continue;
+            }
+            if let Some(queued) = self.patches.iter_mut().find(|p| p.id == patch.id) {
+                queued.merge(patch);
+            } else {
+                self.patches.push_back(patch);
+            }
+        }
+        self.check_lag()
+    }
+
+    /// Drop the queue if the client fell too far behind on updates.
+    fn check_lag(&mut self) -> Queued {
+        let pending = self.pending.len() + self.patches.len();
+        if pending > MAX_PENDING {
+            log::warn!("client lagging with {} updates pending, dropping to resync", pending);
+            self.clear();
+            self.resync_needed = true;
+            return Queued::ResyncNeeded;
+        }
+
+        Queued::Ok
+    }
+
+    /// Take the next batch to send if the client has room for it.
+    pub fn next_batch(&mut self) -> Option<ItemBatch> {
+        let empty =
+            self.snapshot_items.is_empty() && self.pending.is_empty() && self.patches.is_empty();
+        if self.resync_needed || empty || self.in_flight() >= MAX_IN_FLIGHT {
+            return None;
+        }
+
+        let (items, stale) = if self.snapshot_items.is_empty() {
+            let count = self.pending.len().min(BATCH_SIZE);
+            (self.pending.drain(..count).collect(), false)
+        } else {
+            let count = self.snapshot_items.len().min(BATCH_SIZE);
+            let stale = self.stale > 0;
+            self.stale = self.stale.saturating_sub(count);
+            (self.snapshot_items.drain(..count).collect(), stale)
+        };
+        let patches = self.patches.len().min(PATCH_BATCH_SIZE);
+        self.sent += 1;
+        Some(ItemBatch {
+            seq: self.sent,
+            items,
+            stale,
+            patches: self.patches.drain(..patches).collect(),
+        })
+    }
+
+    /// Record that the client applied every batch up to `up_to`.
+    pub fn ack(&mut self, up_to: u64) {
+        if up_to > self.sent {
+            log::warn!("client acked {} but only {} were sent", up_to, self.sent);
+        }
+        self.acked = self.acked.max(up_to.min(self.sent));
+    }
+
+    /// Forget everything queued or in flight so the full item set can be sent again.
+    pub fn reset(&mut self) {
+        self.clear();
+        self.acked = self.sent;
+        self.resync_needed = false;
+    }
+
+    /// Drop the queued items and patches, and the snapshot being sent.
+    fn clear(&mut self) {
+        self.pending.clear();
+        self.snapshot_items.clear();
+        self.stale = 0;
+        self.snapshot = None;
+        self.patches.clear();
+    }
+
+    fn in_flight(&self) -> u64 {
+        self.sent - self.acked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use news_core::test_util;
+
+    use crate::settings::Settings;
+    use crate::store::Store;
+
+    fn dtos(count: usize) -> Vec<ItemDto> {
+        (0..count)
+            .map(|n| {
+                let item = test_util::item(&format!("Item {}", n)).build();
+                ItemDto::new(&item, &Settings::default(), &Store::default())
+            })
+            .collect()
+    }
+
+    /// Serve the page of `stored` that `request` asks for, like the server does.
+    fn page(stored: &[ItemDto], request: &PageRequest) -> Vec<ItemDto> {
+        let start = match &request.after {
+            Some(after) => stored.iter().position(|item| &item.id == after).unwrap() + 1,
+            None => 0,
+        };
+        stored
+            .iter()
+            .skip(start)
+            .take(request.limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Send what the outbox lets through as the client acks it, returning the batches sent.
+    fn drain(outbox: &mut Outbox, stored: &[ItemDto]) -> Vec<ItemBatch> {
+        let mut batches = Vec::new();
+        loop {
+            if let Some(request) = outbox.next_page() {
+                outbox.push_page(&request, page(stored, &request), false);
+            }
+            match outbox.next_batch() {
+                Some(batch) => {
+                    outbox.ack(batch.seq);
+                    batches.push(batch);
+                }
+                None => return batches,
+            }
+        }
+    }
+
+    #[test]
+    fn pages_snapshots_larger_than_the_lag_limit() {
+        let stored = dtos(MAX_PENDING * 2 + 1);
+        let mut outbox = Outbox::default();
+        outbox.start_snapshot();
+        let batches = drain(&mut outbox, &stored);
+        let sent: Vec<&str> = batches
+            .iter()
+            .flat_map(|batch| batch.items.iter().map(|item| item.id.as_str()))
+            .collect();
+        let ids: Vec<&str> = stored.iter().map(|item| item.id.as_str()).collect();
+        assert_eq!(sent, ids);
+        assert_eq!(outbox.next_page(), None);
+    }
+
+    #[test]
+    fn waits_for_acks_before_fetching_pages() {
+        let stored = dtos(PAGE_SIZE * 2);
+        let mut outbox = Outbox::default();
+        outbox.start_snapshot();
+        let request = outbox.next_page().unwrap();
+        assert_eq!(outbox.next_page(), None);
+        outbox.push_page(&request, page(&stored, &request), false);
+        while outbox.next_batch().is_some() {}
+        // The whole window is in flight.
+        assert_eq!(outbox.next_page(), None);
+        outbox.ack(outbox.sent);
+        let request = outbox.next_page().unwrap();
+        assert_eq!(request.after.as_ref(), Some(&stored[PAGE_SIZE - 1].id));
+    }
+
+    #[test]
+    fn resyncs_when_updates_overflow() {
+        let mut outbox = Outbox::default();
+        outbox.start_snapshot();
+        assert_eq!(outbox.push(dtos(MAX_PENDING + 1)), Queued::ResyncNeeded);
+        assert!(outbox.next_batch().is_none());
+        assert_eq!(outbox.next_page(), None);
+        assert_eq!(outbox.push(dtos(1)), Queued::ResyncNeeded);
+
+        outbox.reset();
+        outbox.start_snapshot();
+        let stored = dtos(3);
+        let batches = drain(&mut outbox, &stored);
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].items.len(), 3);
+    }
+
+    #[test]
+    fn ignores_pages_of_abandoned_snapshots() {
+        let stored = dtos(2);
+        let mut outbox = Outbox::default();
+        outbox.start_snapshot();
+        let abandoned = outbox.next_page().unwrap();
+        outbox.reset();
+        outbox.start_snapshot();
+        let request = outbox.next_page().unwrap();
+        outbox.push_page(&abandoned, page(&stored, &abandoned), false);
+        outbox.push_page(&request, page(&stored, &request), false);
+        assert_eq!(outbox.next_batch().unwrap().items.len(), 2);
+    }
+
+    #[test]
+    fn pages_replace_queued_updates() {
+        let stored = dtos(2);
+        let mut outbox = Outbox::default();
+        outbox.push(stored[..1].to_vec());
+        outbox.start_snapshot();
+        let request = outbox.next_page().unwrap();
+        outbox.push_page(&request, page(&stored, &request), false);
+        let batches = drain(&mut outbox, &stored);
+        let count: usize = batches.iter().map(|batch| batch.items.len()).sum();
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn tags_stale_snapshots() {
+        let stored = dtos(BATCH_SIZE + 1);
+        let mut outbox = Outbox::default();
+        outbox.start_snapshot();
+        let request = outbox.next_page().unwrap();
+        outbox.push_page(&request, page(&stored, &request), true);
+        assert!(outbox.next_batch().unwrap().stale);
+        let last = outbox.next_batch().unwrap();
+        assert!(last.stale);
+        assert_eq!(last.items.len(), 1);
+
+        outbox.push(dtos(1));
+        assert!(!outbox.next_batch().unwrap().stale);
+    }
+
+    #[test]
+    fn sends_fresh_snapshots_untagged() {
+        let mut outbox = Outbox::default();
+        outbox.start_snapshot();
+        let request = outbox.next_page().unwrap();
+        outbox.push_page(&request, dtos(1), false);
+        assert!(!outbox.next_batch().unwrap().stale);
+    }
+
+    #[test]
+    fn forgets_stale_items_on_reset() {
+        let mut outbox = Outbox::default();
+        outbox.start_snapshot();
+        let request = outbox.next_page().unwrap();
+        outbox.push_page(&request, dtos(1), true);
+        outbox.reset();
+        outbox.push(dtos(1));
+        assert!(!outbox.next_batch().unwrap().stale);
+    }
+}
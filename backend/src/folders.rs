@@ -0,0 +1,149 @@
+//! Folders organizing the subscribed feeds in the sidebar, through `GET/PUT /api/feeds/order`.
+//!
+//! The layout is stored on the server so that it is the same on every device. Feeds subscribed
+//! after the layout was saved are listed after the ones outside any folder, and feeds no longer
+//! subscribed to are dropped from it.
+
+use actix::Addr;
+use actix_web::{error, web, Error, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashSet;
+
+use crate::server::{GetFeedOrder, NewsServer, SetFeedOrder};
+
+/// Maximum number of folders.
+const MAX_FOLDERS: usize = 100;
+
+/// Order of the feeds in the sidebar.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeedOrder {
+    pub folders: Vec<Folder>,
+    /// URLs of the feeds outside any folder, listed after the folders.
+    pub feeds: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Folder {
+    pub name: String,
+    /// URLs of the feeds in the folder.
+    pub feeds: Vec<String>,
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/api/feeds/order")
+            .route(web::get().to(get_order))
+            .route(web::put().to(put_order)),
+    );
+}
+
+async fn get_order(server: web::Data<Addr<NewsServer>>) -> Result<HttpResponse, Error> {
+    let order = server
+        .send(GetFeedOrder)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(order))
+}
+
+async fn put_order(
+    server: web::Data<Addr<NewsServer>>,
+    order: web::Json<FeedOrder>,
+) -> Result<HttpResponse, Error> {
+    let order = order.into_inner();
+    if order.folders.len() > MAX_FOLDERS {
+        return Err(error::ErrorBadRequest("too many folders"));
+    }
+    let mut names = HashSet::new();
+    for folder in &order.folders {
+        if folder.name.trim().is_empty() {
+            return Err(error::ErrorBadRequest("folders need a name"));
+        }
+        if !names.insert(folder.name.as_str()) {
+            return Err(error::ErrorBadRequest("folder names must be unique"));
+        }
+    }
+
+    let order = server
+        .send(SetFeedOrder(order))
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(order))
+}
+
+/// Fit `order` to the subscribed `feeds`: every feed is listed once, where it was placed first,
+/// and feeds that weren't placed anywhere yet follow the ones outside any folder.
+pub fn arrange(order: &FeedOrder, feeds: &[String]) -> FeedOrder {
+    let subscribed: HashSet<&str> = feeds.iter().map(String::as_str).collect();
+    let mut placed: HashSet<String> = HashSet::new();
+    let mut place = |feeds: &[String]| -> Vec<String> {
+        feeds
+            .iter()
+            .filter(|feed| subscribed.contains(feed.as_str()) && placed.insert(feed.to_string()))
+            .cloned()
+            .collect()
+    };
+
+    let folders: Vec<Folder> = order
+        .folders
+        .iter()
+        .map(|folder| Folder {
+            name: folder.name.clone(),
+            feeds: place(&folder.feeds),
+        })
+        .collect();
+    let mut root = place(&order.feeds);
+    root.extend(place(feeds));
+    FeedOrder {
+        folders,
+        feeds: root,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(feeds: &[&str]) -> Vec<String> {
+        feeds.iter().map(|feed| feed.to_string()).collect()
+    }
+
+    #[test]
+    fn lists_every_feed_without_a_layout() {
+        let feeds = urls(&["https://a.example.com", "https://b.example.com"]);
+        let order = arrange(&FeedOrder::default(), &feeds);
+        assert!(order.folders.is_empty());
+        assert_eq!(order.feeds, feeds);
+    }
+
+    #[test]
+    fn fits_the_layout_to_the_subscriptions() {
+        let feeds = urls(&[
+            "https://a.example.com",
+            "https://b.example.com",
+            "https://new.example.com",
+        ]);
+        let order = FeedOrder {
+            folders: vec![
+                Folder {
+                    name: "Tech".to_string(),
+                    feeds: urls(&["https://b.example.com", "https://gone.example.com"]),
+                },
+                Folder {
+                    name: "Empty".to_string(),
+                    feeds: Vec::new(),
+                },
+            ],
+            feeds: urls(&["https://a.example.com", "https://b.example.com"]),
+        };
+
+        let order = arrange(&order, &feeds);
+        assert_eq!(order.folders[0].feeds, urls(&["https://b.example.com"]));
+        assert_eq!(order.folders[1].name, "Empty");
+        assert_eq!(
+            order.feeds,
+            urls(&["https://a.example.com", "https://new.example.com"])
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! gRPC API for programmatic clients, defined in `proto/news.proto` at the workspace root.
+//!
+//! The gRPC server runs next to the HTTP server when `grpc_bind` is configured and forwards every
+//! call to the `NewsServer` actor.
+
+use actix::Addr;
+use anyhow::Result;
+use chrono::Utc;
+use tonic::{Request, Response, Status};
+
+use std::net::SocketAddr;
+
+use crate::protocol::ItemDto;
+use crate::server::{GetItems, ListFeeds, NewsServer, Subscribe, UpdateItems};
+
+pub mod proto {
+    tonic::include_proto!("news");
+}
+
+use proto::news_server::News;
+
+pub struct Service {
+    server: Addr<NewsServer>,
+}
+
+impl From<ItemDto> for proto::Item {
+    fn from(dto: ItemDto) -> Self {
+        proto::Item {
+            id: dto.id,
+            feed: dto.feed,
+            title: dto.title.unwrap_or_default(),
+            link: dto.link.unwrap_or_default(),
+            description: dto.description.unwrap_or_default(),
+            content: dto.content.unwrap_or_default(),
+            summary: dto.summary.unwrap_or_default(),
+            read: dto.read,
+            starred: dto.starred,
+            pub_date: dto.pub_date.map(|date| prost_types::Timestamp {
+                seconds: date.timestamp(),
+                nanos: date.timestamp_subsec_nanos() as i32,
+            }),
+        }
+    }
+}
+
+fn change(value: i32) -> Option<bool> {
+    match proto::Change::from_i32(value) {
+        Some(proto::Change::Set) => Some(true),
+        Some(proto::Change::Clear) => Some(false),
+        _ => None,
+    }
+}
+
+fn internal(e: actix::MailboxError) -> Status {
+    Status::internal(e.to_string())
+}
+
+#[tonic::async_trait]
+impl News for Service {
+    async fn list_items(
+        &self,
+        request: Request<proto::ListItemsRequest>,
+    ) -> Result<Response<proto::ListItemsResponse>, Status> {
+        let request = request.into_inner();
+        let mut items: Vec<ItemDto> = self
+            .server
+            .send(GetItems)
+            .await
+            .map_err(internal)?
+            .into_iter()
+            .filter(|dto| !request.unread_only || !dto.read)
+            .filter(|dto| request.feed.is_empty() || dto.feed == request.feed)
+            .collect();
+        items.sort_by(|a, b| b.pub_date.cmp(&a.pub_date));
+        if request.limit > 0 {
+            items.truncate(request.limit as usize);
+        }
+
+        Ok(Response::new(proto::ListItemsResponse {
+            items: items.into_iter().map(proto::Item::from).collect(),
+        }))
+    }
+
+    async fn update_items(
+        &self,
+        request: Request<proto::UpdateItemsRequest>,
+    ) -> Result<Response<proto::UpdateItemsResponse>, Status> {
+        let request = request.into_inner();
+        self.server
+            .send(UpdateItems {
+                ids: request.ids,
+                read: change(request.read),
+                starred: change(request.starred),
+                at: Utc::now(),
+            })
+            .await
+            .map_err(internal)?;
+        Ok(Response::new(proto::UpdateItemsResponse {}))
+    }
+
+    async fn list_subscriptions(
+        &self,
+        _: Request<proto::ListSubscriptionsRequest>,
+    ) -> Result<Response<proto::ListSubscriptionsResponse>, Status> {
+        let feeds = self.server.send(ListFeeds).await.map_err(internal)?;
+        Ok(Response::new(proto::ListSubscriptionsResponse { feeds }))
+    }
+
+    async fn subscribe(
+        &self,
+        request: Request<proto::SubscribeRequest>,
+    ) -> Result<Response<proto::SubscribeResponse>, Status> {
+        let url = request.into_inner().url;
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(Status::invalid_argument("feed url must be http or https"));
+        }
+        let added = self
+            .server
+            .send(Subscribe { url })
+            .await
+            .map_err(internal)?;
+        Ok(Response::new(proto::SubscribeResponse { added }))
+    }
+}
+
+/// Serve the gRPC API on `addr` until the process exits.
+pub async fn serve(addr: SocketAddr, server: Addr<NewsServer>) -> Result<()> {
+    log::info!("serving grpc on {}", addr);
+    tonic::transport::Server::builder()
+        .add_service(proto::news_server::NewsServer::new(Service { server }))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
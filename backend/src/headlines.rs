@@ -0,0 +1,173 @@
+//! Public latest headlines a personal website can embed, through `GET /public/headlines`.
+//!
+//! The route is disabled unless a `headlines` section is present in the config, which picks the
+//! view the headlines come from. It needs no token, so it only exposes the title, link, source
+//! and date of the items, and throttles each client harder than the rest of the server. Pages
+//! that can't fetch cross-origin JSON pass `?callback=name` to get JSONP instead. Responses carry
+//! an `ETag` and may be cached for `max_age_secs`.
+
+use actix::Addr;
+use actix_web::http::header;
+use actix_web::{error, web, Error, HttpRequest, HttpResponse};
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::limits::RateLimit;
+use crate::protocol::ItemDto;
+use crate::server::{NewsServer, ViewItems};
+
+/// Number of headlines served when the request doesn't say.
+const DEFAULT_COUNT: usize = 10;
+/// Maximum length of a JSONP callback name.
+const MAX_CALLBACK_LEN: usize = 64;
+
+/// Rate limit applied to the headlines on top of the one of the whole server.
+#[derive(Clone)]
+pub struct HeadlinesLimit(RateLimit);
+
+impl HeadlinesLimit {
+    pub fn new(config: &Config) -> Self {
+        let rate_limit = config
+            .headlines
+            .as_ref()
+            .map(|headlines| headlines.rate_limit.clone())
+            .unwrap_or_default();
+        HeadlinesLimit(RateLimit::new(rate_limit))
+    }
+}
+
+/// An item as shown in the widget.
+#[derive(Debug, Serialize)]
+struct Headline {
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    link: Option<String>,
+    /// Title of the feed the item came from, or its URL.
+    source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub_date: Option<DateTime<Utc>>,
+}
+
+impl From<ItemDto> for Headline {
+    fn from(dto: ItemDto) -> Self {
+        Headline {
+            title: dto.title.unwrap_or_else(|| "Untitled".to_string()),
+            link: dto
+                .link
+                .filter(|link| link.starts_with("https://") || link.starts_with("http://")),
+            source: dto.feed_title.unwrap_or(dto.feed),
+            pub_date: dto.pub_date,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct HeadlinesQuery {
+    /// Number of headlines, capped by the config.
+    n: Option<usize>,
+    /// Name of the JSONP callback the headlines are passed to.
+    callback: Option<String>,
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/public/headlines", web::get().to(headlines));
+}
+
+async fn headlines(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    limit: web::Data<HeadlinesLimit>,
+    server: web::Data<Addr<NewsServer>>,
+    query: web::Query<HeadlinesQuery>,
+) -> Result<HttpResponse, Error> {
+    let headlines = config
+        .headlines
+        .as_ref()
+        .ok_or_else(|| error::ErrorNotFound("headlines are disabled"))?;
+    if let Some(addr) = req.peer_addr() {
+        if !limit.0.allow(addr.ip()) {
+            log::warn!("rate limiting headlines for {}", addr.ip());
+            return Ok(HttpResponse::TooManyRequests().finish());
+        }
+    }
+    let callback = match &query.callback {
+        Some(callback) if !valid_callback(callback) => {
+            return Err(error::ErrorBadRequest("invalid callback"));
+        }
+        callback => callback.as_deref(),
+    };
+
+    let items = server
+        .send(ViewItems {
+            view: headlines.view.clone(),
+            limit: query.n.unwrap_or(DEFAULT_COUNT).min(headlines.max),
+        })
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    let items: Vec<Headline> = items.into_iter().map(Headline::from).collect();
+    let json = serde_json::to_string(&items).map_err(error::ErrorInternalServerError)?;
+    let (content_type, body) = match callback {
+        Some(callback) => ("application/javascript; charset=utf-8", jsonp(callback, &json)),
+        None => ("application/json", json),
+    };
+
+    let etag = format!("\"{}\"", blake3::hash(body.as_bytes()).to_hex());
+    let cache_control = format!("public, max-age={}", headlines.max_age_secs);
+    let fresh = req
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |tags| tags.split(',').any(|tag| tag.trim() == etag));
+    let mut response = if fresh {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response
+        .header(header::ETAG, etag)
+        .header(header::CACHE_CONTROL, cache_control)
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+    if fresh {
+        return Ok(response.finish());
+    }
+    Ok(response.content_type(content_type).body(body))
+}
+
+/// Whether `callback` is a plain JavaScript name, e.g. `widget.render`, that can't inject script.
+fn valid_callback(callback: &str) -> bool {
+    !callback.is_empty()
+        && callback.len() <= MAX_CALLBACK_LEN
+        && !callback.starts_with(|c: char| c.is_ascii_digit())
+        && callback.split('.').all(|part| !part.is_empty())
+        && callback
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$' || c == '.')
+}
+
+/// Wrap `json` in a call to `callback`. The leading comment keeps the response from being read
+/// as anything but script, e.g. by content sniffing Flash.
+fn jsonp(callback: &str, json: &str) -> String {
+    format!("/**/{}({});", callback, json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_plain_callbacks() {
+        assert!(valid_callback("render"));
+        assert!(valid_callback("widget.$render_2"));
+        assert!(!valid_callback(""));
+        assert!(!valid_callback("2render"));
+        assert!(!valid_callback("widget..render"));
+        assert!(!valid_callback("alert(1);render"));
+        assert!(!valid_callback(&"a".repeat(MAX_CALLBACK_LEN + 1)));
+    }
+
+    #[test]
+    fn wraps_jsonp() {
+        assert_eq!(jsonp("render", "[]"), "/**/render([]);");
+    }
+}
@@ -0,0 +1,55 @@
+//! Matching of the user's highlight terms in item text.
+
+use serde::Serialize;
+
+/// Byte range of a match in a string.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Find whole-word, ASCII case-insensitive occurrences of `terms` in `text`.
+///
+/// When `markup` is set the text is HTML and only text outside of tags is searched, so that the
+/// frontend can wrap matches without breaking the markup. Overlapping matches are merged and
+/// spans are returned in order.
+pub fn find(text: &str, terms: &[String], markup: bool) -> Vec<Span> {
+    let haystack = text.to_ascii_lowercase();
+    let mut spans: Vec<Span> = Vec::new();
+    for term in terms.iter().map(|term| term.trim().to_ascii_lowercase()) {
+        if term.is_empty() {
+            continue;
+        }
+        for (start, _) in haystack.match_indices(&term) {
+            let end = start + term.len();
+            if is_word_boundary(&haystack, start, end) && !(markup && in_tag(&haystack, start)) {
+                spans.push(Span { start, end });
+            }
+        }
+    }
+
+    spans.sort_by_key(|span| span.start);
+    let mut merged: Vec<Span> = Vec::with_capacity(spans.len());
+    for span in spans {
+        match merged.last_mut() {
+            Some(last) if span.start <= last.end => last.end = last.end.max(span.end),
+            _ => merged.push(span),
+        }
+    }
+    merged
+}
+
+fn is_word_boundary(text: &str, start: usize, end: usize) -> bool {
+    let before = text[..start].chars().next_back();
+    let after = text[end..].chars().next();
+    !before.map_or(false, char::is_alphanumeric) && !after.map_or(false, char::is_alphanumeric)
+}
+
+/// Whether `offset` is inside an HTML tag.
+fn in_tag(html: &str, offset: usize) -> bool {
+    match html[..offset].rfind(|c| c == '<' || c == '>') {
+        Some(idx) => html[idx..].starts_with('<'),
+        None => false,
+    }
+}
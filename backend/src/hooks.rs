@@ -0,0 +1,145 @@
+//! Hooks run on every new item once it is stored, for local automation like appending headlines
+//! to an org-mode file.
+//!
+//! Each hook of `ingest_hooks` gets the JSON of the items, as sent to clients, one at a time and in
+//! the order they were stored. Hooks run on a thread of their own so that slow ones never hold up
+//! fetching; failures are logged and the item skipped.
+
+use anyhow::{anyhow, Result};
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::IngestHookConfig;
+use crate::protocol::ItemDto;
+
+/// How often a running command is checked for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The configured hooks, each fed by its own worker thread.
+pub struct IngestHooks {
+    workers: Vec<Sender<Vec<u8>>>,
+}
+
+impl IngestHooks {
+    /// Start the workers of `hooks`, or return `None` if there are none.
+    pub fn start(hooks: &[IngestHookConfig]) -> Option<Self> {
+        if hooks.is_empty() {
+            return None;
+        }
+        let workers = hooks
+            .iter()
+            .map(|hook| {
+                let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+                let hook = hook.clone();
+                thread::spawn(move || {
+                    for item in receiver {
+                        if let Err(e) = run(&hook, &item) {
+                            log::warn!("ingest hook failed: {}", e);
+                        }
+                    }
+                });
+                sender
+            })
+            .collect();
+        Some(IngestHooks { workers })
+    }
+
+    /// Queue new items for every hook.
+    pub fn ingest(&self, items: &[ItemDto]) {
+        for item in items {
+            let json = match serde_json::to_vec(item) {
+                Ok(json) => json,
+                Err(e) => {
+                    log::error!("failed to serialize {} for ingest hooks: {}", item.id, e);
+                    continue;
+                }
+            };
+            for worker in &self.workers {
+                // A worker only stops if its thread panicked, which was logged already.
+                let _ = worker.send(json.clone());
+            }
+        }
+    }
+}
+
+/// Run `hook` on the JSON of an item.
+fn run(hook: &IngestHookConfig, item: &[u8]) -> Result<()> {
+    match hook {
+        IngestHookConfig::Command {
+            command,
+            timeout_secs,
+        } => run_command(command, item, Duration::from_secs(*timeout_secs)),
+    }
+}
+
+/// Run `command` with `input` on its standard input, killing it once `timeout` elapsed.
+fn run_command(command: &[String], input: &[u8], timeout: Duration) -> Result<()> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow!("empty ingest hook command"))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Dropping stdin closes it, so the command sees the end of its input.
+    if let Some(mut stdin) = child.stdin.take() {
+        // The command may exit without reading its input, which isn't a failure of its own.
+        if let Err(e) = stdin.write_all(input) {
+            log::debug!("failed to write to {}: {}", program, e);
+        }
+    }
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if !status.success() {
+                return Err(anyhow!("{} exited with {}", program, status));
+            }
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            child.kill()?;
+            child.wait()?;
+            return Err(anyhow!("{} timed out", program));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    fn sh(script: &str) -> Vec<String> {
+        vec!["sh".to_string(), "-c".to_string(), script.to_string()]
+    }
+
+    #[test]
+    fn pipes_items_to_commands() {
+        let path = std::env::temp_dir().join(format!("news-hook-{}", rand::random::<u64>()));
+        let command = sh(&format!("cat >> {}", path.display()));
+        run_command(&command, b"{\"title\":\"a\"}\n", Duration::from_secs(5)).unwrap();
+        run_command(&command, b"{\"title\":\"b\"}\n", Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\"title\":\"a\"}\n{\"title\":\"b\"}\n"
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reports_failures() {
+        let error = run_command(&sh("exit 3"), b"{}", Duration::from_secs(5)).unwrap_err();
+        assert!(error.to_string().starts_with("sh exited with"));
+        let error = run_command(&sh("sleep 5"), b"{}", Duration::from_millis(100)).unwrap_err();
+        assert_eq!(error.to_string(), "sh timed out");
+    }
+}
@@ -0,0 +1,287 @@
+//! Downloading and processing of cached images.
+//!
+//! Images are decoded on the rayon pool, where they may be transcoded to WebP, replacing
+//! the original when that makes them smaller.
+//!
+//! Builds without the `images` feature never download images, their image policy allowing none.
+//!
+//! Cached images are served by the `/cache` route, which serves nothing else kept in the cache
+//! directory: files are only served when their content is that of an image.
+
+use actix_web::{error, http::header, web, Error, HttpResponse};
+use anyhow::{anyhow, Result};
+#[cfg(feature = "images")]
+use image::{io::Reader, DynamicImage};
+
+use std::fs;
+#[cfg(feature = "images")]
+use std::io::Cursor;
+use std::path::{Component, Path, PathBuf};
+
+use crate::news::{self, NewsItem};
+#[cfg(feature = "images")]
+use crate::pool;
+use crate::settings::ImagePolicy;
+#[cfg(feature = "images")]
+use crate::settings::{Transcode, TranscodeFormat};
+
+/// Largest image downloaded, in bytes, whatever the image policy allows.
+#[cfg(feature = "images")]
+const MAX_IMAGE_SIZE: u64 = 20 * 1024 * 1024;
+/// Largest number of pixels of an image decoded, rejecting images that decompress to gigabytes.
+#[cfg(feature = "images")]
+const MAX_IMAGE_PIXELS: u64 = 24_000_000;
+
+/// Width and height of the thumbnail the placeholder is computed from.
+#[cfg(feature = "images")]
+const PLACEHOLDER_SOURCE_SIZE: u32 = 32;
+/// Number of blurhash components along the x and y axes.
+#[cfg(feature = "images")]
+const PLACEHOLDER_COMPONENTS: (u32, u32) = (4, 3);
+
+/// Signatures of the image formats served from the cache, at their offset in the file. SVG is
+/// left out as it may carry scripts.
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (8, b"WEBP", "image/webp"),
+    (4, b"ftypavif", "image/avif"),
+];
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/cache/{path:.*}", web::get().to(get_cached));
+}
+
+/// Serve the cached image at `path` in the cache directory.
+async fn get_cached(path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let not_found = || error::ErrorNotFound("no such image");
+    let relative = relative_path(&path).ok_or_else(not_found)?;
+    let file = news::cache_dir()
+        .map_err(error::ErrorInternalServerError)?
+        .join(relative);
+    let bytes = web::block(move || fs::read(file))
+        .await
+        .map_err(|_| not_found())?;
+    let content_type = content_type(&bytes).ok_or_else(not_found)?;
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .body(bytes))
+}
+
+/// Return `path` as a path relative to the cache directory, `None` when it would leave it.
+fn relative_path(path: &str) -> Option<&Path> {
+    let path = Path::new(path);
+    let normal = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    Some(path).filter(|path| normal && path.components().next().is_some())
+}
+
+/// Return the content type of the image `bytes` hold, `None` when they aren't an image.
+fn content_type(bytes: &[u8]) -> Option<&'static str> {
+    SIGNATURES
+        .iter()
+        .find(|(offset, signature, _)| {
+            bytes
+                .get(*offset..)
+                .map_or(false, |b| b.starts_with(signature))
+        })
+        .map(|(_, _, content_type)| *content_type)
+}
+
+/// Return the blurhash placeholder of a cached image.
+///
+/// The hash is stored next to the image with a `.blurhash` extension so it is only computed
+/// once per image.
+#[cfg(feature = "images")]
+pub fn placeholder(path: &Path) -> Option<String> {
+    let hash_path = path.with_extension("blurhash");
+    if let Ok(hash) = fs::read_to_string(&hash_path) {
+        return Some(hash);
+    }
+
+    let img = match image::open(path) {
+        Ok(img) => img,
+        Err(e) => {
+            log::warn!("failed to open {} for placeholder: {}", path.display(), e);
+            return None;
+        }
+    };
+    let thumbnail = img
+        .thumbnail(PLACEHOLDER_SOURCE_SIZE, PLACEHOLDER_SOURCE_SIZE)
+        .to_rgba();
+    let (width, height) = thumbnail.dimensions();
+    let (x, y) = PLACEHOLDER_COMPONENTS;
+    let hash = blurhash::encode(x, y, width, height, &thumbnail.into_raw());
+
+    if let Err(e) = fs::write(&hash_path, &hash) {
+        log::warn!("failed to store placeholder {}: {}", hash_path.display(), e);
+    }
+    Some(hash)
+}
+
+/// Return the path an image downloaded to `path` is cached at, transcoded or not.
+pub fn cached(path: &Path) -> Option<PathBuf> {
+    std::iter::once(path.with_extension("webp"))
+        .chain(std::iter::once(path.to_path_buf()))
+        .find(|path| path.exists())
+}
+
+/// Images of items in the cache.
+pub trait CachedImage {
+    /// Return the path of the image in the cache, if it was downloaded.
+    fn cached_image(&self) -> Option<PathBuf>;
+    /// Compute the placeholder of the image once it was downloaded.
+    fn cache_placeholder(&mut self);
+}
+
+impl CachedImage for NewsItem {
+    fn cached_image(&self) -> Option<PathBuf> {
+        self.image_path.as_deref().and_then(cached)
+    }
+
+    fn cache_placeholder(&mut self) {
+        // The placeholder is stored next to the original, which transcoding replaces.
+        self.image_placeholder = self
+            .cached_image()
+            .and(self.image_path.as_deref())
+            .and_then(placeholder);
+    }
+}
+
+/// Return the placeholder stored next to a cached image, without computing it.
+#[cfg(not(feature = "images"))]
+pub fn placeholder(path: &Path) -> Option<String> {
+    fs::read_to_string(path.with_extension("blurhash")).ok()
+}
+
+/// Download the image at `url` into `path` unless it is cached already, as `policy` allows.
+#[cfg(feature = "images")]
+pub async fn download(url: &str, path: &Path, policy: &ImagePolicy) -> Result<()> {
+    if cached(path).is_some() {
+        return Ok(());
+    }
+
+    let max_size = policy
+        .max_size()
+        .map_or(MAX_IMAGE_SIZE, |max| max.min(MAX_IMAGE_SIZE));
+    let resp = reqwest::get(url).await?.error_for_status()?;
+    let too_large = || anyhow!("image {} is over the size limit", url);
+    let bytes = news::read_capped(resp, max_size, too_large).await?;
+
+    let url = url.to_string();
+    let path = path.to_path_buf();
+    let transcode = policy.transcode.clone();
+    pool::run(move || {
+        let img = decode(&bytes, &url)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        img.save(&path)?;
+        transcode_image(&path, &img, &transcode)
+    })
+    .await
+}
+
+/// Decode the image `bytes` downloaded from `url`, unless it has too many pixels.
+#[cfg(feature = "images")]
+fn decode(bytes: &[u8], url: &str) -> Result<DynamicImage> {
+    // Only the header is read to get the dimensions.
+    let (width, height) = Reader::new(Cursor::new(bytes))
+        .with_guessed_format()?
+        .into_dimensions()?;
+    let pixels = u64::from(width) * u64::from(height);
+    if pixels > MAX_IMAGE_PIXELS {
+        return Err(anyhow!("image {} has {} pixels, too many", url, pixels));
+    }
+    Ok(image::load_from_memory(bytes)?)
+}
+
+#[cfg(not(feature = "images"))]
+pub async fn download(url: &str, _: &Path, _: &ImagePolicy) -> Result<()> {
+    Err(anyhow!("not downloading {}, built without images", url))
+}
+
+/// Replace the image `img` cached at `path` with its transcoded version if that is smaller.
+#[cfg(feature = "images")]
+fn transcode_image(path: &Path, img: &DynamicImage, settings: &Transcode) -> Result<()> {
+    let ext = match settings.format {
+        TranscodeFormat::Off => return Ok(()),
+        TranscodeFormat::Webp => "webp",
+    };
+    let target = path.with_extension(ext);
+    if target == path {
+        return Ok(());
+    }
+
+    let bytes = encode(img, settings)?;
+    if bytes.len() as u64 >= fs::metadata(path)?.len() {
+        return Ok(());
+    }
+    // Compute the placeholder while the original can still be decoded.
+    placeholder(path);
+    fs::write(&target, bytes)?;
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[cfg(feature = "images")]
+fn encode(img: &DynamicImage, settings: &Transcode) -> Result<Vec<u8>> {
+    let rgba = img.to_rgba();
+    let (width, height) = rgba.dimensions();
+    match settings.format {
+        TranscodeFormat::Off => Err(anyhow!("no format to transcode to")),
+        TranscodeFormat::Webp => {
+            let encoder = webp::Encoder::from_rgba(&rgba, width, height);
+            Ok(encoder.encode(f32::from(settings.quality)).to_vec())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stays_in_the_cache_directory() {
+        assert!(relative_path("example.com/a/b.png").is_some());
+        assert!(relative_path("../news_items.dat").is_none());
+        assert!(relative_path("example.com/../../etc/passwd").is_none());
+        assert!(relative_path("/etc/passwd").is_none());
+        assert!(relative_path("").is_none());
+    }
+
+    #[test]
+    fn only_serves_images() {
+        assert_eq!(content_type(b"\x89PNG\r\n\x1a\n...."), Some("image/png"));
+        assert_eq!(
+            content_type(b"RIFF\x00\x00\x00\x00WEBPVP8 "),
+            Some("image/webp")
+        );
+        assert_eq!(
+            content_type(b"\x00\x00\x00\x1cftypavif"),
+            Some("image/avif")
+        );
+        assert_eq!(content_type(b"<!DOCTYPE html>"), None);
+        assert_eq!(content_type(b"<svg onload=\"alert(1)\">"), None);
+        assert_eq!(content_type(b""), None);
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn rejects_images_too_large_to_decode() {
+        let png = |width, height| {
+            let mut bytes = Vec::new();
+            DynamicImage::new_luma8(width, height)
+                .write_to(&mut bytes, image::ImageOutputFormat::Png)
+                .unwrap();
+            bytes
+        };
+        assert!(decode(&png(16, 16), "small.png").is_ok());
+        // Compressed to a few KiB, but 25 million pixels once decoded.
+        assert!(decode(&png(5000, 5000), "bomb.png").is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! Migration of subscriptions, read history, and starred items from Miniflux and Tiny Tiny RSS.
+//!
+//! Both are queried through their APIs with the credentials given in the request. Items are
+//! matched by link, so read and starred state of items not fetched yet is applied once they are.
+
+use actix::Addr;
+use actix_web::{error, web, Error, HttpRequest, HttpResponse};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::admin;
+use crate::config::Config;
+use crate::server::{Import, NewsServer};
+
+/// Number of entries requested per page.
+const PAGE_SIZE: usize = 200;
+
+/// Where to import from, given as the body of `POST /api/admin/import`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum ImportSource {
+    Miniflux {
+        /// Base URL of the instance, e.g. `https://reader.example.com`.
+        url: String,
+        /// API token created in the Miniflux settings.
+        token: String,
+    },
+    TtRss {
+        /// URL of the instance, e.g. `https://example.com/tt-rss`.
+        url: String,
+        user: String,
+        password: String,
+    },
+}
+
+/// State migrated from another reader.
+#[derive(Debug, Default)]
+pub struct Imported {
+    /// URLs of the subscribed feeds.
+    pub feeds: Vec<String>,
+    /// Links of the items read.
+    pub read: Vec<String>,
+    /// Links of the starred items.
+    pub starred: Vec<String>,
+}
+
+/// Number of feeds and items imported.
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    /// Feeds that weren't subscribed yet.
+    pub feeds: usize,
+    pub read: usize,
+    pub starred: usize,
+}
+
+pub async fn fetch(source: &ImportSource) -> Result<Imported> {
+    match source {
+        ImportSource::Miniflux { url, token } => fetch_miniflux(url, token).await,
+        ImportSource::TtRss {
+            url,
+            user,
+            password,
+        } => fetch_tt_rss(url, user, password).await,
+    }
+}
+
+async fn fetch_miniflux(url: &str, token: &str) -> Result<Imported> {
+    #[derive(Deserialize)]
+    struct Feed {
+        feed_url: String,
+    }
+    #[derive(Deserialize)]
+    struct Entries {
+        total: usize,
+        entries: Vec<Entry>,
+    }
+    #[derive(Deserialize)]
+    struct Entry {
+        url: String,
+        status: String,
+        starred: bool,
+    }
+
+    let client = reqwest::Client::new();
+    let base = url.trim_end_matches('/');
+    let feeds: Vec<Feed> = client
+        .get(&format!("{}/v1/feeds", base))
+        .header("X-Auth-Token", token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let mut imported = Imported {
+        feeds: feeds.into_iter().map(|feed| feed.feed_url).collect(),
+        ..Imported::default()
+    };
+    // Read and starred entries overlap, so walk every entry once.
+    let mut offset = 0;
+    loop {
+        let page: Entries = client
+            .get(&format!("{}/v1/entries", base))
+            .header("X-Auth-Token", token)
+            .query(&[
+                ("limit", PAGE_SIZE.to_string()),
+                ("offset", offset.to_string()),
+                ("order", "id".to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let count = page.entries.len();
+        for entry in page.entries {
+            if entry.status == "read" {
+                imported.read.push(entry.url.clone());
+            }
+            if entry.starred {
+                imported.starred.push(entry.url);
+            }
+        }
+
+        offset += count;
+        if count == 0 || offset >= page.total {
+            break;
+        }
+    }
+    Ok(imported)
+}
+
+async fn fetch_tt_rss(url: &str, user: &str, password: &str) -> Result<Imported> {
+    #[derive(Deserialize)]
+    struct Response<T> {
+        status: u8,
+        content: T,
+    }
+    #[derive(Deserialize)]
+    struct Session {
+        session_id: String,
+    }
+    #[derive(Deserialize)]
+    struct Feed {
+        feed_url: String,
+    }
+    #[derive(Deserialize)]
+    struct Headline {
+        link: String,
+        unread: bool,
+        marked: bool,
+    }
+
+    let client = reqwest::Client::new();
+    let endpoint = format!("{}/api/", url.trim_end_matches('/'));
+    let call = |body: serde_json::Value| {
+        let request = client.post(&endpoint).json(&body);
+        async move {
+            let response: Response<serde_json::Value> =
+                request.send().await?.error_for_status()?.json().await?;
+            if response.status != 0 {
+                return Err(anyhow!("tt-rss error: {}", response.content));
+            }
+            Ok(response.content)
+        }
+    };
+
+    let session: Session = serde_json::from_value(
+        call(json!({ "op": "login", "user": user, "password": password })).await?,
+    )?;
+    let sid = session.session_id;
+
+    // Category -3 holds every feed, including uncategorized ones.
+    let feeds: Vec<Feed> = serde_json::from_value(
+        call(json!({ "sid": sid, "op": "getFeeds", "cat_id": -3, "include_nested": true }))
+            .await?,
+    )?;
+    let mut imported = Imported {
+        feeds: feeds.into_iter().map(|feed| feed.feed_url).collect(),
+        ..Imported::default()
+    };
+
+    // Feed -4 holds every article.
+    let mut skip = 0;
+    loop {
+        let headlines: Vec<Headline> = serde_json::from_value(
+            call(json!({
+                "sid": sid,
+                "op": "getHeadlines",
+                "feed_id": -4,
+                "view_mode": "all_articles",
+                "limit": PAGE_SIZE,
+                "skip": skip,
+            }))
+            .await?,
+        )?;
+        let count = headlines.len();
+        for headline in headlines {
+            if !headline.unread {
+                imported.read.push(headline.link.clone());
+            }
+            if headline.marked {
+                imported.starred.push(headline.link);
+            }
+        }
+
+        skip += count;
+        if count < PAGE_SIZE {
+            break;
+        }
+    }
+
+    let _ = call(json!({ "sid": sid, "op": "logout" })).await;
+    Ok(imported)
+}
+
+pub async fn import(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    source: web::Json<ImportSource>,
+) -> Result<HttpResponse, Error> {
+    admin::authorize(&req, &config)?;
+    let imported = fetch(&source).await.map_err(|e| {
+        log::error!("failed to import: {}", e);
+        error::ErrorBadGateway(e)
+    })?;
+    let summary = server
+        .send(Import(imported))
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(summary))
+}
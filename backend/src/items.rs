@@ -0,0 +1,54 @@
+//! REST listing of items, for clients that don't keep a websocket open.
+
+use actix::Addr;
+use actix_web::{error, http::header, web, Error, HttpResponse};
+use serde::Deserialize;
+
+use crate::server::{NewsServer, QueryItems, UnreadCounts};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(default)]
+pub struct ItemsQuery {
+    /// Leave out read items.
+    pub unread_only: bool,
+    /// Maximum number of items returned, newest first. Every item is returned when unset.
+    pub limit: Option<usize>,
+    /// Only return the items of the feed with this URL.
+    pub feed: Option<String>,
+    /// Only return the items following the one with this id, newest first, to page through the
+    /// items. No item is returned once that one isn't stored anymore.
+    pub after: Option<String>,
+    /// Leave out the description and content of items, keeping their excerpt, for clients
+    /// listing items before they are opened.
+    pub excerpts_only: bool,
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/api/items", web::get().to(get_items))
+        .route("/api/items/unread_counts", web::get().to(get_unread_counts));
+}
+
+async fn get_items(
+    server: web::Data<Addr<NewsServer>>,
+    query: web::Query<ItemsQuery>,
+) -> Result<HttpResponse, Error> {
+    let cached = server
+        .send(QueryItems(query.into_inner()))
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    let mut response = HttpResponse::Ok();
+    // The items are served without waiting for slow feeds, polling again picks up what they bring.
+    if cached.stale {
+        response.header(header::WARNING, "110 - \"Response is Stale\"");
+    }
+    Ok(response.json(cached.items))
+}
+
+/// Number of unread items of each feed.
+async fn get_unread_counts(server: web::Data<Addr<NewsServer>>) -> Result<HttpResponse, Error> {
+    let counts = server
+        .send(UnreadCounts)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(counts))
+}
@@ -0,0 +1,65 @@
+//! Backend of the news app: fetches feeds, keeps the user's state and serves both to clients.
+//!
+//! The `news-backend` binary wires these modules into the HTTP server; they are exposed as a
+//! library so that fuzz targets can drive the parsers directly.
+
+pub mod activity;
+pub mod admin;
+pub mod annotation_export;
+pub mod annotations;
+pub mod anomaly;
+pub mod backup;
+pub mod catch_up;
+pub mod cluster;
+pub mod config;
+pub mod desktop;
+pub mod digest;
+pub mod extract;
+pub mod fanout;
+pub mod flow;
+pub mod folders;
+pub mod grpc;
+pub mod headlines;
+pub mod highlight;
+pub mod hooks;
+pub mod images;
+pub mod import;
+pub mod items;
+pub mod limits;
+pub mod links;
+pub mod media;
+pub mod mutes;
+pub mod news;
+pub mod news_store;
+pub mod nextcloud;
+pub mod outgoing;
+pub mod pdf;
+pub mod plugins;
+pub mod pool;
+pub mod postgres_store;
+pub mod priority;
+pub mod protocol;
+pub mod query_cache;
+pub mod queue;
+pub mod quality;
+pub mod recommend;
+pub mod river;
+pub mod s3;
+pub mod schedule;
+pub mod server;
+pub mod session;
+pub mod settings;
+pub mod shares;
+pub mod snapshot;
+pub mod storage;
+pub mod store;
+pub mod summarize;
+pub mod sync;
+pub mod systemd;
+pub mod tokens;
+pub mod transform;
+pub mod translate;
+pub mod urls;
+pub mod views;
+
+pub use news_core::{charset, diff, sanitize, timezone, typography};
@@ -0,0 +1,159 @@
+//! Connection limits and per-IP request throttling.
+
+use actix_service::{Service, Transform};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::{Error, HttpResponse};
+use futures::future::{ok, Either, Ready};
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use crate::config::RateLimitConfig;
+
+/// How long an idle client's bucket is kept around before it is forgotten.
+const BUCKET_IDLE: Duration = Duration::from_secs(10 * 60);
+
+/// Limit on the number of concurrently open websocket connections.
+#[derive(Clone)]
+pub struct ConnectionLimit {
+    max: usize,
+    open: Arc<AtomicUsize>,
+}
+
+/// Held for the lifetime of a connection; releases its slot when dropped.
+pub struct ConnectionGuard {
+    open: Arc<AtomicUsize>,
+}
+
+impl ConnectionLimit {
+    pub fn new(max: usize) -> Self {
+        ConnectionLimit {
+            max,
+            open: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Take a connection slot, or `None` if all of them are in use.
+    pub fn acquire(&self) -> Option<ConnectionGuard> {
+        let previous = self.open.fetch_add(1, Ordering::SeqCst);
+        if previous >= self.max {
+            self.open.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+
+        Some(ConnectionGuard {
+            open: self.open.clone(),
+        })
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.open.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Middleware applying a token bucket rate limit to each client IP address.
+#[derive(Clone)]
+pub struct RateLimit {
+    config: RateLimitConfig,
+    buckets: Arc<Mutex<HashMap<IpAddr, Bucket>>>,
+}
+
+impl RateLimit {
+    pub fn new(config: RateLimitConfig) -> Self {
+        RateLimit {
+            config,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Take a token from the bucket of `ip`, returning false if it is empty.
+    pub fn allow(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.retain(|_, bucket| now.duration_since(bucket.updated) < BUCKET_IDLE);
+
+        let burst = f64::from(self.config.burst);
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.config.per_second).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+}
+
+impl<S, B> Transform<S> for RateLimit
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RateLimitMiddleware {
+            service,
+            limit: self.clone(),
+        })
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: S,
+    limit: RateLimit,
+}
+
+impl<S, B> Service for RateLimitMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let allowed = match req.peer_addr() {
+            Some(addr) => self.limit.allow(addr.ip()),
+            None => true,
+        };
+
+        if allowed {
+            Either::Left(self.service.call(req))
+        } else {
+            log::warn!("rate limiting {:?}", req.peer_addr());
+            Either::Right(ok(
+                req.into_response(HttpResponse::TooManyRequests().finish().into_body())
+            ))
+        }
+    }
+}
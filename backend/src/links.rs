@@ -0,0 +1,91 @@
+//! Health of the links of saved items and their Wayback Machine copies.
+//!
+//! The links of starred items are checked periodically. Links answering 404 or 410 are flagged as
+//! dead, along with the closest copy of the page the Internet Archive holds, if any. Other failures
+//! such as timeouts may be transient and leave the state of a link unchanged.
+//!
+//! Links of starred items may also be submitted to the Internet Archive as they are starred.
+
+use anyhow::{anyhow, Result};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+
+use std::time::Duration;
+
+/// Endpoint of the Internet Archive availability API.
+const AVAILABILITY_API: &str = "https://archive.org/wayback/available";
+/// Endpoint of the Wayback Machine save API, followed by the URL to save.
+const SAVE_API: &str = "https://web.archive.org/save/";
+const WAYBACK_URL: &str = "https://web.archive.org";
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// Saving a page makes the Wayback Machine crawl it, which takes a while.
+const SAVE_TIMEOUT: Duration = Duration::from_secs(120);
+
+pub fn client() -> Result<Client> {
+    Ok(Client::builder().timeout(TIMEOUT).build()?)
+}
+
+/// Whether `link` is dead, i.e. the page is gone.
+pub async fn is_dead(client: &Client, link: &str) -> Result<bool> {
+    let mut status = client.head(link).send().await?.status();
+    // Plenty of servers don't implement HEAD.
+    if status == StatusCode::METHOD_NOT_ALLOWED || status == StatusCode::NOT_IMPLEMENTED {
+        status = client.get(link).send().await?.status();
+    }
+    Ok(status == StatusCode::NOT_FOUND || status == StatusCode::GONE)
+}
+
+/// Return the URL of the closest copy of `link` in the Wayback Machine.
+pub async fn wayback_copy(client: &Client, link: &str) -> Result<Option<String>> {
+    #[derive(Deserialize)]
+    struct Availability {
+        archived_snapshots: Snapshots,
+    }
+    #[derive(Deserialize)]
+    struct Snapshots {
+        closest: Option<Snapshot>,
+    }
+    #[derive(Deserialize)]
+    struct Snapshot {
+        available: bool,
+        url: String,
+    }
+
+    let availability: Availability = client
+        .get(AVAILABILITY_API)
+        .query(&[("url", link)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(availability
+        .archived_snapshots
+        .closest
+        .filter(|snapshot| snapshot.available)
+        .map(|snapshot| snapshot.url))
+}
+
+/// Ask the Wayback Machine to archive `link`, returning the URL of the archived copy.
+pub async fn save_to_wayback(link: &str) -> Result<String> {
+    let client = Client::builder().timeout(SAVE_TIMEOUT).build()?;
+    let response = client
+        .get(&format!("{}{}", SAVE_API, link))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    // The copy is either given as `Content-Location` or redirected to.
+    if let Some(location) = response
+        .headers()
+        .get(reqwest::header::CONTENT_LOCATION)
+        .and_then(|value| value.to_str().ok())
+    {
+        return Ok(format!("{}{}", WAYBACK_URL, location));
+    }
+    let url = response.url();
+    if url.path().starts_with("/web/") {
+        return Ok(url.to_string());
+    }
+    Err(anyhow!("no archived copy returned for {}", link))
+}
@@ -1,34 +1,166 @@
-mod news;
-
-use std::time::{Duration, Instant};
-
 use actix::prelude::*;
-use actix_web::{middleware, web, App, Error, HttpRequest, HttpResponse, HttpServer};
-use actix_web_actors::ws;
+use actix_cors::Cors;
+use actix_web::middleware::{self, Condition};
+use actix_web::{web, App, HttpServer};
 use fern::colors::{Color, ColoredLevelConfig};
-use json::JsonValue;
 
-/// How often heartbeat pings are sent
-const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
-/// How long before lack of client response causes a timeout
-const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+use std::sync::Arc;
+
+use news_backend::{
+    activity, admin, annotation_export, annotations, backup, catch_up, config, desktop, digest,
+    fanout, folders, grpc, headlines, hooks, images, items, limits, media, mutes, news, news_store,
+    nextcloud, outgoing, pdf, plugins, queue, recommend, server, session, settings, shares,
+    snapshot, summarize, systemd, tokens, translate,
+};
+
+use config::Config;
+use headlines::HeadlinesLimit;
+use limits::{ConnectionLimit, RateLimit};
+use plugins::Plugins;
+use server::NewsServer;
+use settings::SettingsStore;
+use tokens::{TokenAuth, TokenStore};
 
 #[actix_rt::main]
 async fn main() -> std::io::Result<()> {
-    set_up_logging();
+    // The configuration picks the log level, so its errors are logged once logging is set up.
+    let (config, error) = match Config::load() {
+        Ok(config) => (config, None),
+        Err(e) => (Config::default(), Some(e)),
+    };
+    set_up_logging(&config.log_level);
+    if let Some(e) = error {
+        log::error!("failed to load config, using defaults: {}", e);
+    }
+    let connections = ConnectionLimit::new(config.max_connections);
+    let rate_limit = RateLimit::new(config.rate_limit.clone());
+    let headlines_limit = HeadlinesLimit::new(&config);
+    let settings = SettingsStore::load()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    let tokens = TokenStore::load()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    let token_auth = TokenAuth::new(tokens.clone(), &config);
+    let summarizer = summarize::from_config(config.summarizer.as_ref());
+    let notifier = config
+        .desktop_notifications
+        .clone()
+        .map(desktop::DesktopNotifier::new);
+    let cache_cap = config.cache_cap_mb.map(|mb| mb * 1024 * 1024);
+    let db = news_store::from_config(&config.store)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    let fanout = config
+        .redis
+        .as_ref()
+        .map(fanout::Fanout::connect)
+        .transpose()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    let cache_dir = news::cache_dir()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    let media = config
+        .media
+        .clone()
+        .map(|bucket| media::Media::new(bucket, cache_dir.clone()));
+    let plugins = config
+        .plugins
+        .as_ref()
+        .and_then(|plugins| match Plugins::load(plugins) {
+            Ok(plugins) => Some(Arc::new(plugins)),
+            Err(e) => {
+                log::error!("failed to load plugins: {}", e);
+                None
+            }
+        });
+    let news_server = NewsServer::new(
+        config.feeds.clone(),
+        db,
+        settings.get(),
+        summarizer,
+        notifier,
+        fanout,
+        cache_cap,
+    )
+    .with_media(media)
+    .with_hooks(hooks::IngestHooks::start(&config.ingest_hooks))
+    .with_plugins(plugins)
+    .with_fetch_limits(config.fetch_limits)
+    .start();
+    if let Some(grpc_bind) = &config.grpc_bind {
+        let addr = grpc_bind
+            .parse()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let server = news_server.clone();
+        actix_rt::spawn(async move {
+            if let Err(e) = grpc::serve(addr, server).await {
+                log::error!("grpc server failed: {}", e);
+            }
+        });
+    }
+    if let Some(backup) = config.backup.clone() {
+        backup::spawn(backup, news_server.clone());
+    }
+    let bind = config.bind.clone();
 
     // Create Http server with websocket support
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
+        // Only the pages of the allowed origins may call the API, other pages can't even send
+        // requests needing a preflight, e.g. rewriting the settings.
+        let cors = config
+            .allowed_origins
+            .iter()
+            .fold(Cors::new(), |cors, origin| cors.allowed_origin(origin))
+            .finish();
         App::new()
+            .data(config.clone())
+            .data(connections.clone())
+            .data(news_server.clone())
+            .data(settings.clone())
+            .data(tokens.clone())
+            .data(headlines_limit.clone())
+            .wrap(Condition::new(!config.allowed_origins.is_empty(), cors))
+            .wrap(token_auth.clone())
+            .wrap(rate_limit.clone())
+            .wrap(middleware::Compress::default())
             .wrap(middleware::Logger::default())
-            .service(web::resource("/ws/").route(web::get().to(ws_index)))
-    })
-    .bind("127.0.0.1:9001")?
-    .run()
-    .await
+            .configure(activity::configure)
+            .configure(admin::configure)
+            .configure(tokens::configure)
+            .configure(settings::configure)
+            .configure(folders::configure)
+            .configure(items::configure)
+            .configure(annotations::configure)
+            .configure(annotation_export::configure)
+            .configure(digest::configure)
+            .configure(catch_up::configure)
+            .configure(mutes::configure)
+            .configure(queue::configure)
+            .configure(shares::configure)
+            .configure(headlines::configure)
+            .configure(outgoing::configure)
+            .configure(pdf::configure)
+            .configure(snapshot::configure)
+            .configure(recommend::configure)
+            .configure(translate::configure)
+            .configure(nextcloud::configure)
+            .configure(images::configure)
+            .service(web::resource("/ws/").route(web::get().to(session::ws_index)))
+    });
+    let server = match systemd::activated_listener()? {
+        Some(listener) => server.listen(listener)?,
+        None => server.bind(&bind)?,
+    };
+
+    let running = server.run();
+    systemd::notify_ready();
+    let result = running.await;
+    systemd::notify_stopping();
+    result
 }
 
-fn set_up_logging() {
+fn set_up_logging(level: &str) {
+    let level = level.parse().unwrap_or_else(|_| {
+        eprintln!("unknown log level {}, logging everything", level);
+        log::LevelFilter::Trace
+    });
     let colors_line = ColoredLevelConfig::new()
         .error(Color::Red)
         .warn(Color::Yellow)
@@ -51,82 +183,10 @@ fn set_up_logging() {
                 message = message,
             ));
         })
-        .level(log::LevelFilter::Trace)
+        .level(level)
         .chain(std::io::stdout())
         .apply()
         .unwrap();
 
     log::debug!("finished setting up logging! yay!");
 }
-
-async fn ws_index(req: HttpRequest, stream: web::Payload) -> Result<HttpResponse, Error> {
-    ws::start(WebSocket::new(), &req, stream)
-}
-
-struct WebSocket {
-    last_heartbeat: Instant,
-}
-
-impl Actor for WebSocket {
-    type Context = ws::WebsocketContext<Self>;
-
-    /// Method is called on actor start. We start the heartbeat process here.
-    fn started(&mut self, ctx: &mut Self::Context) {
-        self.send_heartbeat(ctx);
-        self.hello_world(ctx);
-    }
-}
-
-/// Handler for `ws::Message`
-impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebSocket {
-    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
-        // process websocket messages
-        log::debug!("WS handle: {:?}", msg);
-        match msg {
-            Ok(ws::Message::Ping(msg)) => {
-                self.last_heartbeat = Instant::now();
-                ctx.pong(&msg);
-            }
-            Ok(ws::Message::Pong(_)) => {
-                self.last_heartbeat = Instant::now();
-            }
-            Ok(ws::Message::Text(text)) => ctx.text(text),
-            Ok(ws::Message::Binary(bin)) => ctx.binary(bin),
-            Ok(ws::Message::Close(_)) => {
-                ctx.stop();
-            }
-            _ => ctx.stop(),
-        }
-    }
-}
-
-impl WebSocket {
-    fn new() -> Self {
-        Self {
-            last_heartbeat: Instant::now(),
-        }
-    }
-
-    /// Send a heartbeat to the client.
-    fn send_heartbeat(&self, ctx: &mut <Self as Actor>::Context) {
-        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
-            if Instant::now().duration_since(act.last_heartbeat) > CLIENT_TIMEOUT {
-                // Heartbeat timed out
-                log::info!("Websocket client heartbeat failed, disconnecting!");
-                ctx.stop();
-                return;
-            }
-
-            ctx.ping(b"{\"pong\": 0}");
-        });
-    }
-
-    /// Send hello world data to client for testing.
-    fn hello_world(&self, ctx: &mut <Self as Actor>::Context) {
-        ctx.run_interval(Duration::from_secs(3), |_act, ctx| {
-            log::info!("Sending hello world");
-            let msg = "{\"hello\": \"world\"}";
-            ctx.text(msg);
-        });
-    }
-}
@@ -0,0 +1,160 @@
+//! Mirroring of the cached images and snapshots to a bucket of an S3 compatible service.
+//!
+//! With a `media` section in the config, every image and snapshot written to the cache directory
+//! is uploaded to the bucket, under its path relative to the cache directory, and the files
+//! missing from the cache directory are downloaded back in the background on start. Containers
+//! then keep the cache on ephemeral storage and need no persistent volume for media. Evicting
+//! files from the cache directory leaves them in the bucket, whose lifecycle rules decide how long
+//! they are kept.
+
+use anyhow::{anyhow, Result};
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::config::S3Config;
+use crate::s3::Bucket;
+
+/// The bucket media are mirrored to.
+#[derive(Clone)]
+pub struct Media {
+    bucket: Bucket,
+    cache_dir: PathBuf,
+    /// Keys known to be in the bucket, which aren't uploaded again.
+    mirrored: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Media {
+    pub fn new(config: S3Config, cache_dir: PathBuf) -> Self {
+        Media {
+            bucket: Bucket::new(config),
+            cache_dir,
+            mirrored: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Upload the files at `paths` in the cache directory that exist and aren't mirrored yet,
+    /// returning how many were.
+    pub async fn upload(&self, paths: Vec<PathBuf>) -> Result<usize> {
+        let mut uploaded = 0;
+        for path in paths {
+            let key = match key(&self.cache_dir, &path) {
+                Some(key) if path.is_file() => key,
+                _ => continue,
+            };
+            if self.mirrored.lock().unwrap().contains(&key) {
+                continue;
+            }
+            let body = fs::read(&path)?;
+            self.bucket.put(&key, body, content_type(&key)).await?;
+            self.mirrored.lock().unwrap().insert(key);
+            uploaded += 1;
+        }
+        Ok(uploaded)
+    }
+
+    /// Download the mirrored files missing from the cache directory, returning how many were.
+    pub async fn restore(&self) -> Result<usize> {
+        let keys = self.bucket.list("").await?;
+        self.mirrored.lock().unwrap().extend(keys.iter().cloned());
+        let mut restored = 0;
+        for key in keys {
+            let path = match path(&self.cache_dir, &key) {
+                Some(path) => path,
+                None => {
+                    log::warn!("ignoring media {} outside the cache directory", key);
+                    continue;
+                }
+            };
+            if path.exists() {
+                continue;
+            }
+            let body = match self.bucket.get(&key).await? {
+                Some(body) => body,
+                None => continue,
+            };
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, body).map_err(|e| anyhow!("failed to write {}: {}", key, e))?;
+            restored += 1;
+        }
+        Ok(restored)
+    }
+}
+
+/// Return the files an image cached at `path` may be stored in: the original, its transcoded
+/// versions and its placeholder.
+pub fn image_files(path: &Path) -> Vec<PathBuf> {
+    let mut files = vec![path.to_path_buf()];
+    files.extend(
+        ["webp", "blurhash"]
+            .iter()
+            .map(|ext| path.with_extension(ext)),
+    );
+    files
+}
+
+/// Return the key of the file at `path` in the cache directory.
+fn key(cache_dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(cache_dir).ok()?;
+    let parts: Option<Vec<&str>> = relative
+        .components()
+        .map(|component| match component {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect();
+    Some(parts?.join("/")).filter(|key| !key.is_empty())
+}
+
+/// Return the path of the file of `key` in the cache directory, or `None` if the key would
+/// escape it.
+fn path(cache_dir: &Path, key: &str) -> Option<PathBuf> {
+    let relative = Path::new(key);
+    let inside = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    Some(cache_dir.join(relative)).filter(|_| inside && !key.is_empty())
+}
+
+fn content_type(key: &str) -> &'static str {
+    match Path::new(key).extension().and_then(|ext| ext.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("blurhash") => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_paths_to_keys() {
+        let cache_dir = Path::new("/var/cache/news");
+        let image = Path::new("/var/cache/news/images/ab/cd.png");
+        assert_eq!(key(cache_dir, image).as_deref(), Some("images/ab/cd.png"));
+        assert_eq!(key(cache_dir, Path::new("/tmp/cd.png")), None);
+        assert_eq!(path(cache_dir, "images/ab/cd.png").as_deref(), Some(image));
+        assert_eq!(path(cache_dir, "../etc/passwd"), None);
+        assert_eq!(path(cache_dir, "/etc/passwd"), None);
+    }
+
+    #[test]
+    fn lists_image_files() {
+        let files = image_files(Path::new("/cache/a.png"));
+        let files: Vec<&str> = files.iter().map(|path| path.to_str().unwrap()).collect();
+        assert_eq!(
+            files,
+            ["/cache/a.png", "/cache/a.webp", "/cache/a.blurhash"]
+        );
+    }
+}
@@ -0,0 +1,51 @@
+//! Temporary mutes of feeds and keywords through `GET/PUT /api/mutes`.
+//!
+//! Items of a muted feed or whose title contains a muted keyword are kept out of the unread list
+//! until the mute expires, which the server checks along with snoozed items.
+
+use actix::Addr;
+use actix_web::{error, web, Error, HttpResponse};
+
+use crate::server::{GetMutes, NewsServer, SetMutes};
+use crate::store::{Mute, MuteTarget};
+
+/// Maximum number of active mutes.
+const MAX_MUTES: usize = 200;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/api/mutes")
+            .route(web::get().to(get_mutes))
+            .route(web::put().to(put_mutes)),
+    );
+}
+
+async fn get_mutes(server: web::Data<Addr<NewsServer>>) -> Result<HttpResponse, Error> {
+    let mutes = server
+        .send(GetMutes)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(mutes))
+}
+
+async fn put_mutes(
+    server: web::Data<Addr<NewsServer>>,
+    mutes: web::Json<Vec<Mute>>,
+) -> Result<HttpResponse, Error> {
+    let mutes = mutes.into_inner();
+    if mutes.len() > MAX_MUTES {
+        return Err(error::ErrorBadRequest("too many mutes"));
+    }
+    let empty = mutes.iter().any(|mute| match &mute.target {
+        MuteTarget::Feed(value) | MuteTarget::Keyword(value) => value.trim().is_empty(),
+    });
+    if empty {
+        return Err(error::ErrorBadRequest("mutes need a feed or keyword"));
+    }
+
+    server
+        .send(SetMutes(mutes.clone()))
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(mutes))
+}
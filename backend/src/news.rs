@@ -1,245 +1,420 @@
-use anyhow::{Error, Result};
-use chrono::prelude::*;
+use anyhow::{anyhow, Error, Result};
 use directories::ProjectDirs;
-use futures::future::{err, join_all, ok};
-use futures::prelude::*;
+use futures::future::join_all;
+use futures::stream::{self, StreamExt};
+#[cfg(feature = "rayon")]
 use rayon::prelude::*;
-use rss::Channel;
-use serde::{Deserialize, Serialize};
 
-use std::cmp::Ordering;
-use std::collections::{BTreeSet, HashMap};
-use std::fmt;
+use std::collections::HashSet;
 use std::fs;
-use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+pub use news_core::item::{
+    merge_duplicates, parse_feed, publish_date, Changes, ItemFields, NewsItem, Revision, Source,
+};
 pub use rss;
 
-pub async fn read_news() -> Result<Vec<NewsItem>> {
-    let proj_dirs = ProjectDirs::from("com", "Big Endian", "News App")
-        .ok_or(Error::msg("Failure to get project directory."))?;
-
-    let feeds = [
-        "http://feeds.arstechnica.com/arstechnica/index",
-        "https://boingboing.net/feed",
-        "http://rss.slashdot.org/Slashdot/slashdotMain",
-        "https://hackaday.com/blog/feed/",
-        "https://www.phoronix.com/rss.php",
-        //"https://www.theatlantic.com/feed/all/",
-        "https://www.newyorker.com/feed/everything",
-    ];
-    let channels: Vec<rss::Channel> = feeds
-        .iter()
-        .map(|url| Channel::from_url(url).unwrap())
-        .collect();
-    log::trace!("loaded channels.");
-    let items: Vec<&rss::Item> = channels.iter().map(|ch| ch.items()).flatten().collect();
-
-    let cache_dir = proj_dirs.cache_dir();
-    fs::create_dir_all(cache_dir)?;
-
-    log::trace!("done gathering items");
-    let news_items: Vec<NewsItem> = items
-        .into_iter()
-        .map(|x| NewsItem::new(x.clone(), &cache_dir))
-        .collect();
-
-    let image_urls: Vec<String> = news_items
-        .iter()
-        .filter_map(|item| item.image_url())
-        .collect();
-    let dl_futures = image_urls.iter().map(|image_url| {
-        // Create path we'll use to store associated image.
-        let path = image_url.replace("https://", "");
-        let path = path.replace("http://", "");
-        let path = cache_dir.join(path);
-
-        reqwest::get(image_url)
-            .and_then(|resp| resp.bytes())
-            .and_then(move |bytes| {
-                if !path.exists() {
-                    let img = image::load_from_memory(&bytes).unwrap();
-                    fs::create_dir_all(path.parent().unwrap()).unwrap();
-                    img.save(&path).unwrap();
+use crate::config::FetchLimits;
+use crate::extract;
+use crate::images;
+#[cfg(feature = "images")]
+use crate::images::CachedImage;
+use crate::plugins::Plugins;
+use crate::pool;
+use crate::settings::{ContentMode, ImageCaching, ImagePolicy};
+use crate::transform;
+use crate::urls;
+
+/// Default set of feeds used when none are configured.
+pub const DEFAULT_FEEDS: &[&str] = &[
+    "http://feeds.arstechnica.com/arstechnica/index",
+    "https://boingboing.net/feed",
+    "http://rss.slashdot.org/Slashdot/slashdotMain",
+    "https://hackaday.com/blog/feed/",
+    "https://www.phoronix.com/rss.php",
+    //"https://www.theatlantic.com/feed/all/",
+    "https://www.newyorker.com/feed/everything",
+];
+
+/// Requests made at once for the items of a feed, to unshorten their links or extract their
+/// articles.
+const MAX_CONCURRENT_REQUESTS: usize = 4;
+
+pub fn project_dirs() -> Result<ProjectDirs> {
+    ProjectDirs::from("com", "Big Endian", "News App")
+        .ok_or(Error::msg("Failure to get project directory."))
+}
+
+/// Return the cache directory, creating it if necessary.
+pub fn cache_dir() -> Result<PathBuf> {
+    let cache_dir = project_dirs()?.cache_dir().to_path_buf();
+    fs::create_dir_all(&cache_dir)?;
+    Ok(cache_dir)
+}
+
+/// Write the file at `path` with `write`, aside then renamed over it, so that a crash never leaves
+/// it truncated.
+pub fn write_file<F>(path: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&mut io::BufWriter<fs::File>) -> Result<()>,
+{
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("no file name in {}", path.display()))?;
+    let partial = path.with_file_name(format!(".{}.partial", name.to_string_lossy()));
+    let mut file = io::BufWriter::new(fs::File::create(&partial)?);
+    write(&mut file)?;
+    file.flush()?;
+    file.get_ref().sync_all()?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Fetch a single feed and download the images of its items into the cache as `policy` allows.
+///
+/// When `content` asks for the full content, the article of each item is extracted too. With
+/// `unshorten`, links to shorteners and feed proxies are replaced by the pages they redirect to.
+/// Both are skipped for the items whose id is `known`, which were stored already.
+/// Feeds a plugin adapts are read by the plugin instead of parsed, and the items of every feed go
+/// through the filters of `plugins`, then through the Lua `transform` of the feed if any.
+///
+/// Feeds larger than the `limits` fail to fetch, and only their first items up to the `limits`
+/// are kept.
+pub async fn fetch_feed(
+    url: &str,
+    content: ContentMode,
+    policy: &ImagePolicy,
+    unshorten: bool,
+    plugins: Option<Arc<Plugins>>,
+    transform: Option<String>,
+    limits: FetchLimits,
+    known: &HashSet<String>,
+) -> Result<Vec<NewsItem>> {
+    let cache_dir = cache_dir()?;
+    let response = reqwest::get(url).await?.error_for_status()?;
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = read_body(response, url, &limits).await?;
+    log::trace!("loaded channel {}", url);
+
+    let feed = url.to_string();
+    let mut news_items = pool::run(move || {
+        let adapted = match &plugins {
+            Some(plugins) => plugins.adapt(&feed, &body, &cache_dir)?,
+            None => None,
+        };
+        let mut items = match adapted {
+            Some(items) => items,
+            None => parse_feed(&body, content_type.as_deref(), &feed, &cache_dir)?,
+        };
+        truncate(&mut items, &feed, &limits);
+        let items = match &plugins {
+            Some(plugins) => plugins.filter(&feed, items, &cache_dir),
+            None => items,
+        };
+        Ok(match &transform {
+            Some(script) => transform::apply(script, &feed, items, &cache_dir),
+            None => items,
+        })
+    })
+    .await?;
+
+    if unshorten {
+        let client = urls::client()?;
+        let client = &client;
+        new_items(&mut news_items, known)
+            .for_each_concurrent(MAX_CONCURRENT_REQUESTS, |item| async move {
+                let target = match item.link() {
+                    Some(link) => urls::unshorten(client, link).await,
+                    None => None,
+                };
+                if let Some(target) = target {
+                    item.set_link(target);
                 }
+            })
+            .await;
+    }
 
-                ok(())
+    if content == ContentMode::FullContent {
+        new_items(&mut news_items, known)
+            .for_each_concurrent(MAX_CONCURRENT_REQUESTS, |item| async move {
+                item.content = extract::full_content(item).await;
             })
-    });
-    join_all(dl_futures).await;
-
-    // TODO rework this.
-    let mut existing_items: Vec<NewsItem> =
-        if let Ok(file) = fs::File::open(proj_dirs.cache_dir().join("news_items.dat")) {
-            log::trace!("opened news_items.dat file");
-            bincode::deserialize_from(file)?
-        } else {
-            Vec::new()
-        };
+            .await;
+    }
 
-    existing_items.extend(news_items);
-    // Take all of the existing items and store them in a set to de-duplicate them.
-    let mut items_set = BTreeSet::new();
-    existing_items.into_iter().for_each(|item| {
-        items_set.insert(item.clone());
-    });
-    let mut existing_items: Vec<&NewsItem> = items_set.iter().collect();
-    let file = fs::File::create(proj_dirs.cache_dir().join("news_items.dat"))?;
-    bincode::serialize_into(file, &existing_items)?;
+    if policy.allows(ImageCaching::All) {
+        let downloads = news_items.iter().filter_map(|item| {
+            let (url, path) = (item.image_url()?, item.image_path.clone()?);
+            Some(async move {
+                if let Err(e) = images::download(&url, &path, policy).await {
+                    log::warn!("failed to cache image: {}", e);
+                }
+            })
+        });
+        join_all(downloads).await;
+    }
 
-    existing_items.reverse();
-    log::trace!("combined all the items together");
+    // Computing placeholders decodes the images.
+    #[cfg(feature = "images")]
+    let news_items = pool::run(move || {
+        news_items.par_iter_mut().for_each(NewsItem::cache_placeholder);
+        Ok(news_items)
+    })
+    .await?;
+    Ok(news_items)
+}
 
-    Ok(items_set.into_iter().collect())
+/// Keep only the first items of `feed` up to the `limits`.
+fn truncate(items: &mut Vec<NewsItem>, feed: &str, limits: &FetchLimits) {
+    if items.len() > limits.max_items {
+        log::warn!(
+            "keeping the first {} of the {} items of {}",
+            limits.max_items,
+            items.len(),
+            feed
+        );
+        items.truncate(limits.max_items);
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct NewsItem {
-    item: rss::Item,
-    pub image_path: Option<PathBuf>,
-    pub_date: Option<chrono::DateTime<chrono::FixedOffset>>,
+/// Stream of the `items` whose id isn't `known`.
+fn new_items<'a>(
+    items: &'a mut [NewsItem],
+    known: &'a HashSet<String>,
+) -> impl stream::Stream<Item = &'a mut NewsItem> {
+    stream::iter(items.iter_mut().filter(move |item| !known.contains(&item.id())))
 }
 
-impl fmt::Display for NewsItem {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{} - {}",
-            self.title().unwrap_or(""),
-            self.description().unwrap_or("")
+/// Read the body of the `response` serving the feed at `url`, aborting once it grows past the
+/// size `limits` allow.
+async fn read_body(
+    response: reqwest::Response,
+    url: &str,
+    limits: &FetchLimits,
+) -> Result<Vec<u8>> {
+    let too_large = || {
+        anyhow!(
+            "feed {} is larger than the limit of {} MiB",
+            url,
+            limits.max_feed_size_mb
         )
-    }
+    };
+    read_capped(response, limits.max_feed_size(), too_large).await
 }
 
-impl PartialEq for NewsItem {
-    fn eq(&self, other: &Self) -> bool {
-        self.item.title() == other.item.title()
-            && self.item.description() == other.item.description()
-            && self.item.pub_date() == other.item.pub_date()
+/// Read the body of `response`, failing with `too_large` as soon as it grows past `max` bytes
+/// so that it is never held in memory whole.
+pub async fn read_capped<E>(
+    mut response: reqwest::Response,
+    max: u64,
+    too_large: E,
+) -> Result<Vec<u8>>
+where
+    E: Fn() -> Error,
+{
+    // The length announced may be missing or wrong, so check the body as well.
+    if response.content_length().map_or(false, |len| len > max) {
+        return Err(too_large());
     }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if (body.len() + chunk.len()) as u64 > max {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
 }
 
-trait NeededData {
-    fn image_url(&self) -> Option<String>;
-    fn publish_date(&self) -> Option<chrono::DateTime<chrono::FixedOffset>>;
-    fn digest(&self) -> blake3::Hash;
-}
-
-impl NeededData for rss::Item {
-    fn digest(&self) -> blake3::Hash {
-        let mut hasher = blake3::Hasher::new();
-        hasher.update(self.title().unwrap().as_bytes());
-        hasher.update(self.description().unwrap().as_bytes());
-        hasher.finalize()
+/// Golden tests of how the feeds of `tests/fixtures/feeds` normalize into items, along with tests
+/// of how items merge. Run them with `UPDATE_GOLDEN=1` to rewrite the golden files after an
+/// intended change.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use news_core::test_util::item;
+    use serde::Serialize;
+    use serde_json::Value;
+
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// What a fixture item normalizes into.
+    #[derive(Serialize)]
+    struct Golden<'a> {
+        title: Option<&'a str>,
+        link: Option<&'a str>,
+        guid: Option<&'a str>,
+        description: Option<&'a str>,
+        content: Option<&'a str>,
+        pub_date: Option<String>,
+        image_url: Option<String>,
+        image_path: Option<&'a Path>,
     }
 
-    fn publish_date(&self) -> Option<DateTime<FixedOffset>> {
-        let pub_date = self.pub_date();
-        if let Some(date) = pub_date {
-            return DateTime::parse_from_str(date, "%a, %d %b %Y %H:%M:%S %z").ok();
-        }
-
-        if let Some(dc) = self.dublin_core_ext() {
-            let dates = dc.dates();
-            if !dates.is_empty() {
-                //println!("Dates: {:#?}", dates[0].parse::<DateTime<Utc>>());
-                return dates[0].parse::<DateTime<FixedOffset>>().ok();
+    impl<'a> Golden<'a> {
+        fn new(item: &'a NewsItem) -> Self {
+            Golden {
+                title: item.title(),
+                link: item.link(),
+                guid: item.guid(),
+                description: item.description(),
+                content: item.feed_content(),
+                pub_date: item.pub_date().map(|date| date.to_rfc3339()),
+                image_url: item.image_url(),
+                image_path: item.image_path.as_deref(),
             }
         }
+    }
 
-        None
+    #[test]
+    fn writes_files_atomically() {
+        let dir = std::env::temp_dir().join(format!("news-write-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.json");
+        fs::write(&path, "old").unwrap();
+        let failed = write_file(&path, |file| {
+            file.write_all(b"half")?;
+            Err(anyhow!("crashed"))
+        });
+        assert!(failed.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        write_file(&path, |file| Ok(file.write_all(b"new")?)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        fs::remove_dir_all(&dir).unwrap();
     }
 
-    fn image_url(&self) -> Option<String> {
-        if let Some(media) = self.extensions().get("media") {
-            if let Some(thumbnail) = media.get("thumbnail") {
-                if let Some(thumbnail) = thumbnail.first() {
-                    if let Some(url) = thumbnail.attrs().get("url") {
-                        return Some(url.to_owned());
-                    }
-                }
+    /// Answer the first request on a local port with `response`, returning the URL requested.
+    fn serve_once(response: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/feed.xml", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let _ = stream.read(&mut [0; 1024]);
+                let _ = stream.write_all(&response);
             }
-        }
-        None
+        });
+        url
     }
-}
 
-impl NewsItem {
-    pub fn new(item: rss::Item, cache_dir: &Path) -> Self {
-        let pub_date = item.publish_date();
+    async fn fetch_body(response: Vec<u8>, limits: &FetchLimits) -> Result<Vec<u8>> {
+        let url = serve_once(response);
+        read_body(reqwest::get(&url).await?, &url, limits).await
+    }
 
-        let image_path = item.image_url().map(|image_url| {
-            let image_path = image_url.replace("https://", "");
-            let image_path = image_path.replace("http://", "");
-            let image_path = cache_dir.join(image_path);
-            image_path
-        });
+    #[actix_rt::test]
+    async fn aborts_feeds_past_the_size_limit() {
+        let limits = FetchLimits {
+            max_feed_size_mb: 1,
+            ..FetchLimits::default()
+        };
+        let max = limits.max_feed_size() as usize;
 
-        NewsItem {
-            item,
-            pub_date,
-            image_path,
-        }
+        let fits = b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nfeed".to_vec();
+        assert_eq!(fetch_body(fits, &limits).await.unwrap(), b"feed");
+
+        // Announced too large, the body isn't even read.
+        let announced = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", max + 1);
+        assert!(fetch_body(announced.into_bytes(), &limits).await.is_err());
+
+        // Without a length, the body is read until it grows too large.
+        let mut unannounced = b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n".to_vec();
+        unannounced.resize(unannounced.len() + max + 1, b' ');
+        assert!(fetch_body(unannounced, &limits).await.is_err());
     }
 
-    pub fn pub_date(&self) -> Option<chrono::DateTime<chrono::FixedOffset>> {
-        self.pub_date
+    #[test]
+    fn keeps_the_first_items() {
+        let limits = FetchLimits {
+            max_items: 2,
+            ..FetchLimits::default()
+        };
+        let mut items: Vec<NewsItem> = ["First", "Second", "Third"]
+            .iter()
+            .map(|title| item(title).build())
+            .collect();
+        truncate(&mut items, "https://example.com/feed", &limits);
+        let titles: Vec<&str> = items.iter().filter_map(NewsItem::title).collect();
+        assert_eq!(titles, ["First", "Second"]);
     }
 
-    pub fn digest(&self) -> blake3::Hash {
-        self.item.digest()
+    fn check(name: &str) {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/feeds");
+        let body = fs::read(dir.join(format!("{}.xml", name))).unwrap();
+        let items = parse_feed(&body, None, "https://example.com/feed", Path::new("/cache"))
+            .unwrap_or_else(|e| panic!("failed to parse {}: {}", name, e));
+        let goldens: Vec<Golden> = items.iter().map(Golden::new).collect();
+        let actual = serde_json::to_value(&goldens).unwrap();
+
+        let path = dir.join(format!("{}.json", name));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            let json = serde_json::to_string_pretty(&actual).unwrap();
+            fs::write(&path, json + "\n").unwrap();
+            return;
+        }
+        let expected: Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(actual, expected, "{} changed", path.display());
     }
 
-    pub fn title(&self) -> Option<&str> {
-        self.item.title()
+    #[test]
+    fn missing_dates() {
+        check("missing_dates");
     }
 
-    pub fn description(&self) -> Option<&str> {
-        self.item.description()
+    #[test]
+    fn cdata() {
+        check("cdata");
     }
 
-    pub fn image_url(&self) -> Option<String> {
-        self.item.image_url()
+    #[test]
+    fn latin1() {
+        check("latin1");
     }
-}
 
-impl Hash for NewsItem {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.item.title().hash(state);
-        self.item.description().hash(state);
-        self.item.pub_date().hash(state);
+    #[test]
+    fn invalid_utf8() {
+        check("invalid_utf8");
+    }
+
+    #[test]
+    fn mislabeled_latin1() {
+        check("mislabeled_latin1");
     }
-}
 
-impl Eq for NewsItem {}
+    #[test]
+    fn relative_urls() {
+        check("relative_urls");
+    }
 
-impl Ord for NewsItem {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.pub_date().cmp(&other.pub_date())
+    #[test]
+    fn tracking() {
+        check("tracking");
     }
-}
 
-impl PartialOrd for NewsItem {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+    #[test]
+    fn entities() {
+        check("entities");
     }
-}
 
-/// Return the publish date for the item.
-pub fn publish_date(item: &rss::Item) -> Option<DateTime<FixedOffset>> {
-    let pub_date = item.pub_date();
-    if let Some(date) = pub_date {
-        return DateTime::parse_from_str(date, "%a, %d %b %Y %H:%M:%S %z").ok();
+    #[test]
+    fn rss1() {
+        check("rss1");
     }
 
-    if let Some(dc) = item.dublin_core_ext() {
-        let dates = dc.dates();
-        if !dates.is_empty() {
-            //println!("Dates: {:#?}", dates[0].parse::<DateTime<Utc>>());
-            return dates[0].parse::<DateTime<FixedOffset>>().ok();
-        }
+    #[test]
+    fn huge_enclosure() {
+        check("huge_enclosure");
     }
 
-    None
+    #[test]
+    fn minimal() {
+        check("minimal");
+    }
 }
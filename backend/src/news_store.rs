@@ -0,0 +1,265 @@
+//! Backends persisting the stored items and the user's state.
+//!
+//! The `NewsServer` actor keeps items, subscribed feeds and state in memory and hands them to a
+//! `NewsStore` to persist whenever they change. Queries on the items go through the store too,
+//! their answers cached by the server until its next write. Files in the project directories are
+//! the default, while the memory backend keeps nothing across restarts and suits tests. Instances
+//! sharing a PostgreSQL database are described in `postgres_store`.
+//!
+//! There is no SQLite backend: PostgreSQL covers deployments outgrowing the files, and SQLite
+//! would add a C library to the build for a single instance the files serve well.
+
+use anyhow::Result;
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::config::StoreConfig;
+use crate::items::ItemsQuery;
+use crate::news::{self, NewsItem};
+use crate::postgres_store::PostgresStore;
+use crate::store::Store;
+
+/// Persists the items and state of the `NewsServer`.
+pub trait NewsStore: Send {
+    /// Load every stored item.
+    fn load_items(&self) -> Result<BTreeSet<NewsItem>>;
+    /// Replace the stored items.
+    fn save_items(&self, items: &BTreeSet<NewsItem>) -> Result<()>;
+    /// Load the user's state, including the feeds subscribed to at runtime.
+    fn load_state(&self) -> Result<Store>;
+    /// Replace the user's state.
+    fn save_state(&self, store: &Store) -> Result<()>;
+
+    /// Return the stored items answering `query`, newest first, e.g. the latest ones or those of
+    /// a feed.
+    fn query_items(&self, query: &ItemsQuery) -> Result<Vec<NewsItem>>;
+    /// Return the number of unread items of each feed, counting none for those of `feeds` that
+    /// have no unread item.
+    fn unread_counts(&self, feeds: &[String]) -> Result<HashMap<String, usize>>;
+
+    /// Claim the scheduled polls of `feeds`, returning the ones this instance should poll, i.e.
+    /// those no other instance sharing the store polled within `fresh_for`.
+    fn claim_feeds(&self, feeds: &[String], _fresh_for: chrono::Duration) -> Result<Vec<String>> {
+        Ok(feeds.to_vec())
+    }
+}
+
+/// Return the store configured.
+pub fn from_config(config: &StoreConfig) -> Result<Box<dyn NewsStore>> {
+    Ok(match config {
+        StoreConfig::File { dir } => Box::new(FileStore::open(dir.as_deref())?),
+        StoreConfig::Memory => Box::new(MemoryStore::default()),
+        StoreConfig::Postgres { url } => Box::new(PostgresStore::connect(url)?),
+    })
+}
+
+/// Keeps the items in `news_items.dat` in the cache directory and the state in `store.json` in
+/// the data directory, or both in the directory configured.
+pub struct FileStore {
+    items: PathBuf,
+    state: PathBuf,
+    /// What was last loaded or saved, answering the queries.
+    memory: MemoryStore,
+}
+
+impl FileStore {
+    /// Open the store in `dir` or else the project directories, creating them if necessary.
+    pub fn open(dir: Option<&Path>) -> Result<FileStore> {
+        let (data_dir, cache_dir) = match dir {
+            Some(dir) => (dir.to_path_buf(), dir.to_path_buf()),
+            None => (news::project_dirs()?.data_dir().to_path_buf(), news::cache_dir()?),
+        };
+        fs::create_dir_all(&data_dir)?;
+        fs::create_dir_all(&cache_dir)?;
+        Ok(FileStore {
+            items: cache_dir.join("news_items.dat"),
+            state: data_dir.join("store.json"),
+            memory: MemoryStore::default(),
+        })
+    }
+}
+
+impl NewsStore for FileStore {
+    fn load_items(&self) -> Result<BTreeSet<NewsItem>> {
+        let items: Vec<NewsItem> = match fs::File::open(&self.items) {
+            Ok(file) => bincode::deserialize_from(file)?,
+            Err(_) => Vec::new(),
+        };
+        // Store the items in a set to de-duplicate them.
+        let items = items.into_iter().collect();
+        self.memory.save_items(&items)?;
+        Ok(items)
+    }
+
+    fn save_items(&self, items: &BTreeSet<NewsItem>) -> Result<()> {
+        let list: Vec<&NewsItem> = items.iter().collect();
+        news::write_file(&self.items, |file| {
+            Ok(bincode::serialize_into(file, &list)?)
+        })?;
+        self.memory.save_items(items)
+    }
+
+    fn load_state(&self) -> Result<Store> {
+        let store = match fs::File::open(&self.state) {
+            Ok(file) => serde_json::from_reader(file)?,
+            Err(_) => Store::default(),
+        };
+        self.memory.save_state(&store)?;
+        Ok(store)
+    }
+
+    fn save_state(&self, store: &Store) -> Result<()> {
+        news::write_file(&self.state, |file| Ok(serde_json::to_writer(file, store)?))?;
+        self.memory.save_state(store)
+    }
+
+    fn query_items(&self, query: &ItemsQuery) -> Result<Vec<NewsItem>> {
+        self.memory.query_items(query)
+    }
+
+    fn unread_counts(&self, feeds: &[String]) -> Result<HashMap<String, usize>> {
+        self.memory.unread_counts(feeds)
+    }
+}
+
+/// Keeps everything in memory, forgetting it on exit.
+#[derive(Default)]
+pub struct MemoryStore {
+    items: Mutex<BTreeSet<NewsItem>>,
+    /// The state serialized, as `Store` can't be cloned.
+    state: Mutex<Option<String>>,
+}
+
+impl NewsStore for MemoryStore {
+    fn load_items(&self) -> Result<BTreeSet<NewsItem>> {
+        Ok(self.items.lock().unwrap().clone())
+    }
+
+    fn save_items(&self, items: &BTreeSet<NewsItem>) -> Result<()> {
+        *self.items.lock().unwrap() = items.clone();
+        Ok(())
+    }
+
+    fn load_state(&self) -> Result<Store> {
+        match &*self.state.lock().unwrap() {
+            Some(state) => Ok(serde_json::from_str(state)?),
+            None => Ok(Store::default()),
+        }
+    }
+
+    fn save_state(&self, store: &Store) -> Result<()> {
+        *self.state.lock().unwrap() = Some(serde_json::to_string(store)?);
+        Ok(())
+    }
+
+    fn query_items(&self, query: &ItemsQuery) -> Result<Vec<NewsItem>> {
+        let state = self.load_state()?;
+        let items = self.items.lock().unwrap();
+        // Items are ordered oldest first.
+        Ok(items
+            .iter()
+            .rev()
+            .skip_while(|item| query.after.as_ref().map_or(false, |id| item.id() != *id))
+            .skip(usize::from(query.after.is_some()))
+            .filter(|item| query.feed.as_ref().map_or(true, |feed| item.feed == *feed))
+            .filter(|item| !query.unread_only || !state.is_read(&item.id()))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    fn unread_counts(&self, feeds: &[String]) -> Result<HashMap<String, usize>> {
+        let state = self.load_state()?;
+        let mut counts: HashMap<String, usize> =
+            feeds.iter().map(|feed| (feed.clone(), 0)).collect();
+        for item in self.items.lock().unwrap().iter() {
+            if !state.is_read(&item.id()) {
+                *counts.entry(item.feed.clone()).or_default() += 1;
+            }
+        }
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::prelude::*;
+    use news_core::test_util::{item, FEED};
+
+    #[test]
+    fn starts_empty() {
+        let store = MemoryStore::default();
+        assert!(store.load_items().unwrap().is_empty());
+        assert!(store.load_state().unwrap().feeds().is_empty());
+    }
+
+    #[test]
+    fn keeps_items_in_memory() {
+        let store = MemoryStore::default();
+        let items: BTreeSet<NewsItem> = ["harbor", "bridge"]
+            .iter()
+            .map(|title| item(title).build())
+            .collect();
+        store.save_items(&items).unwrap();
+        assert_eq!(store.load_items().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn answers_queries() {
+        let store = MemoryStore::default();
+        let other = "https://other.example.com/feed";
+        let date = |hour| Utc.ymd(2020, 5, 4).and_hms(hour, 0, 0);
+        let items: BTreeSet<NewsItem> = vec![
+            item("harbor").date(date(8)).build(),
+            item("bridge").date(date(9)).build(),
+            item("tunnel").feed(other).date(date(10)).build(),
+        ]
+        .into_iter()
+        .collect();
+        store.save_items(&items).unwrap();
+        let mut state = Store::default();
+        let bridge = items.iter().find(|item| item.title() == Some("bridge"));
+        state.mark_read(&bridge.unwrap().id());
+        store.save_state(&state).unwrap();
+
+        let titles = |query: ItemsQuery| -> Vec<String> {
+            let items = store.query_items(&query).unwrap();
+            items
+                .iter()
+                .filter_map(NewsItem::title)
+                .map(str::to_owned)
+                .collect()
+        };
+        let latest = ItemsQuery {
+            limit: Some(2),
+            ..ItemsQuery::default()
+        };
+        assert_eq!(titles(latest), ["tunnel", "bridge"]);
+        let unread = ItemsQuery {
+            unread_only: true,
+            feed: Some(FEED.to_string()),
+            ..ItemsQuery::default()
+        };
+        assert_eq!(titles(unread), ["harbor"]);
+
+        let quiet = "https://quiet.example.com/feed".to_string();
+        let counts = store.unread_counts(&[quiet.clone()]).unwrap();
+        assert_eq!(counts[FEED], 1);
+        assert_eq!(counts[other], 1);
+        assert_eq!(counts[&quiet], 0);
+    }
+
+    #[test]
+    fn keeps_state_in_memory() {
+        let store = MemoryStore::default();
+        let mut state = Store::default();
+        state.mark_read("harbor");
+        store.save_state(&state).unwrap();
+        assert!(store.load_state().unwrap().is_read("harbor"));
+    }
+}
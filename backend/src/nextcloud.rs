@@ -0,0 +1,475 @@
+//! Nextcloud News API v1.2 compatibility, so Nextcloud News clients can use this backend.
+//!
+//! Routes live under `/index.php/apps/news/api/v1-2` and require HTTP basic authentication with
+//! the credentials of the `nextcloud` config section; they are disabled when it is unset. There
+//! are no folders, every feed lives at the root. Nextcloud clients expect numeric ids, so item
+//! and feed ids are derived from the item digest and feed URL.
+
+use actix::Addr;
+use actix_web::{error, http::header, web, Error, HttpRequest, HttpResponse};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::admin;
+use crate::config::Config;
+use crate::protocol::ItemDto;
+use crate::server::{GetItems, ListFeeds, NewsServer, UpdateItems};
+
+/// API version reported to clients.
+const VERSION: &str = "15.0.0";
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/index.php/apps/news/api/v1-2")
+            .route("/version", web::get().to(version))
+            .route("/folders", web::get().to(folders))
+            .route("/feeds", web::get().to(feeds))
+            .route("/feeds/{feed_id}/read", web::put().to(read_feed))
+            .route("/items", web::get().to(items))
+            .route("/items/updated", web::get().to(updated_items))
+            .route("/items/read", web::put().to(read_all))
+            .route("/items/read/multiple", web::put().to(read_multiple))
+            .route("/items/unread/multiple", web::put().to(unread_multiple))
+            .route("/items/star/multiple", web::put().to(star_multiple))
+            .route("/items/unstar/multiple", web::put().to(unstar_multiple))
+            .route("/items/{item_id}/read", web::put().to(read_item))
+            .route("/items/{item_id}/unread", web::put().to(unread_item))
+            .route("/items/{feed_id}/{guid_hash}/star", web::put().to(star_item))
+            .route("/items/{feed_id}/{guid_hash}/unstar", web::put().to(unstar_item)),
+    );
+}
+
+/// Check the request carries the configured basic auth credentials.
+fn authorize(req: &HttpRequest, config: &Config) -> Result<(), Error> {
+    let credentials = match &config.nextcloud {
+        Some(nextcloud) => format!("{}:{}", nextcloud.user, nextcloud.password),
+        None => return Err(error::ErrorNotFound("nextcloud api is disabled")),
+    };
+
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|value| base64::decode(value).ok());
+    match provided {
+        Some(provided) if admin::secret_matches(provided, credentials) => Ok(()),
+        _ => Err(error::ErrorUnauthorized("invalid credentials")),
+    }
+}
+
+/// Numeric id of an item, derived from the hex digest so that it is stable.
+///
+/// Only 48 bits are kept so that the id survives a round trip through javascript numbers.
+fn item_id(id: &str) -> i64 {
+    i64::from_str_radix(&id[..12.min(id.len())], 16).unwrap_or(0)
+}
+
+/// Numeric id of a feed, derived from its URL.
+fn feed_id(url: &str) -> i64 {
+    let hash = blake3::hash(url.as_bytes()).to_hex();
+    i64::from_str_radix(&hash[..12], 16).unwrap()
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Feed {
+    id: i64,
+    url: String,
+    title: String,
+    favicon_link: Option<String>,
+    added: i64,
+    folder_id: i64,
+    unread_count: usize,
+    ordering: i64,
+    link: Option<String>,
+    pinned: bool,
+    update_error_count: i64,
+    last_update_error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    id: i64,
+    guid: String,
+    guid_hash: String,
+    url: Option<String>,
+    title: Option<String>,
+    author: Option<String>,
+    pub_date: i64,
+    body: String,
+    enclosure_mime: Option<String>,
+    enclosure_link: Option<String>,
+    media_thumbnail: Option<String>,
+    feed_id: i64,
+    unread: bool,
+    starred: bool,
+    last_modified: i64,
+    fingerprint: String,
+}
+
+impl Item {
+    fn new(dto: &ItemDto) -> Self {
+        let pub_date = dto.pub_date.map_or(0, |date| date.timestamp());
+        Item {
+            id: item_id(&dto.id),
+            guid: dto.id.clone(),
+            guid_hash: dto.id.clone(),
+            url: dto.link.clone(),
+            title: dto.title.clone(),
+            author: None,
+            pub_date,
+            body: dto
+                .content
+                .clone()
+                .or_else(|| dto.description.clone())
+                .unwrap_or_default(),
+            enclosure_mime: None,
+            enclosure_link: None,
+            media_thumbnail: None,
+            feed_id: feed_id(&dto.feed),
+            unread: !dto.read,
+            starred: dto.starred,
+            last_modified: last_modified(dto),
+            fingerprint: dto.id.clone(),
+        }
+    }
+}
+
+fn last_modified(dto: &ItemDto) -> i64 {
+    dto.updated_at
+        .or(dto.pub_date)
+        .map_or(0, |date| date.timestamp())
+}
+
+async fn version(req: HttpRequest, config: web::Data<Config>) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    Ok(HttpResponse::Ok().json(json!({ "version": VERSION })))
+}
+
+async fn folders(req: HttpRequest, config: web::Data<Config>) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    Ok(HttpResponse::Ok().json(json!({ "folders": [] })))
+}
+
+async fn get_items(server: &Addr<NewsServer>) -> Result<Vec<ItemDto>, Error> {
+    server
+        .send(GetItems)
+        .await
+        .map_err(error::ErrorInternalServerError)
+}
+
+async fn feeds(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let urls = server
+        .send(ListFeeds)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    let items = get_items(&server).await?;
+
+    let feeds: Vec<Feed> = urls
+        .into_iter()
+        .enumerate()
+        .map(|(ordering, url)| Feed {
+            id: feed_id(&url),
+            unread_count: items
+                .iter()
+                .filter(|item| item.feed == url && !item.read)
+                .count(),
+            title: url.clone(),
+            favicon_link: None,
+            added: 0,
+            folder_id: 0,
+            ordering: ordering as i64,
+            link: None,
+            pinned: false,
+            update_error_count: 0,
+            last_update_error: None,
+            url,
+        })
+        .collect();
+    let newest = items.iter().max_by_key(|item| item.pub_date);
+    Ok(HttpResponse::Ok().json(json!({
+        "feeds": feeds,
+        "starredCount": items.iter().filter(|item| item.starred).count(),
+        "newestItemId": newest.map(|item| item_id(&item.id)),
+    })))
+}
+
+/// Selection of items shared by `GET /items` and `GET /items/updated`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ItemsQuery {
+    /// 0 for a feed, 1 for a folder, 2 for starred items, 3 for every item.
+    #[serde(default = "default_type", rename = "type")]
+    kind: u8,
+    /// Id of the feed or folder.
+    #[serde(default)]
+    id: i64,
+    #[serde(default = "default_true")]
+    get_read: bool,
+    #[serde(default = "default_batch_size")]
+    batch_size: i64,
+    /// Only return items listed after the item with this id, unless 0.
+    #[serde(default)]
+    offset: i64,
+    #[serde(default)]
+    oldest_first: bool,
+    #[serde(default)]
+    last_modified: i64,
+}
+
+fn default_type() -> u8 {
+    3
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_batch_size() -> i64 {
+    -1
+}
+
+impl ItemsQuery {
+    fn selects(&self, dto: &ItemDto) -> bool {
+        match self.kind {
+            0 => feed_id(&dto.feed) == self.id,
+            // There are no folders besides the root one.
+            1 => self.id == 0,
+            2 => dto.starred,
+            _ => true,
+        }
+    }
+}
+
+async fn items(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    query: web::Query<ItemsQuery>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let mut items: Vec<Item> = get_items(&server)
+        .await?
+        .iter()
+        .filter(|dto| query.selects(dto))
+        .filter(|dto| query.get_read || !dto.read)
+        .map(Item::new)
+        .collect();
+
+    // Pages go from the newest item to the oldest one unless asked otherwise.
+    if query.oldest_first {
+        items.sort_by_key(|item| (item.pub_date, item.id));
+    } else {
+        items.sort_by_key(|item| std::cmp::Reverse((item.pub_date, item.id)));
+    }
+    if query.offset != 0 {
+        if let Some(position) = items.iter().position(|item| item.id == query.offset) {
+            items.drain(..=position);
+        }
+    }
+    if query.batch_size >= 0 {
+        items.truncate(query.batch_size as usize);
+    }
+    Ok(HttpResponse::Ok().json(json!({ "items": items })))
+}
+
+async fn updated_items(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    query: web::Query<ItemsQuery>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let items: Vec<Item> = get_items(&server)
+        .await?
+        .iter()
+        .filter(|dto| query.selects(dto))
+        .filter(|dto| last_modified(dto) >= query.last_modified)
+        .map(Item::new)
+        .collect();
+    Ok(HttpResponse::Ok().json(json!({ "items": items })))
+}
+
+/// Resolve numeric item ids to item ids.
+async fn resolve<F>(server: &Addr<NewsServer>, select: F) -> Result<Vec<String>, Error>
+where
+    F: Fn(&ItemDto) -> bool,
+{
+    Ok(get_items(server)
+        .await?
+        .into_iter()
+        .filter(|dto| select(dto))
+        .map(|dto| dto.id)
+        .collect())
+}
+
+async fn update(
+    server: &Addr<NewsServer>,
+    ids: Vec<String>,
+    read: Option<bool>,
+    starred: Option<bool>,
+) -> Result<HttpResponse, Error> {
+    server
+        .send(UpdateItems {
+            ids,
+            read,
+            starred,
+            at: Utc::now(),
+        })
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NewestItem {
+    newest_item_id: i64,
+}
+
+/// Mark every item up to the newest one the client knows read.
+async fn read_all(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    body: web::Json<NewestItem>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let items = get_items(&server).await?;
+    let newest = items
+        .iter()
+        .find(|dto| item_id(&dto.id) == body.newest_item_id)
+        .and_then(|dto| dto.pub_date);
+    let ids = items
+        .into_iter()
+        .filter(|dto| newest.is_none() || dto.pub_date <= newest)
+        .map(|dto| dto.id)
+        .collect();
+    update(&server, ids, Some(true), None).await
+}
+
+async fn read_feed(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    feed: web::Path<i64>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let feed = feed.into_inner();
+    let ids = resolve(&server, |dto| feed_id(&dto.feed) == feed).await?;
+    update(&server, ids, Some(true), None).await
+}
+
+async fn read_item(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    item: web::Path<i64>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let item = item.into_inner();
+    let ids = resolve(&server, |dto| item_id(&dto.id) == item).await?;
+    update(&server, ids, Some(true), None).await
+}
+
+async fn unread_item(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    item: web::Path<i64>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let item = item.into_inner();
+    let ids = resolve(&server, |dto| item_id(&dto.id) == item).await?;
+    update(&server, ids, Some(false), None).await
+}
+
+#[derive(Debug, Deserialize)]
+struct ItemIds {
+    items: Vec<i64>,
+}
+
+async fn read_multiple(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    body: web::Json<ItemIds>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let ids = resolve(&server, |dto| body.items.contains(&item_id(&dto.id))).await?;
+    update(&server, ids, Some(true), None).await
+}
+
+async fn unread_multiple(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    body: web::Json<ItemIds>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let ids = resolve(&server, |dto| body.items.contains(&item_id(&dto.id))).await?;
+    update(&server, ids, Some(false), None).await
+}
+
+async fn star_item(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    path: web::Path<(i64, String)>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    update(&server, vec![path.1.clone()], None, Some(true)).await
+}
+
+async fn unstar_item(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    path: web::Path<(i64, String)>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    update(&server, vec![path.1.clone()], None, Some(false)).await
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StarredItem {
+    #[allow(dead_code)]
+    feed_id: i64,
+    guid_hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct StarredItems {
+    items: Vec<StarredItem>,
+}
+
+async fn star_multiple(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    body: web::Json<StarredItems>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let ids = body.into_inner().items.into_iter().map(|item| item.guid_hash).collect();
+    update(&server, ids, None, Some(true)).await
+}
+
+async fn unstar_multiple(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    body: web::Json<StarredItems>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, &config)?;
+    let ids = body.into_inner().items.into_iter().map(|item| item.guid_hash).collect();
+    update(&server, ids, None, Some(false)).await
+}
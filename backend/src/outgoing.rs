@@ -0,0 +1,91 @@
+//! Feeds generated from views through `GET /api/feeds/out/{view}.xml`, so that curated streams can
+//! be piped into other readers and tools.
+//!
+//! `{view}` is `all`, `unread`, `starred`, `feed` along with the `url` of the feed, or `folder`
+//! along with the `name` of the folder. Feeds are served as RSS unless `format=atom` is asked for.
+
+use actix::Addr;
+use actix_web::{error, web, Error, HttpRequest, HttpResponse};
+use serde::Deserialize;
+
+use crate::server::{GetFeedOrder, ListFeeds, NewsServer, ViewItems};
+use crate::views::{Format, View};
+
+/// Number of items of a generated feed unless a limit is given.
+const DEFAULT_ITEMS: usize = 100;
+/// Maximum number of items of a generated feed.
+const MAX_ITEMS: usize = 1_000;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/api/feeds/out/{view}.xml", web::get().to(get_feed));
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct FeedParams {
+    /// URL of the feed of the `feed` view.
+    url: Option<String>,
+    /// Name of the folder of the `folder` view.
+    name: Option<String>,
+    format: Format,
+    /// Maximum number of items, newest first.
+    limit: Option<usize>,
+}
+
+/// Return the view named `name`.
+fn view(name: &str, params: &FeedParams) -> Result<View, Error> {
+    match (name, &params.url, &params.name) {
+        ("all", _, _) => Ok(View::All),
+        ("unread", _, _) => Ok(View::Unread),
+        ("starred", _, _) => Ok(View::Starred),
+        ("feed", Some(url), _) => Ok(View::Feed(url.clone())),
+        ("feed", None, _) => Err(error::ErrorBadRequest("the feed view needs a url")),
+        ("folder", _, Some(folder)) => Ok(View::Folder(folder.clone())),
+        ("folder", _, None) => Err(error::ErrorBadRequest("the folder view needs a name")),
+        _ => Err(error::ErrorNotFound("unknown view")),
+    }
+}
+
+async fn get_feed(
+    req: HttpRequest,
+    server: web::Data<Addr<NewsServer>>,
+    name: web::Path<String>,
+    params: web::Query<FeedParams>,
+) -> Result<HttpResponse, Error> {
+    let params = params.into_inner();
+    let view = view(&name, &params)?;
+    if let View::Feed(feed) = &view {
+        let feeds = server
+            .send(ListFeeds)
+            .await
+            .map_err(error::ErrorInternalServerError)?;
+        if !feeds.contains(feed) {
+            return Err(error::ErrorNotFound("unknown feed"));
+        }
+    }
+    if let View::Folder(name) = &view {
+        let order = server
+            .send(GetFeedOrder)
+            .await
+            .map_err(error::ErrorInternalServerError)?;
+        if !order.folders.iter().any(|folder| &folder.name == name) {
+            return Err(error::ErrorNotFound("unknown folder"));
+        }
+    }
+
+    let items = server
+        .send(ViewItems {
+            view: view.clone(),
+            limit: params.limit.unwrap_or(DEFAULT_ITEMS).min(MAX_ITEMS),
+        })
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    let link = {
+        let info = req.connection_info();
+        format!("{}://{}{}", info.scheme(), info.host(), req.uri())
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(params.format.content_type())
+        .body(params.format.render(&view.name(), &link, &items)))
+}
+
@@ -0,0 +1,204 @@
+//! Export of items as PDF documents through `GET /api/items/{id}/pdf`.
+//!
+//! The sanitized content of an item is laid out as a standalone HTML document, with its lead image
+//! inlined from the cache and followed by the user's notes and highlights, and converted by the
+//! external renderer configured as `pdf_renderer`.
+//! The renderer reads HTML on its standard input and writes the PDF on its standard output, which
+//! WeasyPrint does by default.
+
+use actix::Addr;
+use actix_web::{error, web, Error, HttpResponse};
+use anyhow::{anyhow, Result};
+
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::config::Config;
+use crate::news;
+use crate::protocol::ItemDto;
+use crate::sanitize;
+use crate::server::{GetItem, NewsServer};
+
+/// Styles of the exported document.
+const STYLE: &str = "
+body { font-family: Georgia, serif; font-size: 11pt; line-height: 1.5; }
+img { max-width: 100%; }
+.source { color: #555; font-size: 9pt; }
+.annotations { border-top: 1px solid #ccc; margin-top: 2em; }
+blockquote { border-left: 3px solid #e6c200; margin-left: 0; padding-left: 1em; }
+";
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/api/items/{id}/pdf", web::get().to(export_item));
+}
+
+async fn export_item(
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let item = server
+        .send(GetItem {
+            id: id.into_inner(),
+        })
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("no such item"))?;
+
+    let file_name = file_name(&item);
+    let document = document(&item);
+    let renderer = config.pdf_renderer.clone();
+    let pdf = web::block(move || render(&renderer, &document))
+        .await
+        .map_err(|e| {
+            log::error!("failed to render pdf: {}", e);
+            error::ErrorInternalServerError("failed to render pdf")
+        })?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/pdf")
+        .header(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", file_name),
+        )
+        .body(pdf))
+}
+
+/// Lay out an item as a standalone HTML document.
+fn document(item: &ItemDto) -> String {
+    let title = sanitize::escape(item.title.as_deref().unwrap_or("Untitled"));
+    let feed = sanitize::escape(&item.feed);
+    let date = item.pub_date.map(|date| date.to_rfc3339()).unwrap_or_default();
+    let image = item
+        .image_path
+        .as_deref()
+        .and_then(inline_image)
+        .map(|src| format!("<img src=\"{}\" alt=\"\">", src))
+        .unwrap_or_default();
+    let link = match &item.link {
+        Some(link) => {
+            let link = sanitize::escape(link);
+            format!("<a href=\"{0}\">{0}</a>", link)
+        }
+        None => String::new(),
+    };
+    // Content and description are sanitized when building the DTO.
+    let body = item
+        .content
+        .as_deref()
+        .or_else(|| item.description.as_deref())
+        .unwrap_or("");
+
+    format!(
+        "<!doctype html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{title}</title>
+<meta name=\"author\" content=\"{feed}\">
+<meta name=\"dcterms.created\" content=\"{date}\">
+<meta name=\"generator\" content=\"news\">
+<style>{style}</style>
+</head>
+<body>
+<h1>{title}</h1>
+<p class=\"source\">{feed}<br>{date}<br>{link}</p>
+{image}
+{body}
+{annotations}
+</body>
+</html>",
+        title = title,
+        feed = feed,
+        date = date,
+        style = STYLE,
+        link = link,
+        image = image,
+        body = body,
+        annotations = annotations(item),
+    )
+}
+
+/// Lay out the user's notes and highlights of an item, if any.
+fn annotations(item: &ItemDto) -> String {
+    let annotations = match &item.annotations {
+        Some(annotations) => annotations,
+        None => return String::new(),
+    };
+    let paragraph = |note: &str| format!("<p>{}</p>", sanitize::escape(note));
+    let mut html = String::from("<section class=\"annotations\">\n<h2>Notes</h2>\n");
+    if let Some(note) = &annotations.note {
+        html.push_str(&paragraph(note));
+    }
+    for highlight in &annotations.highlights {
+        html.push_str("<blockquote>");
+        html.push_str(&sanitize::escape(&highlight.text));
+        html.push_str("</blockquote>");
+        if let Some(note) = &highlight.note {
+            html.push_str(&paragraph(note));
+        }
+    }
+    html.push_str("</section>");
+    html
+}
+
+/// Return the cached image at the `/cache/...` URL `path` as a data URI.
+fn inline_image(path: &str) -> Option<String> {
+    let relative = path.strip_prefix("/cache/")?;
+    let file = news::cache_dir().ok()?.join(relative);
+    let data = fs::read(&file).ok()?;
+    let mime = match file.extension().and_then(|ext| ext.to_str()) {
+        Some("png") => "image/png",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        _ => "image/jpeg",
+    };
+    Some(format!("data:{};base64,{}", mime, base64::encode(&data)))
+}
+
+/// Convert an HTML document to PDF with the renderer command.
+fn render(renderer: &[String], document: &str) -> Result<Vec<u8>> {
+    let (program, args) = renderer
+        .split_first()
+        .ok_or_else(|| anyhow!("no pdf renderer configured"))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("renderer stdin unavailable"))?
+        .write_all(document.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Name the PDF after the title of the item.
+fn file_name(item: &ItemDto) -> String {
+    let slug: String = item
+        .title
+        .as_deref()
+        .unwrap_or("article")
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    let slug: Vec<&str> = slug.split('-').filter(|part| !part.is_empty()).collect();
+    if slug.is_empty() {
+        return "article.pdf".to_string();
+    }
+    format!("{}.pdf", slug.join("-").chars().take(80).collect::<String>())
+}
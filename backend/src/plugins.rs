@@ -0,0 +1,363 @@
+//! WebAssembly plugins adapting niche sources into items and filtering the items of feeds, loaded
+//! from the `plugins.dir` of the config in builds with the `plugins` feature.
+//!
+//! Every `*.wasm` file of the directory is a plugin implementing the interface of
+//! `plugins/news.wit`. Source adapters replace the parsing of the feeds they apply to, filters
+//! then see every item fetched and may rewrite or drop it. Plugins are sandboxed: they get no
+//! imports and are interrupted once they run longer than `timeout_ms`. A failing plugin is
+//! logged and skipped, leaving the items as they were.
+
+// Builds without plugins never load any, but keep the interface of the module.
+#![cfg_attr(not(feature = "wasmtime"), allow(dead_code))]
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasmtime")]
+use wasmtime::{Engine, Instance, Module, Store};
+
+use std::fs;
+use std::path::Path;
+#[cfg(feature = "wasmtime")]
+use std::sync::mpsc::{self, RecvTimeoutError};
+#[cfg(feature = "wasmtime")]
+use std::thread;
+use std::time::Duration;
+
+use crate::config::PluginsConfig;
+use crate::news::{ItemFields, NewsItem};
+
+/// What a plugin says of itself when loaded.
+#[derive(Debug, Clone, Deserialize)]
+struct Manifest {
+    name: String,
+    /// Prefixes of the URLs of the feeds the plugin applies to, every feed when empty.
+    #[serde(default)]
+    feeds: Vec<String>,
+}
+
+impl Manifest {
+    fn applies_to(&self, feed: &str) -> bool {
+        self.feeds.is_empty() || self.feeds.iter().any(|prefix| feed.starts_with(prefix))
+    }
+}
+
+/// What is served at the URL of a feed, as passed to source adapters.
+#[derive(Serialize)]
+struct Source<'a> {
+    url: &'a str,
+    body: &'a str,
+}
+
+struct Plugin {
+    manifest: Manifest,
+    #[cfg(feature = "wasmtime")]
+    module: Module,
+    adapts: bool,
+    filters: bool,
+}
+
+/// The loaded plugins, shared by every fetch.
+pub struct Plugins {
+    #[cfg(feature = "wasmtime")]
+    engine: Engine,
+    plugins: Vec<Plugin>,
+    timeout: Duration,
+}
+
+impl Plugins {
+    /// Load the plugins of the configured directory, skipping those failing to load.
+    #[cfg(feature = "wasmtime")]
+    pub fn load(config: &PluginsConfig) -> Result<Self> {
+        let mut wasm_config = wasmtime::Config::new();
+        wasm_config.interruptable(true);
+        let mut plugins = Plugins {
+            engine: Engine::new(&wasm_config),
+            plugins: Vec::new(),
+            timeout: Duration::from_millis(config.timeout_ms),
+        };
+        for path in wasm_files(&config.dir)? {
+            match plugins.load_plugin(&path) {
+                Ok(plugin) => {
+                    log::info!(
+                        "loaded plugin {} from {}",
+                        plugin.manifest.name,
+                        path.display()
+                    );
+                    plugins.plugins.push(plugin);
+                }
+                Err(e) => log::error!("failed to load plugin {}: {}", path.display(), e),
+            }
+        }
+        Ok(plugins)
+    }
+
+    #[cfg(not(feature = "wasmtime"))]
+    pub fn load(config: &PluginsConfig) -> Result<Self> {
+        Err(anyhow!(
+            "not loading the plugins of {}, built without plugins",
+            config.dir.display()
+        ))
+    }
+
+    #[cfg(feature = "wasmtime")]
+    fn load_plugin(&self, path: &Path) -> Result<Plugin> {
+        let module = Module::from_file(&self.engine, path)?;
+        let exports = |name: &str| module.exports().any(|export| export.name() == name);
+        let (adapts, filters) = (exports("news_adapt"), exports("news_filter"));
+        let manifest = self.call(&module, "news_manifest", None)?;
+        let manifest: Manifest = serde_json::from_slice(&manifest)?;
+        if adapts && manifest.feeds.is_empty() {
+            return Err(anyhow!(
+                "{} adapts sources without saying which",
+                manifest.name
+            ));
+        }
+        Ok(Plugin {
+            manifest,
+            module,
+            adapts,
+            filters,
+        })
+    }
+
+    /// Whether a plugin adapts the source of `feed`.
+    pub fn adapts(&self, feed: &str) -> bool {
+        self.adapter(feed).is_some()
+    }
+
+    fn adapter(&self, feed: &str) -> Option<&Plugin> {
+        self.plugins
+            .iter()
+            .find(|plugin| plugin.adapts && plugin.manifest.applies_to(feed))
+    }
+
+    /// Turn the `body` served at the URL of `feed` into items with the plugin adapting it, or
+    /// return `None` if no plugin does.
+    pub fn adapt(
+        &self,
+        feed: &str,
+        body: &[u8],
+        cache_dir: &Path,
+    ) -> Result<Option<Vec<NewsItem>>> {
+        let plugin = match self.adapter(feed) {
+            Some(plugin) => plugin,
+            None => return Ok(None),
+        };
+        let source = Source {
+            url: feed,
+            body: &String::from_utf8_lossy(body),
+        };
+        let items = self.run(plugin, "news_adapt", &serde_json::to_vec(&source)?)?;
+        let items: Vec<ItemFields> = serde_json::from_slice(&items)?;
+        let items = items
+            .into_iter()
+            .map(|fields| NewsItem::from_fields(fields, feed, cache_dir))
+            .collect();
+        Ok(Some(items))
+    }
+
+    /// Pass the items of `feed` through the filters applying to it, in the order they were
+    /// loaded, dropping the items a filter returns none for.
+    pub fn filter(&self, feed: &str, items: Vec<NewsItem>, cache_dir: &Path) -> Vec<NewsItem> {
+        let filters: Vec<&Plugin> = self
+            .plugins
+            .iter()
+            .filter(|plugin| plugin.filters && plugin.manifest.applies_to(feed))
+            .collect();
+        if filters.is_empty() {
+            return items;
+        }
+        items
+            .into_iter()
+            .filter_map(|mut item| {
+                for plugin in &filters {
+                    match self.filter_item(plugin, &item) {
+                        Ok(Some(fields)) => item.set_fields(fields, cache_dir),
+                        Ok(None) => return None,
+                        Err(e) => log::warn!("{}", e),
+                    }
+                }
+                Some(item)
+            })
+            .collect()
+    }
+
+    fn filter_item(&self, plugin: &Plugin, item: &NewsItem) -> Result<Option<ItemFields>> {
+        let fields = self.run(plugin, "news_filter", &serde_json::to_vec(&item.fields())?)?;
+        Ok(serde_json::from_slice(&fields)?)
+    }
+
+    /// Call the function `export` of `plugin` with the JSON `input`, returning its JSON result.
+    fn run(&self, plugin: &Plugin, export: &str, input: &[u8]) -> Result<Vec<u8>> {
+        #[cfg(feature = "wasmtime")]
+        let result = self.call(&plugin.module, export, Some(input));
+        #[cfg(not(feature = "wasmtime"))]
+        let result: Result<Vec<u8>> = Err(anyhow!(
+            "{} bytes not passed, built without plugins",
+            input.len()
+        ));
+        result.map_err(|e| {
+            anyhow!(
+                "plugin {} failed in {}: {}",
+                plugin.manifest.name,
+                export,
+                e
+            )
+        })
+    }
+
+    /// Call `export` on a fresh instance of `module`, interrupting it after the timeout.
+    #[cfg(feature = "wasmtime")]
+    fn call(&self, module: &Module, export: &str, input: Option<&[u8]>) -> Result<Vec<u8>> {
+        let store = Store::new(&self.engine);
+        let interrupt = store.interrupt_handle()?;
+        let (done, finished) = mpsc::channel::<()>();
+        let timeout = self.timeout;
+        thread::spawn(move || {
+            // The call finished once the sender is dropped.
+            if let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(timeout) {
+                interrupt.interrupt();
+            }
+        });
+        let result = invoke(&store, module, export, input);
+        drop(done);
+        result
+    }
+}
+
+/// Instantiate `module` and call `export`, with `input` written into its memory if any.
+#[cfg(feature = "wasmtime")]
+fn invoke(store: &Store, module: &Module, export: &str, input: Option<&[u8]>) -> Result<Vec<u8>> {
+    let instance = Instance::new(store, module, &[])?;
+    let memory = instance
+        .get_memory("memory")
+        .ok_or_else(|| anyhow!("no memory exported"))?;
+    let func = |name: &str| {
+        instance
+            .get_func(name)
+            .ok_or_else(|| anyhow!("no {} exported", name))
+    };
+    let packed = match input {
+        None => func(export)?.get0::<i64>()?()?,
+        Some(input) => {
+            let len = input.len() as i32;
+            let ptr = func("news_alloc")?.get1::<i32, i32>()?(len)? as u32 as usize;
+            // Safe as nothing else accesses the memory of the instance while it is borrowed.
+            let data = unsafe { memory.data_unchecked_mut() };
+            data.get_mut(ptr..ptr + input.len())
+                .ok_or_else(|| anyhow!("news_alloc returned a buffer out of bounds"))?
+                .copy_from_slice(input);
+            func(export)?.get2::<i32, i32, i64>()?(ptr as i32, len)?
+        }
+    };
+    let (ptr, len) = ((packed >> 32) as u32 as usize, packed as u32 as usize);
+    let data = unsafe { memory.data_unchecked() };
+    data.get(ptr..ptr + len)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| anyhow!("{} returned a result out of bounds", export))
+}
+
+/// Return the paths of the plugins in `dir`, sorted so that they load in a stable order.
+fn wasm_files(dir: &Path) -> Result<Vec<std::path::PathBuf>> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| anyhow!("failed to read {}: {}", dir.display(), e))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().map_or(false, |ext| ext == "wasm"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_feed_prefixes() {
+        let manifest = Manifest {
+            name: "mastodon".to_string(),
+            feeds: vec!["https://mastodon.social/@".to_string()],
+        };
+        assert!(manifest.applies_to("https://mastodon.social/@rust"));
+        assert!(!manifest.applies_to("https://example.com/feed"));
+        let every = Manifest {
+            name: "spam".to_string(),
+            feeds: Vec::new(),
+        };
+        assert!(every.applies_to("https://example.com/feed"));
+    }
+
+    /// A plugin echoing the items it filters, or looping forever with `spin`.
+    #[cfg(feature = "wasmtime")]
+    fn plugin(engine: &Engine, spin: bool) -> Plugin {
+        let filter = if spin {
+            "(loop (br 0)) (i64.const 0)"
+        } else {
+            "(i64.or (i64.shl (i64.extend_i32_u (local.get 0)) (i64.const 32)) \
+             (i64.extend_i32_u (local.get 1)))"
+        };
+        let wat = format!(
+            r#"(module
+                 (memory (export "memory") 1)
+                 (data (i32.const 0) "{{\22name\22:\22echo\22}}")
+                 (func (export "news_alloc") (param i32) (result i32) (i32.const 1024))
+                 (func (export "news_manifest") (result i64) (i64.const 15))
+                 (func (export "news_filter") (param i32 i32) (result i64) {}))"#,
+            filter
+        );
+        let module = Module::new(engine, wat).unwrap();
+        Plugin {
+            manifest: Manifest {
+                name: "echo".to_string(),
+                feeds: Vec::new(),
+            },
+            module,
+            adapts: false,
+            filters: true,
+        }
+    }
+
+    #[cfg(feature = "wasmtime")]
+    #[test]
+    fn calls_plugins() {
+        let plugins = Plugins {
+            engine: Engine::new(wasmtime::Config::new().interruptable(true)),
+            plugins: Vec::new(),
+            timeout: Duration::from_secs(5),
+        };
+        let echo = plugin(&plugins.engine, false);
+        let manifest = plugins.call(&echo.module, "news_manifest", None).unwrap();
+        assert_eq!(manifest, br#"{"name":"echo"}"#);
+
+        let fields = ItemFields {
+            title: Some("Story".to_string()),
+            ..ItemFields::default()
+        };
+        assert_eq!(
+            plugins.filter_item(&echo, &item(&fields)).unwrap(),
+            Some(fields)
+        );
+    }
+
+    #[cfg(feature = "wasmtime")]
+    #[test]
+    fn interrupts_slow_plugins() {
+        let plugins = Plugins {
+            engine: Engine::new(wasmtime::Config::new().interruptable(true)),
+            plugins: Vec::new(),
+            timeout: Duration::from_millis(100),
+        };
+        let spin = plugin(&plugins.engine, true);
+        let fields = ItemFields::default();
+        assert!(plugins.filter_item(&spin, &item(&fields)).is_err());
+    }
+
+    #[cfg(feature = "wasmtime")]
+    fn item(fields: &ItemFields) -> NewsItem {
+        NewsItem::from_fields(
+            fields.clone(),
+            "https://example.com/feed",
+            Path::new("/cache"),
+        )
+    }
+}
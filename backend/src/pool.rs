@@ -0,0 +1,72 @@
+//! CPU bound work, such as parsing feeds and decoding images, run on the rayon pool so that it
+//! doesn't stall the async executor serving websocket heartbeats and HTTP responses. Builds
+//! without rayon run it on the blocking pool of actix instead.
+//!
+//! The work handles untrusted feeds and images, so a job that panics fails with an error instead
+//! of taking the server down with it.
+
+#[cfg(feature = "rayon")]
+use anyhow::anyhow;
+use anyhow::Result;
+#[cfg(feature = "rayon")]
+use futures::channel::oneshot;
+
+#[cfg(feature = "rayon")]
+use std::any::Any;
+#[cfg(feature = "rayon")]
+use std::panic::{self, AssertUnwindSafe};
+
+/// Run `f` on the rayon pool, resolving to its result.
+#[cfg(feature = "rayon")]
+pub async fn run<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    rayon::spawn(move || {
+        // Rayon aborts the process when a job panics, so the panic is caught here.
+        let result = panic::catch_unwind(AssertUnwindSafe(f))
+            .unwrap_or_else(|payload| Err(anyhow!("job panicked: {}", panic_message(&*payload))));
+        // The receiver is gone when the caller was dropped, leaving nobody to tell.
+        let _ = tx.send(result);
+    });
+    rx.await?
+}
+
+/// Return the message a panic was raised with.
+#[cfg(feature = "rayon")]
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown cause")
+}
+
+/// Run `f` on the blocking pool, resolving to its result.
+#[cfg(not(feature = "rayon"))]
+pub async fn run<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    use actix_web::error::BlockingError;
+
+    actix_web::web::block(f).await.map_err(|e| match e {
+        BlockingError::Error(e) => e,
+        BlockingError::Canceled => anyhow::anyhow!("job panicked or the blocking pool is gone"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_rt::test]
+    async fn fails_jobs_that_panic() {
+        let result: Result<()> = run(|| panic!("malformed input")).await;
+        assert!(result.is_err());
+        assert_eq!(run(|| Ok(1)).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,355 @@
+//! PostgreSQL backend, letting several instances behind a load balancer share their items and the
+//! user's state.
+//!
+//! Each instance keeps its items in memory like with the other backends and writes back the ones
+//! that changed, so items fetched by another instance are kept rather than overwritten. The read
+//! and starred state of each item is kept in rows of its own along with the time it changed, the
+//! latest change winning whichever instance saves it last. The rest of the state is written whole,
+//! the last instance saving it wins. Scheduled polls of a feed are claimed under an advisory lock
+//! so that only one instance polls it each round.
+//!
+//! Queries run on the database, so they see the items every instance stored. Items keep their
+//! feed and date in columns of their own for them, which rows stored before those columns existed
+//! get on the next save.
+//!
+//! The synchronous client can't run on the threads of the actix runtime, so the connection lives
+//! on a thread of its own running the queries it is sent. The server's actor still waits for their
+//! results, which is why each round claims all its feeds in one go.
+
+use anyhow::{anyhow, Result};
+use chrono::prelude::*;
+use postgres::{Client, NoTls};
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+use crate::items::ItemsQuery;
+use crate::news::NewsItem;
+use crate::news_store::NewsStore;
+use crate::store::Store;
+use crate::sync::Stamped;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS items (id TEXT PRIMARY KEY, item BYTEA NOT NULL);
+    ALTER TABLE items ADD COLUMN IF NOT EXISTS feed TEXT;
+    ALTER TABLE items ADD COLUMN IF NOT EXISTS published BIGINT;
+    CREATE INDEX IF NOT EXISTS items_newest ON items (published DESC, id DESC);
+    CREATE TABLE IF NOT EXISTS state (id INTEGER PRIMARY KEY, store JSONB NOT NULL);
+    CREATE TABLE IF NOT EXISTS item_state (
+        id TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        value BOOLEAN NOT NULL,
+        at BIGINT NOT NULL,
+        PRIMARY KEY (id, kind)
+    );
+    CREATE TABLE IF NOT EXISTS feed_polls (feed TEXT PRIMARY KEY, polled_at TIMESTAMPTZ NOT NULL);
+";
+
+/// Kinds of the rows of `item_state`.
+const READ: &str = "read";
+const STARRED: &str = "starred";
+
+type Job = Box<dyn FnOnce(&mut Client) + Send>;
+
+/// Read and starred state of each item keyed by item id and kind.
+type States = HashMap<(String, &'static str), Stamped>;
+
+pub struct PostgresStore {
+    jobs: Mutex<mpsc::Sender<Job>>,
+    /// Hash of each item this instance loaded or saved, keyed by item id, to write back only the
+    /// items that changed and delete only the ones it dropped.
+    known: Mutex<HashMap<String, blake3::Hash>>,
+    /// Read and starred state of each item as this instance loaded or saved it, to write back only
+    /// the states that changed.
+    states: Mutex<States>,
+}
+
+impl PostgresStore {
+    /// Connect to the database at `url`, creating the tables if necessary.
+    pub fn connect(url: &str) -> Result<PostgresStore> {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let (connected, connection) = mpsc::channel();
+        let url = url.to_string();
+        thread::Builder::new()
+            .name("postgres".to_string())
+            .spawn(move || {
+                let client = Client::connect(&url, NoTls).and_then(|mut client| {
+                    client.batch_execute(SCHEMA)?;
+                    Ok(client)
+                });
+                let mut client = match client {
+                    Ok(client) => client,
+                    Err(e) => {
+                        let _ = connected.send(Err(e));
+                        return;
+                    }
+                };
+                let _ = connected.send(Ok(()));
+                for job in receiver {
+                    job(&mut client);
+                }
+            })?;
+        connection.recv()??;
+        Ok(PostgresStore {
+            jobs: Mutex::new(jobs),
+            known: Mutex::new(HashMap::new()),
+            states: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Run `query` on the thread of the connection, blocking the calling thread until it returns.
+    fn run<T, F>(&self, query: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Client) -> Result<T> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        self.jobs
+            .lock()
+            .unwrap()
+            .send(Box::new(move |client| {
+                let _ = sender.send(query(client));
+            }))
+            .map_err(|_| anyhow!("the postgres connection is closed"))?;
+        receiver
+            .recv()
+            .map_err(|_| anyhow!("the postgres connection is closed"))?
+    }
+}
+
+impl NewsStore for PostgresStore {
+    fn load_items(&self) -> Result<BTreeSet<NewsItem>> {
+        let rows = self.run(|client| {
+            let rows = client.query("SELECT item, feed IS NULL FROM items", &[])?;
+            Ok(rows
+                .iter()
+                .map(|row| (row.get::<_, Vec<u8>>(0), row.get::<_, bool>(1)))
+                .collect::<Vec<_>>())
+        })?;
+        let mut known = self.known.lock().unwrap();
+        let mut items = BTreeSet::new();
+        for (bytes, unindexed) in rows {
+            let item: NewsItem = bincode::deserialize(&bytes)?;
+            // Left unknown, rows stored without their feed and date are written again.
+            if !unindexed {
+                known.insert(item.id(), blake3::hash(&bytes));
+            }
+            items.insert(item);
+        }
+        Ok(items)
+    }
+
+    fn save_items(&self, items: &BTreeSet<NewsItem>) -> Result<()> {
+        let mut known = self.known.lock().unwrap();
+        let mut changed = Vec::new();
+        let mut hashes = HashMap::new();
+        for item in items {
+            let bytes = bincode::serialize(item)?;
+            let id = item.id();
+            let hash = blake3::hash(&bytes);
+            if known.get(&id) != Some(&hash) {
+                changed.push((id.clone(), bytes, item.feed.clone(), published(item)));
+            }
+            hashes.insert(id, hash);
+        }
+        let ids: HashSet<&String> = hashes.keys().collect();
+        let dropped: Vec<String> = known
+            .keys()
+            .filter(|id| !ids.contains(id))
+            .cloned()
+            .collect();
+        if changed.is_empty() && dropped.is_empty() {
+            return Ok(());
+        }
+
+        self.run(move |client| {
+            let mut transaction = client.transaction()?;
+            let upsert = transaction.prepare(
+                "INSERT INTO items (id, item, feed, published) VALUES ($1, $2, $3, $4) \
+                 ON CONFLICT (id) DO UPDATE SET item = EXCLUDED.item, feed = EXCLUDED.feed, \
+                 published = EXCLUDED.published",
+            )?;
+            for (id, item, feed, published) in &changed {
+                transaction.execute(&upsert, &[id, item, feed, published])?;
+            }
+            transaction.execute("DELETE FROM items WHERE id = ANY($1)", &[&dropped])?;
+            transaction.execute("DELETE FROM item_state WHERE id = ANY($1)", &[&dropped])?;
+            transaction.commit()?;
+            Ok(())
+        })?;
+        *known = hashes;
+        Ok(())
+    }
+
+    fn load_state(&self) -> Result<Store> {
+        let (store, rows) = self.run(|client| {
+            let row = client.query_opt("SELECT store FROM state WHERE id = 1", &[])?;
+            let rows = client.query("SELECT id, kind, value, at FROM item_state", &[])?;
+            let rows: Vec<(String, String, bool, i64)> = rows
+                .iter()
+                .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3)))
+                .collect();
+            Ok((row.map(|row| row.get::<_, serde_json::Value>(0)), rows))
+        })?;
+        let mut store = match store {
+            Some(store) => serde_json::from_value(store)?,
+            None => Store::default(),
+        };
+        // The whole state is the one the last instance saved, the rows hold the latest changes of
+        // every instance.
+        let mut states = HashMap::new();
+        for (id, kind, value, at) in rows {
+            let at = Utc.timestamp_nanos(at);
+            let kind = match kind.as_str() {
+                READ => {
+                    store.set_read(&id, value, at);
+                    READ
+                }
+                STARRED => {
+                    store.set_starred(&id, value, at);
+                    STARRED
+                }
+                _ => continue,
+            };
+            states.insert((id, kind), Stamped { value, at });
+        }
+        *self.states.lock().unwrap() = states;
+        Ok(store)
+    }
+
+    fn save_state(&self, store: &Store) -> Result<()> {
+        let mut states = self.states.lock().unwrap();
+        let current = item_states(store);
+        let changed: Vec<(String, &str, bool, i64)> = current
+            .iter()
+            .filter(|(key, state)| states.get(key) != Some(state))
+            .map(|((id, kind), state)| (id.clone(), *kind, state.value, state.at.timestamp_nanos()))
+            .collect();
+        let store = serde_json::to_value(store)?;
+        self.run(move |client| {
+            let mut transaction = client.transaction()?;
+            transaction.execute(
+                "INSERT INTO state (id, store) VALUES (1, $1) \
+                 ON CONFLICT (id) DO UPDATE SET store = EXCLUDED.store",
+                &[&store],
+            )?;
+            // Like `Stamped::supersedes`, so that a change another instance saved isn't undone by
+            // an earlier one.
+            let upsert = transaction.prepare(
+                "INSERT INTO item_state (id, kind, value, at) VALUES ($1, $2, $3, $4) \
+                 ON CONFLICT (id, kind) DO UPDATE SET value = EXCLUDED.value, at = EXCLUDED.at \
+                 WHERE EXCLUDED.at > item_state.at \
+                 OR (EXCLUDED.at = item_state.at AND EXCLUDED.value AND NOT item_state.value)",
+            )?;
+            for (id, kind, value, at) in &changed {
+                transaction.execute(&upsert, &[id, kind, value, at])?;
+            }
+            transaction.commit()?;
+            Ok(())
+        })?;
+        *states = current;
+        Ok(())
+    }
+
+    fn query_items(&self, query: &ItemsQuery) -> Result<Vec<NewsItem>> {
+        let feed = query.feed.clone();
+        let unread_only = query.unread_only;
+        let after = query.after.clone();
+        let limit = query.limit.map(|limit| limit as i64);
+        let rows = self.run(move |client| {
+            // Newest first like the order of `NewsItem`, ties going by id, which is the hex of the
+            // digest that order compares.
+            let rows = client.query(
+                "SELECT item FROM items \
+                 WHERE ($1::text IS NULL OR feed = $1) \
+                 AND NOT ($2 AND EXISTS (SELECT 1 FROM item_state AS state \
+                     WHERE state.id = items.id AND state.kind = $3 AND state.value)) \
+                 AND ($4::text IS NULL OR EXISTS (SELECT 1 FROM items AS after \
+                     WHERE after.id = $4 \
+                     AND (items.published, items.id) < (after.published, after.id))) \
+                 ORDER BY published DESC, id DESC \
+                 LIMIT $5",
+                &[&feed, &unread_only, &READ, &after, &limit],
+            )?;
+            Ok(rows
+                .iter()
+                .map(|row| row.get::<_, Vec<u8>>(0))
+                .collect::<Vec<_>>())
+        })?;
+        rows.iter()
+            .map(|bytes| Ok(bincode::deserialize(bytes)?))
+            .collect()
+    }
+
+    fn unread_counts(&self, feeds: &[String]) -> Result<HashMap<String, usize>> {
+        let rows = self.run(|client| {
+            let rows = client.query(
+                "SELECT feed, count(*) FROM items \
+                 WHERE feed IS NOT NULL AND NOT EXISTS (SELECT 1 FROM item_state AS state \
+                     WHERE state.id = items.id AND state.kind = $1 AND state.value) \
+                 GROUP BY feed",
+                &[&READ],
+            )?;
+            Ok(rows
+                .iter()
+                .map(|row| (row.get::<_, String>(0), row.get::<_, i64>(1)))
+                .collect::<Vec<_>>())
+        })?;
+        let mut counts: HashMap<String, usize> =
+            feeds.iter().map(|feed| (feed.clone(), 0)).collect();
+        counts.extend(rows.into_iter().map(|(feed, count)| (feed, count as usize)));
+        Ok(counts)
+    }
+
+    fn claim_feeds(&self, feeds: &[String], fresh_for: chrono::Duration) -> Result<Vec<String>> {
+        let feeds = feeds.to_vec();
+        let fresh_for = fresh_for.num_seconds() as f64;
+        self.run(move |client| {
+            let mut transaction = client.transaction()?;
+            // An instance claiming a feed at the same time gets its poll, the locks are released
+            // with the transaction.
+            let locked: Vec<String> = transaction
+                .query(
+                    "SELECT feed FROM unnest($1::text[]) AS feeds (feed) \
+                     WHERE pg_try_advisory_xact_lock(hashtext(feed))",
+                    &[&feeds],
+                )?
+                .iter()
+                .map(|row| row.get(0))
+                .collect();
+            let claimed = transaction.query(
+                "INSERT INTO feed_polls (feed, polled_at) \
+                 SELECT feed, now() FROM unnest($1::text[]) AS feeds (feed) \
+                 WHERE NOT EXISTS (SELECT 1 FROM feed_polls AS polls \
+                     WHERE polls.feed = feeds.feed \
+                     AND polls.polled_at > now() - make_interval(secs => $2)) \
+                 ON CONFLICT (feed) DO UPDATE SET polled_at = EXCLUDED.polled_at \
+                 RETURNING feed",
+                &[&locked, &fresh_for],
+            )?;
+            transaction.commit()?;
+            Ok(claimed.iter().map(|row| row.get(0)).collect())
+        })
+    }
+}
+
+/// Date of `item` as stored in the `published` column, in milliseconds since the epoch, undated
+/// items coming first as they do in the order of `NewsItem`.
+fn published(item: &NewsItem) -> i64 {
+    item.pub_date()
+        .map_or(i64::MIN, |date| date.timestamp_millis())
+}
+
+/// Read and starred state of every item of `store`.
+fn item_states(store: &Store) -> States {
+    let read = store
+        .read_states()
+        .into_iter()
+        .map(|(id, state)| ((id, READ), state));
+    let starred = store
+        .starred_states()
+        .into_iter()
+        .map(|(id, state)| ((id, STARRED), state));
+    read.chain(starred).collect()
+}
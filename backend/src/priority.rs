@@ -0,0 +1,152 @@
+//! Priority of unread items, surfacing the ones the user is likely to read.
+//!
+//! The model counts how many items of each feed and with each title keyword were stored and how
+//! many of those the user read. An item is ranked by the read rate of its feed and of its best
+//! known keyword, decayed by its age.
+
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::news::NewsItem;
+use crate::recommend;
+
+/// Weight of the read rate of the feed of an item in its priority.
+const FEED_WEIGHT: f64 = 0.6;
+/// Weight of the read rate of the best known keyword of its title.
+const KEYWORD_WEIGHT: f64 = 0.4;
+/// Number of items a keyword must have appeared in before its read rate counts.
+const MIN_KEYWORD_SEEN: u32 = 3;
+/// Age after which the recency of an item is halved.
+const HALF_LIFE_HOURS: f64 = 24.0;
+
+/// Number of items stored and read.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Tally {
+    seen: u32,
+    read: u32,
+}
+
+impl Tally {
+    /// Share of the items read, starting from even odds.
+    fn rate(self) -> f64 {
+        // Items stored before the model existed can be read without having been seen.
+        let seen = self.seen.max(self.read);
+        (f64::from(self.read) + 1.0) / (f64::from(seen) + 2.0)
+    }
+}
+
+/// What the user tends to read, learned from the items they read.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Interests {
+    feeds: HashMap<String, Tally>,
+    keywords: HashMap<String, Tally>,
+}
+
+impl Interests {
+    /// Count a newly stored item.
+    pub fn observe(&mut self, item: &NewsItem) {
+        self.count(item, |tally| tally.seen += 1);
+    }
+
+    /// Learn from the user reading an item.
+    pub fn learn(&mut self, item: &NewsItem) {
+        self.count(item, |tally| tally.read += 1);
+    }
+
+    fn count<F: Fn(&mut Tally)>(&mut self, item: &NewsItem, count: F) {
+        count(self.feeds.entry(item.feed.clone()).or_default());
+        for keyword in keywords(item) {
+            count(self.keywords.entry(keyword).or_default());
+        }
+    }
+
+    /// Priority of an item between 0 and 1 at `now`.
+    pub fn priority(&self, item: &NewsItem, now: DateTime<Utc>) -> f64 {
+        let feed = self.feeds.get(&item.feed).copied().unwrap_or_default().rate();
+        let keyword = keywords(item)
+            .iter()
+            .filter_map(|keyword| self.keywords.get(keyword))
+            .filter(|tally| tally.seen.max(tally.read) >= MIN_KEYWORD_SEEN)
+            .map(|tally| tally.rate())
+            .max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+            // Titles without a known keyword are ranked by their feed alone.
+            .unwrap_or(feed);
+
+        let age = item
+            .pub_date()
+            .map(|date| (now - date.with_timezone(&Utc)).num_minutes().max(0) as f64 / 60.0)
+            .unwrap_or(0.0);
+        let recency = 0.5_f64.powf(age / HALF_LIFE_HOURS);
+        (FEED_WEIGHT * feed + KEYWORD_WEIGHT * keyword) * (0.5 + 0.5 * recency)
+    }
+}
+
+fn keywords(item: &NewsItem) -> Vec<String> {
+    let mut keywords: Vec<String> = recommend::keywords(item.title().unwrap_or("")).collect();
+    keywords.sort_unstable();
+    keywords.dedup();
+    keywords
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use news_core::test_util;
+
+    fn now() -> DateTime<Utc> {
+        Utc.ymd(2020, 5, 9).and_hms(12, 0, 0)
+    }
+
+    fn item(feed: &str, title: &str, hours_ago: i64) -> NewsItem {
+        let date = now() - chrono::Duration::hours(hours_ago);
+        test_util::item(title).feed(feed).date(date).build()
+    }
+
+    #[test]
+    fn prefers_feeds_the_user_reads() {
+        let mut interests = Interests::default();
+        for n in 0..10 {
+            let read = item("https://read.example.com/feed", &format!("Story {}", n), 30);
+            let skipped = item("https://skipped.example.com/feed", &format!("Post {}", n), 30);
+            interests.observe(&read);
+            interests.observe(&skipped);
+            interests.learn(&read);
+        }
+
+        let read = item("https://read.example.com/feed", "Harbor reopens", 1);
+        let skipped = item("https://skipped.example.com/feed", "Harbor reopens", 1);
+        assert!(interests.priority(&read, now()) > interests.priority(&skipped, now()));
+    }
+
+    #[test]
+    fn prefers_keywords_the_user_reads() {
+        let feed = "https://example.com/feed";
+        let mut interests = Interests::default();
+        for n in 0..5 {
+            let compiler = item(feed, &format!("Compiler release {}", n), 30);
+            let football = item(feed, &format!("Football scores {}", n), 30);
+            interests.observe(&compiler);
+            interests.observe(&football);
+            interests.learn(&compiler);
+        }
+
+        let compiler = item(feed, "Compiler bugs", 1);
+        let football = item(feed, "Football transfers", 1);
+        assert!(interests.priority(&compiler, now()) > interests.priority(&football, now()));
+    }
+
+    #[test]
+    fn prefers_recent_items() {
+        let interests = Interests::default();
+        let fresh = item("https://example.com/feed", "Fresh", 1);
+        let stale = item("https://example.com/feed", "Stale", 72);
+        let (fresh, stale) = (interests.priority(&fresh, now()), interests.priority(&stale, now()));
+        assert!(fresh > stale);
+        assert!(fresh <= 1.0 && stale >= 0.0);
+    }
+}
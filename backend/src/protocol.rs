@@ -0,0 +1,355 @@
+//! Messages exchanged with the frontend over the websocket.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use std::path::Path;
+
+use crate::annotations::Annotations;
+use crate::highlight::{self, Span};
+use crate::images::CachedImage;
+use crate::news::{self, Changes, NewsItem, Source};
+use crate::sanitize;
+use crate::settings::{ContentMode, Settings};
+use crate::snapshot;
+use crate::store::{ReadPosition, Store};
+use crate::typography;
+
+/// Serialization format used for messages sent to a client.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    Json,
+    Msgpack,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding::Json
+    }
+}
+
+/// Query parameters accepted when a client opens the websocket.
+#[derive(Debug, Default, Deserialize)]
+pub struct ConnectParams {
+    #[serde(default)]
+    pub encoding: Encoding,
+    /// Token authorizing the client when the server requires one.
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// An encoded message ready to be written to the websocket.
+pub enum Frame {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+/// Encode a message with the encoding negotiated by the client.
+///
+/// JSON is sent as text, MessagePack as a binary frame.
+pub fn encode(msg: &ServerMessage, encoding: Encoding) -> Result<Frame> {
+    match encoding {
+        Encoding::Json => Ok(Frame::Text(serde_json::to_string(msg)?)),
+        Encoding::Msgpack => Ok(Frame::Binary(rmp_serde::to_vec_named(msg)?)),
+    }
+}
+
+/// Message sent from the server to a client.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Items(ItemBatch),
+    /// Where the user left off reading, possibly on another device, sent once connected.
+    Position(ReadPosition),
+    /// The client fell too far behind and must ask for a full resync.
+    ResyncNeeded,
+    /// How far the feeds being refreshed are fetched.
+    Progress(RefreshProgress),
+}
+
+/// Progress of a refresh, which refreshes started before it finished join.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RefreshProgress {
+    /// Number of feeds fetched, successfully or not.
+    pub feeds_done: usize,
+    pub feeds_total: usize,
+    /// Feed whose fetch started last, `None` once every feed was fetched.
+    pub current_feed: Option<String>,
+}
+
+impl RefreshProgress {
+    pub fn finished(&self) -> bool {
+        self.feeds_done == self.feeds_total
+    }
+}
+
+/// Message sent from a client to the server.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Acknowledge every batch up to and including sequence number `up_to`.
+    Ack { up_to: u64 },
+    /// Ask the server to send the full item set again.
+    Resync,
+    /// The user read an item at `at` on a device whose clock read `sent_at` when sending it.
+    MarkRead {
+        id: String,
+        at: Option<chrono::DateTime<chrono::Utc>>,
+        sent_at: Option<chrono::DateTime<chrono::Utc>>,
+    },
+    /// The user flagged an item as spam or clickbait.
+    MarkJunk { id: String },
+    /// The user starred or unstarred an item, dated like `MarkRead`.
+    Star {
+        id: String,
+        starred: bool,
+        at: Option<chrono::DateTime<chrono::Utc>>,
+        sent_at: Option<chrono::DateTime<chrono::Utc>>,
+    },
+    /// Hide an item until `until`.
+    Snooze {
+        id: String,
+        until: chrono::DateTime<chrono::Utc>,
+    },
+    /// The user focused an item, which other devices resume reading from.
+    Position { id: String },
+    /// Stop the refresh in progress, which then reports the feeds fetched as its progress.
+    CancelRefresh,
+    /// Items of the subscribed feed `feed`, titled `title`, that the client fetched itself while
+    /// the server was unreachable. The server fetches the feed again rather than store them.
+    Fetched {
+        feed: String,
+        title: Option<String>,
+        items: Vec<news::rss::Item>,
+    },
+}
+
+/// A batch of news items.
+///
+/// Items are kept as a flat array of small structs so that repeated keys compress well and no
+/// per-channel metadata is repeated for every item.
+#[derive(Debug, Serialize)]
+pub struct ItemBatch {
+    /// Sequence number the client acknowledges once the batch is applied.
+    pub seq: u64,
+    pub items: Vec<ItemDto>,
+    /// The items were stored before a refresh still running, whose fetched items follow.
+    pub stale: bool,
+    /// Changes to the read or starred state of items the client already has.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub patches: Vec<ItemPatch>,
+}
+
+/// Change to the read or starred state of an item, sent instead of the whole item when nothing
+/// else changed. Only the fields that changed are set.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ItemPatch {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub starred: Option<bool>,
+    /// Last time the read or starred state of the item changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ItemPatch {
+    /// Fold a later patch of the same item into this one.
+    pub fn merge(&mut self, later: ItemPatch) {
+        self.read = later.read.or(self.read);
+        self.starred = later.starred.or(self.starred);
+        self.updated_at = later.updated_at.or(self.updated_at);
+    }
+
+    /// Apply the patch to the DTO of its item.
+    pub fn apply(&self, dto: &mut ItemDto) {
+        if let Some(read) = self.read {
+            dto.read = read;
+        }
+        if let Some(starred) = self.starred {
+            dto.starred = starred;
+        }
+        if self.updated_at.is_some() {
+            dto.updated_at = self.updated_at;
+        }
+    }
+}
+
+/// The subset of a news item the frontend needs to render it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemDto {
+    pub id: String,
+    /// URL of the feed the item came from.
+    pub feed: String,
+    /// Title of the channel of the feed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed_title: Option<String>,
+    /// Other feeds the item was also published in.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub also_in: Vec<Source>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Start of the text of the description, shown in the list while the item is collapsed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+    /// Full article content, only set for feeds configured to show it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// Short summary of a long article, shown as a TL;DR.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// Path of the cached image under the `/cache` route.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_path: Option<String>,
+    /// Blurhash shown in place of the image until it loads.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_placeholder: Option<String>,
+    /// Path of the archived snapshot of the article under the `/cache` route.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_path: Option<String>,
+    /// Copy of the item submitted to the Wayback Machine when it was starred.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived_url: Option<String>,
+    /// Number of times the feed edited the item.
+    pub revisions: usize,
+    /// Differences with the previous revision when the item was edited.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changes: Option<Changes>,
+    /// Whether the link of the item was found dead.
+    pub dead_link: bool,
+    /// Copy of the dead link in the Wayback Machine.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wayback_url: Option<String>,
+    /// Matches of the highlight terms in the title.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub title_highlights: Vec<Span>,
+    /// Matches of the highlight terms in the text of the sanitized description.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub description_highlights: Vec<Span>,
+    /// Whether the user read the item on any device.
+    pub read: bool,
+    pub starred: bool,
+    /// Quality score between 0 (spam or clickbait) and 1.
+    pub quality: f64,
+    /// Whether the item belongs in the low quality folder.
+    pub low_quality: bool,
+    /// Likelihood between 0 and 1 that the user reads the item, ranking the priority inbox.
+    pub priority: f64,
+    /// Estimated minutes it takes to read the item.
+    pub reading_minutes: u32,
+    /// Whether the item is kept out of the unread list because its feed is paused or muted, or
+    /// its title contains a muted keyword.
+    pub muted: bool,
+    /// Time the item is hidden until.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snoozed_until: Option<chrono::DateTime<chrono::Utc>>,
+    /// Id shared by the items covering the same story, if any other item covers it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<String>,
+    /// Whether the item is left out of the river because its feed surfaced enough items that day.
+    pub overflow: bool,
+    /// Last time the read or starred state of the item changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// The user's notes and highlights.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+    /// Publish date, serialized as RFC 3339 in UTC.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pub_date: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ItemDto {
+    /// Assemble the DTO of an item, honoring the content preference of its feed.
+    pub fn new(item: &NewsItem, settings: &Settings, store: &Store) -> Self {
+        let content = match settings.feed(&item.feed).content {
+            ContentMode::Summary => None,
+            ContentMode::FullContent => item.content.clone(),
+        };
+
+        let smart = settings.typography.smart_punctuation;
+        let title = item.title().map(|title| {
+            if smart {
+                typography::smarten(title)
+            } else {
+                title.to_owned()
+            }
+        });
+        let excerpt = item
+            .description()
+            .map(|html| sanitize::excerpt(html, sanitize::EXCERPT_LEN))
+            .filter(|excerpt| !excerpt.is_empty());
+        let description = item
+            .description()
+            .map(|html| smarten_html(sanitize::html(html), smart));
+        let content = content.map(|html| smarten_html(sanitize::html(&html), smart));
+        let title_highlights = title
+            .as_deref()
+            .map(|title| highlight::find(title, &settings.highlights, false))
+            .unwrap_or_default();
+        let description_highlights = description
+            .as_deref()
+            .map(|description| highlight::find(description, &settings.highlights, true))
+            .unwrap_or_default();
+
+        let id = item.id();
+        let dead_link = store.dead_link(&id);
+        ItemDto {
+            archived_url: store.archive_url(&id).map(str::to_owned),
+            revisions: item.revisions.len(),
+            changes: item.changes.clone(),
+            dead_link: dead_link.is_some(),
+            wayback_url: dead_link.and_then(|dead_link| dead_link.wayback_url.clone()),
+            snapshot_path: Some(snapshot::url(&id)).filter(|_| store.has_snapshot(&id)),
+            read: store.is_read(&id),
+            starred: store.is_starred(&id),
+            updated_at: store.updated(&id),
+            quality: item.quality,
+            priority: store.interests.priority(item, chrono::Utc::now()),
+            reading_minutes: item.reading_minutes(),
+            low_quality: item.quality < settings.quality_threshold || store.is_junk(&id),
+            muted: settings.feed(&item.feed).paused
+                || store.is_muted(&item.feed, item.title().unwrap_or("")),
+            snoozed_until: store.snoozed_until(&id),
+            annotations: store.annotations(&id).cloned(),
+            id,
+            feed: item.feed.clone(),
+            feed_title: item.feed_title.clone(),
+            also_in: item.also_in.clone(),
+            title,
+            link: item.link().map(str::to_owned),
+            description,
+            excerpt,
+            content,
+            summary: item.summary.clone(),
+            image_path: item.cached_image().as_deref().and_then(cache_url),
+            image_placeholder: item.image_placeholder.clone(),
+            title_highlights,
+            description_highlights,
+            cluster: None,
+            overflow: false,
+            pub_date: item.pub_date().map(|date| date.with_timezone(&chrono::Utc)),
+        }
+    }
+}
+
+fn smarten_html(html: String, smart: bool) -> String {
+    if smart {
+        typography::smarten_html(&html)
+    } else {
+        html
+    }
+}
+
+/// Return the URL path a cached file is served from.
+fn cache_url(path: &Path) -> Option<String> {
+    let cache_dir = news::cache_dir().ok()?;
+    let relative = path.strip_prefix(cache_dir).ok()?;
+    Some(format!("/cache/{}", relative.to_string_lossy()))
+}
@@ -0,0 +1,129 @@
+//! Quality scoring of ingested items.
+//!
+//! Items get a score between 0 (spam or clickbait) and 1 from a few heuristics on their title and
+//! description, lowered further by a naive Bayes model once the user marked enough items as junk.
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use crate::news::NewsItem;
+use crate::recommend;
+
+/// Phrases typical of clickbait titles.
+const CLICKBAIT: &[&str] = &[
+    "you won't believe",
+    "you will never believe",
+    "what happened next",
+    "this one trick",
+    "will blow your mind",
+    "doctors hate",
+    "can't stop",
+    "shocking",
+    "jaw-dropping",
+    "mind-blowing",
+    "the reason why",
+    "must see",
+    "gone wrong",
+    "sponsored",
+];
+/// Number of junk and read items needed before the Bayes model is trusted.
+const MIN_TRAINING: usize = 5;
+
+/// Word counts of items the user marked as junk or read.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bayes {
+    junk_words: HashMap<String, u32>,
+    ham_words: HashMap<String, u32>,
+    junk_items: usize,
+    ham_items: usize,
+}
+
+impl Bayes {
+    pub fn train(&mut self, item: &NewsItem, junk: bool) {
+        let (words, items) = if junk {
+            (&mut self.junk_words, &mut self.junk_items)
+        } else {
+            (&mut self.ham_words, &mut self.ham_items)
+        };
+        *items += 1;
+        for word in tokens(item) {
+            *words.entry(word).or_insert(0) += 1;
+        }
+    }
+
+    /// Probability that an item is junk, `None` until enough items were trained on.
+    pub fn junk_probability(&self, item: &NewsItem) -> Option<f64> {
+        if self.junk_items < MIN_TRAINING || self.ham_items < MIN_TRAINING {
+            return None;
+        }
+
+        let junk_total: u32 = self.junk_words.values().sum();
+        let ham_total: u32 = self.ham_words.values().sum();
+        let vocabulary = (self.junk_words.len() + self.ham_words.len()) as f64;
+        let items = (self.junk_items + self.ham_items) as f64;
+        let mut junk = (self.junk_items as f64 / items).ln();
+        let mut ham = (self.ham_items as f64 / items).ln();
+        for word in tokens(item) {
+            let count = |words: &HashMap<String, u32>| f64::from(*words.get(&word).unwrap_or(&0));
+            junk += ((count(&self.junk_words) + 1.0) / (f64::from(junk_total) + vocabulary)).ln();
+            ham += ((count(&self.ham_words) + 1.0) / (f64::from(ham_total) + vocabulary)).ln();
+        }
+        Some(1.0 / (1.0 + (ham - junk).exp()))
+    }
+}
+
+/// Score the quality of an item between 0 and 1.
+pub fn score(item: &NewsItem, bayes: &Bayes) -> f64 {
+    let heuristic = heuristic_score(item);
+    match bayes.junk_probability(item) {
+        Some(junk) => heuristic.min(1.0 - junk),
+        None => heuristic,
+    }
+}
+
+fn heuristic_score(item: &NewsItem) -> f64 {
+    let title = item.title().unwrap_or("");
+    let lower = title.to_lowercase();
+    let mut score: f64 = 1.0;
+
+    if CLICKBAIT.iter().any(|phrase| lower.contains(phrase)) {
+        score -= 0.5;
+    }
+
+    let letters: Vec<char> = title.chars().filter(|c| c.is_alphabetic()).collect();
+    let upper = letters.iter().filter(|c| c.is_uppercase()).count();
+    if letters.len() >= 10 && upper * 2 > letters.len() {
+        score -= 0.3;
+    }
+
+    if title.matches('!').count() >= 2 || title.contains("?!") {
+        score -= 0.2;
+    }
+
+    // Listicles such as "17 things you ...".
+    let listicle = lower
+        .split_whitespace()
+        .next()
+        .map_or(false, |word| word.parse::<u32>().is_ok())
+        && lower.contains("things");
+    if listicle {
+        score -= 0.2;
+    }
+
+    if item.description().map_or(true, |d| d.trim().len() < 20) && item.content.is_none() {
+        score -= 0.1;
+    }
+
+    score.max(0.0)
+}
+
+fn tokens(item: &NewsItem) -> Vec<String> {
+    let mut words: Vec<String> = recommend::keywords(item.title().unwrap_or(""))
+        .chain(recommend::keywords(item.description().unwrap_or("")))
+        .collect();
+    words.sort_unstable();
+    words.dedup();
+    words
+}
@@ -0,0 +1,63 @@
+//! In-memory LRU cache of the answers to hot queries on the stored items.
+//!
+//! Building the DTO of an item sanitizes its HTML, so answers to the queries every websocket
+//! connection and dashboard makes are kept until the server next writes anything.
+
+use lru::LruCache;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::items::ItemsQuery;
+use crate::protocol::ItemDto;
+
+/// Number of distinct item queries whose answers are kept.
+const CAPACITY: usize = 16;
+
+pub struct QueryCache {
+    items: RefCell<LruCache<ItemsQuery, Vec<ItemDto>>>,
+    /// Number of unread items keyed by feed.
+    unread_counts: RefCell<Option<HashMap<String, usize>>>,
+}
+
+impl Default for QueryCache {
+    fn default() -> Self {
+        QueryCache::new()
+    }
+}
+
+impl QueryCache {
+    pub fn new() -> Self {
+        QueryCache {
+            items: RefCell::new(LruCache::new(CAPACITY)),
+            unread_counts: RefCell::new(None),
+        }
+    }
+
+    /// Return the answer to `query`, computing it with `answer` unless it is cached.
+    pub fn items(&self, query: ItemsQuery, answer: impl FnOnce() -> Vec<ItemDto>) -> Vec<ItemDto> {
+        if let Some(items) = self.items.borrow_mut().get(&query) {
+            return items.clone();
+        }
+        let items = answer();
+        self.items.borrow_mut().put(query, items.clone());
+        items
+    }
+
+    /// Return the unread counts, computing them with `answer` unless they are cached.
+    pub fn unread_counts(
+        &self,
+        answer: impl FnOnce() -> HashMap<String, usize>,
+    ) -> HashMap<String, usize> {
+        self.unread_counts
+            .borrow_mut()
+            .get_or_insert_with(answer)
+            .clone()
+    }
+
+    /// Forget every answer, after a write.
+    pub fn invalidate(&self) {
+        self.items.borrow_mut().clear();
+        *self.unread_counts.borrow_mut() = None;
+    }
+}
@@ -0,0 +1,44 @@
+//! The queue of items to read next through `GET/PUT /api/queue`.
+//!
+//! The user orders the queue by hand, so it is stored as a whole list of item ids that every
+//! device replaces when it adds, removes or moves an item.
+
+use actix::Addr;
+use actix_web::{error, web, Error, HttpResponse};
+
+use crate::server::{GetQueue, NewsServer, SetQueue};
+
+/// Maximum number of queued items.
+const MAX_QUEUED: usize = 500;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/api/queue")
+            .route(web::get().to(get_queue))
+            .route(web::put().to(put_queue)),
+    );
+}
+
+async fn get_queue(server: web::Data<Addr<NewsServer>>) -> Result<HttpResponse, Error> {
+    let queue = server
+        .send(GetQueue)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(queue))
+}
+
+async fn put_queue(
+    server: web::Data<Addr<NewsServer>>,
+    queue: web::Json<Vec<String>>,
+) -> Result<HttpResponse, Error> {
+    let queue = queue.into_inner();
+    if queue.len() > MAX_QUEUED {
+        return Err(error::ErrorBadRequest("too many queued items"));
+    }
+
+    let queue = server
+        .send(SetQueue(queue))
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(queue))
+}
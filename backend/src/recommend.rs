@@ -0,0 +1,127 @@
+//! Feed recommendations based on what the user actually reads.
+//!
+//! Titles of read items are split into keywords, and feeds from a bundled catalog are ranked by
+//! how many of those keywords match their topics.
+
+use actix::Addr;
+use actix_web::{error, web, Error, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use crate::config::Config;
+use crate::news::NewsItem;
+use crate::server::{NewsServer, Recommend};
+use crate::store::Store;
+
+/// Number of feeds recommended at most.
+const MAX_RECOMMENDATIONS: usize = 10;
+/// Number of matching keywords given as the reason for a recommendation.
+const MAX_REASONS: usize = 3;
+/// Words too common to say anything about the user's interests.
+const STOPWORDS: &[&str] = &[
+    "about", "after", "again", "also", "been", "before", "being", "could", "does", "from", "have",
+    "here", "into", "just", "more", "most", "new", "next", "over", "says", "some", "than", "that",
+    "their", "them", "then", "there", "these", "they", "this", "what", "when", "where", "which",
+    "while", "will", "with", "would", "your",
+];
+
+/// A feed in the bundled catalog.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CatalogFeed {
+    pub title: String,
+    pub url: String,
+    pub topics: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Recommendation {
+    pub title: String,
+    pub url: String,
+    pub score: usize,
+    /// Keywords from read items that matched the feed's topics.
+    pub reasons: Vec<String>,
+}
+
+/// Return the bundled catalog of public feeds.
+pub fn catalog() -> Vec<CatalogFeed> {
+    serde_json::from_str(include_str!("../data/catalog.json")).expect("invalid bundled catalog")
+}
+
+/// Split a title into lowercase keywords.
+pub fn keywords(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric() && c != '-')
+        .map(|word| word.trim_matches('-').to_lowercase())
+        .filter(|word| word.chars().count() >= 3 && !STOPWORDS.contains(&word.as_str()))
+}
+
+/// Rank catalog feeds the user isn't subscribed to by affinity with the items they read.
+pub fn recommend<'a, I>(
+    items: I,
+    store: &Store,
+    subscribed: &[String],
+    catalog: &[CatalogFeed],
+) -> Vec<Recommendation>
+where
+    I: IntoIterator<Item = &'a NewsItem>,
+{
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for item in items {
+        if !store.is_read(&item.id()) {
+            continue;
+        }
+        for keyword in keywords(item.title().unwrap_or("")) {
+            *counts.entry(keyword).or_insert(0) += 1;
+        }
+    }
+
+    let mut recommendations: Vec<Recommendation> = catalog
+        .iter()
+        .filter(|feed| !subscribed.contains(&feed.url))
+        .filter_map(|feed| {
+            let mut matches: Vec<(&String, usize)> = feed
+                .topics
+                .iter()
+                .filter_map(|topic| counts.get(topic).map(|&count| (topic, count)))
+                .collect();
+            if matches.is_empty() {
+                return None;
+            }
+
+            matches.sort_by(|a, b| b.1.cmp(&a.1));
+            Some(Recommendation {
+                title: feed.title.clone(),
+                url: feed.url.clone(),
+                score: matches.iter().map(|(_, count)| count).sum(),
+                reasons: matches
+                    .into_iter()
+                    .take(MAX_REASONS)
+                    .map(|(topic, _)| topic.clone())
+                    .collect(),
+            })
+        })
+        .collect();
+
+    recommendations.sort_by(|a, b| b.score.cmp(&a.score));
+    recommendations.truncate(MAX_RECOMMENDATIONS);
+    recommendations
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/api/recommendations", web::get().to(get_recommendations));
+}
+
+async fn get_recommendations(
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+) -> Result<HttpResponse, Error> {
+    if !config.recommendations {
+        return Err(error::ErrorNotFound("recommendations are disabled"));
+    }
+
+    let recommendations = server
+        .send(Recommend)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(recommendations))
+}
@@ -0,0 +1,125 @@
+//! Balancing of the river of news, the timeline merging every feed.
+//!
+//! A feed posting dozens of items a day would drown out the others, so only the first items a
+//! feed publishes each day, in the time zone of the settings, surface in the river, up to the
+//! limit configured for it. The others stay in the view of their feed. Counting from the start of
+//! the day keeps the items already surfaced in place as more arrive.
+
+use chrono::prelude::*;
+
+use std::collections::{HashMap, HashSet};
+
+use crate::news::NewsItem;
+use crate::settings::Settings;
+
+/// Return the ids of the items left out of the river because their feed already surfaced as many
+/// items that day as allowed.
+pub fn overflow<'a, I>(items: I, settings: &Settings) -> HashSet<String>
+where
+    I: IntoIterator<Item = &'a NewsItem>,
+{
+    let mut dated: Vec<(&NewsItem, DateTime<FixedOffset>)> = items
+        .into_iter()
+        .filter_map(|item| Some((item, item.pub_date()?)))
+        .collect();
+    dated.sort_by_key(|(_, date)| *date);
+
+    let mut counts: HashMap<(&str, NaiveDate), usize> = HashMap::new();
+    let mut overflow = HashSet::new();
+    for (item, date) in dated {
+        let max = match settings.max_per_day(&item.feed) {
+            Some(max) => max,
+            None => continue,
+        };
+        let day = settings
+            .local(date.with_timezone(&Utc))
+            .date()
+            .naive_local();
+        let count = counts.entry((item.feed.as_str(), day)).or_default();
+        *count += 1;
+        if *count > max {
+            overflow.insert(item.id());
+        }
+    }
+    overflow
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use news_core::test_util::item;
+
+    use crate::settings::FeedSettings;
+
+    const BUSY: &str = "https://busy.example.com/feed";
+    const QUIET: &str = "https://quiet.example.com/feed";
+
+    /// Return `count` items of `feed` published an hour apart from 8:00 on `day` of May 2020.
+    fn day_of(feed: &str, day: u32, count: u32) -> Vec<NewsItem> {
+        (0..count)
+            .map(|hour| {
+                let date = Local.ymd(2020, 5, day).and_hms(8 + hour, 0, 0);
+                let title = format!("{} {} {}", feed, day, hour);
+                item(&title).feed(feed).date(date).build()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_item_surfaces_by_default() {
+        let items = day_of(BUSY, 4, 10);
+        assert!(overflow(&items, &Settings::default()).is_empty());
+    }
+
+    #[test]
+    fn limits_items_per_feed_and_day() {
+        let mut settings = Settings::default();
+        settings.river_max_per_day = Some(3);
+        let mut items = day_of(BUSY, 4, 5);
+        items.extend(day_of(BUSY, 5, 2));
+        items.extend(day_of(QUIET, 4, 3));
+        // Out of order, as stored items and fetched ones are mixed.
+        items.reverse();
+
+        let overflow = overflow(&items, &settings);
+        let expected: HashSet<String> = day_of(BUSY, 4, 5)[3..].iter().map(NewsItem::id).collect();
+        assert_eq!(overflow, expected);
+    }
+
+    #[test]
+    fn feeds_override_the_limit() {
+        let mut settings = Settings::default();
+        settings.river_max_per_day = Some(1);
+        let quiet = FeedSettings {
+            max_per_day: Some(3),
+            ..FeedSettings::default()
+        };
+        settings.feeds.insert(QUIET.to_string(), quiet);
+        let mut items = day_of(BUSY, 4, 2);
+        items.extend(day_of(QUIET, 4, 3));
+
+        let overflow = overflow(&items, &settings);
+        assert_eq!(overflow.len(), 1);
+        assert!(overflow.contains(&day_of(BUSY, 4, 2)[1].id()));
+    }
+
+    #[test]
+    fn days_follow_the_time_zone_of_the_settings() {
+        let mut settings = Settings::default();
+        settings.river_max_per_day = Some(1);
+        let late = Utc.ymd(2020, 5, 4).and_hms(23, 30, 0).with_timezone(&Local);
+        let early = Utc.ymd(2020, 5, 5).and_hms(0, 30, 0).with_timezone(&Local);
+        let items = vec![
+            item("Late").feed(BUSY).date(late).build(),
+            item("Early").feed(BUSY).date(early).build(),
+        ];
+
+        settings.timezone = Some("UTC".to_string());
+        assert!(overflow(&items, &settings).is_empty());
+        // Both fall on the evening of May 4 in New York.
+        settings.timezone = Some("America/New_York".to_string());
+        let overflow = overflow(&items, &settings);
+        assert_eq!(overflow.into_iter().collect::<Vec<_>>(), [items[1].id()]);
+    }
+}
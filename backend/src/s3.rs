@@ -0,0 +1,286 @@
+//! Client of S3 compatible object stores, e.g. AWS S3 or MinIO, signing requests with AWS
+//! Signature Version 4.
+//!
+//! Only the calls the backend needs are implemented. Buckets are addressed path style, as
+//! `{endpoint}/{bucket}/{key}`, which every S3 compatible service supports, and the keys given are
+//! relative to the `prefix` of the config.
+
+use anyhow::{anyhow, Result};
+use chrono::prelude::*;
+use hmac::{Hmac, Mac, NewMac};
+use reqwest::{Method, StatusCode, Url};
+use sha2::{Digest, Sha256};
+
+use crate::config::S3Config;
+
+/// A bucket, or the part of it under the configured prefix.
+#[derive(Clone)]
+pub struct Bucket {
+    config: S3Config,
+    client: reqwest::Client,
+}
+
+impl Bucket {
+    pub fn new(config: S3Config) -> Self {
+        Bucket {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Store `body` as the object `key`, replacing any object already there.
+    pub async fn put(&self, key: &str, body: Vec<u8>, content_type: &str) -> Result<()> {
+        self.send(Method::PUT, key, &[], body, Some(content_type))
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Return the content of the object `key`, or `None` if there is none.
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let response = self.send(Method::GET, key, &[], Vec::new(), None).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(response.error_for_status()?.bytes().await?.to_vec()))
+    }
+
+    /// Return the keys of the objects starting with `prefix`, in lexicographic order.
+    pub async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let full_prefix = format!("{}{}", self.config.prefix, prefix);
+        let mut keys = Vec::new();
+        let mut continuation = None;
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", full_prefix.as_str())];
+            if let Some(token) = &continuation {
+                query.push(("continuation-token", token.as_str()));
+            }
+            let listing = self
+                .send(Method::GET, "", &query, Vec::new(), None)
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            keys.extend(
+                elements(&listing, "Key")
+                    .into_iter()
+                    .filter_map(|key| key.strip_prefix(&self.config.prefix).map(str::to_string)),
+            );
+            let truncated = elements(&listing, "IsTruncated").iter().any(|text| text == "true");
+            continuation = elements(&listing, "NextContinuationToken").into_iter().next();
+            if !truncated || continuation.is_none() {
+                return Ok(keys);
+            }
+        }
+    }
+
+    /// Delete the object `key`, which succeeds as well when there is none.
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        self.send(Method::DELETE, key, &[], Vec::new(), None)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Send a signed request about the object `key`, or about the bucket when `key` is empty.
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        query: &[(&str, &str)],
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> Result<reqwest::Response> {
+        let endpoint = Url::parse(&self.config.endpoint)?;
+        let host = match (endpoint.host_str(), endpoint.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(anyhow!("no host in {}", self.config.endpoint)),
+        };
+        let mut path = format!(
+            "{}/{}",
+            endpoint.path().trim_end_matches('/'),
+            encode(&self.config.bucket, false)
+        );
+        if !key.is_empty() {
+            path = format!("{}/{}", path, encode(&format!("{}{}", self.config.prefix, key), true));
+        }
+        let mut query: Vec<String> = query
+            .iter()
+            .map(|(name, value)| format!("{}={}", encode(name, false), encode(value, false)))
+            .collect();
+        query.sort();
+        let query = query.join("&");
+
+        let now = Utc::now();
+        let payload_hash = hex(&Sha256::digest(&body));
+        let request = SignedRequest {
+            method: method.as_str(),
+            host: &host,
+            path: &path,
+            query: &query,
+            payload_hash: &payload_hash,
+            now,
+        };
+        let authorization = request.authorization(&self.config);
+
+        let mut url = format!("{}://{}{}", endpoint.scheme(), host, path);
+        if !query.is_empty() {
+            url = format!("{}?{}", url, query);
+        }
+        let mut request = self
+            .client
+            .request(method, &url)
+            .header("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string())
+            .header("x-amz-content-sha256", payload_hash)
+            .header("Authorization", authorization)
+            .body(body);
+        if let Some(content_type) = content_type {
+            request = request.header("Content-Type", content_type);
+        }
+        Ok(request.send().await?)
+    }
+}
+
+/// What Signature Version 4 signs of a request.
+struct SignedRequest<'a> {
+    method: &'a str,
+    host: &'a str,
+    /// URI encoded path.
+    path: &'a str,
+    /// URI encoded query, its parameters sorted.
+    query: &'a str,
+    /// Hex encoded SHA-256 hash of the body.
+    payload_hash: &'a str,
+    now: DateTime<Utc>,
+}
+
+impl SignedRequest<'_> {
+    /// Return the `Authorization` header of the request.
+    fn authorization(&self, config: &S3Config) -> String {
+        let date = self.now.format("%Y%m%d").to_string();
+        let timestamp = self.now.format("%Y%m%dT%H%M%SZ").to_string();
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            self.method,
+            self.path,
+            self.query,
+            self.host,
+            self.payload_hash,
+            timestamp,
+            signed_headers,
+            self.payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            timestamp,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = signing_key(&config.secret_key, &date, &config.region, "s3");
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            config.access_key,
+            scope,
+            signed_headers,
+            hex(&hmac(&key, string_to_sign.as_bytes()))
+        )
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_varkey(key).expect("hmac takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Derive the key requests of `date` to `service` in `region` are signed with.
+fn signing_key(secret_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac(format!("AWS4{}", secret_key).as_bytes(), date.as_bytes());
+    let key = hmac(&key, region.as_bytes());
+    let key = hmac(&key, service.as_bytes());
+    hmac(&key, b"aws4_request")
+}
+
+/// URI encode `text` the way Signature Version 4 expects, keeping slashes in paths.
+fn encode(text: &str, keep_slashes: bool) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if keep_slashes => encoded.push('/'),
+            byte => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Return the text of the XML elements named `name`, which hold no other element.
+fn elements(xml: &str, name: &str) -> Vec<String> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let mut texts = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let end = match rest.find(&close) {
+            Some(end) => end,
+            None => break,
+        };
+        texts.push(
+            rest[..end]
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&"),
+        );
+        rest = &rest[end + close.len()..];
+    }
+    texts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derives_signing_keys() {
+        // The example of the AWS documentation.
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex(&key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn encodes_uris() {
+        assert_eq!(encode("news/backup 1.opml", true), "news/backup%201.opml");
+        assert_eq!(encode("news/", false), "news%2F");
+        assert_eq!(encode("é~", false), "%C3%A9~");
+    }
+
+    #[test]
+    fn reads_listings() {
+        let listing = "<ListBucketResult><IsTruncated>false</IsTruncated>\
+                       <Contents><Key>news/a&amp;b.opml</Key></Contents>\
+                       <Contents><Key>news/c.opml</Key></Contents></ListBucketResult>";
+        assert_eq!(elements(listing, "Key"), vec!["news/a&b.opml", "news/c.opml"]);
+        assert_eq!(elements(listing, "IsTruncated"), vec!["false"]);
+        assert!(elements(listing, "NextContinuationToken").is_empty());
+    }
+}
@@ -0,0 +1,109 @@
+//! Items feeds date in the future, e.g. posts scheduled ahead of time or dated in the wrong time
+//! zone. Sorted newest first, they would stay at the top of the lists until their date, so they
+//! are either dated when they were fetched or held back until their date passes, as the settings
+//! choose.
+
+use chrono::prelude::*;
+
+use std::collections::HashMap;
+
+use crate::news::NewsItem;
+use crate::settings::FutureItems;
+
+/// Minutes an item may be dated ahead of the server, whose clock may lag the feed's.
+const SLACK_MINUTES: i64 = 5;
+
+/// Whether `item` is dated in the future as of `now`.
+pub fn is_future(item: &NewsItem, now: DateTime<Utc>) -> bool {
+    let slack = chrono::Duration::minutes(SLACK_MINUTES);
+    item.pub_date().map_or(false, |date| date > now + slack)
+}
+
+/// Split the fetched `items` into the ones to store now and the ones to hold back until their
+/// date. Items dated in the future are dated `now` when clamped, unless one was `stored` already,
+/// which keeps the date it was stored with.
+pub fn schedule<'a, I>(
+    items: Vec<NewsItem>,
+    stored: I,
+    future_items: FutureItems,
+    now: DateTime<Utc>,
+) -> (Vec<NewsItem>, Vec<NewsItem>)
+where
+    I: IntoIterator<Item = &'a NewsItem>,
+{
+    let (future, mut ready): (Vec<NewsItem>, Vec<NewsItem>) =
+        items.into_iter().partition(|item| is_future(item, now));
+    if future.is_empty() || future_items == FutureItems::Hold {
+        return (ready, future);
+    }
+
+    let dates: HashMap<String, DateTime<FixedOffset>> = stored
+        .into_iter()
+        .filter_map(|item| Some((item.id(), item.pub_date()?)))
+        .collect();
+    for mut item in future {
+        log::debug!("{:?} is dated in the future, dating it now", item.title());
+        let date = dates
+            .get(&item.id())
+            .copied()
+            .unwrap_or_else(|| now.with_timezone(&FixedOffset::east(0)));
+        item.set_pub_date(date);
+        ready.push(item);
+    }
+    (ready, Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use news_core::test_util::item;
+
+    fn titles(items: &[NewsItem]) -> Vec<&str> {
+        items.iter().filter_map(NewsItem::title).collect()
+    }
+
+    #[test]
+    fn clamps_future_dates() {
+        let now = Utc.ymd(2020, 5, 9).and_hms(12, 0, 0);
+        let items = vec![
+            item("Past").date(now - chrono::Duration::hours(1)).build(),
+            item("Skewed")
+                .date(now + chrono::Duration::minutes(2))
+                .build(),
+            item("Future")
+                .date(Utc.ymd(2030, 1, 1).and_hms(0, 0, 0))
+                .build(),
+        ];
+        let (ready, held) = schedule(items, &[], FutureItems::Clamp, now);
+        assert!(held.is_empty());
+        assert_eq!(titles(&ready), ["Past", "Skewed", "Future"]);
+        assert_eq!(
+            ready[1].pub_date().unwrap(),
+            now + chrono::Duration::minutes(2)
+        );
+        assert_eq!(ready[2].pub_date().unwrap(), now);
+
+        // Fetched again later, the item keeps the date it was stored with.
+        let later = now + chrono::Duration::hours(1);
+        let refetched = vec![item("Future")
+            .date(Utc.ymd(2030, 1, 1).and_hms(0, 0, 0))
+            .build()];
+        let (ready, _) = schedule(refetched, &ready, FutureItems::Clamp, later);
+        assert_eq!(ready[0].pub_date().unwrap(), now);
+    }
+
+    #[test]
+    fn holds_future_items() {
+        let now = Utc.ymd(2020, 5, 9).and_hms(12, 0, 0);
+        let tomorrow = now + chrono::Duration::days(1);
+        let items = vec![
+            item("Past").date(now).build(),
+            item("Tomorrow").date(tomorrow).build(),
+        ];
+        let (ready, held) = schedule(items, &[], FutureItems::Hold, now);
+        assert_eq!(titles(&ready), ["Past"]);
+        assert_eq!(titles(&held), ["Tomorrow"]);
+        assert!(!is_future(&held[0], tomorrow));
+    }
+}
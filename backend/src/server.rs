@@ -0,0 +1,2184 @@
+//! The `NewsServer` actor owns the stored items, schedules feed fetches, and broadcasts fetched
+//! items to every connected websocket session.
+
+use actix::prelude::*;
+use chrono::prelude::*;
+use serde::Serialize;
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::activity::{self, Event};
+use crate::annotations::Annotations;
+use crate::anomaly::{self, Anomaly};
+use crate::backup::BackupData;
+use crate::cluster;
+use crate::config::FetchLimits;
+use crate::images::{self, CachedImage};
+use crate::items::ItemsQuery;
+use crate::desktop::DesktopNotifier;
+use crate::digest::{self, DigestDay};
+use crate::fanout::{Fanout, RemoteItems};
+use crate::hooks::IngestHooks;
+use crate::folders::{self, FeedOrder};
+use crate::news::{self, NewsItem};
+use crate::news_store::NewsStore;
+use crate::import::{ImportSummary, Imported};
+use crate::links;
+use crate::media::{self, Media};
+use crate::plugins::Plugins;
+use crate::protocol::{ItemDto, ItemPatch, RefreshProgress};
+use crate::quality;
+use crate::query_cache::QueryCache;
+use crate::recommend::{self, CatalogFeed, Recommendation};
+use crate::river;
+use crate::schedule;
+use crate::settings::{FutureItems, ImageCaching, Settings};
+use crate::snapshot;
+use crate::storage;
+use crate::store::{Mute, ReadPosition, Share, Store};
+use crate::summarize::{self, Summarizer};
+use crate::translate::Translation;
+use crate::views::View;
+
+/// Number of feeds fetched at the same time.
+const MAX_CONCURRENT_FETCHES: usize = 4;
+/// How often snoozed and held items are checked for being due.
+const SNOOZE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// How often the links of starred items are checked for being dead.
+const LINK_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// How often the size of the cache is checked against its cap.
+const CACHE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Updates pushed to a websocket session.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub enum Push {
+    Items(Vec<ItemDto>),
+    /// Changes to only the read or starred state of items.
+    Patches(Vec<ItemPatch>),
+    Progress(RefreshProgress),
+}
+
+/// Register a websocket session to receive pushed items.
+#[derive(Message)]
+#[rtype(result = "usize")]
+pub struct Connect {
+    pub addr: Recipient<Push>,
+}
+
+/// Unregister a websocket session.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Disconnect {
+    pub id: usize,
+}
+
+/// Queue a fetch of one feed, or of every feed that isn't paused when `feed` is `None`.
+#[derive(Message)]
+#[rtype(result = "Vec<String>")]
+pub struct Refresh {
+    pub feed: Option<String>,
+}
+
+/// The user focused an item.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SetPosition {
+    pub id: String,
+}
+
+/// Return where the user left off reading.
+#[derive(Message)]
+#[rtype(result = "Option<ReadPosition>")]
+pub struct GetPosition;
+
+/// Return the active mutes.
+#[derive(Message)]
+#[rtype(result = "Vec<Mute>")]
+pub struct GetMutes;
+
+/// Replace the active mutes.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SetMutes(pub Vec<Mute>);
+
+/// Return the read-only shares.
+#[derive(Message)]
+#[rtype(result = "Vec<Share>")]
+pub struct GetShares;
+
+/// Return the share of a token, if it wasn't revoked.
+#[derive(Message)]
+#[rtype(result = "Option<Share>")]
+pub struct GetShare(pub String);
+
+/// Add a read-only share.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct AddShare(pub Share);
+
+/// Revoke the share of a token, returning whether there was one.
+#[derive(Message)]
+#[rtype(result = "bool")]
+pub struct RevokeShare(pub String);
+
+/// Return the folders and order of the subscribed feeds.
+#[derive(Message)]
+#[rtype(result = "FeedOrder")]
+pub struct GetFeedOrder;
+
+/// Replace the folders and order of the feeds, returning the order kept for the subscribed feeds.
+#[derive(Message)]
+#[rtype(result = "FeedOrder")]
+pub struct SetFeedOrder(pub FeedOrder);
+
+/// Return the ids of the items to read next, in reading order.
+#[derive(Message)]
+#[rtype(result = "Vec<String>")]
+pub struct GetQueue;
+
+/// Replace the items to read next, returning the queue kept: unknown and repeated items are
+/// dropped.
+#[derive(Message)]
+#[rtype(result = "Vec<String>")]
+pub struct SetQueue(pub Vec<String>);
+
+/// Return the DTOs of at most `limit` items of a view, newest first.
+#[derive(Message)]
+#[rtype(result = "Vec<ItemDto>")]
+pub struct ViewItems {
+    pub view: View,
+    pub limit: usize,
+}
+
+/// Return the daily digests of the last `days` days in the time zone of `offset`, newest first.
+#[derive(Message)]
+#[rtype(result = "Vec<DigestDay>")]
+pub struct Digest {
+    pub days: u32,
+    /// Offset of the reader's time zone, that of the settings when unset.
+    pub offset: Option<FixedOffset>,
+}
+
+/// Return every stored item, newest first.
+#[derive(Message)]
+#[rtype(result = "Vec<ItemDto>")]
+pub struct GetItems;
+
+/// Return the DTOs of the stored items answering a query, newest first, without waiting for a
+/// refresh in progress.
+#[derive(Message)]
+#[rtype(result = "CachedItems")]
+pub struct QueryItems(pub ItemsQuery);
+
+/// Stored items, possibly about to be outdated by a refresh.
+#[derive(Debug)]
+pub struct CachedItems {
+    pub items: Vec<ItemDto>,
+    /// A refresh is running, whose fetched items are pushed to sessions once stored.
+    pub stale: bool,
+}
+
+/// Return the number of unread items of each feed.
+#[derive(Message)]
+#[rtype(result = "HashMap<String, usize>")]
+pub struct UnreadCounts;
+
+/// List queued and running fetches.
+#[derive(Message)]
+#[rtype(result = "Vec<JobInfo>")]
+pub struct ListJobs;
+
+/// Stop the refresh in progress, dropping queued fetches and abandoning running ones.
+#[derive(Message)]
+#[rtype(result = "CancelledRefresh")]
+pub struct CancelRefresh;
+
+/// What a canceled refresh got done.
+#[derive(Debug, Serialize)]
+pub struct CancelledRefresh {
+    /// Feeds fetched before the refresh was canceled, successfully or not.
+    pub completed: Vec<String>,
+    /// Feeds left unfetched.
+    pub cancelled: Vec<String>,
+}
+
+/// Return what the scheduled backups hold.
+#[derive(Message)]
+#[rtype(result = "anyhow::Result<BackupData>")]
+pub struct GetBackup;
+
+/// Drop stored items published before `before`, returning how many were removed.
+#[derive(Message)]
+#[rtype(result = "anyhow::Result<usize>")]
+pub struct Prune {
+    pub before: DateTime<Utc>,
+}
+
+/// The user read an item.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct MarkRead {
+    pub id: String,
+    /// Time the item was read, on the server's clock.
+    pub at: DateTime<Utc>,
+}
+
+/// The user flagged an item as spam or clickbait.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct MarkJunk {
+    pub id: String,
+}
+
+/// A client fetched a subscribed feed itself while the server was unreachable.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct FetchedByClient {
+    pub feed: String,
+}
+
+/// Hide an item until `until`.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Snooze {
+    pub id: String,
+    pub until: DateTime<Utc>,
+}
+
+/// Return a stored item.
+#[derive(Message)]
+#[rtype(result = "Option<ItemDto>")]
+pub struct GetItem {
+    pub id: String,
+}
+
+/// Return the annotations of an item, `None` if there is no such item.
+#[derive(Message)]
+#[rtype(result = "Option<Annotations>")]
+pub struct GetAnnotations {
+    pub id: String,
+}
+
+/// Return the DTOs of the items the user annotated, newest first.
+#[derive(Message)]
+#[rtype(result = "Vec<ItemDto>")]
+pub struct GetAnnotated;
+
+/// Replace the annotations of an item, returning them, or `None` if there is no such item.
+#[derive(Message)]
+#[rtype(result = "Option<Annotations>")]
+pub struct SetAnnotations {
+    pub id: String,
+    pub annotations: Annotations,
+}
+
+/// Return the cached translation of an item.
+#[derive(Message)]
+#[rtype(result = "Option<Translation>")]
+pub struct GetTranslation {
+    pub id: String,
+    pub to: String,
+}
+
+/// Cache the translation of an item.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SaveTranslation {
+    pub id: String,
+    pub translation: Translation,
+}
+
+/// Change the read or starred state of items.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct UpdateItems {
+    pub ids: Vec<String>,
+    pub read: Option<bool>,
+    pub starred: Option<bool>,
+    /// Time of the change, on the server's clock. Items changed later elsewhere are left alone.
+    pub at: DateTime<Utc>,
+}
+
+/// Subscribe to a feed, returning whether it wasn't subscribed to already.
+#[derive(Message)]
+#[rtype(result = "bool")]
+pub struct Subscribe {
+    pub url: String,
+}
+
+/// Apply state migrated from another reader.
+#[derive(Message)]
+#[rtype(result = "ImportSummary")]
+pub struct Import(pub Imported);
+
+/// List the URLs of the subscribed feeds.
+#[derive(Message)]
+#[rtype(result = "Vec<String>")]
+pub struct ListFeeds;
+
+/// Recommend feeds based on reading history.
+#[derive(Message)]
+#[rtype(result = "Vec<Recommendation>")]
+pub struct Recommend;
+
+/// The user changed their settings.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SettingsChanged(pub Settings);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+}
+
+/// A queued or running fetch of a feed.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub feed: String,
+    pub state: JobState,
+    pub since: DateTime<Utc>,
+    /// Handle of the fetch once running, which canceling the refresh drops.
+    #[serde(skip)]
+    handle: Option<SpawnHandle>,
+}
+
+pub struct NewsServer {
+    feeds: Vec<String>,
+    settings: Settings,
+    /// Handle of the periodic refresh, replaced when the interval changes.
+    refresh_timer: Option<SpawnHandle>,
+    /// Time of the last scheduled refresh that actually ran.
+    last_scheduled_refresh: Option<DateTime<Utc>>,
+    items: BTreeSet<NewsItem>,
+    /// Fetched items dated in the future, stored once their date passes.
+    held: Vec<NewsItem>,
+    /// Cluster id of every item covering the same story as another one, keyed by item id.
+    clusters: HashMap<String, String>,
+    /// Ids of the items left out of the river to keep busy feeds from drowning out the others.
+    overflow: HashSet<String>,
+    store: Store,
+    /// Persists the items and the store whenever they change.
+    db: Box<dyn NewsStore>,
+    catalog: Vec<CatalogFeed>,
+    summarizer: Arc<dyn Summarizer>,
+    /// Shows desktop notifications about new items when configured.
+    notifier: Option<DesktopNotifier>,
+    /// Shares stored items with the other instances when configured.
+    fanout: Option<Fanout>,
+    sessions: HashMap<usize, Recipient<Push>>,
+    next_session: usize,
+    /// Fetches in the order they were queued.
+    jobs: Vec<JobInfo>,
+    /// Progress of the latest refresh.
+    progress: RefreshProgress,
+    /// Feeds fetched by the latest refresh, in the order they completed.
+    refreshed: Vec<String>,
+    /// Anomaly of each feed posting at an unusual rate, already reported.
+    anomalies: HashMap<String, Anomaly>,
+    /// Maximum size of the cache directory in bytes.
+    cache_cap: Option<u64>,
+    /// Answers to hot queries, forgotten on every write.
+    queries: QueryCache,
+    /// Bucket the cached images and snapshots are mirrored to when configured.
+    media: Option<Media>,
+    /// Hooks new items are passed to when configured.
+    hooks: Option<IngestHooks>,
+    /// Plugins adapting sources and filtering fetched items when configured.
+    plugins: Option<Arc<Plugins>>,
+    /// Limits on the size of fetched feeds and the number of items taken from them.
+    fetch_limits: FetchLimits,
+}
+
+impl Actor for NewsServer {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        if let Some(fanout) = &self.fanout {
+            fanout.subscribe(ctx.address().recipient());
+        }
+        self.schedule_refresh(ctx);
+        ctx.run_interval(SNOOZE_CHECK_INTERVAL, |act, _| {
+            act.wake_snoozed();
+            act.expire_mutes();
+            act.release_held();
+        });
+        ctx.run_interval(LINK_CHECK_INTERVAL, |act, ctx| act.check_links(ctx));
+        if self.cache_cap.is_some() {
+            self.enforce_cache_cap();
+            ctx.run_interval(CACHE_CHECK_INTERVAL, |act, _| act.enforce_cache_cap());
+        }
+        self.restore_media(ctx);
+    }
+}
+
+impl NewsServer {
+    pub fn new(
+        mut feeds: Vec<String>,
+        db: Box<dyn NewsStore>,
+        settings: Settings,
+        summarizer: Arc<dyn Summarizer>,
+        notifier: Option<DesktopNotifier>,
+        fanout: Option<Fanout>,
+        cache_cap: Option<u64>,
+    ) -> Self {
+        let items = db.load_items().unwrap_or_else(|e| {
+            log::error!("failed to load stored items: {}", e);
+            BTreeSet::new()
+        });
+
+        let store = db.load_state().unwrap_or_else(|e| {
+            log::error!("failed to load store: {}", e);
+            Store::default()
+        });
+
+        let clusters = cluster::cluster(&items);
+        let overflow = river::overflow(&items, &settings);
+        for feed in store.feeds() {
+            if !feeds.contains(feed) {
+                feeds.push(feed.clone());
+            }
+        }
+
+        NewsServer {
+            feeds,
+            settings,
+            refresh_timer: None,
+            last_scheduled_refresh: None,
+            items,
+            held: Vec::new(),
+            clusters,
+            overflow,
+            store,
+            db,
+            catalog: recommend::catalog(),
+            summarizer,
+            notifier,
+            fanout,
+            sessions: HashMap::new(),
+            next_session: 0,
+            jobs: Vec::new(),
+            progress: RefreshProgress::default(),
+            refreshed: Vec::new(),
+            anomalies: HashMap::new(),
+            cache_cap,
+            queries: QueryCache::new(),
+            media: None,
+            hooks: None,
+            plugins: None,
+            fetch_limits: FetchLimits::default(),
+        }
+    }
+
+    /// Mirror the cached images and snapshots to `media`.
+    pub fn with_media(mut self, media: Option<Media>) -> Self {
+        self.media = media;
+        self
+    }
+
+    /// Pass every new item to `hooks` once stored.
+    pub fn with_hooks(mut self, hooks: Option<IngestHooks>) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Fetch feeds through `plugins`.
+    pub fn with_plugins(mut self, plugins: Option<Arc<Plugins>>) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    /// Limit the size of fetched feeds and the number of items taken from them to `limits`.
+    pub fn with_fetch_limits(mut self, limits: FetchLimits) -> Self {
+        self.fetch_limits = limits;
+        self
+    }
+
+    /// (Re)start the periodic refresh of every feed using the configured interval.
+    fn schedule_refresh(&mut self, ctx: &mut Context<Self>) {
+        if let Some(handle) = self.refresh_timer.take() {
+            ctx.cancel_future(handle);
+        }
+
+        let interval = Duration::from_secs(u64::from(self.settings.refresh_interval_minutes) * 60);
+        self.refresh_timer = Some(ctx.run_interval(interval, |act, ctx| {
+            if act.deferred_by_quiet_hours() {
+                log::debug!("skipping scheduled refresh during quiet hours");
+                return;
+            }
+            log::info!("starting scheduled refresh");
+            act.last_scheduled_refresh = Some(Utc::now());
+            let feeds = act.claim_feeds(act.active_feeds());
+            act.refresh(feeds, ctx);
+            act.cache_starred_images(ctx);
+            act.detect_anomalies();
+        }));
+    }
+
+    /// Return the feeds that aren't paused.
+    fn active_feeds(&self) -> Vec<String> {
+        self.feeds
+            .iter()
+            .filter(|feed| !self.settings.feed(feed).paused)
+            .cloned()
+            .collect()
+    }
+
+    /// Keep the feeds that no other instance sharing the store polled lately, claiming them.
+    fn claim_feeds(&self, feeds: Vec<String>) -> Vec<String> {
+        // Half the interval, so that instances whose timers drift apart don't both skip a round.
+        let interval = chrono::Duration::minutes(i64::from(self.settings.refresh_interval_minutes));
+        match self.db.claim_feeds(&feeds, interval / 2) {
+            Ok(claimed) => claimed,
+            Err(e) => {
+                // Polling twice beats not polling at all.
+                log::error!("failed to claim feeds: {}", e);
+                feeds
+            }
+        }
+    }
+
+    /// Whether it is quiet hours and the reduced refresh interval hasn't elapsed yet.
+    fn deferred_by_quiet_hours(&self) -> bool {
+        let quiet_hours = &self.settings.quiet_hours;
+        if !quiet_hours.contains(self.settings.now().time()) {
+            return false;
+        }
+        let interval =
+            chrono::Duration::minutes(i64::from(quiet_hours.refresh_interval_minutes));
+        match self.last_scheduled_refresh {
+            Some(last) => Utc::now() - last < interval,
+            None => false,
+        }
+    }
+
+    /// Queue fetches of `feeds` and start as many as allowed, returning the newly queued ones.
+    fn refresh(&mut self, feeds: Vec<String>, ctx: &mut Context<Self>) -> Vec<String> {
+        let queued: Vec<String> = feeds
+            .into_iter()
+            .filter(|feed| self.enqueue(feed))
+            .collect();
+        // Feeds queued while others are fetched count towards the same refresh.
+        if self.progress.finished() {
+            self.progress = RefreshProgress::default();
+            self.refreshed.clear();
+        }
+        self.progress.feeds_total += queued.len();
+        self.run_queue(ctx);
+        // Sessions asking for a refresh learn how far it is even when it queued nothing.
+        self.broadcast_progress();
+        queued
+    }
+
+    /// Queue a fetch of `feed` unless one is already queued or running.
+    fn enqueue(&mut self, feed: &str) -> bool {
+        if self.jobs.iter().any(|job| job.feed == feed) {
+            return false;
+        }
+
+        self.jobs.push(JobInfo {
+            feed: feed.to_string(),
+            state: JobState::Queued,
+            since: Utc::now(),
+            handle: None,
+        });
+        true
+    }
+
+    /// Start queued fetches while there is capacity for them.
+    fn run_queue(&mut self, ctx: &mut Context<Self>) {
+        loop {
+            let running = self
+                .jobs
+                .iter()
+                .filter(|job| job.state == JobState::Running)
+                .count();
+            if running >= MAX_CONCURRENT_FETCHES {
+                return;
+            }
+
+            let job = match self
+                .jobs
+                .iter_mut()
+                .find(|job| job.state == JobState::Queued)
+            {
+                Some(job) => job,
+                None => return,
+            };
+            job.state = JobState::Running;
+            job.since = Utc::now();
+
+            let feed = job.feed.clone();
+            self.progress.current_feed = Some(feed.clone());
+            let feed_settings = self.settings.feed(&feed);
+            let (content, transform) = (feed_settings.content, feed_settings.transform);
+            let images = self.settings.images.clone();
+            let unshorten = self.settings.unshorten_links;
+            let summarizer = self.summarizer.clone();
+            let mirror = self.media.clone();
+            let plugins = self.plugins.clone();
+            let limits = self.fetch_limits;
+            let known: HashSet<String> = self.items.iter().map(NewsItem::id).collect();
+            log::debug!("fetching {}", feed);
+            let fetched = feed.clone();
+            // Canceling drops the future at its next await, so fetches stop between requests.
+            let handle = async move {
+                let mut result = news::fetch_feed(
+                    &feed, content, &images, unshorten, plugins, transform, limits, &known,
+                )
+                .await;
+                if let Ok(items) = result.as_mut() {
+                    summarize::summarize_items(summarizer.as_ref(), items, &known).await;
+                    if let Some(mirror) = &mirror {
+                        let images = items.iter().filter_map(|item| item.cached_image());
+                        mirror_images(mirror, images.collect()).await;
+                    }
+                }
+                (feed, result)
+            }
+            .into_actor(self)
+            .map(|(feed, result), act, ctx| {
+                act.jobs.retain(|job| job.feed != feed);
+                act.refreshed.push(feed.clone());
+                match result {
+                    Ok(items) => act.store(items),
+                    Err(e) => {
+                        log::error!("failed to fetch {}: {}", feed, e);
+                        activity::record(Event::FetchFailed {
+                            feed,
+                            error: e.to_string(),
+                        });
+                    }
+                }
+                act.progress.feeds_done += 1;
+                act.run_queue(ctx);
+                if act.progress.finished() {
+                    act.progress.current_feed = None;
+                }
+                act.broadcast_progress();
+            })
+            .spawn(ctx);
+            if let Some(job) = self.jobs.iter_mut().find(|job| job.feed == fetched) {
+                job.handle = Some(handle);
+            }
+        }
+    }
+
+    /// Build the DTO of a stored item.
+    fn dto(&self, item: &NewsItem) -> ItemDto {
+        let mut dto = ItemDto::new(item, &self.settings, &self.store);
+        dto.cluster = self.clusters.get(&dto.id).cloned();
+        dto.overflow = self.overflow.contains(&dto.id);
+        dto
+    }
+
+    /// Store fetched items and push them to every session, along with stored items that joined
+    /// a different cluster or were trimmed because of them. Items dated in the future are dated
+    /// now or held back, as the settings choose.
+    fn store(&mut self, items: Vec<NewsItem>) {
+        let (mut items, held) = schedule::schedule(
+            items,
+            &self.items,
+            self.settings.future_items,
+            Utc::now(),
+        );
+        self.hold(held);
+        self.merge_aliases(&mut items);
+        self.revise(&mut items);
+        let mut changed: HashSet<String> = self.merge_duplicates(&mut items);
+        changed.extend(items.iter().map(NewsItem::id));
+        let mut dirty = false;
+        for item in items.iter_mut() {
+            item.quality = quality::score(item, &self.store.bayes);
+            // Revisions of stored items were counted when first stored.
+            if item.revisions.is_empty() {
+                self.store.interests.observe(item);
+                dirty = true;
+            }
+            if let Some(link) = item.link() {
+                dirty |= self.store.apply_pending(&item.id(), link);
+            }
+        }
+        if dirty {
+            if let Err(e) = self.db.save_state(&self.store) {
+                log::error!("failed to save store: {}", e);
+            }
+        }
+        self.notify_desktop(&items);
+        self.run_hooks(&items);
+        if let Some(fanout) = self.fanout.as_mut() {
+            fanout.publish(&items);
+        }
+        self.items.extend(items);
+        if let Err(e) = self.db.save_items(&self.items) {
+            log::error!("failed to save items: {}", e);
+        }
+        self.push_changes(changed);
+    }
+
+    /// Hold back fetched items dated in the future, replacing the ones held already.
+    fn hold(&mut self, items: Vec<NewsItem>) {
+        for item in items {
+            log::debug!("holding {:?} until {:?}", item.title(), item.pub_date());
+            let id = item.id();
+            self.held.retain(|held| held.id() != id);
+            self.held.push(item);
+        }
+    }
+
+    /// Store the held items whose date passed, or every one once they aren't held anymore.
+    fn release_held(&mut self) {
+        let now = Utc::now();
+        let hold = self.settings.future_items == FutureItems::Hold;
+        let (due, held): (Vec<NewsItem>, Vec<NewsItem>) = self
+            .held
+            .drain(..)
+            .partition(|item| !hold || !schedule::is_future(item, now));
+        self.held = held;
+        if !due.is_empty() {
+            log::info!("releasing {} held item(s)", due.len());
+            self.store(due);
+        }
+    }
+
+    /// Take in items another instance stored and push them to every session.
+    fn store_remote(&mut self, items: Vec<NewsItem>) {
+        let changed: HashSet<String> = items.iter().map(NewsItem::id).collect();
+        // Revised items replace their previous version, which may be dated differently.
+        self.items.retain(|item| !changed.contains(&item.id()));
+        self.items.extend(items);
+        if let Err(e) = self.db.save_items(&self.items) {
+            log::error!("failed to save items: {}", e);
+        }
+        self.push_changes(changed);
+    }
+
+    /// Push the items among `changed` to every session, along with stored items that joined a
+    /// different cluster or were trimmed because of them.
+    fn push_changes(&mut self, mut changed: HashSet<String>) {
+        changed.extend(self.trim_feeds());
+
+        let clusters = cluster::cluster(&self.items);
+        changed.extend(
+            self.items
+                .iter()
+                .map(NewsItem::id)
+                .filter(|id| clusters.get(id) != self.clusters.get(id)),
+        );
+        self.clusters = clusters;
+        let overflow = river::overflow(&self.items, &self.settings);
+        changed.extend(self.overflow.symmetric_difference(&overflow).cloned());
+        self.overflow = overflow;
+        let dtos: Vec<ItemDto> = self
+            .items
+            .iter()
+            .filter(|item| changed.contains(&item.id()))
+            .map(|item| self.dto(item))
+            .collect();
+
+        self.broadcast(dtos);
+    }
+
+    /// Return the link of a stored item.
+    fn link(&self, id: &str) -> Option<String> {
+        self.items
+            .iter()
+            .find(|item| item.id() == id)
+            .and_then(NewsItem::link)
+            .map(str::to_owned)
+    }
+
+    /// Push the current state of a stored item to every session.
+    fn push_item(&self, id: &str) {
+        let dtos = self
+            .items
+            .iter()
+            .filter(|item| item.id() == id)
+            .map(|item| self.dto(item))
+            .collect();
+        self.broadcast(dtos);
+    }
+
+    /// Archive a snapshot of the page of an item unless there already is one.
+    fn archive(&self, id: &str, ctx: &mut Context<Self>) {
+        if self.store.has_snapshot(id) {
+            return;
+        }
+        let link = match self.link(id) {
+            Some(link) => link,
+            None => return,
+        };
+
+        let id = id.to_string();
+        let mirror = self.media.clone();
+        async move {
+            let result = snapshot::archive(&id, &link).await;
+            if let (Ok(()), Some(mirror), Ok(path)) = (&result, &mirror, snapshot::path(&id)) {
+                if let Err(e) = mirror.upload(vec![path]).await {
+                    log::warn!("failed to mirror the snapshot of {}: {}", link, e);
+                }
+            }
+            (id, link, result)
+        }
+        .into_actor(self)
+        .map(|(id, link, result), act, _| match result {
+            Ok(()) => {
+                log::info!("archived {}", link);
+                act.store.add_snapshot(&id);
+                if let Err(e) = act.db.save_state(&act.store) {
+                    log::error!("failed to save store: {}", e);
+                }
+                act.push_item(&id);
+            }
+            Err(e) => log::error!("failed to archive {}: {}", link, e),
+        })
+        .spawn(ctx);
+    }
+
+    /// Submit the link of an item to the Wayback Machine unless it was already.
+    fn save_to_wayback(&self, id: &str, ctx: &mut Context<Self>) {
+        if self.store.archive_url(id).is_some() {
+            return;
+        }
+        let link = match self.link(id) {
+            Some(link) => link,
+            None => return,
+        };
+
+        let id = id.to_string();
+        async move {
+            let result = links::save_to_wayback(&link).await;
+            (id, link, result)
+        }
+        .into_actor(self)
+        .map(|(id, link, result), act, _| match result {
+            Ok(url) => {
+                log::info!("saved {} to the wayback machine as {}", link, url);
+                act.store.set_archive_url(&id, url);
+                if let Err(e) = act.db.save_state(&act.store) {
+                    log::error!("failed to save store: {}", e);
+                }
+                act.push_item(&id);
+            }
+            Err(e) => log::error!("failed to save {} to the wayback machine: {}", link, e),
+        })
+        .spawn(ctx);
+    }
+
+    /// Download the images of starred items missing from the cache, when only those are cached.
+    fn cache_starred_images(&self, ctx: &mut Context<Self>) {
+        let policy = &self.settings.images;
+        if !policy.allows(ImageCaching::Starred) {
+            return;
+        }
+        let downloads: Vec<(String, String, PathBuf)> = self
+            .items
+            .iter()
+            .filter(|item| self.store.is_starred(&item.id()) && item.cached_image().is_none())
+            .filter_map(|item| Some((item.id(), item.image_url()?, item.image_path.clone()?)))
+            .collect();
+        if downloads.is_empty() {
+            return;
+        }
+
+        let policy = policy.clone();
+        let mirror = self.media.clone();
+        async move {
+            let mut cached = Vec::new();
+            let mut paths = Vec::new();
+            for (id, url, path) in downloads {
+                match images::download(&url, &path, &policy).await {
+                    Ok(()) => {
+                        cached.push(id);
+                        paths.extend(images::cached(&path));
+                    }
+                    Err(e) => log::warn!("failed to cache image: {}", e),
+                }
+            }
+            if let Some(mirror) = &mirror {
+                mirror_images(mirror, paths).await;
+            }
+            cached
+        }
+        .into_actor(self)
+        .map(|cached, act, _| {
+            for id in &cached {
+                act.push_item(id);
+            }
+        })
+        .spawn(ctx);
+    }
+
+    /// Download the media missing from the cache directory from their mirror, then push the items
+    /// whose images came back.
+    fn restore_media(&self, ctx: &mut Context<Self>) {
+        let mirror = match &self.media {
+            Some(mirror) => mirror.clone(),
+            None => return,
+        };
+        async move { mirror.restore().await }
+            .into_actor(self)
+            .map(|result, act, _| match result {
+                Ok(0) => {}
+                Ok(count) => {
+                    log::info!("restored {} cached file(s) from the media bucket", count);
+                    let dtos = act
+                        .items
+                        .iter()
+                        .filter(|item| item.cached_image().is_some())
+                        .map(|item| act.dto(item))
+                        .collect();
+                    act.broadcast(dtos);
+                }
+                Err(e) => log::error!("failed to restore media: {}", e),
+            })
+            .spawn(ctx);
+    }
+
+    /// Pass the fetched items that are new to the ingest hooks.
+    fn run_hooks(&self, items: &[NewsItem]) {
+        let hooks = match &self.hooks {
+            Some(hooks) => hooks,
+            None => return,
+        };
+        let known: HashSet<String> = self.items.iter().map(NewsItem::id).collect();
+        let new: Vec<ItemDto> = items
+            .iter()
+            .filter(|item| !known.contains(&item.id()))
+            .map(|item| self.dto(item))
+            .collect();
+        hooks.ingest(&new);
+    }
+
+    /// Show desktop notifications about the fetched items that are new and match a rule.
+    fn notify_desktop(&self, items: &[NewsItem]) {
+        let notifier = match &self.notifier {
+            Some(notifier) => notifier,
+            None => return,
+        };
+        if self.settings.quiet_hours.contains(self.settings.now().time()) {
+            return;
+        }
+
+        let known: HashSet<String> = self.items.iter().map(NewsItem::id).collect();
+        let new: Vec<&NewsItem> = items
+            .iter()
+            .filter(|item| {
+                let id = item.id();
+                !known.contains(&id)
+                    && !self.settings.feed(&item.feed).paused
+                    && !self.store.is_muted(&item.feed, item.title().unwrap_or(""))
+                    && !self.store.is_read(&id)
+                    && !self.store.is_junk(&id)
+                    && item.quality >= self.settings.quality_threshold
+            })
+            .filter(|item| notifier.matches(item))
+            .collect();
+        if !new.is_empty() {
+            notifier.notify(&new);
+            activity::record(Event::Notified { count: new.len() });
+        }
+    }
+
+    /// Relabel fetched items of alias feeds with their canonical feed, and drop the fetched items
+    /// already stored through another URL of the same feed.
+    fn merge_aliases(&self, items: &mut Vec<NewsItem>) {
+        for item in items.iter_mut() {
+            item.feed = self.settings.canonical_feed(&item.feed).to_string();
+        }
+        let merged: HashSet<&str> = self
+            .settings
+            .aliases()
+            .into_iter()
+            .map(|(_, canonical)| canonical)
+            .collect();
+        if merged.is_empty() {
+            return;
+        }
+
+        // Another URL of a feed may give items other guids, but they keep their links.
+        let stored: HashMap<(&str, &str), Option<&str>> = self
+            .items
+            .iter()
+            .filter(|item| merged.contains(item.feed.as_str()))
+            .filter_map(|item| Some(((item.feed.as_str(), item.link()?), item.guid())))
+            .collect();
+        items.retain(|item| {
+            let key = match item.link() {
+                Some(link) => (item.feed.as_str(), link),
+                None => return true,
+            };
+            match stored.get(&key) {
+                Some(guid) => *guid == item.guid(),
+                None => true,
+            }
+        });
+    }
+
+    /// Relabel the stored items of alias feeds with their canonical feed, dropping the items
+    /// stored through both URLs.
+    fn relabel_aliases(&mut self) {
+        let aliased = self
+            .items
+            .iter()
+            .any(|item| self.settings.canonical_feed(&item.feed) != item.feed);
+        if !aliased {
+            return;
+        }
+
+        let items = std::mem::take(&mut self.items);
+        let mut links = HashSet::new();
+        for mut item in items {
+            item.feed = self.settings.canonical_feed(&item.feed).to_string();
+            let key = item.link().map(|link| (item.feed.clone(), link.to_string()));
+            if let Some(key) = key {
+                if !links.insert(key) {
+                    continue;
+                }
+            }
+            self.items.insert(item);
+        }
+        log::info!("merged the items of alias feeds");
+        if let Err(e) = self.db.save_items(&self.items) {
+            log::error!("failed to save items: {}", e);
+        }
+        self.clusters = cluster::cluster(&self.items);
+        self.overflow = river::overflow(&self.items, &self.settings);
+        self.push_all();
+    }
+
+    /// Turn fetched items the feed edited into new revisions of the stored ones, replacing them,
+    /// and drop fetched items matching the latest revision of a stored one.
+    fn revise(&mut self, items: &mut Vec<NewsItem>) {
+        let stored: HashMap<(&str, &str), &NewsItem> = self
+            .items
+            .iter()
+            .filter_map(|item| Some(((item.feed.as_str(), item.guid()?), item)))
+            .collect();
+
+        let mut replaced = Vec::new();
+        items.retain(|item| {
+            let key = match item.guid() {
+                Some(guid) => (item.feed.as_str(), guid),
+                None => return true,
+            };
+            let previous = match stored.get(&key) {
+                Some(previous) => previous,
+                None => return true,
+            };
+            if !item.edits(previous) {
+                // Fetched again unchanged, the stored item holds the history of revised ones.
+                return previous.revisions.is_empty();
+            }
+            replaced.push((*previous).clone());
+            true
+        });
+        if replaced.is_empty() {
+            return;
+        }
+
+        for previous in &replaced {
+            if let Some(item) = items.iter_mut().find(|item| item.edits(previous)) {
+                log::info!("{:?} was edited", item.title());
+                item.revise(previous);
+            }
+            let id = previous.id();
+            self.items.retain(|item| item.id() != id);
+        }
+    }
+
+    /// Merge fetched items linking to the same page as a stored item of another feed into it,
+    /// returning the ids of the stored items that gained a source.
+    fn merge_duplicates(&mut self, items: &mut Vec<NewsItem>) -> HashSet<String> {
+        let merged = news::merge_duplicates(&self.items, items);
+        for (id, sources) in &merged {
+            let mut original = match self.items.iter().find(|item| item.id() == *id) {
+                Some(original) => original.clone(),
+                None => continue,
+            };
+            log::debug!("{:?} is also in {:?}", original.title(), sources);
+            original.also_in.extend(sources.iter().cloned());
+            self.items.retain(|item| item.id() != *id);
+            self.items.insert(original);
+        }
+        merged.keys().cloned().collect()
+    }
+
+    /// Evict cached images, then the bodies of the oldest items, while the cache is over its cap.
+    fn enforce_cache_cap(&mut self) {
+        let usage = match storage::usage(self.cache_cap) {
+            Ok(usage) => usage,
+            Err(e) => {
+                log::error!("failed to measure the cache: {}", e);
+                return;
+            }
+        };
+        if usage.excess() == 0 {
+            return;
+        }
+        log::info!("cache is {} byte(s) over its cap", usage.excess());
+
+        let cached: HashMap<PathBuf, String> = self
+            .items
+            .iter()
+            .filter_map(|item| Some((item.cached_image()?, item.id())))
+            .collect();
+        // Starred items are meant to be kept, so are their images.
+        let kept: HashSet<PathBuf> = cached
+            .iter()
+            .filter(|(_, id)| self.store.is_starred(id))
+            .map(|(path, _)| path.clone())
+            .collect();
+        let evicted = match storage::evict_images(usage.excess(), &kept) {
+            Ok(evicted) => evicted,
+            Err(e) => {
+                log::error!("failed to evict cached images: {}", e);
+                Vec::new()
+            }
+        };
+        let mut changed: HashSet<String> = evicted
+            .iter()
+            .filter_map(|path| cached.get(path).cloned())
+            .collect();
+
+        let mut excess = storage::usage(self.cache_cap).map_or(0, |usage| usage.excess());
+        let mut dropped = 0;
+        if excess > 0 {
+            // Items are ordered oldest first.
+            let items = std::mem::take(&mut self.items);
+            self.items = items
+                .into_iter()
+                .map(|mut item| {
+                    let id = item.id();
+                    // Highlights are ranges of the body.
+                    let kept = self.store.is_starred(&id) || self.store.is_annotated(&id);
+                    if excess > 0 && !kept {
+                        let freed = item.drop_body();
+                        if freed > 0 {
+                            excess = excess.saturating_sub(freed);
+                            dropped += 1;
+                            changed.insert(id);
+                        }
+                    }
+                    item
+                })
+                .collect();
+            if let Err(e) = self.db.save_items(&self.items) {
+                log::error!("failed to save items: {}", e);
+            }
+        }
+
+        activity::record(Event::CacheEvicted {
+            freed_images: evicted.len(),
+            dropped_bodies: dropped,
+        });
+        let dtos = self
+            .items
+            .iter()
+            .filter(|item| changed.contains(&item.id()))
+            .map(|item| self.dto(item))
+            .collect();
+        self.broadcast(dtos);
+    }
+
+    /// Report the feeds that started posting at an unusual rate according to their stored items.
+    fn detect_anomalies(&mut self) {
+        let settings = &self.settings.anomalies;
+        if !settings.enabled {
+            self.anomalies.clear();
+            return;
+        }
+
+        let mut dates: HashMap<&str, Vec<DateTime<Utc>>> = HashMap::new();
+        for item in &self.items {
+            if let Some(date) = item.pub_date() {
+                dates
+                    .entry(&item.feed)
+                    .or_default()
+                    .push(date.with_timezone(&Utc));
+            }
+        }
+
+        let now = Utc::now();
+        let mut anomalies = HashMap::new();
+        // Items of aliases are stored under their canonical feed.
+        for feed in self.active_feeds() {
+            if self.settings.canonical_feed(&feed) != feed {
+                continue;
+            }
+            let dates = dates.remove(feed.as_str()).unwrap_or_default();
+            if let Some(anomaly) = anomaly::detect(dates, now, settings) {
+                anomalies.insert(feed, anomaly);
+            }
+        }
+
+        for (feed, anomaly) in &anomalies {
+            // Report each anomaly once rather than on every refresh while it lasts.
+            let reported = self.anomalies.get(feed).map_or(false, |reported| {
+                std::mem::discriminant(reported) == std::mem::discriminant(anomaly)
+            });
+            if reported {
+                continue;
+            }
+            log::warn!("{}", anomaly.describe(feed));
+            activity::record(match anomaly {
+                Anomaly::Silent { since, usual } => Event::FeedSilent {
+                    feed: feed.clone(),
+                    since: *since,
+                    usual_interval_hours: usual.num_minutes() as f64 / 60.0,
+                },
+                Anomaly::Flood { count, usual } => Event::FeedFlooded {
+                    feed: feed.clone(),
+                    count: *count,
+                    usual_count: *usual,
+                },
+            });
+            if settings.notify {
+                if let Some(notifier) = &self.notifier {
+                    notifier.alert("Unusual feed activity".to_string(), anomaly.describe(feed));
+                }
+            }
+        }
+        self.anomalies = anomalies;
+    }
+
+    /// Mark read the unread items of feeds limited to their latest items beyond that limit,
+    /// returning their ids.
+    fn trim_feeds(&mut self) -> Vec<String> {
+        let mut unread: HashMap<&str, Vec<String>> = HashMap::new();
+        // Items are ordered oldest first.
+        for item in self.items.iter().rev() {
+            let id = item.id();
+            if !self.store.is_read(&id) {
+                unread.entry(&item.feed).or_default().push(id);
+            }
+        }
+
+        let mut trimmed = Vec::new();
+        for (feed, ids) in unread {
+            if let Some(keep) = self.settings.feed(feed).keep_latest {
+                if ids.len() > keep {
+                    activity::record(Event::FeedLimitApplied {
+                        feed: feed.to_string(),
+                        count: ids.len() - keep,
+                    });
+                    trimmed.extend(ids.into_iter().skip(keep));
+                }
+            }
+        }
+        if trimmed.is_empty() {
+            return trimmed;
+        }
+
+        log::debug!("marking {} item(s) beyond feed limits read", trimmed.len());
+        for id in &trimmed {
+            self.store.mark_read(id);
+        }
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+        trimmed
+    }
+
+    /// Push the items whose snooze is over back to every session as unread.
+    fn wake_snoozed(&mut self) {
+        let due = self.store.wake(Utc::now());
+        if due.is_empty() {
+            return;
+        }
+        log::debug!("waking {} snoozed item(s)", due.len());
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+
+        let dtos = self
+            .items
+            .iter()
+            .filter(|item| due.contains(&item.id()))
+            .map(|item| self.dto(item))
+            .collect();
+        self.broadcast(dtos);
+    }
+
+    /// Check whether the links of starred items are dead, looking up Wayback Machine copies of
+    /// newly dead ones.
+    fn check_links(&self, ctx: &mut Context<Self>) {
+        let links: Vec<(String, String, bool)> = self
+            .items
+            .iter()
+            .filter_map(|item| {
+                let id = item.id();
+                if !self.store.is_starred(&id) {
+                    return None;
+                }
+                let known_dead = self.store.dead_link(&id).is_some();
+                Some((id, item.link()?.to_string(), known_dead))
+            })
+            .collect();
+        if links.is_empty() {
+            return;
+        }
+        log::debug!("checking {} starred link(s)", links.len());
+
+        async move {
+            let client = match links::client() {
+                Ok(client) => client,
+                Err(e) => {
+                    log::error!("failed to create link check client: {}", e);
+                    return Vec::new();
+                }
+            };
+            let mut results = Vec::new();
+            for (id, link, known_dead) in links {
+                match links::is_dead(&client, &link).await {
+                    Ok(true) if known_dead => {}
+                    Ok(true) => {
+                        let wayback_url = links::wayback_copy(&client, &link)
+                            .await
+                            .unwrap_or_else(|e| {
+                                log::warn!("failed to look up {} in the archive: {}", link, e);
+                                None
+                            });
+                        results.push((id, Some(wayback_url)));
+                    }
+                    Ok(false) => results.push((id, None)),
+                    Err(e) => log::debug!("failed to check {}: {}", link, e),
+                }
+            }
+            results
+        }
+        .into_actor(self)
+        .map(|results, act, _| {
+            let mut changed = HashSet::new();
+            for (id, dead) in results {
+                let flipped = match dead {
+                    Some(wayback_url) => act.store.set_dead_link(&id, wayback_url),
+                    None => act.store.clear_dead_link(&id),
+                };
+                if flipped {
+                    changed.insert(id);
+                }
+            }
+            if changed.is_empty() {
+                return;
+            }
+            log::info!("{} starred link(s) changed state", changed.len());
+            if let Err(e) = act.db.save_state(&act.store) {
+                log::error!("failed to save store: {}", e);
+            }
+            let dtos = act
+                .items
+                .iter()
+                .filter(|item| changed.contains(&item.id()))
+                .map(|item| act.dto(item))
+                .collect();
+            act.broadcast(dtos);
+        })
+        .spawn(ctx);
+    }
+
+    /// Lift expired mutes, pushing every item back to the sessions if any was.
+    fn expire_mutes(&mut self) {
+        if !self.store.expire_mutes(Utc::now()) {
+            return;
+        }
+        log::debug!("lifting expired mutes");
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+        self.push_all();
+    }
+
+    /// Push every item to every session.
+    fn push_all(&self) {
+        let dtos = self.items.iter().map(|item| self.dto(item)).collect();
+        self.broadcast(dtos);
+    }
+
+    /// Return the DTOs of the items answering `query`, newest first.
+    fn query_items(&self, query: ItemsQuery) -> Vec<ItemDto> {
+        self.queries.items(query.clone(), || {
+            let items = self.db.query_items(&query).unwrap_or_else(|e| {
+                log::error!("failed to query items: {}", e);
+                Vec::new()
+            });
+            items
+                .iter()
+                .map(|item| {
+                    let mut dto = self.dto(item);
+                    if query.excerpts_only {
+                        dto.description = None;
+                        dto.content = None;
+                    }
+                    dto
+                })
+                .collect()
+        })
+    }
+
+    /// Push items to every session.
+    fn broadcast(&self, dtos: Vec<ItemDto>) {
+        // Every change visible to clients is broadcast.
+        self.queries.invalidate();
+        for session in self.sessions.values() {
+            let _ = session.do_send(Push::Items(dtos.clone()));
+        }
+    }
+
+    /// Push the progress of the refresh to every session.
+    fn broadcast_progress(&self) {
+        for session in self.sessions.values() {
+            let _ = session.do_send(Push::Progress(self.progress.clone()));
+        }
+    }
+
+    /// Push the read and starred state of the stored items among `ids` to every session, leaving
+    /// out whichever of the two didn't change.
+    fn broadcast_patches(&self, ids: &[String], read: bool, starred: bool) {
+        self.queries.invalidate();
+        let patches: Vec<ItemPatch> = self
+            .items
+            .iter()
+            .map(NewsItem::id)
+            .filter(|id| ids.contains(id))
+            .map(|id| ItemPatch {
+                read: Some(self.store.is_read(&id)).filter(|_| read),
+                starred: Some(self.store.is_starred(&id)).filter(|_| starred),
+                updated_at: self.store.updated(&id),
+                id,
+            })
+            .collect();
+        for session in self.sessions.values() {
+            let _ = session.do_send(Push::Patches(patches.clone()));
+        }
+    }
+}
+
+impl Handler<Connect> for NewsServer {
+    type Result = usize;
+
+    fn handle(&mut self, msg: Connect, _: &mut Context<Self>) -> Self::Result {
+        let id = self.next_session;
+        self.next_session += 1;
+        self.sessions.insert(id, msg.addr);
+        id
+    }
+}
+
+impl Handler<Disconnect> for NewsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: Disconnect, _: &mut Context<Self>) {
+        self.sessions.remove(&msg.id);
+    }
+}
+
+impl Handler<Refresh> for NewsServer {
+    type Result = MessageResult<Refresh>;
+
+    fn handle(&mut self, msg: Refresh, ctx: &mut Context<Self>) -> Self::Result {
+        let feeds = match msg.feed {
+            Some(feed) => vec![feed],
+            None => self.active_feeds(),
+        };
+        MessageResult(self.refresh(feeds, ctx))
+    }
+}
+
+impl Handler<MarkRead> for NewsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: MarkRead, _: &mut Context<Self>) {
+        if self.store.set_read(&msg.id, true, msg.at) {
+            if let Some(item) = self.items.iter().find(|item| item.id() == msg.id) {
+                self.store.bayes.train(item, false);
+                self.store.interests.learn(item);
+            }
+            if let Err(e) = self.db.save_state(&self.store) {
+                log::error!("failed to save store: {}", e);
+            }
+        }
+        // Echoed even when already read, as clients wait for it to settle their local change.
+        self.broadcast_patches(&[msg.id], true, false);
+    }
+}
+
+impl Handler<MarkJunk> for NewsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: MarkJunk, _: &mut Context<Self>) {
+        if !self.store.mark_junk(&msg.id) {
+            return;
+        }
+        let item = self.items.iter().find(|item| item.id() == msg.id);
+        if let Some(item) = item {
+            self.store.bayes.train(item, true);
+        }
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+
+        let dtos = item.map(|item| self.dto(item)).into_iter().collect();
+        self.broadcast(dtos);
+    }
+}
+
+impl Handler<GetAnnotations> for NewsServer {
+    type Result = MessageResult<GetAnnotations>;
+
+    fn handle(&mut self, msg: GetAnnotations, _: &mut Context<Self>) -> Self::Result {
+        if !self.items.iter().any(|item| item.id() == msg.id) {
+            return MessageResult(None);
+        }
+        let annotations = self.store.annotations(&msg.id).cloned();
+        MessageResult(Some(annotations.unwrap_or_default()))
+    }
+}
+
+impl Handler<GetAnnotated> for NewsServer {
+    type Result = MessageResult<GetAnnotated>;
+
+    fn handle(&mut self, _: GetAnnotated, _: &mut Context<Self>) -> Self::Result {
+        let annotated = self
+            .items
+            .iter()
+            .rev()
+            .filter(|item| self.store.is_annotated(&item.id()))
+            .map(|item| self.dto(item))
+            .collect();
+        MessageResult(annotated)
+    }
+}
+
+impl Handler<SetAnnotations> for NewsServer {
+    type Result = MessageResult<SetAnnotations>;
+
+    fn handle(&mut self, msg: SetAnnotations, _: &mut Context<Self>) -> Self::Result {
+        let item = match self.items.iter().find(|item| item.id() == msg.id) {
+            Some(item) => item,
+            None => return MessageResult(None),
+        };
+        self.store.set_annotations(&msg.id, msg.annotations.clone());
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+        // Other devices render them in the reading pane.
+        let dtos = vec![self.dto(item)];
+        self.broadcast(dtos);
+        MessageResult(Some(msg.annotations))
+    }
+}
+
+impl Handler<FetchedByClient> for NewsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: FetchedByClient, ctx: &mut Context<Self>) {
+        // Clients only sync back feeds the server would fetch itself.
+        if !self.feeds.contains(&msg.feed) {
+            log::warn!("ignoring unknown feed {} fetched by a client", msg.feed);
+            return;
+        }
+        // The items the client sent can't be trusted, the server fetches the feed again instead.
+        log::info!("refetching {} fetched by a client", msg.feed);
+        ctx.notify(Refresh {
+            feed: Some(msg.feed),
+        });
+    }
+}
+
+impl Handler<Snooze> for NewsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: Snooze, _: &mut Context<Self>) {
+        self.store.snooze(&msg.id, msg.until);
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+
+        let dtos = self
+            .items
+            .iter()
+            .filter(|item| item.id() == msg.id)
+            .map(|item| self.dto(item))
+            .collect();
+        self.broadcast(dtos);
+    }
+}
+
+impl Handler<GetItem> for NewsServer {
+    type Result = MessageResult<GetItem>;
+
+    fn handle(&mut self, msg: GetItem, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(
+            self.items
+                .iter()
+                .find(|item| item.id() == msg.id)
+                .map(|item| self.dto(item)),
+        )
+    }
+}
+
+impl Handler<GetTranslation> for NewsServer {
+    type Result = MessageResult<GetTranslation>;
+
+    fn handle(&mut self, msg: GetTranslation, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.store.translation(&msg.id, &msg.to).cloned())
+    }
+}
+
+impl Handler<SaveTranslation> for NewsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SaveTranslation, _: &mut Context<Self>) {
+        self.store.add_translation(&msg.id, msg.translation);
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+    }
+}
+
+impl Handler<UpdateItems> for NewsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: UpdateItems, ctx: &mut Context<Self>) {
+        if msg.starred == Some(true) {
+            for id in &msg.ids {
+                if self.settings.archive_starred {
+                    self.archive(id, ctx);
+                }
+                if self.settings.wayback_starred {
+                    self.save_to_wayback(id, ctx);
+                }
+            }
+        }
+        let mut marked_read = 0;
+        for id in &msg.ids {
+            if let Some(read) = msg.read {
+                if self.store.set_read(id, read, msg.at) && read {
+                    marked_read += 1;
+                }
+            }
+            if let Some(starred) = msg.starred {
+                self.store.set_starred(id, starred, msg.at);
+            }
+        }
+        if marked_read > 1 {
+            activity::record(Event::MarkedRead { count: marked_read });
+        }
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+        if msg.starred == Some(true) {
+            self.cache_starred_images(ctx);
+        }
+        self.broadcast_patches(&msg.ids, msg.read.is_some(), msg.starred.is_some());
+    }
+}
+
+impl Handler<Subscribe> for NewsServer {
+    type Result = bool;
+
+    fn handle(&mut self, msg: Subscribe, ctx: &mut Context<Self>) -> Self::Result {
+        if self.feeds.contains(&msg.url) || !self.store.add_feed(&msg.url) {
+            return false;
+        }
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+        activity::record(Event::FeedAdded {
+            feed: msg.url.clone(),
+        });
+        self.feeds.push(msg.url.clone());
+        self.queries.invalidate();
+        self.refresh(vec![msg.url], ctx);
+        true
+    }
+}
+
+impl Handler<Import> for NewsServer {
+    type Result = MessageResult<Import>;
+
+    fn handle(&mut self, msg: Import, ctx: &mut Context<Self>) -> Self::Result {
+        let Imported {
+            feeds,
+            read,
+            starred,
+        } = msg.0;
+        let mut summary = ImportSummary::default();
+
+        let mut new_feeds = Vec::new();
+        for feed in feeds {
+            if self.store.add_feed(&feed) && !self.feeds.contains(&feed) {
+                activity::record(Event::FeedAdded { feed: feed.clone() });
+                self.feeds.push(feed.clone());
+                new_feeds.push(feed);
+            }
+        }
+        summary.feeds = new_feeds.len();
+
+        let ids: HashMap<&str, String> = self
+            .items
+            .iter()
+            .filter_map(|item| Some((item.link()?, item.id())))
+            .collect();
+        let mut changed = HashSet::new();
+        for link in &read {
+            match ids.get(link.as_str()) {
+                Some(id) => {
+                    self.store.mark_read(id);
+                    changed.insert(id.clone());
+                }
+                None => self.store.add_pending(link, true, false),
+            }
+        }
+        for link in &starred {
+            match ids.get(link.as_str()) {
+                Some(id) => {
+                    self.store.star(id);
+                    changed.insert(id.clone());
+                }
+                None => self.store.add_pending(link, false, true),
+            }
+        }
+        summary.read = read.len();
+        summary.starred = starred.len();
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+        log::info!(
+            "imported {} feed(s), {} read and {} starred item(s)",
+            summary.feeds,
+            summary.read,
+            summary.starred
+        );
+
+        let changed: Vec<String> = changed.into_iter().collect();
+        self.broadcast_patches(&changed, !read.is_empty(), !starred.is_empty());
+        self.refresh(new_feeds, ctx);
+        MessageResult(summary)
+    }
+}
+
+impl Handler<ListFeeds> for NewsServer {
+    type Result = MessageResult<ListFeeds>;
+
+    fn handle(&mut self, _: ListFeeds, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.feeds.clone())
+    }
+}
+
+impl Handler<Recommend> for NewsServer {
+    type Result = MessageResult<Recommend>;
+
+    fn handle(&mut self, _: Recommend, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(recommend::recommend(
+            &self.items,
+            &self.store,
+            &self.feeds,
+            &self.catalog,
+        ))
+    }
+}
+
+impl Handler<SettingsChanged> for NewsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SettingsChanged, ctx: &mut Context<Self>) {
+        let reschedule =
+            msg.0.refresh_interval_minutes != self.settings.refresh_interval_minutes;
+        let repush = msg.0.highlights != self.settings.highlights
+            || msg.0.quality_threshold != self.settings.quality_threshold
+            || msg.0.paused_feeds() != self.settings.paused_feeds();
+        let realias = msg.0.aliases() != self.settings.aliases();
+        record_pauses(&self.settings, &msg.0);
+        self.settings = msg.0;
+        self.queries.invalidate();
+        let overflow = river::overflow(&self.items, &self.settings);
+        let repush = repush || overflow != self.overflow;
+        self.overflow = overflow;
+        if reschedule {
+            self.schedule_refresh(ctx);
+        }
+        if realias {
+            self.relabel_aliases();
+        } else if repush {
+            self.push_all();
+        }
+    }
+}
+
+impl Handler<SetPosition> for NewsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetPosition, _: &mut Context<Self>) {
+        self.store.set_position(&msg.id);
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+    }
+}
+
+impl Handler<GetPosition> for NewsServer {
+    type Result = MessageResult<GetPosition>;
+
+    fn handle(&mut self, _: GetPosition, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.store.position().cloned())
+    }
+}
+
+impl Handler<GetMutes> for NewsServer {
+    type Result = MessageResult<GetMutes>;
+
+    fn handle(&mut self, _: GetMutes, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.store.mutes().to_vec())
+    }
+}
+
+impl Handler<SetMutes> for NewsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetMutes, _: &mut Context<Self>) {
+        self.store.set_mutes(msg.0);
+        self.store.expire_mutes(Utc::now());
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+        self.push_all();
+    }
+}
+
+impl Handler<GetShares> for NewsServer {
+    type Result = MessageResult<GetShares>;
+
+    fn handle(&mut self, _: GetShares, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.store.shares().to_vec())
+    }
+}
+
+impl Handler<GetShare> for NewsServer {
+    type Result = MessageResult<GetShare>;
+
+    fn handle(&mut self, msg: GetShare, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.store.share(&msg.0).cloned())
+    }
+}
+
+impl Handler<AddShare> for NewsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: AddShare, _: &mut Context<Self>) {
+        self.store.add_share(msg.0);
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+    }
+}
+
+impl Handler<RevokeShare> for NewsServer {
+    type Result = bool;
+
+    fn handle(&mut self, msg: RevokeShare, _: &mut Context<Self>) -> Self::Result {
+        if !self.store.remove_share(&msg.0) {
+            return false;
+        }
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+        true
+    }
+}
+
+impl Handler<GetFeedOrder> for NewsServer {
+    type Result = MessageResult<GetFeedOrder>;
+
+    fn handle(&mut self, _: GetFeedOrder, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(folders::arrange(self.store.feed_order(), &self.feeds))
+    }
+}
+
+impl Handler<SetFeedOrder> for NewsServer {
+    type Result = MessageResult<SetFeedOrder>;
+
+    fn handle(&mut self, msg: SetFeedOrder, _: &mut Context<Self>) -> Self::Result {
+        let order = folders::arrange(&msg.0, &self.feeds);
+        self.store.set_feed_order(order.clone());
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+        MessageResult(order)
+    }
+}
+
+impl Handler<GetQueue> for NewsServer {
+    type Result = MessageResult<GetQueue>;
+
+    fn handle(&mut self, _: GetQueue, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.store.queue().to_vec())
+    }
+}
+
+impl Handler<SetQueue> for NewsServer {
+    type Result = MessageResult<SetQueue>;
+
+    fn handle(&mut self, msg: SetQueue, _: &mut Context<Self>) -> Self::Result {
+        let mut queue: Vec<String> = Vec::with_capacity(msg.0.len());
+        for id in msg.0 {
+            if !queue.contains(&id) && self.items.iter().any(|item| item.id() == id) {
+                queue.push(id);
+            }
+        }
+        self.store.set_queue(queue.clone());
+        if let Err(e) = self.db.save_state(&self.store) {
+            log::error!("failed to save store: {}", e);
+        }
+        MessageResult(queue)
+    }
+}
+
+impl Handler<ViewItems> for NewsServer {
+    type Result = MessageResult<ViewItems>;
+
+    fn handle(&mut self, msg: ViewItems, _: &mut Context<Self>) -> Self::Result {
+        // Items are ordered oldest first.
+        let dtos = self
+            .items
+            .iter()
+            .rev()
+            .map(|item| self.dto(item))
+            .filter(|dto| msg.view.shows(dto, self.store.feed_order()))
+            .take(msg.limit)
+            .collect();
+        MessageResult(dtos)
+    }
+}
+
+impl Handler<Digest> for NewsServer {
+    type Result = MessageResult<Digest>;
+
+    fn handle(&mut self, msg: Digest, _: &mut Context<Self>) -> Self::Result {
+        let offset = msg.offset.unwrap_or_else(|| *self.settings.now().offset());
+        let today = Utc::now().with_timezone(&offset).date().naive_local();
+        let since = today - chrono::Duration::days(i64::from(msg.days) - 1);
+        let dtos = self
+            .items
+            .iter()
+            .filter(|item| {
+                item.pub_date().map_or(false, |date| {
+                    date.with_timezone(&offset).date().naive_local() >= since
+                })
+            })
+            .map(|item| self.dto(item));
+        MessageResult(digest::group(dtos, since, offset))
+    }
+}
+
+impl Handler<GetItems> for NewsServer {
+    type Result = MessageResult<GetItems>;
+
+    fn handle(&mut self, _: GetItems, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.query_items(ItemsQuery::default()))
+    }
+}
+
+impl Handler<QueryItems> for NewsServer {
+    type Result = MessageResult<QueryItems>;
+
+    fn handle(&mut self, msg: QueryItems, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(CachedItems {
+            items: self.query_items(msg.0),
+            stale: !self.jobs.is_empty(),
+        })
+    }
+}
+
+impl Handler<UnreadCounts> for NewsServer {
+    type Result = MessageResult<UnreadCounts>;
+
+    fn handle(&mut self, _: UnreadCounts, _: &mut Context<Self>) -> Self::Result {
+        let counts = self.queries.unread_counts(|| {
+            self.db.unread_counts(&self.feeds).unwrap_or_else(|e| {
+                log::error!("failed to count unread items: {}", e);
+                HashMap::new()
+            })
+        });
+        MessageResult(counts)
+    }
+}
+
+impl Handler<ListJobs> for NewsServer {
+    type Result = MessageResult<ListJobs>;
+
+    fn handle(&mut self, _: ListJobs, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.jobs.clone())
+    }
+}
+
+impl Handler<RemoteItems> for NewsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: RemoteItems, _: &mut Context<Self>) {
+        self.store_remote(msg.0);
+    }
+}
+
+impl Handler<CancelRefresh> for NewsServer {
+    type Result = MessageResult<CancelRefresh>;
+
+    fn handle(&mut self, _: CancelRefresh, ctx: &mut Context<Self>) -> Self::Result {
+        let cancelled: Vec<String> = self
+            .jobs
+            .drain(..)
+            .map(|job| {
+                if let Some(handle) = job.handle {
+                    ctx.cancel_future(handle);
+                }
+                job.feed
+            })
+            .collect();
+        if !cancelled.is_empty() {
+            log::info!("canceled fetching {} feed(s)", cancelled.len());
+        }
+        // The refresh ends with the feeds fetched so far.
+        self.progress.feeds_total = self.progress.feeds_done;
+        self.progress.current_feed = None;
+        self.broadcast_progress();
+        MessageResult(CancelledRefresh {
+            completed: self.refreshed.clone(),
+            cancelled,
+        })
+    }
+}
+
+impl Handler<GetBackup> for NewsServer {
+    type Result = anyhow::Result<BackupData>;
+
+    fn handle(&mut self, _: GetBackup, _: &mut Context<Self>) -> Self::Result {
+        let mut titles = HashMap::new();
+        for item in &self.items {
+            if let Some(title) = &item.feed_title {
+                titles.entry(item.feed.clone()).or_insert_with(|| title.clone());
+            }
+        }
+        Ok(BackupData {
+            order: folders::arrange(self.store.feed_order(), &self.feeds),
+            titles,
+            state: serde_json::to_vec_pretty(&self.store)?,
+            settings: self.settings.clone(),
+        })
+    }
+}
+
+impl Handler<Prune> for NewsServer {
+    type Result = anyhow::Result<usize>;
+
+    fn handle(&mut self, msg: Prune, _: &mut Context<Self>) -> Self::Result {
+        let before = self.items.len();
+        self.items.retain(|item| match item.pub_date() {
+            Some(date) => date >= msg.before,
+            None => true,
+        });
+        self.db.save_items(&self.items)?;
+        let ids: HashSet<String> = self.items.iter().map(NewsItem::id).collect();
+        if self.store.retain_queued(|id| ids.contains(id)) {
+            self.db.save_state(&self.store)?;
+        }
+        self.clusters = cluster::cluster(&self.items);
+        self.overflow = river::overflow(&self.items, &self.settings);
+        self.queries.invalidate();
+        let count = before - self.items.len();
+        activity::record(Event::Pruned {
+            before: msg.before,
+            count,
+        });
+        Ok(count)
+    }
+}
+
+/// Mirror the images cached at `paths`, along with their placeholders.
+async fn mirror_images(mirror: &Media, paths: Vec<PathBuf>) {
+    let files = paths.iter().flat_map(|path| media::image_files(path)).collect();
+    if let Err(e) = mirror.upload(files).await {
+        log::warn!("failed to mirror cached images: {}", e);
+    }
+}
+
+/// Record the feeds paused or resumed between `old` and `new` settings.
+fn record_pauses(old: &Settings, new: &Settings) {
+    let (old, new) = (old.paused_feeds(), new.paused_feeds());
+    for feed in new.iter().filter(|feed| !old.contains(feed)) {
+        activity::record(Event::FeedPaused {
+            feed: feed.to_string(),
+        });
+    }
+    for feed in old.iter().filter(|feed| !new.contains(feed)) {
+        activity::record(Event::FeedResumed {
+            feed: feed.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use news_core::test_util::item;
+
+    use crate::news_store::MemoryStore;
+    use crate::summarize::NoopSummarizer;
+
+    fn server() -> NewsServer {
+        NewsServer::new(
+            Vec::new(),
+            Box::new(MemoryStore::default()),
+            Settings::default(),
+            Arc::new(NoopSummarizer),
+            None,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn stores_every_clamped_item() {
+        let mut server = server();
+        let future = Utc::now() + chrono::Duration::days(1);
+        server.store(vec![
+            item("First").date(future).build(),
+            item("Second").date(future).build(),
+        ]);
+        assert_eq!(server.items.len(), 2);
+        assert!(server
+            .items
+            .iter()
+            .all(|item| !schedule::is_future(item, Utc::now())));
+    }
+
+    #[test]
+    fn replaces_revised_remote_items() {
+        let mut server = server();
+        let date = Utc::now() - chrono::Duration::hours(1);
+        server.store(vec![
+            item("Story").date(date).build(),
+            item("Other story").date(date).build(),
+        ]);
+        let mut revised = item("Story").date(date).build();
+        let later = date + chrono::Duration::minutes(10);
+        revised.set_pub_date(later.with_timezone(&FixedOffset::east(0)));
+        server.store_remote(vec![revised]);
+        assert_eq!(server.items.len(), 2);
+        let story = server
+            .items
+            .iter()
+            .find(|item| item.title() == Some("Story"));
+        assert_eq!(story.and_then(NewsItem::pub_date).unwrap(), later);
+        assert!(server
+            .items
+            .iter()
+            .any(|item| item.title() == Some("Other story")));
+    }
+}
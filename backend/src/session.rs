@@ -0,0 +1,338 @@
+//! Websocket sessions with the frontend.
+
+use std::time::{Duration, Instant};
+
+use actix::prelude::*;
+use actix_web::{error, http::header, web, Error, HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use chrono::Utc;
+
+use crate::admin;
+use crate::config::Config;
+use crate::flow::{Outbox, Queued};
+use crate::items::ItemsQuery;
+use crate::limits::{ConnectionGuard, ConnectionLimit};
+use crate::protocol::{self, ClientMessage, ConnectParams, Encoding, Frame, ServerMessage};
+use crate::server::{
+    CachedItems, CancelRefresh, Connect, Disconnect, FetchedByClient, GetPosition, MarkJunk,
+    MarkRead, NewsServer, Push, QueryItems, Refresh, SetPosition, Snooze, UpdateItems,
+};
+use crate::sync;
+
+/// How often heartbeat pings are sent
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+/// How long before lack of client response causes a timeout
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub async fn ws_index(
+    req: HttpRequest,
+    params: web::Query<ConnectParams>,
+    config: web::Data<Config>,
+    connections: web::Data<ConnectionLimit>,
+    server: web::Data<Addr<NewsServer>>,
+    stream: web::Payload,
+) -> Result<HttpResponse, Error> {
+    authorize(&req, params.token.as_deref(), &config)?;
+    let connection = match connections.acquire() {
+        Some(connection) => connection,
+        None => {
+            log::warn!("rejecting websocket connection, limit reached");
+            return Ok(HttpResponse::ServiceUnavailable().finish());
+        }
+    };
+
+    // Browsers offer the permessage-deflate extension, but the websocket codec of actix-web 2
+    // can't set the RSV1 bit compressed frames carry, so the handshake leaves the offer
+    // unanswered and frames go out uncompressed.
+    log::debug!("ws connect: encoding={:?}", params.encoding);
+    ws::start(
+        WebSocket::new(server.get_ref().clone(), params.encoding, connection),
+        &req,
+        stream,
+    )
+}
+
+/// Check the client connects from an allowed page and gives the websocket token, if one is
+/// configured.
+fn authorize(req: &HttpRequest, token: Option<&str>, config: &Config) -> Result<(), Error> {
+    if let Some(origin) = req.headers().get(header::ORIGIN) {
+        let origin = origin.to_str().unwrap_or("");
+        let host = req.connection_info().host().to_string();
+        if !allowed_origin(origin, &host, &config.allowed_origins) {
+            log::warn!("rejecting websocket connection from {}", origin);
+            return Err(error::ErrorForbidden("origin not allowed"));
+        }
+    }
+
+    let expected = match &config.ws_token {
+        Some(token) => token,
+        None => return Ok(()),
+    };
+    let provided = token.or_else(|| {
+        req.headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+    });
+    match provided {
+        Some(token) if admin::secret_matches(token, expected) => Ok(()),
+        _ => {
+            log::warn!("rejecting websocket connection with an invalid token");
+            Err(error::ErrorUnauthorized("invalid token"))
+        }
+    }
+}
+
+/// Whether a page of `origin` may open the websocket of the server reached as `host`.
+fn allowed_origin(origin: &str, host: &str, allowed: &[String]) -> bool {
+    let origin = origin.trim_end_matches('/');
+    origin.splitn(2, "://").nth(1) == Some(host)
+        || allowed
+            .iter()
+            .any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+}
+
+struct WebSocket {
+    /// Id assigned by the news server once registered.
+    id: usize,
+    server: Addr<NewsServer>,
+    last_heartbeat: Instant,
+    encoding: Encoding,
+    outbox: Outbox,
+    /// Releases this connection's slot when the actor is dropped.
+    _connection: ConnectionGuard,
+}
+
+impl Actor for WebSocket {
+    type Context = ws::WebsocketContext<Self>;
+
+    /// Method is called on actor start. We start the heartbeat process and register with the
+    /// news server here, then start refreshing the feeds and push the stored items right away so
+    /// the client has something to show while they are fetched.
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.send_heartbeat(ctx);
+
+        let addr = ctx.address();
+        self.server
+            .send(Connect {
+                addr: addr.recipient(),
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    Ok(id) => {
+                        act.id = id;
+                        // The server handles messages in order, so the stored items are taken once
+                        // the refresh started and are tagged stale if it has feeds to fetch.
+                        act.server.do_send(Refresh { feed: None });
+                        act.push_news(ctx);
+                        act.send_position(ctx);
+                    }
+                    Err(_) => ctx.stop(),
+                }
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    fn stopping(&mut self, _: &mut Self::Context) -> Running {
+        self.server.do_send(Disconnect { id: self.id });
+        Running::Stop
+    }
+}
+
+/// Handler for updates pushed by the news server.
+impl Handler<Push> for WebSocket {
+    type Result = ();
+
+    fn handle(&mut self, msg: Push, ctx: &mut Self::Context) {
+        let queued = match msg {
+            Push::Items(items) => self.outbox.push(items),
+            Push::Patches(patches) => self.outbox.patch(patches),
+            // Progress isn't worth flow control, a client that lags gets newer progress later.
+            Push::Progress(progress) => return self.send(ctx, &ServerMessage::Progress(progress)),
+        };
+        self.after_queue(ctx, queued);
+    }
+}
+
+/// Handler for `ws::Message`
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebSocket {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        // process websocket messages
+        log::debug!("WS handle: {:?}", msg);
+        match msg {
+            Ok(ws::Message::Ping(msg)) => {
+                self.last_heartbeat = Instant::now();
+                ctx.pong(&msg);
+            }
+            Ok(ws::Message::Pong(_)) => {
+                self.last_heartbeat = Instant::now();
+            }
+            Ok(ws::Message::Text(text)) => match serde_json::from_str::<ClientMessage>(&text) {
+                Ok(msg) => self.handle_client_message(msg, ctx),
+                Err(e) => log::warn!("ignoring bad client message: {}", e),
+            },
+            Ok(ws::Message::Binary(_)) => log::warn!("ignoring binary client message"),
+            Ok(ws::Message::Close(_)) => {
+                ctx.stop();
+            }
+            _ => ctx.stop(),
+        }
+    }
+}
+
+impl WebSocket {
+    fn new(server: Addr<NewsServer>, encoding: Encoding, connection: ConnectionGuard) -> Self {
+        Self {
+            id: 0,
+            server,
+            last_heartbeat: Instant::now(),
+            encoding,
+            outbox: Outbox::default(),
+            _connection: connection,
+        }
+    }
+
+    fn handle_client_message(&mut self, msg: ClientMessage, ctx: &mut <Self as Actor>::Context) {
+        match msg {
+            ClientMessage::Ack { up_to } => {
+                self.outbox.ack(up_to);
+                self.flush(ctx);
+            }
+            ClientMessage::Resync => {
+                self.outbox.reset();
+                self.push_news(ctx);
+            }
+            ClientMessage::MarkRead { id, at, sent_at } => self.server.do_send(MarkRead {
+                id,
+                at: sync::server_time(at, sent_at, Utc::now()),
+            }),
+            ClientMessage::MarkJunk { id } => self.server.do_send(MarkJunk { id }),
+            ClientMessage::Star {
+                id,
+                starred,
+                at,
+                sent_at,
+            } => self.server.do_send(UpdateItems {
+                ids: vec![id],
+                read: None,
+                starred: Some(starred),
+                at: sync::server_time(at, sent_at, Utc::now()),
+            }),
+            ClientMessage::Snooze { id, until } => self.server.do_send(Snooze { id, until }),
+            ClientMessage::Position { id } => self.server.do_send(SetPosition { id }),
+            ClientMessage::CancelRefresh => self.server.do_send(CancelRefresh),
+            ClientMessage::Fetched { feed, .. } => self.server.do_send(FetchedByClient { feed }),
+        }
+    }
+
+    /// Tell the client where the user left off reading.
+    fn send_position(&self, ctx: &mut <Self as Actor>::Context) {
+        self.server
+            .send(GetPosition)
+            .into_actor(self)
+            .map(|res, act, ctx| match res {
+                Ok(Some(position)) => act.send(ctx, &ServerMessage::Position(position)),
+                Ok(None) => {}
+                Err(e) => log::error!("failed to get read position: {}", e),
+            })
+            .spawn(ctx);
+    }
+
+    /// Send a heartbeat to the client.
+    fn send_heartbeat(&self, ctx: &mut <Self as Actor>::Context) {
+        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
+            if Instant::now().duration_since(act.last_heartbeat) > CLIENT_TIMEOUT {
+                // Heartbeat timed out
+                log::info!("Websocket client heartbeat failed, disconnecting!");
+                ctx.stop();
+                return;
+            }
+
+            ctx.ping(b"{\"pong\": 0}");
+        });
+    }
+
+    /// Start pushing every stored item to the client, a page at a time.
+    fn push_news(&mut self, ctx: &mut <Self as Actor>::Context) {
+        self.outbox.start_snapshot();
+        self.fetch_page(ctx);
+    }
+
+    /// Fetch the next page of stored items once the client took the previous one.
+    fn fetch_page(&mut self, ctx: &mut <Self as Actor>::Context) {
+        let request = match self.outbox.next_page() {
+            Some(request) => request,
+            None => return,
+        };
+        let query = ItemsQuery {
+            after: request.after.clone(),
+            limit: Some(request.limit),
+            ..ItemsQuery::default()
+        };
+        self.server
+            .send(QueryItems(query))
+            .into_actor(self)
+            .map(move |res, act, ctx| match res {
+                Ok(CachedItems { items, stale }) => {
+                    act.outbox.push_page(&request, items, stale);
+                    act.flush(ctx);
+                }
+                Err(e) => log::error!("failed to get items: {}", e),
+            })
+            .spawn(ctx);
+    }
+
+    /// Send what flow control allows after queueing, or ask the client to resync if it lags.
+    fn after_queue(&mut self, ctx: &mut <Self as Actor>::Context, queued: Queued) {
+        match queued {
+            Queued::Ok => self.flush(ctx),
+            Queued::ResyncNeeded => self.send(ctx, &ServerMessage::ResyncNeeded),
+        }
+    }
+
+    /// Send queued batches until the client's in-flight window is full, then fetch the next page
+    /// of stored items if it has room left.
+    fn flush(&mut self, ctx: &mut <Self as Actor>::Context) {
+        while let Some(batch) = self.outbox.next_batch() {
+            self.send(ctx, &ServerMessage::Items(batch));
+        }
+        self.fetch_page(ctx);
+    }
+
+    /// Serialize a message and send it to the client using the negotiated encoding.
+    fn send(&self, ctx: &mut <Self as Actor>::Context, msg: &ServerMessage) {
+        match protocol::encode(msg, self.encoding) {
+            Ok(Frame::Text(text)) => ctx.text(text),
+            Ok(Frame::Binary(bytes)) => ctx.binary(bytes),
+            Err(e) => log::error!("failed to encode message: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_own_origin() {
+        assert!(allowed_origin("http://localhost:9001", "localhost:9001", &[]));
+        assert!(allowed_origin("https://news.example.com", "news.example.com", &[]));
+    }
+
+    #[test]
+    fn allows_configured_origins() {
+        let allowed = vec!["https://news.example.com/".to_string()];
+        assert!(allowed_origin("https://news.example.com", "10.0.0.2:9001", &allowed));
+        assert!(allowed_origin("HTTPS://NEWS.EXAMPLE.COM", "10.0.0.2:9001", &allowed));
+    }
+
+    #[test]
+    fn rejects_other_origins() {
+        let allowed = vec!["https://news.example.com".to_string()];
+        assert!(!allowed_origin("https://evil.example.com", "localhost:9001", &allowed));
+        assert!(!allowed_origin("https://news.example.com.evil.com", "localhost:9001", &allowed));
+        assert!(!allowed_origin("null", "localhost:9001", &allowed));
+    }
+}
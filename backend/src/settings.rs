@@ -0,0 +1,586 @@
+//! User settings shared with the frontend through `GET/PUT /api/settings`.
+
+use actix::Addr;
+use actix_web::{error, web, Error, HttpResponse};
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, Local, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use crate::news;
+use crate::server::{ListFeeds, NewsServer, SettingsChanged, Subscribe};
+use crate::timezone;
+
+/// Maximum size of the user stylesheet in bytes.
+const MAX_CUSTOM_CSS_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Minutes between automatic refreshes of every feed.
+    pub refresh_interval_minutes: u32,
+    /// Number of entries the frontend shows before "show more".
+    pub items_per_page: usize,
+    pub theme: Theme,
+    /// Raise the contrast of the theme, e.g. for low vision.
+    pub high_contrast: bool,
+    /// Stylesheet the frontend applies over the theme.
+    pub custom_css: String,
+    /// Language of the frontend, e.g. `de`, following the browser when unset.
+    pub language: Option<String>,
+    /// IANA name of the time zone days are grouped by and quiet hours follow, e.g.
+    /// `Europe/Paris`. The frontend saves the browser's when it loads settings naming none, the
+    /// server's applying until then. Dates are stored in UTC regardless.
+    pub timezone: Option<String>,
+    /// Filter selected when the frontend starts.
+    pub default_filter: DefaultFilter,
+    /// Mark an item read when it is expanded in the list.
+    pub mark_read_on_expand: bool,
+    /// Show the priority inbox, listing unread items by how likely the user is to read them.
+    pub priority_inbox: bool,
+    pub typography: Typography,
+    /// Archive a self-contained snapshot of the page of items when they are starred.
+    pub archive_starred: bool,
+    /// Submit the link of items to the Wayback Machine when they are starred.
+    pub wayback_starred: bool,
+    /// Replace links to shorteners and feed proxies by the pages they redirect to.
+    pub unshorten_links: bool,
+    pub images: ImagePolicy,
+    pub notifications: NotificationSettings,
+    pub quiet_hours: QuietHours,
+    pub anomalies: AnomalySettings,
+    /// Items scoring below this quality go to the low quality folder instead of the unread list.
+    pub quality_threshold: f64,
+    /// Terms emphasized wherever they appear in titles and summaries.
+    pub highlights: Vec<String>,
+    /// Only surface this many items of each feed per day in the river of every feed, leaving the
+    /// rest to the view of their feed. Every item surfaces when unset.
+    pub river_max_per_day: Option<usize>,
+    /// What becomes of the items feeds date in the future, which would otherwise stay at the top
+    /// of the lists until their date.
+    pub future_items: FutureItems,
+    /// Settings of individual feeds keyed by feed URL.
+    pub feeds: HashMap<String, FeedSettings>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeedSettings {
+    pub content: ContentMode,
+    /// Only keep this many unread items, marking older ones read as new ones arrive.
+    pub keep_latest: Option<usize>,
+    /// Stop fetching the feed and keep its items out of the unread list, keeping its history.
+    pub paused: bool,
+    /// URL of the feed this one is an alias of, e.g. after a site moved its feed. Items of both
+    /// are merged under that feed.
+    pub alias_of: Option<String>,
+    /// Number of items surfacing in the river per day, overriding `river_max_per_day`.
+    pub max_per_day: Option<usize>,
+    /// Lua script run on every item fetched from the feed, rewriting or dropping it.
+    pub transform: Option<String>,
+}
+
+/// What the reading pane shows for the items of a feed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentMode {
+    /// The summary provided by the feed.
+    Summary,
+    /// The full article, extracted from the linked page when the feed doesn't provide it.
+    FullContent,
+}
+
+impl Default for ContentMode {
+    fn default() -> Self {
+        ContentMode::Summary
+    }
+}
+
+/// What becomes of the items a feed dates in the future.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FutureItems {
+    /// Date them when they are first fetched.
+    Clamp,
+    /// Keep them from being stored until their date passes.
+    Hold,
+}
+
+impl Default for FutureItems {
+    fn default() -> Self {
+        FutureItems::Clamp
+    }
+}
+
+/// Which images of items are downloaded into the cache, for low-disk or metered deployments.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImagePolicy {
+    pub caching: ImageCaching,
+    /// Skip images larger than this many KiB. Images over 20 MiB are skipped regardless.
+    pub max_size_kb: Option<u32>,
+    /// Only download images during these hours, e.g. when the connection isn't metered. Images
+    /// skipped meanwhile are downloaded by the next refresh within them.
+    pub schedule: ImageSchedule,
+    pub transcode: Transcode,
+}
+
+/// Re-encoding of downloaded images, saving disk space and bandwidth.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transcode {
+    pub format: TranscodeFormat,
+    /// Encoding quality from 1 to 100.
+    pub quality: u8,
+}
+
+impl Default for Transcode {
+    fn default() -> Self {
+        Transcode {
+            format: TranscodeFormat::Off,
+            quality: 75,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscodeFormat {
+    /// Keep images as downloaded.
+    Off,
+    Webp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageCaching {
+    All,
+    /// Only the images of starred items.
+    Starred,
+    Off,
+}
+
+impl Default for ImageCaching {
+    fn default() -> Self {
+        ImageCaching::All
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageSchedule {
+    pub enabled: bool,
+    /// Local time downloads start at.
+    pub start: NaiveTime,
+    /// Local time downloads end at, possibly on the next day.
+    pub end: NaiveTime,
+}
+
+impl Default for ImageSchedule {
+    fn default() -> Self {
+        ImageSchedule {
+            enabled: false,
+            start: NaiveTime::from_hms(1, 0, 0),
+            end: NaiveTime::from_hms(6, 0, 0),
+        }
+    }
+}
+
+impl ImagePolicy {
+    /// Whether images with the given caching may be downloaded right now.
+    pub fn allows(&self, caching: ImageCaching) -> bool {
+        if !cfg!(feature = "images") || self.caching != caching {
+            return false;
+        }
+        !self.schedule.enabled
+            || between(self.schedule.start, self.schedule.end, Local::now().time())
+    }
+
+    /// Largest image downloaded, in bytes.
+    pub fn max_size(&self) -> Option<u64> {
+        self.max_size_kb.map(|kb| u64::from(kb) * 1024)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    /// Follow the browser's preferred color scheme.
+    System,
+    Light,
+    Dark,
+    /// Light theme with warm, low contrast colors.
+    Sepia,
+    /// Dark theme with the cold colors of the Nord palette.
+    Nord,
+}
+
+/// How the reading pane lays out article text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Typography {
+    pub font: Font,
+    pub font_size_px: u32,
+    /// Line height as a multiple of the font size.
+    pub line_height: f64,
+    /// Maximum width of the text in characters.
+    pub max_width_ch: u32,
+    /// Show curly quotes, dashes and ellipses in titles and articles instead of the straight
+    /// quotes, hyphens and dots of their feeds.
+    pub smart_punctuation: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Font {
+    Sans,
+    Serif,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultFilter {
+    All,
+    Read,
+    Unread,
+    LowQuality,
+    Priority,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    /// Show a notification when new items arrive.
+    pub new_items: bool,
+}
+
+/// Watch for feeds posting at an unusual rate, recording anomalies in the activity log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnomalySettings {
+    pub enabled: bool,
+    /// A feed is silent after this many times its average interval between items.
+    pub silence_factor: f64,
+    /// A feed floods when a day brings this many times its average daily volume.
+    pub flood_factor: f64,
+    /// Also show a desktop notification about anomalies, when those are configured.
+    pub notify: bool,
+}
+
+impl Default for AnomalySettings {
+    fn default() -> Self {
+        AnomalySettings {
+            enabled: true,
+            silence_factor: 3.0,
+            flood_factor: 10.0,
+            notify: false,
+        }
+    }
+}
+
+/// Hours during which notifications are suppressed and feeds are refreshed less often.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuietHours {
+    pub enabled: bool,
+    /// Time quiet hours start at, in the time zone of the settings.
+    pub start: NaiveTime,
+    /// Time quiet hours end at in the time zone of the settings, possibly on the next day.
+    pub end: NaiveTime,
+    /// Minutes between automatic refreshes during quiet hours.
+    pub refresh_interval_minutes: u32,
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        QuietHours {
+            enabled: false,
+            start: NaiveTime::from_hms(22, 0, 0),
+            end: NaiveTime::from_hms(7, 0, 0),
+            refresh_interval_minutes: 180,
+        }
+    }
+}
+
+impl QuietHours {
+    /// Whether `time` falls within quiet hours.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        self.enabled && between(self.start, self.end, time)
+    }
+}
+
+/// Whether `time` falls between `start` and `end`, possibly on the next day.
+fn between(start: NaiveTime, end: NaiveTime, time: NaiveTime) -> bool {
+    if start <= end {
+        start <= time && time < end
+    } else {
+        time >= start || time < end
+    }
+}
+
+impl Default for Typography {
+    fn default() -> Self {
+        Typography {
+            font: Font::Sans,
+            font_size_px: 16,
+            line_height: 1.6,
+            max_width_ch: 70,
+            smart_punctuation: false,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            refresh_interval_minutes: 30,
+            items_per_page: 50,
+            theme: Theme::System,
+            high_contrast: false,
+            custom_css: String::new(),
+            language: None,
+            timezone: None,
+            default_filter: DefaultFilter::All,
+            mark_read_on_expand: true,
+            priority_inbox: false,
+            typography: Typography::default(),
+            archive_starred: false,
+            wayback_starred: false,
+            unshorten_links: false,
+            images: ImagePolicy::default(),
+            notifications: NotificationSettings::default(),
+            quiet_hours: QuietHours::default(),
+            anomalies: AnomalySettings::default(),
+            quality_threshold: 0.5,
+            highlights: Vec::new(),
+            river_max_per_day: None,
+            future_items: FutureItems::Clamp,
+            feeds: HashMap::new(),
+        }
+    }
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings { new_items: false }
+    }
+}
+
+impl Settings {
+    /// Return `date` in the time zone of the settings.
+    pub fn local(&self, date: DateTime<Utc>) -> DateTime<FixedOffset> {
+        let offset = self
+            .timezone
+            .as_deref()
+            .and_then(|name| timezone::offset(name, date))
+            .unwrap_or_else(|| Local.offset_from_utc_datetime(&date.naive_utc()));
+        date.with_timezone(&offset)
+    }
+
+    /// Return the current time in the time zone of the settings.
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        self.local(Utc::now())
+    }
+
+    /// Return the settings of a feed, using defaults for feeds without any.
+    pub fn feed(&self, url: &str) -> FeedSettings {
+        self.feeds.get(url).cloned().unwrap_or_default()
+    }
+
+    /// Return the number of items of a feed surfacing in the river per day, if limited.
+    pub fn max_per_day(&self, url: &str) -> Option<usize> {
+        self.feed(url).max_per_day.or(self.river_max_per_day)
+    }
+
+    /// Return the URLs of the paused feeds.
+    pub fn paused_feeds(&self) -> Vec<&str> {
+        let mut paused: Vec<&str> = self
+            .feeds
+            .iter()
+            .filter(|(_, feed)| feed.paused)
+            .map(|(url, _)| url.as_str())
+            .collect();
+        paused.sort_unstable();
+        paused
+    }
+
+    /// Return the feed the items of `url` are merged into.
+    pub fn canonical_feed<'a>(&'a self, url: &'a str) -> &'a str {
+        self.feeds
+            .get(url)
+            .and_then(|feed| feed.alias_of.as_deref())
+            .unwrap_or(url)
+    }
+
+    /// Return the pairs of alias and canonical feeds, sorted.
+    pub fn aliases(&self) -> Vec<(&str, &str)> {
+        let mut aliases: Vec<(&str, &str)> = self
+            .feeds
+            .iter()
+            .filter_map(|(url, feed)| Some((url.as_str(), feed.alias_of.as_deref()?)))
+            .collect();
+        aliases.sort_unstable();
+        aliases
+    }
+
+    fn validate(&self) -> Result<(), &'static str> {
+        if self.refresh_interval_minutes == 0 {
+            return Err("refresh interval must be at least one minute");
+        }
+        if self.items_per_page == 0 || self.items_per_page > 500 {
+            return Err("items per page must be between 1 and 500");
+        }
+        if let Some(name) = &self.timezone {
+            if !timezone::is_valid(name) {
+                return Err("unknown time zone");
+            }
+        }
+        if self.feeds.values().any(|feed| feed.keep_latest == Some(0)) {
+            return Err("feeds must keep at least one unread item");
+        }
+        let max_per_day = self.feeds.values().map(|feed| feed.max_per_day);
+        if std::iter::once(self.river_max_per_day)
+            .chain(max_per_day)
+            .any(|max| max == Some(0))
+        {
+            return Err("feeds must surface at least one item per day");
+        }
+        for (alias, canonical) in self.aliases() {
+            if alias == canonical {
+                return Err("a feed can't be an alias of itself");
+            }
+            if self.feed(canonical).alias_of.is_some() {
+                return Err("a feed can't be an alias of another alias");
+            }
+        }
+        if self.images.max_size_kb == Some(0) {
+            return Err("image size limit must be at least 1 KiB");
+        }
+        if !(1..=100).contains(&self.images.transcode.quality) {
+            return Err("image quality must be between 1 and 100");
+        }
+        if self.custom_css.len() > MAX_CUSTOM_CSS_LEN {
+            return Err("custom css must be at most 64 KiB");
+        }
+        if !(10..=32).contains(&self.typography.font_size_px) {
+            return Err("font size must be between 10 and 32 pixels");
+        }
+        if !(1.0..=3.0).contains(&self.typography.line_height) {
+            return Err("line height must be between 1 and 3");
+        }
+        if !(30..=200).contains(&self.typography.max_width_ch) {
+            return Err("content width must be between 30 and 200 characters");
+        }
+        if !(0.0..=1.0).contains(&self.quality_threshold) {
+            return Err("quality threshold must be between 0 and 1");
+        }
+        if self.quiet_hours.refresh_interval_minutes == 0 {
+            return Err("quiet hours refresh interval must be at least one minute");
+        }
+        if self.anomalies.silence_factor <= 1.0 || self.anomalies.flood_factor <= 1.0 {
+            return Err("anomaly factors must be greater than 1");
+        }
+        Ok(())
+    }
+}
+
+/// Settings persisted as `settings.json` in the project configuration directory.
+#[derive(Clone)]
+pub struct SettingsStore {
+    path: PathBuf,
+    settings: Arc<RwLock<Settings>>,
+}
+
+impl SettingsStore {
+    pub fn load() -> Result<Self> {
+        let config_dir = news::project_dirs()?.config_dir().to_path_buf();
+        fs::create_dir_all(&config_dir)?;
+        let path = config_dir.join("settings.json");
+        let settings = match fs::File::open(&path) {
+            Ok(file) => serde_json::from_reader(file)?,
+            Err(_) => Settings::default(),
+        };
+
+        Ok(SettingsStore {
+            path,
+            settings: Arc::new(RwLock::new(settings)),
+        })
+    }
+
+    pub fn get(&self) -> Settings {
+        self.settings.read().unwrap().clone()
+    }
+
+    pub fn set(&self, settings: Settings) -> Result<()> {
+        news::write_file(&self.path, |file| {
+            Ok(serde_json::to_writer_pretty(file, &settings)?)
+        })?;
+        *self.settings.write().unwrap() = settings;
+        Ok(())
+    }
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/api/settings")
+            .route(web::get().to(get_settings))
+            .route(web::put().to(put_settings)),
+    )
+    .service(
+        web::resource("/api/feeds")
+            .route(web::get().to(get_feeds))
+            .route(web::post().to(post_feed)),
+    );
+}
+
+#[derive(Debug, Deserialize)]
+struct NewFeed {
+    url: String,
+}
+
+/// Subscribe to a feed, answering whether it wasn't subscribed to already.
+async fn post_feed(
+    server: web::Data<Addr<NewsServer>>,
+    feed: web::Json<NewFeed>,
+) -> Result<HttpResponse, Error> {
+    let url = feed.into_inner().url.trim().to_string();
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(error::ErrorBadRequest("feed url must be http or https"));
+    }
+    let added = server
+        .send(Subscribe { url })
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "added": added })))
+}
+
+/// List the URLs of the subscribed feeds.
+async fn get_feeds(server: web::Data<Addr<NewsServer>>) -> Result<HttpResponse, Error> {
+    let feeds = server
+        .send(ListFeeds)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(feeds))
+}
+
+async fn get_settings(store: web::Data<SettingsStore>) -> HttpResponse {
+    HttpResponse::Ok().json(store.get())
+}
+
+async fn put_settings(
+    store: web::Data<SettingsStore>,
+    server: web::Data<Addr<NewsServer>>,
+    settings: web::Json<Settings>,
+) -> Result<HttpResponse, Error> {
+    let settings = settings.into_inner();
+    settings.validate().map_err(error::ErrorBadRequest)?;
+    store
+        .set(settings.clone())
+        .map_err(error::ErrorInternalServerError)?;
+    server.do_send(SettingsChanged(settings.clone()));
+    Ok(HttpResponse::Ok().json(settings))
+}
@@ -0,0 +1,253 @@
+//! Read-only share links exposing a view of the items as a public page and RSS or Atom feed.
+//!
+//! Shares are managed through `/api/shares`, which requires the admin token like the other
+//! administrative routes. A share token only grants the `GET` routes under `/shared/{token}`, and
+//! revoking the share makes them answer 404.
+
+use actix::Addr;
+use actix_web::{error, web, Error, HttpRequest, HttpResponse};
+use chrono::prelude::*;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::admin;
+use crate::config::Config;
+use crate::protocol::ItemDto;
+use crate::sanitize;
+use crate::server::{
+    AddShare, GetFeedOrder, GetShare, GetShares, ListFeeds, NewsServer, RevokeShare, ViewItems,
+};
+use crate::store::Share;
+use crate::views::{Format, View};
+
+/// Length of the generated share tokens.
+const TOKEN_LEN: usize = 32;
+/// Number of items shown by a share, newest first.
+const SHARED_ITEMS: usize = 100;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/api/shares")
+            .route(web::get().to(get_shares))
+            .route(web::post().to(create_share)),
+    )
+    .route("/api/shares/{token}", web::delete().to(revoke_share))
+    .service(
+        web::scope("/shared/{token}")
+            .route("", web::get().to(shared_page))
+            .route("/items", web::get().to(shared_items))
+            .route("/rss.xml", web::get().to(shared_rss))
+            .route("/atom.xml", web::get().to(shared_atom)),
+    );
+}
+
+#[derive(Debug, Deserialize)]
+struct NewShare {
+    view: View,
+    title: Option<String>,
+}
+
+/// An item as exposed to readers of a share, without the user's state.
+#[derive(Debug, Serialize)]
+struct SharedItem {
+    title: Option<String>,
+    link: Option<String>,
+    description: Option<String>,
+    /// Title of the feed the item came from, or its URL.
+    source: String,
+    pub_date: Option<DateTime<Utc>>,
+}
+
+impl From<ItemDto> for SharedItem {
+    fn from(dto: ItemDto) -> Self {
+        SharedItem {
+            title: dto.title,
+            link: dto.link,
+            description: dto.description,
+            source: dto.feed_title.unwrap_or(dto.feed),
+            pub_date: dto.pub_date,
+        }
+    }
+}
+
+async fn get_shares(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+) -> Result<HttpResponse, Error> {
+    admin::authorize(&req, &config)?;
+    let shares = server
+        .send(GetShares)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(shares))
+}
+
+async fn create_share(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    share: web::Json<NewShare>,
+) -> Result<HttpResponse, Error> {
+    admin::authorize(&req, &config)?;
+    let NewShare { view, title } = share.into_inner();
+    if let View::Feed(feed) = &view {
+        let feeds = server
+            .send(ListFeeds)
+            .await
+            .map_err(error::ErrorInternalServerError)?;
+        if !feeds.contains(feed) {
+            return Err(error::ErrorNotFound("unknown feed"));
+        }
+    }
+    if let View::Folder(name) = &view {
+        let order = server
+            .send(GetFeedOrder)
+            .await
+            .map_err(error::ErrorInternalServerError)?;
+        if !order.folders.iter().any(|folder| &folder.name == name) {
+            return Err(error::ErrorNotFound("unknown folder"));
+        }
+    }
+
+    let share = Share {
+        token: rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(TOKEN_LEN)
+            .collect(),
+        view,
+        title: title.filter(|title| !title.trim().is_empty()),
+        created_at: Utc::now(),
+    };
+    server
+        .send(AddShare(share.clone()))
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Created().json(share))
+}
+
+async fn revoke_share(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    token: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    admin::authorize(&req, &config)?;
+    let revoked = server
+        .send(RevokeShare(token.into_inner()))
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    if !revoked {
+        return Err(error::ErrorNotFound("unknown share"));
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Return the share of `token` and the items it exposes, failing with 404 for unknown tokens.
+async fn scope(server: &Addr<NewsServer>, token: String) -> Result<(Share, Vec<ItemDto>), Error> {
+    let share = server
+        .send(GetShare(token))
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("unknown share"))?;
+    let items = server
+        .send(ViewItems {
+            view: share.view.clone(),
+            limit: SHARED_ITEMS,
+        })
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok((share, items))
+}
+
+async fn shared_page(
+    server: web::Data<Addr<NewsServer>>,
+    token: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let (share, items) = scope(&server, token.into_inner()).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(page(&share, items)))
+}
+
+async fn shared_items(
+    server: web::Data<Addr<NewsServer>>,
+    token: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let (_, items) = scope(&server, token.into_inner()).await?;
+    let items: Vec<SharedItem> = items.into_iter().map(SharedItem::from).collect();
+    Ok(HttpResponse::Ok().json(items))
+}
+
+async fn shared_rss(
+    req: HttpRequest,
+    server: web::Data<Addr<NewsServer>>,
+    token: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    shared_feed(req, &server, token.into_inner(), Format::Rss).await
+}
+
+async fn shared_atom(
+    req: HttpRequest,
+    server: web::Data<Addr<NewsServer>>,
+    token: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    shared_feed(req, &server, token.into_inner(), Format::Atom).await
+}
+
+async fn shared_feed(
+    req: HttpRequest,
+    server: &Addr<NewsServer>,
+    token: String,
+    format: Format,
+) -> Result<HttpResponse, Error> {
+    let link = {
+        let info = req.connection_info();
+        format!("{}://{}/shared/{}", info.scheme(), info.host(), token)
+    };
+    let (share, items) = scope(server, token).await?;
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .body(format.render(&share.title(), &link, &items)))
+}
+
+/// Render the public page of a share.
+fn page(share: &Share, items: Vec<ItemDto>) -> String {
+    let title = sanitize::escape(&share.title());
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title>\
+         <link rel=\"alternate\" type=\"application/rss+xml\" href=\"{token}/rss.xml\">\
+         <link rel=\"alternate\" type=\"application/atom+xml\" href=\"{token}/atom.xml\">\
+         </head><body><h1>{title}</h1>\n",
+        title = title,
+        token = sanitize::escape(&share.token),
+    );
+    for item in items.into_iter().map(SharedItem::from) {
+        let title = sanitize::escape(item.title.as_deref().unwrap_or("Untitled"));
+        html.push_str("<article><h2>");
+        let link = item.link.as_deref().filter(|link| {
+            link.starts_with("https://") || link.starts_with("http://")
+        });
+        match link {
+            Some(link) => html.push_str(&format!(
+                "<a href=\"{}\" rel=\"noopener noreferrer\">{}</a>",
+                sanitize::escape(link),
+                title
+            )),
+            None => html.push_str(&title),
+        }
+        html.push_str(&format!("</h2><p>{}", sanitize::escape(&item.source)));
+        if let Some(date) = item.pub_date {
+            html.push_str(&format!(", {}", date.format("%Y-%m-%d %H:%M UTC")));
+        }
+        html.push_str("</p>");
+        // Descriptions were sanitized when building the DTOs.
+        if let Some(description) = &item.description {
+            html.push_str(description);
+        }
+        html.push_str("</article>\n");
+    }
+    html.push_str("</body></html>\n");
+    html
+}
@@ -0,0 +1,177 @@
+//! Self-contained HTML snapshots of starred articles.
+//!
+//! With `archive_starred` enabled, the page of an item is fetched when it is starred and saved as a
+//! single HTML file under `snapshots/` in the cache directory, served to API clients by
+//! `GET /api/items/{id}/snapshot`. Its stylesheets and images are inlined so it stays readable
+//! after the source removes the article. Scripts and other active content are stripped.
+
+use actix_web::{error, http::header, web, Error, HttpResponse};
+use anyhow::Result;
+use reqwest::Url;
+use scraper::{Html, Selector};
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::news;
+use crate::sanitize;
+
+/// Images larger than this are left out of snapshots.
+const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Return the path of the snapshot of an item.
+pub fn path(id: &str) -> Result<PathBuf> {
+    let dir = news::cache_dir()?.join("snapshots");
+    fs::create_dir_all(&dir)?;
+    Ok(dir.join(format!("{}.html", id)))
+}
+
+/// Return the URL the snapshot of an item is served at.
+pub fn url(id: &str) -> String {
+    format!("/api/items/{}/snapshot", id)
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/api/items/{id}/snapshot", web::get().to(get_snapshot));
+}
+
+async fn get_snapshot(id: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = id.into_inner();
+    // Item ids are hex digests, anything else could name a file outside of the snapshots.
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(error::ErrorNotFound("no such snapshot"));
+    }
+    let snapshot = web::block(move || fs::read(path(&id)?).map_err(anyhow::Error::from))
+        .await
+        .map_err(|_| error::ErrorNotFound("no such snapshot"))?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        // Scripts were stripped, the sandbox keeps any left over from running.
+        .header(header::CONTENT_SECURITY_POLICY, "sandbox")
+        .body(snapshot))
+}
+
+/// Fetch the page at `link` and save its snapshot as the one of item `id`.
+pub async fn archive(id: &str, link: &str) -> Result<()> {
+    let client = reqwest::Client::new();
+    let base = Url::parse(link)?;
+    let page = client.get(link).send().await?.error_for_status()?.text().await?;
+
+    let (title, stylesheets, inline_styles, images) = {
+        let document = Html::parse_document(&page);
+        let select = |selector: &str| Selector::parse(selector).expect("valid selector");
+        let title = document
+            .select(&select("title"))
+            .next()
+            .map(|title| title.text().collect::<String>())
+            .unwrap_or_default();
+        let stylesheets: Vec<Url> = document
+            .select(&select("link[rel~=stylesheet][href]"))
+            .filter_map(|link| base.join(link.value().attr("href")?).ok())
+            .collect();
+        let inline_styles: Vec<String> = document
+            .select(&select("style"))
+            .map(|style| style.text().collect())
+            .collect();
+        let images: HashSet<Url> = document
+            .select(&select("img[src]"))
+            .filter_map(|img| base.join(img.value().attr("src")?).ok())
+            .filter(|url| url.scheme() == "http" || url.scheme() == "https")
+            .collect();
+        (title, stylesheets, inline_styles, images)
+    };
+
+    let mut css = String::new();
+    for stylesheet in stylesheets {
+        match fetch_text(&client, &stylesheet).await {
+            Ok(text) => css.push_str(&text),
+            Err(e) => log::warn!("failed to fetch stylesheet {}: {}", stylesheet, e),
+        }
+        css.push('\n');
+    }
+    for style in inline_styles {
+        css.push_str(&style);
+        css.push('\n');
+    }
+
+    let mut data_uris = HashMap::new();
+    for image in images {
+        match fetch_data_uri(&client, &image).await {
+            Ok(Some(uri)) => {
+                data_uris.insert(image.to_string(), uri);
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("failed to fetch image {}: {}", image, e),
+        }
+    }
+
+    let body = ammonia::Builder::default()
+        .add_generic_attributes(&["class", "id"])
+        .add_url_schemes(&["data"])
+        .link_rel(Some("noopener noreferrer"))
+        .attribute_filter(move |element, attribute, value| {
+            match (element, attribute) {
+                ("img", "src") => {
+                    let url = base.join(value).ok()?;
+                    // Images that couldn't be fetched are dropped rather than hotlinked.
+                    data_uris.get(url.as_str()).cloned().map(Into::into)
+                }
+                (_, "href") if value.starts_with("data:") => None,
+                (_, "href") => base.join(value).ok().map(|url| url.to_string().into()),
+                _ => Some(value.into()),
+            }
+        })
+        .clean(&page)
+        .to_string();
+
+    let snapshot = format!(
+        "<!doctype html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{title}</title>
+<style>{css}</style>
+</head>
+<body>
+<p style=\"font: 12px sans-serif; padding: 4px; background: #ffd;\">
+Snapshot of <a href=\"{link}\">{link}</a>
+</p>
+{body}
+</body>
+</html>",
+        title = sanitize::escape(title.trim()),
+        css = css.replace("</style", "<\\/style"),
+        link = sanitize::escape(link),
+        body = body,
+    );
+    fs::write(path(id)?, snapshot)?;
+    Ok(())
+}
+
+async fn fetch_text(client: &reqwest::Client, url: &Url) -> Result<String> {
+    Ok(client
+        .get(url.as_str())
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?)
+}
+
+/// Fetch an image as a data URI, `None` when it is too large to inline.
+async fn fetch_data_uri(client: &reqwest::Client, url: &Url) -> Result<Option<String>> {
+    let response = client.get(url.as_str()).send().await?.error_for_status()?;
+    let mime = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .filter(|mime| mime.starts_with("image/"))
+        .unwrap_or("image/jpeg")
+        .to_string();
+    let data = response.bytes().await?;
+    if data.len() > MAX_IMAGE_BYTES {
+        return Ok(None);
+    }
+    Ok(Some(format!("data:{};base64,{}", mime, base64::encode(&data))))
+}
@@ -0,0 +1,133 @@
+//! Disk usage of the cache directory and eviction keeping it under `cache_cap_mb`.
+//!
+//! Cached images are evicted least recently used first, going by their access time. When that
+//! isn't enough, the server also drops the bodies of its oldest items.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::news;
+
+/// Size of the cache directory, in bytes.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Usage {
+    pub total_bytes: u64,
+    /// Cached images of items along with their placeholders.
+    pub images_bytes: u64,
+    /// Archived snapshots of starred items.
+    pub snapshots_bytes: u64,
+    /// The stored items.
+    pub items_bytes: u64,
+    pub cap_bytes: Option<u64>,
+}
+
+impl Usage {
+    /// Number of bytes to free to get back under the cap.
+    pub fn excess(&self) -> u64 {
+        self.cap_bytes.map_or(0, |cap| self.total_bytes.saturating_sub(cap))
+    }
+}
+
+struct CachedFile {
+    path: PathBuf,
+    size: u64,
+    /// Last time the file was read, or written when access times aren't recorded.
+    used: SystemTime,
+}
+
+/// Measure the cache directory against the cap of `cap_bytes`.
+pub fn usage(cap_bytes: Option<u64>) -> Result<Usage> {
+    let cache_dir = news::cache_dir()?;
+    let snapshots = cache_dir.join("snapshots");
+    let items = cache_dir.join("news_items.dat");
+    let mut usage = Usage {
+        cap_bytes,
+        ..Usage::default()
+    };
+    for file in files(&cache_dir) {
+        usage.total_bytes += file.size;
+        if file.path.starts_with(&snapshots) {
+            usage.snapshots_bytes += file.size;
+        } else if file.path == items {
+            usage.items_bytes += file.size;
+        } else {
+            usage.images_bytes += file.size;
+        }
+    }
+    Ok(usage)
+}
+
+/// Delete cached images least recently used first until `bytes` were freed, sparing the ones in
+/// `kept`. Returns the paths of the deleted images.
+pub fn evict_images(bytes: u64, kept: &HashSet<PathBuf>) -> Result<Vec<PathBuf>> {
+    let cache_dir = news::cache_dir()?;
+    let snapshots = cache_dir.join("snapshots");
+    let items = cache_dir.join("news_items.dat");
+    let mut images: Vec<CachedFile> = files(&cache_dir)
+        .into_iter()
+        .filter(|file| !file.path.starts_with(&snapshots) && file.path != items)
+        // Placeholders go along with their image.
+        .filter(|file| file.path.extension().map_or(true, |ext| ext != "blurhash"))
+        .filter(|file| !kept.contains(&file.path))
+        .collect();
+    images.sort_by_key(|file| file.used);
+
+    let mut freed = 0;
+    let mut evicted = Vec::new();
+    for image in images {
+        if freed >= bytes {
+            break;
+        }
+        if let Err(e) = fs::remove_file(&image.path) {
+            log::warn!("failed to evict {}: {}", image.path.display(), e);
+            continue;
+        }
+        let placeholder = image.path.with_extension("blurhash");
+        if let Ok(metadata) = fs::metadata(&placeholder) {
+            if fs::remove_file(&placeholder).is_ok() {
+                freed += metadata.len();
+            }
+        }
+        freed += image.size;
+        evicted.push(image.path);
+    }
+    Ok(evicted)
+}
+
+/// List the files under `dir` recursively, skipping the ones that can't be read.
+fn files(dir: &Path) -> Vec<CachedFile> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!("failed to list {}: {}", dir.display(), e);
+            return Vec::new();
+        }
+    };
+
+    let mut found = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if metadata.is_dir() {
+            found.extend(files(&entry.path()));
+        } else {
+            let used = metadata
+                .accessed()
+                .or_else(|_| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            found.push(CachedFile {
+                path: entry.path(),
+                size: metadata.len(),
+                used,
+            });
+        }
+    }
+    found
+}
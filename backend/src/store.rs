@@ -0,0 +1,449 @@
+//! Persistent state of the user's interactions with items.
+
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, HashSet};
+
+use crate::annotations::Annotations;
+use crate::folders::FeedOrder;
+use crate::priority::Interests;
+use crate::quality::Bayes;
+use crate::sync::Stamped;
+use crate::translate::Translation;
+use crate::views::View;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Store {
+    /// Time each item was read, keyed by item id.
+    read: HashMap<String, DateTime<Utc>>,
+    /// Time each snoozed item is due again, keyed by item id.
+    snoozed: HashMap<String, DateTime<Utc>>,
+    /// Feeds subscribed to at runtime, in addition to the configured ones.
+    feeds: Vec<String>,
+    /// Ids of the starred items.
+    starred: HashSet<String>,
+    /// Links of imported read items that weren't fetched yet.
+    pending_read: HashSet<String>,
+    /// Links of imported starred items that weren't fetched yet.
+    pending_starred: HashSet<String>,
+    /// Ids of the items the user marked as junk.
+    junk: HashSet<String>,
+    /// Model of junk learned from the items marked read or junk.
+    pub bayes: Bayes,
+    /// Model of the feeds and keywords the user reads, ranking the priority inbox.
+    pub interests: Interests,
+    /// Last time the read or starred state of each item changed, keyed by item id.
+    updated: HashMap<String, DateTime<Utc>>,
+    /// Time of the change the read state of each item resolved to, keyed by item id.
+    read_stamps: HashMap<String, DateTime<Utc>>,
+    /// Time of the change the starred state of each item resolved to, keyed by item id.
+    starred_stamps: HashMap<String, DateTime<Utc>>,
+    /// Translations of items, keyed by item id then language.
+    translations: HashMap<String, HashMap<String, Translation>>,
+    /// Ids of the items whose page was archived as a snapshot.
+    snapshots: HashSet<String>,
+    /// Starred items whose link is dead, keyed by item id.
+    dead_links: HashMap<String, DeadLink>,
+    /// URLs of the Wayback Machine copies of items, keyed by item id.
+    archive_urls: HashMap<String, String>,
+    /// Feeds and keywords muted for a while.
+    mutes: Vec<Mute>,
+    /// Item the user last focused on any device.
+    position: Option<ReadPosition>,
+    /// Read-only links sharing a view of the items.
+    shares: Vec<Share>,
+    /// Ids of the items to read next, in reading order.
+    queue: Vec<String>,
+    /// Folders and order of the feeds in the sidebar.
+    feed_order: FeedOrder,
+    /// Notes and highlights of items, keyed by item id.
+    annotations: HashMap<String, Annotations>,
+}
+
+/// Where the user left off reading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadPosition {
+    /// Id of the item last focused.
+    pub id: String,
+    pub at: DateTime<Utc>,
+}
+
+/// A feed or keyword kept out of the unread list until `until`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mute {
+    #[serde(flatten)]
+    pub target: MuteTarget,
+    pub until: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum MuteTarget {
+    /// URL of a feed.
+    Feed(String),
+    /// Word or phrase matched case insensitively against titles.
+    Keyword(String),
+}
+
+/// A view of the items anyone holding `token` can read, but not change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Share {
+    pub token: String,
+    pub view: View,
+    /// Title of the shared page, defaulting to the name of the view.
+    pub title: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Share {
+    pub fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| self.view.name())
+    }
+}
+
+/// A link found dead by the periodic check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLink {
+    /// Time the link was first found dead.
+    pub since: DateTime<Utc>,
+    /// Closest copy of the page in the Wayback Machine.
+    pub wayback_url: Option<String>,
+}
+
+impl Store {
+    /// Mark an item read, returning whether it wasn't already.
+    pub fn mark_read(&mut self, id: &str) -> bool {
+        self.set_read(id, true, Utc::now())
+    }
+
+    /// Mark an item unread, returning whether it was read.
+    pub fn mark_unread(&mut self, id: &str) -> bool {
+        self.set_read(id, false, Utc::now())
+    }
+
+    /// Apply a change of the read state of an item made at `at`, unless a later change was
+    /// already applied. Returns whether the state changed.
+    pub fn set_read(&mut self, id: &str, read: bool, at: DateTime<Utc>) -> bool {
+        let current = self.read_stamp(id);
+        if !(Stamped { value: read, at }).supersedes(&current) {
+            return false;
+        }
+        self.read_stamps.insert(id.to_string(), at);
+        if read == current.value {
+            return false;
+        }
+        if read {
+            self.read.insert(id.to_string(), at);
+        } else {
+            self.read.remove(id);
+        }
+        self.touch(id, at);
+        true
+    }
+
+    pub fn is_read(&self, id: &str) -> bool {
+        self.read.contains_key(id)
+    }
+
+    /// Read state of an item along with the time of the change it resolved to.
+    fn read_stamp(&self, id: &str) -> Stamped {
+        Stamped {
+            value: self.is_read(id),
+            at: self
+                .read_stamps
+                .get(id)
+                .or_else(|| self.read.get(id))
+                .copied()
+                .unwrap_or_else(|| Utc.timestamp(0, 0)),
+        }
+    }
+
+    /// Read state of every item that was ever marked read or unread, keyed by item id.
+    pub fn read_states(&self) -> HashMap<String, Stamped> {
+        self.read
+            .keys()
+            .chain(self.read_stamps.keys())
+            .map(|id| (id.clone(), self.read_stamp(id)))
+            .collect()
+    }
+
+    pub fn feeds(&self) -> &[String] {
+        &self.feeds
+    }
+
+    /// Subscribe to a feed, returning whether it is new.
+    pub fn add_feed(&mut self, url: &str) -> bool {
+        if self.feeds.iter().any(|feed| feed == url) {
+            return false;
+        }
+        self.feeds.push(url.to_string());
+        true
+    }
+
+    pub fn star(&mut self, id: &str) {
+        self.set_starred(id, true, Utc::now());
+    }
+
+    pub fn unstar(&mut self, id: &str) {
+        self.set_starred(id, false, Utc::now());
+    }
+
+    /// Apply a change of the starred state of an item made at `at`, unless a later change was
+    /// already applied. Returns whether the state changed.
+    pub fn set_starred(&mut self, id: &str, starred: bool, at: DateTime<Utc>) -> bool {
+        let current = self.starred_stamp(id);
+        if !(Stamped { value: starred, at }).supersedes(&current) {
+            return false;
+        }
+        self.starred_stamps.insert(id.to_string(), at);
+        if starred == current.value {
+            return false;
+        }
+        if starred {
+            self.starred.insert(id.to_string());
+        } else {
+            self.starred.remove(id);
+        }
+        self.touch(id, at);
+        true
+    }
+
+    pub fn is_starred(&self, id: &str) -> bool {
+        self.starred.contains(id)
+    }
+
+    /// Starred state of an item along with the time of the change it resolved to.
+    fn starred_stamp(&self, id: &str) -> Stamped {
+        Stamped {
+            value: self.is_starred(id),
+            at: self
+                .starred_stamps
+                .get(id)
+                .copied()
+                .unwrap_or_else(|| Utc.timestamp(0, 0)),
+        }
+    }
+
+    /// Starred state of every item that was ever starred or unstarred, keyed by item id.
+    pub fn starred_states(&self) -> HashMap<String, Stamped> {
+        self.starred
+            .iter()
+            .chain(self.starred_stamps.keys())
+            .map(|id| (id.clone(), self.starred_stamp(id)))
+            .collect()
+    }
+
+    /// Remember imported state of an item that wasn't fetched yet.
+    pub fn add_pending(&mut self, link: &str, read: bool, starred: bool) {
+        if read {
+            self.pending_read.insert(link.to_string());
+        }
+        if starred {
+            self.pending_starred.insert(link.to_string());
+        }
+    }
+
+    /// Apply imported state waiting for an item now that it was fetched, returning whether
+    /// there was any.
+    pub fn apply_pending(&mut self, id: &str, link: &str) -> bool {
+        let read = self.pending_read.remove(link);
+        if read {
+            self.mark_read(id);
+        }
+        let starred = self.pending_starred.remove(link);
+        if starred {
+            self.star(id);
+        }
+        read || starred
+    }
+
+    /// Mark an item as junk, returning whether it wasn't already.
+    pub fn mark_junk(&mut self, id: &str) -> bool {
+        self.junk.insert(id.to_string())
+    }
+
+    pub fn is_junk(&self, id: &str) -> bool {
+        self.junk.contains(id)
+    }
+
+    pub fn add_snapshot(&mut self, id: &str) {
+        self.snapshots.insert(id.to_string());
+    }
+
+    pub fn has_snapshot(&self, id: &str) -> bool {
+        self.snapshots.contains(id)
+    }
+
+    pub fn archive_url(&self, id: &str) -> Option<&str> {
+        self.archive_urls.get(id).map(String::as_str)
+    }
+
+    pub fn set_archive_url(&mut self, id: &str, url: String) {
+        self.archive_urls.insert(id.to_string(), url);
+    }
+
+    pub fn dead_link(&self, id: &str) -> Option<&DeadLink> {
+        self.dead_links.get(id)
+    }
+
+    /// Flag the link of an item dead, returning whether it wasn't already.
+    pub fn set_dead_link(&mut self, id: &str, wayback_url: Option<String>) -> bool {
+        if self.dead_links.contains_key(id) {
+            return false;
+        }
+        let dead_link = DeadLink {
+            since: Utc::now(),
+            wayback_url,
+        };
+        self.dead_links.insert(id.to_string(), dead_link);
+        true
+    }
+
+    /// Clear the dead flag of a link that resolves again, returning whether it was set.
+    pub fn clear_dead_link(&mut self, id: &str) -> bool {
+        self.dead_links.remove(id).is_some()
+    }
+
+    pub fn position(&self) -> Option<&ReadPosition> {
+        self.position.as_ref()
+    }
+
+    pub fn set_position(&mut self, id: &str) {
+        self.position = Some(ReadPosition {
+            id: id.to_string(),
+            at: Utc::now(),
+        });
+    }
+
+    pub fn mutes(&self) -> &[Mute] {
+        &self.mutes
+    }
+
+    pub fn set_mutes(&mut self, mutes: Vec<Mute>) {
+        self.mutes = mutes;
+    }
+
+    /// Whether an item of `feed` titled `title` is muted.
+    pub fn is_muted(&self, feed: &str, title: &str) -> bool {
+        let title = title.to_lowercase();
+        self.mutes.iter().any(|mute| match &mute.target {
+            MuteTarget::Feed(muted) => muted == feed,
+            MuteTarget::Keyword(keyword) => title.contains(&keyword.to_lowercase()),
+        })
+    }
+
+    /// Lift the mutes expired by `now`, returning whether there were any.
+    pub fn expire_mutes(&mut self, now: DateTime<Utc>) -> bool {
+        let count = self.mutes.len();
+        self.mutes.retain(|mute| mute.until > now);
+        self.mutes.len() != count
+    }
+
+    pub fn shares(&self) -> &[Share] {
+        &self.shares
+    }
+
+    pub fn share(&self, token: &str) -> Option<&Share> {
+        self.shares.iter().find(|share| share.token == token)
+    }
+
+    pub fn add_share(&mut self, share: Share) {
+        self.shares.push(share);
+    }
+
+    /// Revoke the share of `token`, returning whether there was one.
+    pub fn remove_share(&mut self, token: &str) -> bool {
+        let count = self.shares.len();
+        self.shares.retain(|share| share.token != token);
+        self.shares.len() != count
+    }
+
+    pub fn feed_order(&self) -> &FeedOrder {
+        &self.feed_order
+    }
+
+    pub fn set_feed_order(&mut self, order: FeedOrder) {
+        self.feed_order = order;
+    }
+
+    pub fn queue(&self) -> &[String] {
+        &self.queue
+    }
+
+    pub fn set_queue(&mut self, queue: Vec<String>) {
+        self.queue = queue;
+    }
+
+    /// Drop the items for which `keep` is false from the queue, returning whether there were any.
+    pub fn retain_queued<F: FnMut(&String) -> bool>(&mut self, keep: F) -> bool {
+        let count = self.queue.len();
+        self.queue.retain(keep);
+        self.queue.len() != count
+    }
+
+    /// Hide an item until `until`.
+    pub fn snooze(&mut self, id: &str, until: DateTime<Utc>) {
+        self.snoozed.insert(id.to_string(), until);
+    }
+
+    pub fn snoozed_until(&self, id: &str) -> Option<DateTime<Utc>> {
+        self.snoozed.get(id).copied()
+    }
+
+    /// Wake the items whose snooze is over by `now`, returning their ids.
+    ///
+    /// Woken items are marked unread so that they re-enter the unread stream.
+    pub fn wake(&mut self, now: DateTime<Utc>) -> Vec<String> {
+        let due: Vec<String> = self
+            .snoozed
+            .iter()
+            .filter(|(_, &until)| until <= now)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &due {
+            self.snoozed.remove(id);
+            self.mark_unread(id);
+        }
+        due
+    }
+
+    /// Last time the read or starred state of an item changed.
+    pub fn updated(&self, id: &str) -> Option<DateTime<Utc>> {
+        self.updated.get(id).copied()
+    }
+
+    fn touch(&mut self, id: &str, at: DateTime<Utc>) {
+        let updated = self.updated.entry(id.to_string()).or_insert(at);
+        *updated = (*updated).max(at);
+    }
+
+    pub fn translation(&self, id: &str, to: &str) -> Option<&Translation> {
+        self.translations.get(id)?.get(to)
+    }
+
+    pub fn add_translation(&mut self, id: &str, translation: Translation) {
+        self.translations
+            .entry(id.to_string())
+            .or_default()
+            .insert(translation.to.clone(), translation);
+    }
+
+    pub fn annotations(&self, id: &str) -> Option<&Annotations> {
+        self.annotations.get(id)
+    }
+
+    /// Replace the annotations of an item, forgetting them if they are empty.
+    pub fn set_annotations(&mut self, id: &str, annotations: Annotations) {
+        if annotations.is_empty() {
+            self.annotations.remove(id);
+        } else {
+            self.annotations.insert(id.to_string(), annotations);
+        }
+    }
+
+    pub fn is_annotated(&self, id: &str) -> bool {
+        self.annotations.contains_key(id)
+    }
+}
@@ -0,0 +1,141 @@
+//! Short summaries of long extracted articles.
+//!
+//! Summaries come from a pluggable `Summarizer`. Without a `summarizer` section in the config the
+//! no-op summarizer is used and items are left without summary.
+
+use anyhow::{anyhow, Result};
+use futures::future::{self, BoxFuture};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::config::SummarizerConfig;
+use crate::news::NewsItem;
+use crate::sanitize;
+
+/// Articles with fewer words are short enough to read as is.
+const MIN_WORDS: usize = 300;
+/// Maximum number of characters of an article sent to the summarizer.
+const MAX_INPUT_CHARS: usize = 12_000;
+
+/// Produces a summary of a few sentences out of the plain text of an article.
+pub trait Summarizer: Send + Sync {
+    fn summarize<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Option<String>>>;
+}
+
+/// Summarizer used when none is configured.
+pub struct NoopSummarizer;
+
+impl Summarizer for NoopSummarizer {
+    fn summarize<'a>(&'a self, _: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        Box::pin(future::ok(None))
+    }
+}
+
+/// Summarizer calling an OpenAI compatible chat completions endpoint.
+pub struct OpenAiSummarizer {
+    config: SummarizerConfig,
+    client: reqwest::Client,
+}
+
+impl OpenAiSummarizer {
+    pub fn new(config: SummarizerConfig) -> Self {
+        OpenAiSummarizer {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage<'a>>,
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatReply,
+}
+
+#[derive(Deserialize)]
+struct ChatReply {
+    content: String,
+}
+
+impl Summarizer for OpenAiSummarizer {
+    fn summarize<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        Box::pin(async move {
+            let request = ChatRequest {
+                model: &self.config.model,
+                messages: vec![
+                    ChatMessage {
+                        role: "system",
+                        content: "Summarize the article in two or three sentences.",
+                    },
+                    ChatMessage {
+                        role: "user",
+                        content: text,
+                    },
+                ],
+            };
+            let mut builder = self.client.post(&self.config.endpoint).json(&request);
+            if let Some(api_key) = &self.config.api_key {
+                builder = builder.bearer_auth(api_key);
+            }
+            let response: ChatResponse = builder.send().await?.error_for_status()?.json().await?;
+            let choice = response
+                .choices
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow!("no summary returned"))?;
+            Ok(Some(choice.message.content.trim().to_string()))
+        })
+    }
+}
+
+/// Build the summarizer described by the config.
+pub fn from_config(config: Option<&SummarizerConfig>) -> Arc<dyn Summarizer> {
+    match config {
+        Some(config) => Arc::new(OpenAiSummarizer::new(config.clone())),
+        None => Arc::new(NoopSummarizer),
+    }
+}
+
+/// Summarize the long extracted articles of items that aren't `known` yet.
+pub async fn summarize_items(
+    summarizer: &dyn Summarizer,
+    items: &mut [NewsItem],
+    known: &HashSet<String>,
+) {
+    for item in items.iter_mut() {
+        if known.contains(&item.id()) {
+            continue;
+        }
+        let text = match &item.content {
+            Some(content) => sanitize::text(content),
+            None => continue,
+        };
+        if text.split_whitespace().count() < MIN_WORDS {
+            continue;
+        }
+
+        let input: String = text.chars().take(MAX_INPUT_CHARS).collect();
+        match summarizer.summarize(&input).await {
+            Ok(summary) => item.summary = summary,
+            Err(e) => log::warn!("failed to summarize {:?}: {}", item.link(), e),
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! Conflict resolution of the read and starred state of items changed on several devices.
+//!
+//! Each state of an item is a last-writer-wins register: a change only applies if it was made
+//! after the one the register holds. Changes are dated by the device that made them, which may
+//! have been offline for a while, then brought to the server's clock to make up for skew. Two
+//! changes made at the same time resolve to read or starred, so a star is never lost to a race.
+
+use chrono::prelude::*;
+
+/// A state along with the time it was written, on the server's clock.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stamped {
+    pub value: bool,
+    pub at: DateTime<Utc>,
+}
+
+impl Stamped {
+    /// Whether this write supersedes the `current` one.
+    pub fn supersedes(&self, current: &Stamped) -> bool {
+        self.at > current.at || (self.at == current.at && self.value && !current.value)
+    }
+}
+
+/// Bring the time `at` a device made a change at to the server's clock.
+///
+/// `sent` is the time on the device when it sent the change and `now` the time on the server when
+/// it received it, their difference being taken as the skew of the device. Changes are never dated
+/// after `now`, and changes without a time or whose correction overflows are dated `now`.
+pub fn server_time(
+    at: Option<DateTime<Utc>>,
+    sent: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> DateTime<Utc> {
+    let at = match (at, sent) {
+        (Some(at), Some(sent)) => at.checked_add_signed(now - sent).unwrap_or(now),
+        (Some(at), None) => at,
+        (None, _) => now,
+    };
+    at.min(now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn time(hour: u32, min: u32) -> DateTime<Utc> {
+        Utc.ymd(2020, 5, 1).and_hms(hour, min, 0)
+    }
+
+    /// Apply writes in order to a register holding `false` since the epoch.
+    fn apply(writes: &[Stamped]) -> Stamped {
+        let mut register = Stamped {
+            value: false,
+            at: Utc.timestamp(0, 0),
+        };
+        for write in writes {
+            if write.supersedes(&register) {
+                register = *write;
+            }
+        }
+        register
+    }
+
+    #[test]
+    fn later_write_wins() {
+        let star = Stamped {
+            value: true,
+            at: time(10, 0),
+        };
+        let unstar = Stamped {
+            value: false,
+            at: time(10, 5),
+        };
+        assert_eq!(apply(&[star, unstar]), unstar);
+    }
+
+    #[test]
+    fn converges_whatever_the_arrival_order() {
+        let writes = [
+            Stamped {
+                value: true,
+                at: time(10, 0),
+            },
+            Stamped {
+                value: false,
+                at: time(10, 5),
+            },
+            Stamped {
+                value: true,
+                at: time(10, 5),
+            },
+        ];
+        let expected = apply(&writes);
+        let mut reversed = writes;
+        reversed.reverse();
+        assert_eq!(apply(&reversed), expected);
+        assert_eq!(apply(&[writes[1], writes[0], writes[2]]), expected);
+        assert_eq!(apply(&[writes[2], writes[0], writes[1]]), expected);
+    }
+
+    #[test]
+    fn concurrent_writes_keep_the_star() {
+        let star = Stamped {
+            value: true,
+            at: time(10, 0),
+        };
+        let unstar = Stamped {
+            value: false,
+            at: time(10, 0),
+        };
+        assert!(apply(&[star, unstar]).value);
+        assert!(apply(&[unstar, star]).value);
+    }
+
+    #[test]
+    fn clock_ahead_is_corrected() {
+        // The phone runs an hour ahead and stars an item at 10:00 real time, then the laptop
+        // unstars it at 10:05. The phone's change must not win for being dated 11:00.
+        let now = time(10, 0);
+        let phone = server_time(Some(time(11, 0)), Some(time(11, 0)), now);
+        assert_eq!(phone, time(10, 0));
+        let laptop = server_time(Some(time(10, 5)), Some(time(10, 5)), time(10, 5));
+        let star = Stamped {
+            value: true,
+            at: phone,
+        };
+        let unstar = Stamped {
+            value: false,
+            at: laptop,
+        };
+        assert!(!apply(&[star, unstar]).value);
+        assert!(!apply(&[unstar, star]).value);
+    }
+
+    #[test]
+    fn clock_behind_is_corrected() {
+        // The laptop stars an item at 10:00, then the phone running an hour behind marks it
+        // unstarred at 10:05 real time. The phone's change must win despite being dated 9:05.
+        let laptop = server_time(Some(time(10, 0)), Some(time(10, 0)), time(10, 0));
+        let phone = server_time(Some(time(9, 5)), Some(time(9, 5)), time(10, 5));
+        assert_eq!(phone, time(10, 5));
+        let star = Stamped {
+            value: true,
+            at: laptop,
+        };
+        let unstar = Stamped {
+            value: false,
+            at: phone,
+        };
+        assert!(!apply(&[star, unstar]).value);
+    }
+
+    #[test]
+    fn offline_change_keeps_its_time() {
+        // The phone, running five minutes behind, reads an item offline at 10:00 real time and
+        // only reconnects at 12:00, after the laptop marked the item unread at 11:00.
+        let skew = Duration::minutes(5);
+        let phone = server_time(
+            Some(time(10, 0) - skew),
+            Some(time(12, 0) - skew),
+            time(12, 0),
+        );
+        assert_eq!(phone, time(10, 0));
+        let read = Stamped {
+            value: true,
+            at: phone,
+        };
+        let unread = Stamped {
+            value: false,
+            at: time(11, 0),
+        };
+        assert!(!apply(&[unread, read]).value);
+    }
+
+    #[test]
+    fn future_changes_are_dated_now() {
+        let now = time(10, 0);
+        assert_eq!(server_time(Some(time(12, 0)), None, now), now);
+        assert_eq!(server_time(None, None, now), now);
+    }
+
+    #[test]
+    fn absurd_times_are_dated_now() {
+        let now = time(10, 0);
+        let early = Utc.ymd(-200_000, 1, 1).and_hms(0, 0, 0);
+        let late = Utc.ymd(200_000, 1, 1).and_hms(0, 0, 0);
+        assert_eq!(server_time(Some(late), Some(early), now), now);
+        assert_eq!(server_time(Some(early), Some(late), now), now);
+    }
+}
@@ -0,0 +1,32 @@
+//! Integration with systemd socket activation and readiness notification.
+//!
+//! Both are no-ops when the backend isn't started by systemd.
+
+use listenfd::ListenFd;
+use sd_notify::NotifyState;
+
+use std::net::TcpListener;
+
+/// Take the listening socket passed in by systemd socket activation, if any.
+pub fn activated_listener() -> std::io::Result<Option<TcpListener>> {
+    let mut listenfd = ListenFd::from_env();
+    let listener = listenfd.take_tcp_listener(0)?;
+    if let Some(listener) = &listener {
+        log::info!("using socket activated listener {:?}", listener.local_addr());
+    }
+    Ok(listener)
+}
+
+/// Tell systemd that the service finished starting up.
+pub fn notify_ready() {
+    if let Err(e) = sd_notify::notify(false, &[NotifyState::Ready]) {
+        log::warn!("failed to notify systemd of readiness: {}", e);
+    }
+}
+
+/// Tell systemd that the service is shutting down.
+pub fn notify_stopping() {
+    if let Err(e) = sd_notify::notify(false, &[NotifyState::Stopping]) {
+        log::warn!("failed to notify systemd of shutdown: {}", e);
+    }
+}
@@ -0,0 +1,437 @@
+//! API tokens scripts authenticate with as `Authorization: Bearer <token>` on the REST API.
+//!
+//! Tokens are minted through `POST /api/tokens`, listed through `GET /api/tokens` and revoked
+//! through `DELETE /api/tokens/{id}`, all of which take the admin token. A token is only shown
+//! once, when it is minted: only its BLAKE3 hash is kept, in `tokens.json` in the project data
+//! directory. Read tokens are limited to `GET` requests, read-write tokens may use any method;
+//! neither opens the admin routes.
+//!
+//! Requests to the API carrying no token are let through unless `require_api_token` is set.
+//! When it is, opening the websocket takes a read-write token too, as it changes the state of
+//! items, unless `ws_token` is set, which it then takes instead. Browsers can't set headers on
+//! the websocket, so there the token may also come as the `token` query parameter.
+
+use actix_service::{Service, Transform};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method};
+use actix_web::{error, web, Error, HttpRequest, HttpResponse};
+use anyhow::Result;
+use chrono::prelude::*;
+use futures::future::{ok, Either, Ready};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+
+use crate::admin;
+use crate::config::Config;
+use crate::news;
+use crate::protocol::ConnectParams;
+
+/// Prefix of the tokens, telling them apart from the admin token in logs and secret scanners.
+const PREFIX: &str = "news_";
+/// Maximum length of the name of a token, in characters.
+const MAX_NAME_CHARS: usize = 100;
+
+/// What a token gives access to.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Read,
+    ReadWrite,
+}
+
+impl Scope {
+    /// Whether the scope allows requests with `method`.
+    pub fn allows(self, method: &Method) -> bool {
+        match self {
+            Scope::Read => method == Method::GET || method == Method::HEAD,
+            Scope::ReadWrite => true,
+        }
+    }
+}
+
+/// A token, as listed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    /// What the user named the token after, e.g. the script using it.
+    pub name: String,
+    pub scope: Scope,
+    pub created_at: DateTime<Utc>,
+    /// Hex encoded BLAKE3 hash of the token.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    hash: String,
+}
+
+/// The tokens, shared by the routes and the middleware checking them.
+#[derive(Clone)]
+pub struct TokenStore {
+    path: PathBuf,
+    tokens: Arc<RwLock<Vec<ApiToken>>>,
+}
+
+impl TokenStore {
+    pub fn load() -> Result<Self> {
+        let data_dir = news::project_dirs()?.data_dir().to_path_buf();
+        fs::create_dir_all(&data_dir)?;
+        let path = data_dir.join("tokens.json");
+        let tokens = match fs::File::open(&path) {
+            Ok(file) => serde_json::from_reader(file)?,
+            Err(_) => Vec::new(),
+        };
+
+        Ok(TokenStore {
+            path,
+            tokens: Arc::new(RwLock::new(tokens)),
+        })
+    }
+
+    /// Return the tokens, without their hashes.
+    pub fn list(&self) -> Vec<ApiToken> {
+        let tokens = self.tokens.read().unwrap();
+        tokens
+            .iter()
+            .map(|token| ApiToken {
+                hash: String::new(),
+                ..token.clone()
+            })
+            .collect()
+    }
+
+    /// Mint a token, returning it along with the secret the client authenticates with.
+    pub fn mint(&self, name: String, scope: Scope) -> Result<(ApiToken, String)> {
+        let mut rng = rand::thread_rng();
+        let secret = format!(
+            "{}{}",
+            PREFIX,
+            base64::encode_config(&rng.gen::<[u8; 32]>(), base64::URL_SAFE_NO_PAD)
+        );
+        let token = ApiToken {
+            id: format!("{:016x}", rng.gen::<u64>()),
+            name,
+            scope,
+            created_at: Utc::now(),
+            hash: hash(&secret),
+        };
+
+        let mut tokens = self.tokens.write().unwrap();
+        tokens.push(token.clone());
+        self.save(&tokens)?;
+        Ok((
+            ApiToken {
+                hash: String::new(),
+                ..token
+            },
+            secret,
+        ))
+    }
+
+    /// Revoke a token, returning whether there was one with this id.
+    pub fn revoke(&self, id: &str) -> Result<bool> {
+        let mut tokens = self.tokens.write().unwrap();
+        let count = tokens.len();
+        tokens.retain(|token| token.id != id);
+        if tokens.len() == count {
+            return Ok(false);
+        }
+        self.save(&tokens)?;
+        Ok(true)
+    }
+
+    /// Return the scope of the token `secret`, or `None` if it isn't a live token.
+    pub fn scope(&self, secret: &str) -> Option<Scope> {
+        let hash = hash(secret);
+        let tokens = self.tokens.read().unwrap();
+        tokens
+            .iter()
+            .find(|token| token.hash == hash)
+            .map(|token| token.scope)
+    }
+
+    fn save(&self, tokens: &[ApiToken]) -> Result<()> {
+        news::write_file(&self.path, |file| {
+            Ok(serde_json::to_writer_pretty(file, tokens)?)
+        })
+    }
+}
+
+fn hash(secret: &str) -> String {
+    blake3::hash(secret.as_bytes()).to_hex().to_string()
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/api/tokens")
+            .route(web::get().to(list_tokens))
+            .route(web::post().to(mint_token)),
+    )
+    .route("/api/tokens/{id}", web::delete().to(revoke_token));
+}
+
+#[derive(Debug, Deserialize)]
+struct NewToken {
+    name: String,
+    scope: Scope,
+}
+
+#[derive(Debug, Serialize)]
+struct MintedToken {
+    #[serde(flatten)]
+    token: ApiToken,
+    /// The token itself, only ever shown here.
+    secret: String,
+}
+
+async fn list_tokens(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    tokens: web::Data<TokenStore>,
+) -> Result<HttpResponse, Error> {
+    admin::authorize(&req, &config)?;
+    Ok(HttpResponse::Ok().json(tokens.list()))
+}
+
+async fn mint_token(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    tokens: web::Data<TokenStore>,
+    new: web::Json<NewToken>,
+) -> Result<HttpResponse, Error> {
+    admin::authorize(&req, &config)?;
+    let NewToken { name, scope } = new.into_inner();
+    let name = name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+        return Err(error::ErrorBadRequest("invalid token name"));
+    }
+
+    let (token, secret) = tokens.mint(name, scope).map_err(|e| {
+        log::error!("failed to save tokens: {}", e);
+        error::ErrorInternalServerError("failed to save tokens")
+    })?;
+    Ok(HttpResponse::Created().json(MintedToken { token, secret }))
+}
+
+async fn revoke_token(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    tokens: web::Data<TokenStore>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    admin::authorize(&req, &config)?;
+    let revoked = tokens.revoke(&id).map_err(|e| {
+        log::error!("failed to save tokens: {}", e);
+        error::ErrorInternalServerError("failed to save tokens")
+    })?;
+    if !revoked {
+        return Err(error::ErrorNotFound("no such token"));
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// What to do with a request to the API.
+#[derive(Debug, PartialEq)]
+enum Access {
+    Allow,
+    /// The request carries no token, or one that isn't live.
+    Unauthorized,
+    /// The token doesn't allow the method of the request.
+    Forbidden,
+}
+
+/// Middleware checking the tokens of the requests to the API.
+#[derive(Clone)]
+pub struct TokenAuth {
+    tokens: TokenStore,
+    admin_token: Option<String>,
+    ws_token: Option<String>,
+    required: bool,
+}
+
+impl TokenAuth {
+    pub fn new(tokens: TokenStore, config: &Config) -> Self {
+        TokenAuth {
+            tokens,
+            admin_token: config.admin_token.clone(),
+            ws_token: config.ws_token.clone(),
+            required: config.require_api_token,
+        }
+    }
+
+    fn access(&self, path: &str, method: &Method, bearer: Option<&str>) -> Access {
+        // The admin routes, including those of the tokens, check the admin token themselves,
+        // and CORS preflight requests never carry credentials. The websocket checks its own
+        // token unless API tokens are required.
+        let websocket = path == "/ws/";
+        let exempt = !(path.starts_with("/api/") || (websocket && self.required))
+            || path.starts_with("/api/admin/")
+            || path == "/api/tokens"
+            || path.starts_with("/api/tokens/")
+            || method == Method::OPTIONS;
+        if exempt {
+            return Access::Allow;
+        }
+
+        let secret = match bearer {
+            Some(secret) => secret,
+            None if self.required => return Access::Unauthorized,
+            None => return Access::Allow,
+        };
+        let admin = self.admin_token.as_deref();
+        if admin.map_or(false, |admin| admin::secret_matches(secret, admin)) {
+            return Access::Allow;
+        }
+        let ws_token = self.ws_token.as_deref().filter(|_| websocket);
+        if ws_token.map_or(false, |ws_token| admin::secret_matches(secret, ws_token)) {
+            return Access::Allow;
+        }
+        match self.tokens.scope(secret) {
+            Some(Scope::ReadWrite) if websocket => Access::Allow,
+            Some(_) if websocket => Access::Forbidden,
+            Some(scope) if scope.allows(method) => Access::Allow,
+            Some(_) => Access::Forbidden,
+            None => Access::Unauthorized,
+        }
+    }
+}
+
+impl<S, B> Transform<S> for TokenAuth
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TokenAuthMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(TokenAuthMiddleware {
+            service,
+            auth: self.clone(),
+        })
+    }
+}
+
+pub struct TokenAuthMiddleware<S> {
+    service: S,
+    auth: TokenAuth,
+}
+
+impl<S, B> Service for TokenAuthMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let bearer = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::to_owned)
+            .or_else(|| {
+                web::Query::<ConnectParams>::from_query(req.query_string())
+                    .ok()
+                    .and_then(|params| params.into_inner().token)
+            });
+        let access = self.auth.access(req.path(), req.method(), bearer.as_deref());
+        let response = match access {
+            Access::Allow => return Either::Left(self.service.call(req)),
+            Access::Unauthorized => HttpResponse::Unauthorized().body("invalid api token"),
+            Access::Forbidden => HttpResponse::Forbidden().body("read only api token"),
+        };
+        Either::Right(ok(req.into_response(response.into_body())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Directory the tokens of a test are saved in, removed when the test is done.
+    struct TempDir(PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn auth(required: bool) -> (TokenAuth, String, String, TempDir) {
+        let dir = std::env::temp_dir().join(format!("news-tokens-{}", rand::random::<u64>()));
+        fs::create_dir_all(&dir).unwrap();
+        let tokens = TokenStore {
+            path: dir.join("tokens.json"),
+            tokens: Arc::new(RwLock::new(Vec::new())),
+        };
+        let (_, read) = tokens.mint("reader".to_string(), Scope::Read).unwrap();
+        let (_, write) = tokens.mint("writer".to_string(), Scope::ReadWrite).unwrap();
+        let auth = TokenAuth {
+            tokens,
+            admin_token: Some("adm1n".to_string()),
+            ws_token: Some("w5".to_string()),
+            required,
+        };
+        (auth, read, write, TempDir(dir))
+    }
+
+    #[test]
+    fn scopes_tokens() {
+        let (auth, read, write, _dir) = auth(false);
+        assert_eq!(auth.access("/api/items", &Method::GET, Some(&read)), Access::Allow);
+        assert_eq!(auth.access("/api/queue", &Method::PUT, Some(&read)), Access::Forbidden);
+        assert_eq!(auth.access("/api/queue", &Method::PUT, Some(&write)), Access::Allow);
+        assert_eq!(auth.access("/api/queue", &Method::PUT, Some("adm1n")), Access::Allow);
+        assert_eq!(auth.access("/api/items", &Method::GET, Some("news_x")), Access::Unauthorized);
+        assert_eq!(auth.access("/api/items", &Method::GET, None), Access::Allow);
+        // Tokens never open the admin routes, which check the admin token themselves.
+        assert_eq!(auth.access("/api/admin/jobs", &Method::GET, Some(&read)), Access::Allow);
+        // The websocket checks its own token when API tokens aren't required.
+        assert_eq!(auth.access("/ws/", &Method::GET, None), Access::Allow);
+    }
+
+    #[test]
+    fn requires_tokens_when_configured() {
+        let (auth, read, _, _dir) = auth(true);
+        assert_eq!(auth.access("/api/items", &Method::GET, None), Access::Unauthorized);
+        assert_eq!(auth.access("/api/items", &Method::GET, Some(&read)), Access::Allow);
+        assert_eq!(auth.access("/api/items", &Method::OPTIONS, None), Access::Allow);
+    }
+
+    #[test]
+    fn requires_write_tokens_on_the_websocket() {
+        let (auth, read, write, _dir) = auth(true);
+        assert_eq!(auth.access("/ws/", &Method::GET, None), Access::Unauthorized);
+        assert_eq!(auth.access("/ws/", &Method::GET, Some(&read)), Access::Forbidden);
+        assert_eq!(auth.access("/ws/", &Method::GET, Some(&write)), Access::Allow);
+        assert_eq!(auth.access("/ws/", &Method::GET, Some("w5")), Access::Allow);
+        assert_eq!(auth.access("/api/items", &Method::GET, Some("w5")), Access::Unauthorized);
+    }
+
+    #[test]
+    fn revokes_tokens() {
+        let (auth, read, _, _dir) = auth(false);
+        let id = auth.tokens.list()[0].id.clone();
+        assert!(auth.tokens.list().iter().all(|token| token.hash.is_empty()));
+        assert!(auth.tokens.revoke(&id).unwrap());
+        assert!(!auth.tokens.revoke(&id).unwrap());
+        assert_eq!(auth.access("/api/items", &Method::GET, Some(&read)), Access::Unauthorized);
+    }
+}
@@ -0,0 +1,209 @@
+//! Per-feed Lua scripts rewriting or dropping the items fetched from a feed, set as the
+//! `transform` of the feed's settings, in builds with the `lua` feature.
+//!
+//! The script runs once per item with the global `item` holding its `title`, `link`, `guid`,
+//! `description`, `content`, `image_url` and `pub_date` (RFC 3339), and `feed` the URL of the
+//! feed. Changes to `item` are kept, and returning `false` drops the item, e.g.
+//!
+//! ```lua
+//! item.title = item.title:gsub("^Sponsored: ", "")
+//! item.image_url = item.image_url or item.description:match('data%-src="([^"]+)"')
+//! return not item.title:find("Deals")
+//! ```
+//!
+//! Scripts only get the `string`, `table`, `math` and `utf8` libraries, may allocate
+//! `MEMORY_LIMIT` bytes and run `TIME_LIMIT` per item. An item the script fails on is kept as
+//! fetched.
+
+#[cfg(feature = "mlua")]
+use chrono::DateTime;
+#[cfg(feature = "mlua")]
+use mlua::{Function, HookTriggers, Lua, StdLib, Table, Value};
+
+#[cfg(feature = "mlua")]
+use std::cell::Cell;
+use std::path::Path;
+#[cfg(feature = "mlua")]
+use std::rc::Rc;
+#[cfg(feature = "mlua")]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "mlua")]
+use crate::news::ItemFields;
+use crate::news::NewsItem;
+
+/// Bytes a script may allocate.
+#[cfg(feature = "mlua")]
+const MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+/// Time a script may run on each item.
+#[cfg(feature = "mlua")]
+const TIME_LIMIT: Duration = Duration::from_millis(100);
+/// Number of instructions run between checks of the time limit.
+#[cfg(feature = "mlua")]
+const CHECK_EVERY: u32 = 1000;
+
+/// Run `script` on the `items` of `feed`, with their images cached in `cache_dir`.
+#[cfg(feature = "mlua")]
+pub fn apply(script: &str, feed: &str, items: Vec<NewsItem>, cache_dir: &Path) -> Vec<NewsItem> {
+    let deadline = Rc::new(Cell::new(Instant::now() + TIME_LIMIT));
+    let lua = match sandbox(feed, deadline.clone()) {
+        Ok(lua) => lua,
+        Err(e) => {
+            log::error!("failed to set up lua: {}", e);
+            return items;
+        }
+    };
+    let function = match lua.load(script).into_function() {
+        Ok(function) => function,
+        Err(e) => {
+            log::warn!("failed to load the transform of {}: {}", feed, e);
+            return items;
+        }
+    };
+    items
+        .into_iter()
+        .filter_map(|mut item| {
+            deadline.set(Instant::now() + TIME_LIMIT);
+            match run(&lua, &function, &item) {
+                Ok(Some(fields)) => item.set_fields(fields, cache_dir),
+                Ok(None) => return None,
+                Err(e) => log::warn!("transform of {} failed: {}", feed, e),
+            }
+            Some(item)
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "mlua"))]
+pub fn apply(_: &str, feed: &str, items: Vec<NewsItem>, _: &Path) -> Vec<NewsItem> {
+    log::warn!("not transforming the items of {}, built without lua", feed);
+    items
+}
+
+/// Set up a sandboxed interpreter for the transform of `feed`, whose scripts error once past
+/// `deadline`.
+#[cfg(feature = "mlua")]
+fn sandbox(feed: &str, deadline: Rc<Cell<Instant>>) -> mlua::Result<Lua> {
+    let lua = Lua::new_with(StdLib::STRING | StdLib::TABLE | StdLib::MATH | StdLib::UTF8)?;
+    lua.set_memory_limit(MEMORY_LIMIT)?;
+    let triggers = HookTriggers {
+        every_nth_instruction: Some(CHECK_EVERY),
+        ..HookTriggers::default()
+    };
+    lua.set_hook(triggers, move |_, _| {
+        if Instant::now() > deadline.get() {
+            return Err(mlua::Error::RuntimeError("timed out".to_string()));
+        }
+        Ok(())
+    })?;
+    lua.globals().set("feed", feed)?;
+    Ok(lua)
+}
+
+/// Run the transform on `item`, returning its fields as rewritten, or `None` to drop it.
+#[cfg(feature = "mlua")]
+fn run(lua: &Lua, function: &Function, item: &NewsItem) -> mlua::Result<Option<ItemFields>> {
+    let table = to_table(lua, item.fields())?;
+    lua.globals().set("item", table.clone())?;
+    if let Value::Boolean(false) = function.call::<_, Value>(())? {
+        return Ok(None);
+    }
+    from_table(table).map(Some)
+}
+
+#[cfg(feature = "mlua")]
+fn to_table(lua: &Lua, fields: ItemFields) -> mlua::Result<Table> {
+    let table = lua.create_table()?;
+    table.set("title", fields.title)?;
+    table.set("link", fields.link)?;
+    table.set("guid", fields.guid)?;
+    table.set("description", fields.description)?;
+    table.set("content", fields.content)?;
+    table.set("image_url", fields.image_url)?;
+    table.set("pub_date", fields.pub_date.map(|date| date.to_rfc3339()))?;
+    Ok(table)
+}
+
+#[cfg(feature = "mlua")]
+fn from_table(table: Table) -> mlua::Result<ItemFields> {
+    let pub_date: Option<String> = table.get("pub_date")?;
+    Ok(ItemFields {
+        title: table.get("title")?,
+        link: table.get("link")?,
+        guid: table.get("guid")?,
+        description: table.get("description")?,
+        content: table.get("content")?,
+        image_url: table.get("image_url")?,
+        // A date the script garbled is dropped rather than failing the whole item.
+        pub_date: pub_date.and_then(|date| DateTime::parse_from_rfc3339(&date).ok()),
+    })
+}
+
+#[cfg(all(test, feature = "mlua"))]
+mod tests {
+    use super::*;
+
+    fn items(titles: &[&str]) -> Vec<NewsItem> {
+        titles
+            .iter()
+            .map(|title| {
+                let fields = ItemFields {
+                    title: Some(title.to_string()),
+                    description: Some("<img data-src=\"https://example.com/a.png\">".to_string()),
+                    ..ItemFields::default()
+                };
+                NewsItem::from_fields(fields, "https://example.com/feed", Path::new("/cache"))
+            })
+            .collect()
+    }
+
+    fn titles(items: &[NewsItem]) -> Vec<&str> {
+        items.iter().filter_map(NewsItem::title).collect()
+    }
+
+    #[test]
+    fn rewrites_and_drops_items() {
+        let script = r#"
+            item.title = item.title:gsub("^Sponsored: ", "")
+            item.image_url = item.description:match('data%-src="([^"]+)"')
+            return not item.title:find("Deals")
+        "#;
+        let feed = "https://example.com/feed";
+        let cache_dir = Path::new("/cache");
+        let transformed = apply(
+            script,
+            feed,
+            items(&["Sponsored: News", "Deals"]),
+            cache_dir,
+        );
+        assert_eq!(titles(&transformed), ["News"]);
+        assert_eq!(
+            transformed[0].image_url().as_deref(),
+            Some("https://example.com/a.png")
+        );
+    }
+
+    #[test]
+    fn keeps_items_the_script_fails_on() {
+        let cache_dir = Path::new("/cache");
+        let feed = "https://example.com/feed";
+        let spin = apply("while true do end", feed, items(&["Story"]), cache_dir);
+        assert_eq!(titles(&spin), ["Story"]);
+        let sandboxed = apply(
+            "os.remove('/etc/passwd')",
+            feed,
+            items(&["Story"]),
+            cache_dir,
+        );
+        assert_eq!(titles(&sandboxed), ["Story"]);
+        let hog = "local t = {} for i = 1, 1e9 do t[i] = i end";
+        assert_eq!(
+            titles(&apply(hog, feed, items(&["Story"]), cache_dir)),
+            ["Story"]
+        );
+        assert_eq!(
+            titles(&apply("syntax error", feed, items(&["Story"]), cache_dir)),
+            ["Story"]
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! On demand translation of items through DeepL or LibreTranslate.
+//!
+//! Translation is disabled unless a `translation` section is present in the config. Results are
+//! cached in the store so an item is only sent to the translation service once per language.
+
+use actix::Addr;
+use actix_web::{error, web, Error, HttpResponse};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, TranslationConfig, TranslationProvider};
+use crate::sanitize;
+use crate::server::{GetItem, GetTranslation, NewsServer, SaveTranslation};
+
+/// Translated text of an item.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Translation {
+    /// Language the item was translated to.
+    pub to: String,
+    pub title: Option<String>,
+    /// Translated content, or description when the item has no content.
+    pub body: Option<String>,
+}
+
+/// Translate `texts` to the language `to`, keeping their order.
+pub async fn translate(
+    config: &TranslationConfig,
+    texts: &[String],
+    to: &str,
+) -> Result<Vec<String>> {
+    let client = reqwest::Client::new();
+    match config.provider {
+        TranslationProvider::Deepl => {
+            #[derive(Deserialize)]
+            struct Response {
+                translations: Vec<DeeplTranslation>,
+            }
+            #[derive(Deserialize)]
+            struct DeeplTranslation {
+                text: String,
+            }
+
+            let mut form: Vec<(&str, &str)> = vec![("target_lang", to), ("tag_handling", "html")];
+            form.extend(texts.iter().map(|text| ("text", text.as_str())));
+            let response: Response = client
+                .post(&config.endpoint)
+                .header(
+                    "Authorization",
+                    format!("DeepL-Auth-Key {}", config.api_key.as_deref().unwrap_or("")),
+                )
+                .form(&form)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            Ok(response.translations.into_iter().map(|t| t.text).collect())
+        }
+        TranslationProvider::LibreTranslate => {
+            #[derive(Serialize)]
+            struct Request<'a> {
+                q: &'a str,
+                source: &'a str,
+                target: &'a str,
+                format: &'a str,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                api_key: Option<&'a str>,
+            }
+            #[derive(Deserialize)]
+            struct Response {
+                #[serde(rename = "translatedText")]
+                translated_text: String,
+            }
+
+            let mut translated = Vec::with_capacity(texts.len());
+            for text in texts {
+                let response: Response = client
+                    .post(&config.endpoint)
+                    .json(&Request {
+                        q: text,
+                        source: "auto",
+                        target: to,
+                        format: "html",
+                        api_key: config.api_key.as_deref(),
+                    })
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                translated.push(response.translated_text);
+            }
+            Ok(translated)
+        }
+    }
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/api/items/{id}/translate", web::post().to(translate_item));
+}
+
+#[derive(Debug, Deserialize)]
+struct TranslateParams {
+    /// Language code to translate to, e.g. `en`.
+    to: String,
+}
+
+async fn translate_item(
+    config: web::Data<Config>,
+    server: web::Data<Addr<NewsServer>>,
+    id: web::Path<String>,
+    params: web::Query<TranslateParams>,
+) -> Result<HttpResponse, Error> {
+    let translation_config = match &config.translation {
+        Some(translation_config) => translation_config,
+        None => return Err(error::ErrorNotFound("translation is disabled")),
+    };
+    let id = id.into_inner();
+    let to = params.into_inner().to.to_lowercase();
+    let valid = !to.is_empty()
+        && to.len() <= 8
+        && to.chars().all(|c| c.is_ascii_alphabetic() || c == '-');
+    if !valid {
+        return Err(error::ErrorBadRequest("invalid target language"));
+    }
+
+    let cached = server
+        .send(GetTranslation {
+            id: id.clone(),
+            to: to.clone(),
+        })
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    if let Some(translation) = cached {
+        return Ok(HttpResponse::Ok().json(translation));
+    }
+
+    let item = server
+        .send(GetItem { id: id.clone() })
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("unknown item"))?;
+
+    let title = item.title.unwrap_or_default();
+    let body = item.content.or(item.description).unwrap_or_default();
+    let translated = translate(translation_config, &[title.clone(), body.clone()], &to)
+        .await
+        .and_then(|translated| match translated.as_slice() {
+            [title, body] => Ok((title.clone(), body.clone())),
+            _ => Err(anyhow!("unexpected number of translations")),
+        })
+        .map_err(|e| {
+            log::error!("failed to translate {}: {}", id, e);
+            error::ErrorBadGateway(e)
+        })?;
+
+    let translation = Translation {
+        to,
+        title: Some(translated.0).filter(|_| !title.is_empty()),
+        // The translation service is trusted no more than the feed itself.
+        body: Some(sanitize::html(&translated.1)).filter(|_| !body.is_empty()),
+    };
+    server.do_send(SaveTranslation {
+        id,
+        translation: translation.clone(),
+    });
+    Ok(HttpResponse::Ok().json(translation))
+}
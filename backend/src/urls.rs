@@ -0,0 +1,47 @@
+//! Normalization of the URLs items link to, in `news_core::urls`, along with replacing the links
+//! of shorteners and feed proxies by the pages they redirect to, which takes a request.
+
+use anyhow::Result;
+use reqwest::Client;
+
+use std::time::Duration;
+
+pub use news_core::urls::*;
+
+/// Hosts of link shorteners and feed proxies, which redirect to the actual page.
+const SHORTENERS: &[&str] = &[
+    "feedproxy.google.com",
+    "feeds.feedburner.com",
+    "t.co",
+    "bit.ly",
+    "buff.ly",
+    "ow.ly",
+    "dlvr.it",
+    "trib.al",
+    "tinyurl.com",
+    "goo.gl",
+];
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn client() -> Result<Client> {
+    Ok(Client::builder().timeout(TIMEOUT).build()?)
+}
+
+/// Return the page `url` redirects to, without tracking parameters, if it points to a link
+/// shortener or feed proxy.
+pub async fn unshorten(client: &Client, url: &str) -> Option<String> {
+    let host = Url::parse(url).ok()?.host_str()?.to_ascii_lowercase();
+    if !SHORTENERS.contains(&host.as_str()) {
+        return None;
+    }
+    let response = match client.head(url).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            log::warn!("failed to follow {}: {}", url, e);
+            return None;
+        }
+    };
+    let target = response.url().as_str();
+    let target = strip_tracking(target).unwrap_or_else(|| target.to_string());
+    Some(target).filter(|target| target != url)
+}
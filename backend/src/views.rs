@@ -0,0 +1,225 @@
+//! Views of the stored items that can be shared or exported outside the app.
+
+use chrono::prelude::*;
+use rss::{Channel, Guid, Item};
+use serde::{Deserialize, Serialize};
+
+use crate::folders::FeedOrder;
+use crate::protocol::ItemDto;
+use crate::sanitize::escape;
+
+/// Subset of the items, matching the lists of the frontend.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum View {
+    /// Every item of the river but low quality ones.
+    All,
+    /// Unread items of the river that aren't low quality, muted or snoozed.
+    Unread,
+    Starred,
+    /// Items of the feed at this URL.
+    Feed(String),
+    /// Items of the feeds in the folder of this name.
+    Folder(String),
+}
+
+impl View {
+    /// Whether the item of `dto` belongs in the view, the feeds being arranged in `order`.
+    pub fn shows(&self, dto: &ItemDto, order: &FeedOrder) -> bool {
+        match self {
+            View::All => !dto.low_quality && !dto.overflow,
+            View::Unread => {
+                View::All.shows(dto, order)
+                    && !dto.read
+                    && !dto.muted
+                    && dto.snoozed_until.is_none()
+            }
+            View::Starred => dto.starred,
+            View::Feed(feed) => &dto.feed == feed,
+            View::Folder(name) => order
+                .folders
+                .iter()
+                .any(|folder| &folder.name == name && folder.feeds.contains(&dto.feed)),
+        }
+    }
+
+    /// Name of the view shown to readers.
+    pub fn name(&self) -> String {
+        match self {
+            View::All => "All items".to_string(),
+            View::Unread => "Unread items".to_string(),
+            View::Starred => "Starred items".to_string(),
+            View::Feed(feed) => feed.clone(),
+            View::Folder(name) => name.clone(),
+        }
+    }
+}
+
+/// Format of the feeds generated from views.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Rss,
+    Atom,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Format::Rss
+    }
+}
+
+impl Format {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Rss => "application/rss+xml; charset=utf-8",
+            Format::Atom => "application/atom+xml; charset=utf-8",
+        }
+    }
+
+    /// Return a feed titled `title` of the items of `dtos`, published on the page at `link`.
+    pub fn render(self, title: &str, link: &str, dtos: &[ItemDto]) -> String {
+        match self {
+            Format::Rss => rss(title, link, dtos),
+            Format::Atom => atom(title, link, dtos),
+        }
+    }
+}
+
+/// Return an RSS feed titled `title` of the items of `dtos`, published on the page at `link`.
+pub fn rss(title: &str, link: &str, dtos: &[ItemDto]) -> String {
+    let items: Vec<Item> = dtos
+        .iter()
+        .map(|dto| {
+            let mut item = Item::default();
+            item.set_title(dto.title.clone());
+            item.set_link(dto.link.clone());
+            item.set_description(dto.description.clone());
+            item.set_pub_date(dto.pub_date.map(|date| date.to_rfc2822()));
+            let mut guid = Guid::default();
+            guid.set_value(dto.id.clone());
+            guid.set_permalink(false);
+            item.set_guid(guid);
+            item
+        })
+        .collect();
+
+    let mut channel = Channel::default();
+    channel.set_title(title);
+    channel.set_link(link);
+    channel.set_description(title);
+    channel.set_items(items);
+    channel.to_string()
+}
+
+/// Return an Atom feed titled `title` of the items of `dtos`, published on the page at `link`.
+pub fn atom(title: &str, link: &str, dtos: &[ItemDto]) -> String {
+    // Atom requires every entry and the feed to be dated.
+    let updated = |dto: &ItemDto| dto.pub_date.unwrap_or_else(Utc::now);
+    let feed_updated = dtos.iter().map(updated).max().unwrap_or_else(Utc::now);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">");
+    xml.push_str(&format!(
+        "<id>{link}</id><title>{}</title><updated>{}</updated><link href=\"{link}\"/>",
+        escape(title),
+        feed_updated.to_rfc3339(),
+        link = escape(link),
+    ));
+    for dto in dtos {
+        xml.push_str(&format!(
+            "<entry><id>urn:news:item:{}</id><title>{}</title><updated>{}</updated>",
+            dto.id,
+            escape(dto.title.as_deref().unwrap_or("")),
+            updated(dto).to_rfc3339()
+        ));
+        if let Some(published) = dto.pub_date {
+            xml.push_str(&format!("<published>{}</published>", published.to_rfc3339()));
+        }
+        if let Some(link) = &dto.link {
+            xml.push_str(&format!("<link href=\"{}\"/>", escape(link)));
+        }
+        if let Some(description) = &dto.description {
+            xml.push_str(&format!("<summary type=\"html\">{}</summary>", escape(description)));
+        }
+        xml.push_str("</entry>");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use news_core::test_util;
+
+    use crate::folders::Folder;
+    use crate::news::NewsItem;
+    use crate::settings::Settings;
+    use crate::store::Store;
+
+    const FEED: &str = "https://example.com/feed.xml";
+
+    fn item(title: &str) -> NewsItem {
+        test_util::item(title)
+            .feed(FEED)
+            .link("https://example.com/harbor")
+            .date(Utc.ymd(2020, 5, 5).and_hms(8, 0, 0))
+            .build()
+    }
+
+    #[test]
+    fn views_match_the_lists_of_the_frontend() {
+        let (read, starred, junk) = (item("Read"), item("Starred"), item("Junk"));
+        let mut store = Store::default();
+        store.mark_read(&read.id());
+        store.star(&starred.id());
+        store.mark_junk(&junk.id());
+        let settings = Settings::default();
+        let dto = |item: &NewsItem| ItemDto::new(item, &settings, &store);
+        let (read, starred, junk) = (dto(&read), dto(&starred), dto(&junk));
+        let order = FeedOrder {
+            folders: vec![Folder {
+                name: "Local".to_string(),
+                feeds: vec![FEED.to_string()],
+            }],
+            feeds: Vec::new(),
+        };
+        let shows = |view: View, dto: &ItemDto| view.shows(dto, &order);
+
+        assert!(shows(View::All, &read) && !shows(View::All, &junk));
+        assert!(!shows(View::Unread, &read) && shows(View::Unread, &starred));
+        assert!(!shows(View::Unread, &junk));
+        assert!(shows(View::Starred, &starred) && !shows(View::Starred, &read));
+        assert!(shows(View::Feed(FEED.to_string()), &junk));
+        let other = View::Feed("https://example.com/other.xml".to_string());
+        assert!(!shows(other, &read));
+        assert!(shows(View::Folder("Local".to_string()), &read));
+        assert!(!shows(View::Folder("Elsewhere".to_string()), &read));
+    }
+
+    #[test]
+    fn writes_rss() {
+        let dto = ItemDto::new(&item("Harbor <reopens>"), &Settings::default(), &Store::default());
+        let rss = rss("Shared", "https://news.example.com/shared/t", &[dto]);
+        let channel = Channel::read_from(rss.as_bytes()).unwrap();
+        assert_eq!(channel.title(), "Shared");
+        let item = &channel.items()[0];
+        assert_eq!(item.title(), Some("Harbor <reopens>"));
+        assert_eq!(item.link(), Some("https://example.com/harbor"));
+        assert_eq!(item.pub_date(), Some("Tue, 05 May 2020 08:00:00 +0000"));
+        assert_eq!(item.guid().map(Guid::is_permalink), Some(false));
+    }
+
+    #[test]
+    fn writes_atom() {
+        let dto = ItemDto::new(&item("Harbor <reopens>"), &Settings::default(), &Store::default());
+        let atom = atom("Shared & curated", "https://news.example.com/out", &[dto]);
+        assert!(atom.contains("<title>Shared &amp; curated</title>"));
+        assert!(atom.contains("<title>Harbor &lt;reopens&gt;</title>"));
+        assert!(atom.contains("<link href=\"https://example.com/harbor\"/>"));
+        assert!(atom.contains("<published>2020-05-05T08:00:00+00:00</published>"));
+        assert_eq!(atom.matches("<entry>").count(), 1);
+    }
+}
@@ -0,0 +1,352 @@
+//! Harness running the backend against a mock feed server and driving it over the websocket.
+
+#![allow(dead_code)]
+
+use serde_json::{json, Value};
+use tungstenite::client::IntoClientRequest;
+use tungstenite::http::HeaderValue;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long the backend may take to start listening.
+const START_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait for pushed items before failing.
+const PUSH_TIMEOUT: Duration = Duration::from_secs(20);
+/// How long reading the websocket blocks before checking the deadline again.
+const POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Name of the project directories of the backend under the XDG base directories.
+const PROJECT: &str = "newsapp";
+
+static NEXT_HOME: AtomicUsize = AtomicUsize::new(0);
+
+/// Return the path of a fixture.
+pub fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// Wait until `done` holds, failing after a while.
+pub fn wait_until<F: Fn() -> bool>(what: &str, done: F) {
+    let deadline = Instant::now() + PUSH_TIMEOUT;
+    while !done() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// HTTP server serving fixture files, with `{{base}}` in feeds replaced by its own URL.
+pub struct MockServer {
+    pub base: String,
+    /// Number of requests made for each path.
+    hits: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl MockServer {
+    /// Serve the files of the fixture directory `dir` under their names.
+    pub fn start(dir: &str) -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind mock server");
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let hits = Arc::new(Mutex::new(HashMap::new()));
+
+        let root = fixture(dir);
+        let (served_base, served_hits) = (base.clone(), hits.clone());
+        thread::spawn(move || {
+            for stream in listener.incoming().filter_map(|stream| stream.ok()) {
+                if let Err(e) = serve(stream, &root, &served_base, &served_hits) {
+                    eprintln!("mock server failed to answer: {}", e);
+                }
+            }
+        });
+        MockServer { base, hits }
+    }
+
+    /// URL of the file `name`.
+    pub fn url(&self, name: &str) -> String {
+        format!("{}/{}", self.base, name)
+    }
+
+    /// Number of times `path` was requested.
+    pub fn hits(&self, path: &str) -> usize {
+        self.hits.lock().unwrap().get(path).copied().unwrap_or(0)
+    }
+}
+
+fn serve(
+    mut stream: TcpStream,
+    root: &Path,
+    base: &str,
+    hits: &Mutex<HashMap<String, usize>>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Skip the headers, no request has a body.
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line == "\r\n" {
+            break;
+        }
+    }
+
+    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+    *hits.lock().unwrap().entry(path.clone()).or_insert(0) += 1;
+    let file = root.join(path.trim_start_matches('/'));
+    let (status, content_type, body) = match fs::read(&file) {
+        Ok(body) if path.ends_with(".xml") => {
+            let body = String::from_utf8_lossy(&body).replace("{{base}}", base);
+            ("200 OK", "application/rss+xml", body.into_bytes())
+        }
+        Ok(body) if path.ends_with(".png") => ("200 OK", "image/png", body),
+        Ok(body) => ("200 OK", "application/octet-stream", body),
+        Err(_) => ("404 Not Found", "text/plain", b"not found".to_vec()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+    stream.flush()
+}
+
+/// The backend binary running with its own configuration and data directories.
+pub struct Backend {
+    pub addr: SocketAddr,
+    home: PathBuf,
+    feeds: Vec<String>,
+    /// Configuration overriding the defaults of the harness.
+    config: Value,
+    child: Child,
+}
+
+impl Backend {
+    /// Start the backend aggregating `feeds`.
+    pub fn start(feeds: Vec<String>) -> Backend {
+        Backend::with_config(feeds, json!({}))
+    }
+
+    /// Start the backend aggregating `feeds`, with the fields of `config` added to its
+    /// configuration.
+    pub fn with_config(feeds: Vec<String>, config: Value) -> Backend {
+        let home = std::env::temp_dir().join(format!(
+            "news-e2e-{}-{}",
+            std::process::id(),
+            NEXT_HOME.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&home);
+        let addr = free_addr();
+        let child = spawn(&home, addr, &feeds, &config);
+        Backend {
+            addr,
+            home,
+            feeds,
+            config,
+            child,
+        }
+    }
+
+    /// Stop the backend and start it again on the same directories.
+    pub fn restart(&mut self) {
+        self.stop();
+        self.addr = free_addr();
+        self.child = spawn(&self.home, self.addr, &self.feeds, &self.config);
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// Open a websocket to the backend.
+    pub fn connect(&self) -> Client {
+        self.try_connect("", None).expect("failed to open websocket")
+    }
+
+    /// Open a websocket to the backend with `query` appended to its URL, from a page of `origin`.
+    pub fn try_connect(&self, query: &str, origin: Option<&str>) -> tungstenite::Result<Client> {
+        let url = format!("ws://{}/ws/?encoding=json{}", self.addr, query);
+        let mut request = url.into_client_request()?;
+        if let Some(origin) = origin {
+            let origin = HeaderValue::from_str(origin).expect("invalid origin");
+            request.headers_mut().insert("Origin", origin);
+        }
+        let (socket, _) = tungstenite::connect(request)?;
+        if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+            stream.set_read_timeout(Some(POLL_TIMEOUT)).unwrap();
+        }
+        Ok(Client {
+            socket,
+            items: HashMap::new(),
+            batches: 0,
+            resyncs: 0,
+            progress: Vec::new(),
+        })
+    }
+
+    fn stop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for Backend {
+    fn drop(&mut self) {
+        self.stop();
+        let _ = fs::remove_dir_all(&self.home);
+    }
+}
+
+/// Pick a port nothing listens on.
+fn free_addr() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("failed to find a free port");
+    listener.local_addr().unwrap()
+}
+
+/// Write the configuration under `home` and start the backend, waiting until it listens.
+fn spawn(home: &Path, addr: SocketAddr, feeds: &[String], extra: &Value) -> Child {
+    let config_dir = home.join("config").join(PROJECT);
+    fs::create_dir_all(&config_dir).unwrap();
+    let mut config = json!({
+        "bind": addr.to_string(),
+        "feeds": feeds,
+        "recommendations": false,
+        "rate_limit": { "burst": 10_000, "per_second": 10_000.0 },
+    });
+    for (key, value) in extra.as_object().into_iter().flatten() {
+        config[key] = value.clone();
+    }
+    fs::write(config_dir.join("config.json"), config.to_string()).unwrap();
+
+    let child = Command::new(env!("CARGO_BIN_EXE_news-backend"))
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("failed to start the backend");
+
+    let deadline = Instant::now() + START_TIMEOUT;
+    while TcpStream::connect(addr).is_err() {
+        assert!(Instant::now() < deadline, "backend didn't listen on {}", addr);
+        thread::sleep(Duration::from_millis(50));
+    }
+    child
+}
+
+/// Websocket client keeping the items pushed to it, keyed by id, as the frontend does.
+pub struct Client {
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+    pub items: HashMap<String, Value>,
+    /// Number of item batches received.
+    pub batches: usize,
+    /// Number of times the server asked for a resync.
+    pub resyncs: usize,
+    /// Progress of the refresh reported so far, oldest first.
+    pub progress: Vec<Value>,
+}
+
+impl Client {
+    pub fn send(&mut self, msg: Value) {
+        self.socket
+            .send(Message::Text(msg.to_string()))
+            .expect("failed to send message");
+    }
+
+    /// Apply pushed messages until `done` holds for the items received so far.
+    pub fn wait_for<F>(&mut self, what: &str, done: F)
+    where
+        F: Fn(&HashMap<String, Value>) -> bool,
+    {
+        let deadline = Instant::now() + PUSH_TIMEOUT;
+        while !done(&self.items) {
+            assert!(
+                Instant::now() < deadline,
+                "timed out waiting for {}, got {:#?}",
+                what,
+                self.items
+            );
+            self.poll();
+        }
+    }
+
+    /// Apply pushed messages until another batch of items was received.
+    pub fn wait_for_batch(&mut self) {
+        let (batches, deadline) = (self.batches, Instant::now() + PUSH_TIMEOUT);
+        while self.batches == batches {
+            assert!(Instant::now() < deadline, "timed out waiting for a batch");
+            self.poll();
+        }
+    }
+
+    /// Apply pushed messages until the server reported the refresh finished.
+    pub fn wait_for_refresh(&mut self) {
+        let deadline = Instant::now() + PUSH_TIMEOUT;
+        let finished = |progress: &Value| progress["feeds_done"] == progress["feeds_total"];
+        while !self.progress.last().map_or(false, finished) {
+            assert!(Instant::now() < deadline, "timed out waiting for the refresh");
+            self.poll();
+        }
+    }
+
+    /// Return the item titled `title`.
+    pub fn by_title(&self, title: &str) -> Option<&Value> {
+        self.items.values().find(|item| item["title"] == title)
+    }
+
+    /// Apply the next pushed message, if any comes before the poll timeout.
+    fn poll(&mut self) {
+        let text = match self.socket.read() {
+            Ok(Message::Text(text)) => text,
+            Ok(_) => return,
+            Err(tungstenite::Error::Io(e))
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut =>
+            {
+                return
+            }
+            Err(e) => panic!("websocket failed: {}", e),
+        };
+        let msg: Value = serde_json::from_str(&text).expect("bad server message");
+        match msg["type"].as_str() {
+            Some("items") => {
+                for item in msg["items"].as_array().into_iter().flatten() {
+                    let id = item["id"].as_str().unwrap().to_string();
+                    self.items.insert(id, item.clone());
+                }
+                for patch in msg["patches"].as_array().into_iter().flatten() {
+                    let item = match self.items.get_mut(patch["id"].as_str().unwrap()) {
+                        Some(item) => item,
+                        None => continue,
+                    };
+                    for field in &["read", "starred", "updated_at"] {
+                        if !patch[field].is_null() {
+                            item[field] = patch[field].clone();
+                        }
+                    }
+                }
+                self.batches += 1;
+                self.send(json!({ "type": "ack", "up_to": msg["seq"] }));
+            }
+            Some("resync_needed") => {
+                self.resyncs += 1;
+                self.send(json!({ "type": "resync" }));
+            }
+            Some("progress") => self.progress.push(msg),
+            _ => {}
+        }
+    }
+}
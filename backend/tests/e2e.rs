@@ -0,0 +1,638 @@
+//! End-to-end tests running the backend against fixture feeds served by a mock server, driving it
+//! over the websocket like the frontend does.
+
+mod common;
+
+use serde_json::{json, Value};
+
+use std::collections::HashMap;
+
+use common::{Backend, MockServer};
+
+const TITLES: [&str; 5] = [
+    "Harbor reopens after storm",
+    "Bridge vote delayed",
+    "Library extends hours",
+    "Compiler release candidate",
+    "Kernel patch merged",
+];
+
+/// Start the backend aggregating the fixture feeds.
+fn start() -> (MockServer, Backend) {
+    let feeds = MockServer::start("e2e");
+    let backend = Backend::start(vec![feeds.url("world.xml"), feeds.url("tech.xml")]);
+    (feeds, backend)
+}
+
+fn has_every_item(items: &HashMap<String, Value>) -> bool {
+    TITLES
+        .iter()
+        .all(|title| items.values().any(|item| item["title"] == *title))
+}
+
+fn id_of(client: &common::Client, title: &str) -> String {
+    client.by_title(title).unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn ingests_fixture_feeds() {
+    let (feeds, backend) = start();
+    let mut client = backend.connect();
+    client.wait_for("every fixture item", has_every_item);
+
+    let harbor = client.by_title("Harbor reopens after storm").unwrap();
+    assert_eq!(harbor["feed"], feeds.url("world.xml"));
+    assert_eq!(harbor["link"], feeds.url("world/harbor"));
+    assert_eq!(harbor["read"], false);
+    assert_eq!(harbor["pub_date"], "2020-05-05T08:00:00Z");
+    let kernel = client.by_title("Kernel patch merged").unwrap();
+    assert_eq!(kernel["feed"], feeds.url("tech.xml"));
+}
+
+#[test]
+fn reports_refresh_progress() {
+    let (_feeds, backend) = start();
+    let mut client = backend.connect();
+    client.wait_for_refresh();
+
+    let progress = client.progress.last().unwrap();
+    assert_eq!(progress["feeds_done"], 2);
+    assert_eq!(progress["feeds_total"], 2);
+    assert!(progress["current_feed"].is_null());
+}
+
+#[test]
+fn cancels_refreshes() {
+    let feeds = MockServer::start("e2e");
+    let config = json!({ "admin_token": "adm1n" });
+    let urls = vec![feeds.url("world.xml"), feeds.url("tech.xml")];
+    let backend = Backend::with_config(urls, config);
+    let mut client = backend.connect();
+    client.wait_for_refresh();
+
+    let http = reqwest::blocking::Client::new();
+    let anonymous = http.post(&backend.url("/api/admin/refresh/cancel")).send().unwrap();
+    assert_eq!(anonymous.status().as_u16(), 401);
+    let cancelled: Value = http
+        .post(&backend.url("/api/admin/refresh/cancel"))
+        .bearer_auth("adm1n")
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.json())
+        .expect("failed to cancel the refresh");
+    let mut completed: Vec<&str> = cancelled["completed"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|feed| feed.as_str().unwrap())
+        .collect();
+    completed.sort();
+    assert_eq!(completed, [feeds.url("tech.xml"), feeds.url("world.xml")]);
+    assert_eq!(cancelled["cancelled"], json!([]));
+
+    let jobs: Value = http
+        .get(&backend.url("/api/admin/jobs"))
+        .bearer_auth("adm1n")
+        .send()
+        .and_then(|response| response.json())
+        .expect("failed to list the jobs");
+    assert_eq!(jobs, json!([]));
+}
+
+#[test]
+fn refetches_feeds_fetched_by_clients() {
+    let (feeds, backend) = start();
+    let mut client = backend.connect();
+    client.wait_for_refresh();
+    client.wait_for("every fixture item", has_every_item);
+
+    let item = |title: &str, slug: &str| {
+        json!({
+            "title": title,
+            "link": format!("https://example.com/{}", slug),
+            "pub_date": "Sat, 09 May 2020 08:00:00 +0000",
+            "categories": [],
+            "extensions": {},
+        })
+    };
+    // Feeds the server isn't subscribed to are ignored.
+    client.send(json!({
+        "type": "fetched",
+        "feed": "https://elsewhere.example.org/feed",
+        "title": null,
+        "items": [item("Unsubscribed story", "unsubscribed")],
+    }));
+    // The server fetches the feed itself instead of storing what the client sent.
+    let fetched = feeds.hits("/world.xml");
+    client.send(json!({
+        "type": "fetched",
+        "feed": feeds.url("world.xml"),
+        "title": "World",
+        "items": [item("Forged story", "forged")],
+    }));
+    common::wait_until("the feed to be fetched again", || {
+        feeds.hits("/world.xml") > fetched
+    });
+    client.wait_for_refresh();
+    assert!(client.by_title("Forged story").is_none());
+    assert!(client.by_title("Unsubscribed story").is_none());
+}
+
+#[test]
+fn caches_images() {
+    let (feeds, backend) = start();
+    let mut client = backend.connect();
+    let has_image = |items: &HashMap<String, Value>| {
+        items
+            .values()
+            .any(|item| item["title"] == TITLES[0] && item["image_path"].is_string())
+    };
+    client.wait_for("the cached image", has_image);
+
+    let harbor = client.by_title(TITLES[0]).unwrap();
+    let path = harbor["image_path"].as_str().unwrap();
+    let image = reqwest::blocking::get(&backend.url(path))
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.bytes())
+        .expect("failed to get the cached image");
+    assert!(!image.is_empty());
+    assert!(harbor["image_placeholder"].is_string());
+    assert_eq!(feeds.hits("/images/pixel.png"), 1);
+}
+
+#[test]
+fn deduplicates_items() {
+    let (feeds, backend) = start();
+    let mut client = backend.connect();
+    client.wait_for("every fixture item", has_every_item);
+    assert_eq!(client.items.len(), TITLES.len());
+
+    // Connecting again refreshes every feed, which must yield the same items rather than copies.
+    let fetched = feeds.hits("/tech.xml");
+    let mut again = backend.connect();
+    again.wait_for("every fixture item", has_every_item);
+    common::wait_until("the feeds to be fetched again", || feeds.hits("/tech.xml") > fetched);
+    again.wait_for_batch();
+    assert_eq!(again.items.len(), TITLES.len());
+    client.wait_for_batch();
+    assert_eq!(client.items.len(), TITLES.len());
+}
+
+#[test]
+fn syncs_read_state_across_clients() {
+    let (_feeds, backend) = start();
+    let mut reader = backend.connect();
+    reader.wait_for("every fixture item", has_every_item);
+    let mut other = backend.connect();
+    other.wait_for("every fixture item", has_every_item);
+
+    let id = id_of(&reader, "Bridge vote delayed");
+    reader.send(json!({ "type": "mark_read", "id": id }));
+    let is_read =
+        |items: &HashMap<String, Value>| items.get(&id).map_or(false, |i| i["read"] == true);
+    reader.wait_for("the read state to be echoed", is_read);
+    other.wait_for("the read state to be pushed", is_read);
+
+    let mut late = backend.connect();
+    late.wait_for("the read item", is_read);
+    assert_eq!(late.by_title("Library extends hours").unwrap()["read"], false);
+}
+
+#[test]
+fn keeps_state_across_reconnections_and_restarts() {
+    let (_feeds, mut backend) = start();
+    let mut client = backend.connect();
+    client.wait_for("every fixture item", has_every_item);
+    let id = id_of(&client, "Kernel patch merged");
+    client.send(json!({ "type": "star", "id": id, "starred": true }));
+    let is_starred =
+        |items: &HashMap<String, Value>| items.get(&id).map_or(false, |i| i["starred"] == true);
+    client.wait_for("the star to be echoed", is_starred);
+    drop(client);
+
+    let mut reconnected = backend.connect();
+    reconnected.wait_for("the starred item", is_starred);
+    drop(reconnected);
+
+    backend.restart();
+    let mut restarted = backend.connect();
+    restarted.wait_for("the starred item", is_starred);
+    restarted.wait_for("every fixture item", has_every_item);
+    assert_eq!(restarted.items.len(), TITLES.len());
+}
+
+#[test]
+fn forgets_state_across_restarts_in_memory() {
+    let feeds = MockServer::start("e2e");
+    let config = json!({ "store": { "kind": "memory" } });
+    let urls = vec![feeds.url("world.xml"), feeds.url("tech.xml")];
+    let mut backend = Backend::with_config(urls, config);
+    let mut client = backend.connect();
+    client.wait_for("every fixture item", has_every_item);
+    let id = id_of(&client, "Kernel patch merged");
+    client.send(json!({ "type": "star", "id": id, "starred": true }));
+    client.wait_for("the star to be echoed", |items| items[&id]["starred"] == true);
+    drop(client);
+
+    backend.restart();
+    let mut restarted = backend.connect();
+    restarted.wait_for("every fixture item", has_every_item);
+    assert_eq!(restarted.items[&id]["starred"], false);
+}
+
+#[test]
+fn resends_everything_on_resync() {
+    let (_feeds, backend) = start();
+    let mut client = backend.connect();
+    client.wait_for("every fixture item", has_every_item);
+
+    client.items.clear();
+    client.send(json!({ "type": "resync" }));
+    client.wait_for("every fixture item again", has_every_item);
+    assert_eq!(client.items.len(), TITLES.len());
+}
+
+/// Return the HTTP status a websocket handshake was refused with.
+fn refusal(result: tungstenite::Result<common::Client>) -> u16 {
+    match result {
+        Err(tungstenite::Error::Http(response)) => response.status().as_u16(),
+        Err(e) => panic!("websocket failed: {}", e),
+        Ok(_) => panic!("websocket was opened"),
+    }
+}
+
+#[test]
+fn requires_the_websocket_token() {
+    let feeds = MockServer::start("e2e");
+    let config = json!({ "ws_token": "s3cret" });
+    let backend = Backend::with_config(vec![feeds.url("world.xml")], config);
+
+    assert_eq!(refusal(backend.try_connect("", None)), 401);
+    assert_eq!(refusal(backend.try_connect("&token=guess", None)), 401);
+    let mut client = backend
+        .try_connect("&token=s3cret", None)
+        .expect("failed to open websocket with the token");
+    client.wait_for("the world items", |items| items.len() == 3);
+}
+
+#[test]
+fn checks_the_origin_of_pages() {
+    let (_feeds, backend) = start();
+    let evil = backend.try_connect("", Some("https://evil.example.com"));
+    assert_eq!(refusal(evil), 403);
+
+    let own = format!("http://{}", backend.addr);
+    backend
+        .try_connect("", Some(&own))
+        .expect("failed to open websocket from the server's own origin");
+    backend
+        .try_connect("", Some("http://localhost:8080"))
+        .expect("failed to open websocket from the frontend's development server");
+}
+
+#[test]
+fn shares_a_read_only_view() {
+    let feeds = MockServer::start("e2e");
+    let config = json!({ "admin_token": "adm1n" });
+    let urls = vec![feeds.url("world.xml"), feeds.url("tech.xml")];
+    let backend = Backend::with_config(urls, config);
+    let mut client = backend.connect();
+    client.wait_for("every fixture item", has_every_item);
+
+    let http = reqwest::blocking::Client::new();
+    let view = json!({ "view": { "kind": "feed", "value": feeds.url("tech.xml") } });
+    let anonymous = http.post(&backend.url("/api/shares")).json(&view).send().unwrap();
+    assert_eq!(anonymous.status().as_u16(), 401);
+    let share: Value = http
+        .post(&backend.url("/api/shares"))
+        .bearer_auth("adm1n")
+        .json(&view)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.json())
+        .expect("failed to create a share");
+    let shared = format!("/shared/{}", share["token"].as_str().unwrap());
+
+    let items: Value = reqwest::blocking::get(&backend.url(&format!("{}/items", shared)))
+        .and_then(|response| response.json())
+        .expect("failed to get the shared items");
+    let titles: Vec<&str> = items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, ["Compiler release candidate", "Kernel patch merged"]);
+    assert!(items[0].get("read").is_none());
+    let rss = reqwest::blocking::get(&backend.url(&format!("{}/rss.xml", shared)))
+        .and_then(|response| response.text())
+        .expect("failed to get the shared feed");
+    assert!(rss.contains("<title>Kernel patch merged</title>"));
+    let page = reqwest::blocking::get(&backend.url(&shared)).unwrap();
+    assert!(page.text().unwrap().contains("Compiler release candidate"));
+    let write = http.post(&backend.url(&shared)).send().unwrap();
+    assert!(write.status().is_client_error());
+
+    let revoke = http
+        .delete(&backend.url(&format!("/api/shares/{}", share["token"].as_str().unwrap())))
+        .bearer_auth("adm1n")
+        .send()
+        .unwrap();
+    assert_eq!(revoke.status().as_u16(), 204);
+    let revoked = reqwest::blocking::get(&backend.url(&shared)).unwrap();
+    assert_eq!(revoked.status().as_u16(), 404);
+}
+
+#[test]
+fn generates_feeds_of_views() {
+    let (feeds, backend) = start();
+    let mut client = backend.connect();
+    client.wait_for("every fixture item", has_every_item);
+    let id = id_of(&client, "Library extends hours");
+    client.send(json!({ "type": "star", "id": id, "starred": true }));
+    let is_starred =
+        |items: &HashMap<String, Value>| items.get(&id).map_or(false, |i| i["starred"] == true);
+    client.wait_for("the star to be echoed", is_starred);
+
+    let get = |path: &str| {
+        reqwest::blocking::get(&backend.url(path))
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.text())
+    };
+    let rss = get("/api/feeds/out/starred.xml").expect("failed to get the starred feed");
+    assert!(rss.contains("<rss"));
+    assert!(rss.contains("<title>Library extends hours</title>"));
+    assert_eq!(rss.matches("<item>").count(), 1);
+
+    let atom = get(&format!(
+        "/api/feeds/out/feed.xml?format=atom&url={}",
+        feeds.url("tech.xml")
+    ))
+    .expect("failed to get the feed of a feed");
+    assert!(atom.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
+    assert_eq!(atom.matches("<entry>").count(), 2);
+
+    assert!(get("/api/feeds/out/folders.xml").is_err());
+}
+
+#[test]
+fn keeps_the_reading_queue_in_order() {
+    let (_feeds, mut backend) = start();
+    let mut client = backend.connect();
+    client.wait_for("every fixture item", has_every_item);
+    let kernel = id_of(&client, "Kernel patch merged");
+    let harbor = id_of(&client, "Harbor reopens after storm");
+
+    let queue = json!([kernel, harbor, "unknown", kernel]);
+    let kept: Value = reqwest::blocking::Client::new()
+        .put(&backend.url("/api/queue"))
+        .json(&queue)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.json())
+        .expect("failed to set the queue");
+    assert_eq!(kept, json!([kernel, harbor]));
+
+    backend.restart();
+    let queue: Value = reqwest::blocking::get(&backend.url("/api/queue"))
+        .and_then(|response| response.json())
+        .expect("failed to get the queue");
+    assert_eq!(queue, json!([kernel, harbor]));
+}
+
+#[test]
+fn subscribes_to_feeds() {
+    let feeds = MockServer::start("e2e");
+    let backend = Backend::start(vec![feeds.url("world.xml")]);
+    let mut client = backend.connect();
+    let has_world = |items: &HashMap<String, Value>| {
+        items.values().any(|item| item["title"] == "Harbor reopens after storm")
+    };
+    client.wait_for("the items of the first feed", has_world);
+
+    let http = reqwest::blocking::Client::new();
+    let subscribe = |url: String| -> Value {
+        http.post(&backend.url("/api/feeds"))
+            .json(&json!({ "url": url }))
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json())
+            .expect("failed to subscribe")
+    };
+    assert_eq!(subscribe(feeds.url("world.xml")), json!({ "added": false }));
+    assert_eq!(subscribe(feeds.url("tech.xml")), json!({ "added": true }));
+    client.wait_for("every fixture item", has_every_item);
+
+    let subscribed: Vec<String> = reqwest::blocking::get(&backend.url("/api/feeds"))
+        .and_then(|response| response.json())
+        .expect("failed to list feeds");
+    assert_eq!(subscribed, [feeds.url("world.xml"), feeds.url("tech.xml")]);
+}
+
+#[test]
+fn annotates_items() {
+    let (_feeds, mut backend) = start();
+    let mut client = backend.connect();
+    client.wait_for("every fixture item", has_every_item);
+    let harbor = id_of(&client, "Harbor reopens after storm");
+    let url = backend.url(&format!("/api/items/{}/annotations", harbor));
+
+    let http = reqwest::blocking::Client::new();
+    let annotations = json!({
+        "note": "Check the follow-up",
+        "highlights": [{ "start": 4, "end": 10, "text": "harbor", "note": "  " }],
+    });
+    let saved: Value = http
+        .put(&url)
+        .json(&annotations)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.json())
+        .expect("failed to annotate the item");
+    assert_eq!(saved["note"], "Check the follow-up");
+    assert_eq!(saved["highlights"][0]["text"], "harbor");
+    assert!(saved["highlights"][0].get("note").is_none());
+    client.wait_for("the annotations", |items| {
+        items[&harbor]["annotations"]["note"] == "Check the follow-up"
+    });
+
+    let mismatched = json!({ "highlights": [{ "start": 0, "end": 3, "text": "harbor" }] });
+    let rejected = http.put(&url).json(&mismatched).send().unwrap();
+    assert_eq!(rejected.status().as_u16(), 400);
+
+    backend.restart();
+    let kept: Value = reqwest::blocking::get(&url)
+        .and_then(|response| response.json())
+        .expect("failed to get the annotations");
+    assert_eq!(kept, saved);
+}
+
+#[test]
+fn exports_annotations_as_markdown() {
+    let (_feeds, backend) = start();
+    let mut client = backend.connect();
+    client.wait_for("every fixture item", has_every_item);
+    let harbor = id_of(&client, "Harbor reopens after storm");
+    let annotations = json!({
+        "note": "Check the follow-up",
+        "highlights": [{ "start": 4, "end": 10, "text": "harbor" }],
+    });
+    reqwest::blocking::Client::new()
+        .put(&backend.url(&format!("/api/items/{}/annotations", harbor)))
+        .json(&annotations)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .expect("failed to annotate the item");
+
+    let bundle = reqwest::blocking::get(&backend.url("/api/annotations/export?by=folder"))
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.bytes())
+        .expect("failed to export the annotations");
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(&bundle[..]));
+    let mut documents = HashMap::new();
+    for entry in archive.entries().expect("invalid bundle") {
+        let mut entry = entry.expect("invalid bundle entry");
+        let name = entry.path().unwrap().display().to_string();
+        let mut document = String::new();
+        std::io::Read::read_to_string(&mut entry, &mut document).unwrap();
+        documents.insert(name, document);
+    }
+    let document = &documents["unfiled.md"];
+    assert!(document.starts_with("# Unfiled\n"));
+    assert!(document.contains("## [Harbor reopens after storm]("));
+    assert!(document.contains("\nCheck the follow-up\n"));
+    assert!(document.contains("\n> harbor\n"));
+
+    let disabled = reqwest::blocking::Client::new()
+        .post(&backend.url("/api/annotations/readwise"))
+        .send()
+        .unwrap();
+    assert_eq!(disabled.status().as_u16(), 404);
+}
+
+#[test]
+fn scopes_api_tokens() {
+    let feeds = MockServer::start("e2e");
+    let config = json!({ "admin_token": "adm1n", "require_api_token": true });
+    let backend = Backend::with_config(vec![feeds.url("world.xml")], config);
+    let http = reqwest::blocking::Client::new();
+
+    let minted: Value = http
+        .post(&backend.url("/api/tokens"))
+        .bearer_auth("adm1n")
+        .json(&json!({ "name": "weekly report", "scope": "read" }))
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.json())
+        .expect("failed to mint a token");
+    let secret = minted["secret"].as_str().unwrap();
+    assert_eq!(minted["scope"], "read");
+
+    let status = |request: reqwest::blocking::RequestBuilder| request.send().unwrap().status();
+    assert_eq!(status(http.get(&backend.url("/api/queue"))), 401);
+    assert_eq!(status(http.get(&backend.url("/api/queue")).bearer_auth(secret)), 200);
+    let write = http.put(&backend.url("/api/queue")).json(&json!([]));
+    assert_eq!(status(write.bearer_auth(secret)), 403);
+    assert_eq!(status(http.get(&backend.url("/api/admin/jobs")).bearer_auth(secret)), 401);
+    // The websocket changes state, which read tokens may not.
+    assert_eq!(status(http.get(&backend.url("/ws/"))), 401);
+    let ws = backend.url(&format!("/ws/?token={}", secret));
+    assert_eq!(status(http.get(&ws)), 403);
+
+    let listed: Value = http
+        .get(&backend.url("/api/tokens"))
+        .bearer_auth("adm1n")
+        .send()
+        .and_then(|response| response.json())
+        .expect("failed to list the tokens");
+    assert_eq!(listed[0]["name"], "weekly report");
+    assert!(listed[0].get("secret").is_none() && listed[0].get("hash").is_none());
+
+    let token = backend.url(&format!("/api/tokens/{}", minted["id"].as_str().unwrap()));
+    let revoke = http.delete(&token);
+    assert_eq!(status(revoke.bearer_auth("adm1n")), 204);
+    assert_eq!(status(http.get(&backend.url("/api/queue")).bearer_auth(secret)), 401);
+}
+
+#[test]
+fn serves_public_headlines() {
+    let feeds = MockServer::start("e2e");
+    let config = json!({ "headlines": { "view": { "kind": "all" }, "max": 3 } });
+    let urls = vec![feeds.url("world.xml"), feeds.url("tech.xml")];
+    let backend = Backend::with_config(urls, config);
+    let mut client = backend.connect();
+    client.wait_for("every fixture item", has_every_item);
+    let http = reqwest::blocking::Client::new();
+
+    let response = http
+        .get(&backend.url("/public/headlines?n=20"))
+        .send()
+        .and_then(|response| response.error_for_status())
+        .expect("failed to get the headlines");
+    assert_eq!(response.headers()["cache-control"], "public, max-age=300");
+    let etag = response.headers()["etag"].clone();
+    let headlines: Value = response.json().unwrap();
+    let headlines = headlines.as_array().unwrap();
+    assert_eq!(headlines.len(), 3);
+    assert!(headlines.iter().all(|headline| TITLES.contains(&headline["title"].as_str().unwrap())));
+    assert!(headlines[0].get("read").is_none());
+
+    let cached = http
+        .get(&backend.url("/public/headlines?n=20"))
+        .header("If-None-Match", etag)
+        .send()
+        .unwrap();
+    assert_eq!(cached.status(), 304);
+
+    let script = http
+        .get(&backend.url("/public/headlines?n=1&callback=widget.render"))
+        .send()
+        .and_then(|response| response.text())
+        .expect("failed to get the headlines as jsonp");
+    assert!(script.starts_with("/**/widget.render([{"));
+    let injected = http
+        .get(&backend.url("/public/headlines?callback=alert(1)"))
+        .send()
+        .unwrap();
+    assert_eq!(injected.status(), 400);
+}
+
+#[test]
+#[cfg(unix)]
+fn pipes_new_items_to_ingest_hooks() {
+    use std::time::{Duration, Instant};
+
+    let feeds = MockServer::start("e2e");
+    let log = std::env::temp_dir().join(format!("news-e2e-hook-{}", std::process::id()));
+    let script = format!("cat >> '{0}' && echo >> '{0}'", log.display());
+    let hook = json!({ "kind": "command", "command": ["sh", "-c", script] });
+    let urls = vec![feeds.url("world.xml"), feeds.url("tech.xml")];
+    let backend = Backend::with_config(urls, json!({ "ingest_hooks": [hook] }));
+    let mut client = backend.connect();
+    client.wait_for("every fixture item", has_every_item);
+
+    // Hooks run in the background, possibly after the items reached clients.
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let hooked = loop {
+        let hooked: Vec<Value> = std::fs::read_to_string(&log)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        let every_title = TITLES
+            .iter()
+            .all(|title| hooked.iter().any(|item| item["title"] == *title));
+        if every_title || Instant::now() > deadline {
+            break hooked;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    let _ = std::fs::remove_file(&log);
+    for title in TITLES.iter() {
+        let count = hooked.iter().filter(|item| item["title"] == *title).count();
+        assert_eq!(count, 1, "{} was hooked {} time(s)", title, count);
+    }
+}
@@ -0,0 +1,191 @@
+//! Detection of the character encoding of feeds, which are transcoded to UTF-8 before parsing.
+//!
+//! Feeds often declare ISO-8859-1 while being UTF-8, or claim UTF-8 while being Windows-1252, so
+//! the charset of the `Content-Type` header and the XML declaration are only trusted when the body
+//! isn't valid UTF-8 already.
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Number of bytes searched for the XML declaration.
+const DECLARATION_LEN: usize = 1_024;
+
+/// Return `body`, served as `content_type`, transcoded to UTF-8.
+///
+/// The encoding named by the XML declaration is replaced by UTF-8 so that the parser doesn't decode
+/// the body a second time. Malformed sequences are replaced by U+FFFD.
+pub fn to_utf8<'a>(body: &'a [u8], content_type: Option<&str>) -> Cow<'a, [u8]> {
+    let encoding = detect(body, content_type);
+    let (text, actual, malformed) = encoding.decode(body);
+    if malformed {
+        log::debug!("feed has malformed {} sequences", actual.name());
+    }
+
+    let declared = declared_range(&text).filter(|range| !is_utf8(&text[range.clone()]));
+    match (text, declared) {
+        (Cow::Borrowed(text), None) => Cow::Borrowed(text.as_bytes()),
+        (text, None) => Cow::Owned(text.into_owned().into_bytes()),
+        (text, Some(range)) => {
+            let mut text = text.into_owned();
+            text.replace_range(range, "UTF-8");
+            Cow::Owned(text.into_bytes())
+        }
+    }
+}
+
+/// Guess the encoding of `body`, which a byte order mark or valid UTF-8 settle.
+fn detect(body: &[u8], content_type: Option<&str>) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(body) {
+        return encoding;
+    }
+    // A declaration in UTF-16 without a byte order mark.
+    if body.starts_with(b"<\0?\0") {
+        return UTF_16LE;
+    }
+    if body.starts_with(b"\0<\0?") {
+        return UTF_16BE;
+    }
+    if std::str::from_utf8(body).is_ok() {
+        return UTF_8;
+    }
+
+    let declared = content_type
+        .and_then(header_charset)
+        .or_else(|| declared_encoding(body))
+        .and_then(|label| Encoding::for_label(label.trim().as_bytes()));
+    match declared {
+        // The body starts like ASCII, so an encoding that doesn't is a lie.
+        Some(encoding) if encoding != UTF_8 && encoding.is_ascii_compatible() => encoding,
+        // Text without a single multi-byte sequence was written in a single-byte encoding, of
+        // which Windows-1252 is by far the most common.
+        _ if !has_multibyte(body) => WINDOWS_1252,
+        _ => UTF_8,
+    }
+}
+
+/// Return the charset parameter of a `Content-Type` header.
+fn header_charset(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let mut parts = param.splitn(2, '=');
+        let name = parts.next()?.trim();
+        let value = parts.next()?.trim().trim_matches('"');
+        Some(value).filter(|_| name.eq_ignore_ascii_case("charset"))
+    })
+}
+
+/// Return the encoding named by the XML declaration of `body`.
+fn declared_encoding(body: &[u8]) -> Option<&str> {
+    let head = &body[..body.len().min(DECLARATION_LEN)];
+    let head = match std::str::from_utf8(head) {
+        Ok(head) => head,
+        Err(e) => std::str::from_utf8(&head[..e.valid_up_to()]).ok()?,
+    };
+    declared_range(head).map(|range| &head[range])
+}
+
+/// Return the range of the encoding named by the XML declaration at the start of `text`.
+fn declared_range(text: &str) -> Option<Range<usize>> {
+    if !text.starts_with("<?xml") {
+        return None;
+    }
+    let declaration = &text[..text.find("?>")?];
+    let name = declaration.find("encoding")? + "encoding".len();
+    let rest = declaration[name..].trim_start();
+    let rest = rest.strip_prefix('=')?.trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    // The value starts after the quote, `rest` being the end of the declaration.
+    let start = declaration.len() - rest.len() + 1;
+    let len = declaration[start..].find(quote)?;
+    Some(start..start + len)
+}
+
+fn is_utf8(label: &str) -> bool {
+    label.eq_ignore_ascii_case("utf-8") || label.eq_ignore_ascii_case("utf8")
+}
+
+/// Whether `body` holds any valid multi-byte UTF-8 sequence, showing it is UTF-8 with a few stray
+/// bytes rather than text in a single-byte encoding.
+fn has_multibyte(mut body: &[u8]) -> bool {
+    loop {
+        match std::str::from_utf8(body) {
+            Ok(text) => return !text.is_ascii(),
+            Err(e) => {
+                let (valid, rest) = body.split_at(e.valid_up_to());
+                if !valid.is_ascii() {
+                    return true;
+                }
+                body = &rest[e.error_len().unwrap_or(rest.len())..];
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf8(body: &[u8], content_type: Option<&str>) -> String {
+        String::from_utf8(to_utf8(body, content_type).into_owned()).unwrap()
+    }
+
+    #[test]
+    fn keeps_utf8() {
+        let body = "<?xml version=\"1.0\"?><title>Café</title>".as_bytes();
+        assert!(matches!(to_utf8(body, None), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn transcodes_declared_encoding() {
+        let body = b"<?xml version=\"1.0\" encoding='ISO-8859-1'?><title>Caf\xe9</title>";
+        assert_eq!(
+            utf8(body, None),
+            "<?xml version=\"1.0\" encoding='UTF-8'?><title>Café</title>"
+        );
+    }
+
+    #[test]
+    fn prefers_header_charset() {
+        let body = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><title>\xc1\xc2</title>";
+        let content_type = "application/rss+xml; charset=\"koi8-r\"";
+        assert_eq!(
+            utf8(body, Some(content_type)),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><title>аб</title>"
+        );
+    }
+
+    #[test]
+    fn ignores_declaration_of_utf8_body() {
+        let body = "<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><title>Café</title>";
+        assert_eq!(
+            utf8(body.as_bytes(), Some("text/xml; charset=iso-8859-1")),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><title>Café</title>"
+        );
+    }
+
+    #[test]
+    fn single_byte_text_claiming_utf8_is_windows_1252() {
+        let body = b"<?xml version=\"1.0\" encoding=\"UTF-8\"?><title>\x93Caf\xe9\x94</title>";
+        assert!(utf8(body, None).ends_with("<title>“Café”</title>"));
+    }
+
+    #[test]
+    fn stray_bytes_in_utf8_are_replaced() {
+        let mut body = "<title>Café — closed".as_bytes().to_vec();
+        body.extend_from_slice(b"\xff</title>");
+        assert_eq!(utf8(&body, None), "<title>Café — closed\u{fffd}</title>");
+    }
+
+    #[test]
+    fn detects_utf16() {
+        let body: Vec<u8> = "<?xml version=\"1.0\" encoding=\"UTF-16\"?><title>Café</title>"
+            .encode_utf16()
+            .flat_map(|unit| unit.to_le_bytes().to_vec())
+            .collect();
+        assert_eq!(
+            utf8(&body, None),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><title>Café</title>"
+        );
+    }
+}
@@ -0,0 +1,78 @@
+//! Word diffs between revisions of an item.
+
+use serde::{Deserialize, Serialize};
+
+/// Texts longer than this many words are diffed as a whole replacement.
+const MAX_WORDS: usize = 3000;
+
+/// A run of words kept, added, or removed between two texts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Same,
+    Added,
+    Removed,
+}
+
+/// Diff the words of `old` and `new`, merging consecutive words of the same kind.
+pub fn words(old: &str, new: &str) -> Vec<Change> {
+    let old: Vec<&str> = old.split_whitespace().collect();
+    let new: Vec<&str> = new.split_whitespace().collect();
+    if old.len() > MAX_WORDS || new.len() > MAX_WORDS {
+        let mut changes = Vec::new();
+        push(&mut changes, ChangeKind::Removed, &old);
+        push(&mut changes, ChangeKind::Added, &new);
+        return changes;
+    }
+
+    // Length of the longest common subsequence of old[i..] and new[j..].
+    let mut lcs = vec![vec![0u16; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            push(&mut changes, ChangeKind::Same, &old[i..=i]);
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            push(&mut changes, ChangeKind::Added, &new[j..=j]);
+            j += 1;
+        } else {
+            push(&mut changes, ChangeKind::Removed, &old[i..=i]);
+            i += 1;
+        }
+    }
+    changes
+}
+
+fn push(changes: &mut Vec<Change>, kind: ChangeKind, words: &[&str]) {
+    if words.is_empty() {
+        return;
+    }
+    match changes.last_mut() {
+        Some(last) if last.kind == kind => {
+            last.text.push(' ');
+            last.text.push_str(&words.join(" "));
+        }
+        _ => changes.push(Change {
+            kind,
+            text: words.join(" "),
+        }),
+    }
+}
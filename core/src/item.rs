@@ -0,0 +1,543 @@
+//! News items normalized from the items of RSS feeds, with their publish dates, identity and
+//! revisions, and the merging of the copies of an item published in several feeds.
+
+use anyhow::Result;
+use chrono::prelude::*;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use rss::Channel;
+use serde::{Deserialize, Serialize};
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use crate::charset;
+use crate::diff::{self, Change};
+use crate::sanitize;
+use crate::typography;
+use crate::urls;
+
+/// Reading speed of an average adult, used to estimate reading times.
+const WORDS_PER_MINUTE: u32 = 230;
+
+/// Parse the items of the feed at `feed` from its `body`, served as `content_type` in whatever
+/// encoding, with their images cached in `cache_dir`.
+pub fn parse_feed(
+    body: &[u8],
+    content_type: Option<&str>,
+    feed: &str,
+    cache_dir: &Path,
+) -> Result<Vec<NewsItem>> {
+    let body = charset::to_utf8(body, content_type);
+    let channel = Channel::read_from(&body[..])?;
+    let base = urls::base(feed, channel.link());
+    let title = Some(channel.title().trim())
+        .filter(|title| !title.is_empty())
+        .map(str::to_owned);
+    #[cfg(feature = "rayon")]
+    let items = channel.items().par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let items = channel.items().iter();
+    Ok(items
+        .map(|item| {
+            let mut item = item.clone();
+            urls::normalize_item(&mut item, base.as_ref());
+            typography::normalize_item(&mut item);
+            let mut item = NewsItem::new(item, feed, cache_dir);
+            item.feed_title = title.clone();
+            item
+        })
+        .collect())
+}
+
+/// Drop the items linking to the same page as a stored item of another feed, returning the feeds
+/// they came from keyed by the id of that stored item.
+pub fn merge_duplicates(
+    stored: &BTreeSet<NewsItem>,
+    items: &mut Vec<NewsItem>,
+) -> HashMap<String, Vec<Source>> {
+    let stored: HashMap<String, &NewsItem> = stored
+        .iter()
+        .filter_map(|item| Some((item.canonical_link()?, item)))
+        .collect();
+    let mut merged: HashMap<String, Vec<Source>> = HashMap::new();
+    items.retain(|item| {
+        let original = match item.canonical_link().and_then(|link| stored.get(&link)) {
+            Some(original) if original.feed != item.feed => original,
+            _ => return true,
+        };
+        let source = item.source();
+        if !original.also_in.iter().any(|known| known.feed == source.feed) {
+            let sources = merged.entry(original.id()).or_default();
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+        false
+    });
+    merged
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewsItem {
+    item: rss::Item,
+    pub image_path: Option<PathBuf>,
+    /// Blurhash of the cached image shown while it loads.
+    pub image_placeholder: Option<String>,
+    pub_date: Option<chrono::DateTime<chrono::FixedOffset>>,
+    /// URL of the feed the item came from.
+    pub feed: String,
+    /// Full article content extracted for feeds configured to show it.
+    pub content: Option<String>,
+    /// Short summary of a long extracted article.
+    pub summary: Option<String>,
+    /// Quality score between 0 (spam or clickbait) and 1, assigned when the item is stored.
+    pub quality: f64,
+    /// Earlier versions of the item, oldest first, when the feed edited it.
+    pub revisions: Vec<Revision>,
+    /// Differences with the previous revision.
+    pub changes: Option<Changes>,
+    /// Other feeds the item was published in, whose copies were merged into it.
+    pub also_in: Vec<Source>,
+    /// Title of the channel of the feed.
+    pub feed_title: Option<String>,
+}
+
+/// A feed an item was published in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Source {
+    pub feed: String,
+    /// Title of the channel of the feed.
+    pub title: Option<String>,
+}
+
+/// Title and description an item had before the feed edited it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Time the edit replacing this revision was noticed.
+    pub replaced_at: DateTime<Utc>,
+}
+
+/// Word diffs of the title and the text of the description against the previous revision.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Changes {
+    pub title: Vec<Change>,
+    pub description: Vec<Change>,
+}
+
+/// What plugins and transforms see of an item and may rewrite, in a shape independent of RSS.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ItemFields {
+    pub title: Option<String>,
+    pub link: Option<String>,
+    pub guid: Option<String>,
+    pub description: Option<String>,
+    /// Content provided by the feed itself.
+    pub content: Option<String>,
+    pub image_url: Option<String>,
+    pub pub_date: Option<DateTime<FixedOffset>>,
+}
+
+impl fmt::Display for NewsItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} - {}",
+            self.title().unwrap_or(""),
+            self.description().unwrap_or("")
+        )
+    }
+}
+
+impl PartialEq for NewsItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.item.title() == other.item.title()
+            && self.item.description() == other.item.description()
+            && self.item.pub_date() == other.item.pub_date()
+    }
+}
+
+trait NeededData {
+    fn image_url(&self) -> Option<String>;
+    fn publish_date(&self) -> Option<chrono::DateTime<chrono::FixedOffset>>;
+    fn digest(&self) -> blake3::Hash;
+}
+
+impl NeededData for rss::Item {
+    fn digest(&self) -> blake3::Hash {
+        digest(self.title().unwrap_or(""), self.description().unwrap_or(""))
+    }
+
+    fn publish_date(&self) -> Option<DateTime<FixedOffset>> {
+        let pub_date = self.pub_date();
+        if let Some(date) = pub_date {
+            return DateTime::parse_from_str(date, "%a, %d %b %Y %H:%M:%S %z").ok();
+        }
+
+        if let Some(dc) = self.dublin_core_ext() {
+            let dates = dc.dates();
+            if !dates.is_empty() {
+                //println!("Dates: {:#?}", dates[0].parse::<DateTime<Utc>>());
+                return dates[0].parse::<DateTime<FixedOffset>>().ok();
+            }
+        }
+
+        None
+    }
+
+    fn image_url(&self) -> Option<String> {
+        if let Some(media) = self.extensions().get("media") {
+            if let Some(thumbnail) = media.get("thumbnail") {
+                if let Some(thumbnail) = thumbnail.first() {
+                    if let Some(url) = thumbnail.attrs().get("url") {
+                        return Some(url.to_owned());
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Path an image downloaded from `url` is cached at.
+fn image_path(url: &str, cache_dir: &Path) -> PathBuf {
+    cache_dir.join(url.replace("https://", "").replace("http://", ""))
+}
+
+fn digest(title: &str, description: &str) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(title.as_bytes());
+    hasher.update(description.as_bytes());
+    hasher.finalize()
+}
+
+impl NewsItem {
+    pub fn new(item: rss::Item, feed: &str, cache_dir: &Path) -> Self {
+        let pub_date = item.publish_date();
+        let image_path = item.image_url().map(|url| image_path(&url, cache_dir));
+
+        NewsItem {
+            item,
+            pub_date,
+            image_path,
+            image_placeholder: None,
+            feed: feed.to_string(),
+            content: None,
+            summary: None,
+            quality: 1.0,
+            revisions: Vec::new(),
+            changes: None,
+            also_in: Vec::new(),
+            feed_title: None,
+        }
+    }
+
+    /// Build an item of `feed` from the fields returned by a plugin.
+    pub fn from_fields(fields: ItemFields, feed: &str, cache_dir: &Path) -> Self {
+        let mut item = NewsItem::new(rss::Item::default(), feed, cache_dir);
+        item.set_fields(fields, cache_dir);
+        item
+    }
+
+    /// The fields of the item plugins and transforms may rewrite.
+    pub fn fields(&self) -> ItemFields {
+        ItemFields {
+            title: self.title().map(str::to_owned),
+            link: self.link().map(str::to_owned),
+            guid: self.item.guid().map(|guid| guid.value().to_owned()),
+            description: self.description().map(str::to_owned),
+            content: self.feed_content().map(str::to_owned),
+            image_url: self.image_url(),
+            pub_date: self.pub_date,
+        }
+    }
+
+    /// Replace the fields of the item by `fields`, with its image cached in `cache_dir`.
+    pub fn set_fields(&mut self, fields: ItemFields, cache_dir: &Path) {
+        self.item.set_title(fields.title);
+        self.item.set_link(fields.link);
+        self.item.set_guid(fields.guid.map(|value| {
+            let mut guid = rss::Guid::default();
+            guid.set_value(value);
+            guid.set_permalink(false);
+            guid
+        }));
+        self.item.set_description(fields.description);
+        self.item.set_content(fields.content);
+        if fields.image_url != self.image_url() {
+            let mut extensions = self.item.extensions().clone();
+            let media = extensions.entry("media".to_string()).or_default();
+            media.remove("thumbnail");
+            if let Some(url) = &fields.image_url {
+                let mut thumbnail = rss::extension::Extension::default();
+                thumbnail.name = "media:thumbnail".to_string();
+                thumbnail.attrs.insert("url".to_string(), url.clone());
+                media.insert("thumbnail".to_string(), vec![thumbnail]);
+            }
+            self.item.set_extensions(extensions);
+            self.image_path = fields.image_url.map(|url| image_path(&url, cache_dir));
+            self.image_placeholder = None;
+        }
+        self.item.set_pub_date(fields.pub_date.map(|date| date.to_rfc2822()));
+        self.pub_date = fields.pub_date;
+    }
+
+    pub fn pub_date(&self) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+        self.pub_date
+    }
+
+    /// Date the item at `date` in place of the date the feed gave it.
+    pub fn set_pub_date(&mut self, date: DateTime<FixedOffset>) {
+        self.item.set_pub_date(date.to_rfc2822());
+        self.pub_date = Some(date);
+    }
+
+    /// The item as the feed provided it, with its URLs normalized.
+    pub fn rss_item(&self) -> &rss::Item {
+        &self.item
+    }
+
+    /// Digest of the title and description the item was first fetched with, so that it keeps
+    /// its identity across edits.
+    pub fn digest(&self) -> blake3::Hash {
+        match self.revisions.first() {
+            Some(first) => digest(
+                first.title.as_deref().unwrap_or(""),
+                first.description.as_deref().unwrap_or(""),
+            ),
+            None => self.item.digest(),
+        }
+    }
+
+    /// Identifier of the item shared with clients.
+    pub fn id(&self) -> String {
+        self.digest().to_hex().to_string()
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.item.title()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.item.description()
+    }
+
+    pub fn link(&self) -> Option<&str> {
+        self.item.link()
+    }
+
+    pub fn set_link(&mut self, link: String) {
+        self.item.set_link(link);
+    }
+
+    /// Estimated minutes it takes to read the item, from the words of its content or description.
+    pub fn reading_minutes(&self) -> u32 {
+        let html = self.content.as_deref().or_else(|| self.description()).unwrap_or("");
+        let words = sanitize::text(html).split_whitespace().count() as u32;
+        // Even a headline takes a moment.
+        ((words + WORDS_PER_MINUTE - 1) / WORDS_PER_MINUTE).max(1)
+    }
+
+    /// The feed the item was published in.
+    pub fn source(&self) -> Source {
+        Source {
+            feed: self.feed.clone(),
+            title: self.feed_title.clone(),
+        }
+    }
+
+    /// Key of the page the item links to, shared by the copies of the item in other feeds.
+    pub fn canonical_link(&self) -> Option<String> {
+        self.link().and_then(urls::canonical)
+    }
+
+    /// Identifier of the item in its feed, falling back to its link.
+    pub fn guid(&self) -> Option<&str> {
+        self.item
+            .guid()
+            .map(|guid| guid.value())
+            .or_else(|| self.link())
+    }
+
+    /// Whether the feed edited the title or description of `previous` into those of `self`.
+    pub fn edits(&self, previous: &NewsItem) -> bool {
+        self.feed == previous.feed
+            && self.guid().is_some()
+            && self.guid() == previous.guid()
+            && (self.title() != previous.title() || self.description() != previous.description())
+    }
+
+    /// Record `previous` as the revision this item replaces, keeping its history.
+    pub fn revise(&mut self, previous: &NewsItem) {
+        self.revisions = previous.revisions.clone();
+        self.revisions.push(Revision {
+            title: previous.title().map(str::to_owned),
+            description: previous.description().map(str::to_owned),
+            replaced_at: Utc::now(),
+        });
+        let text = |item: &NewsItem| item.description().map(sanitize::text).unwrap_or_default();
+        self.changes = Some(Changes {
+            title: diff::words(
+                previous.title().unwrap_or(""),
+                self.title().unwrap_or(""),
+            ),
+            description: diff::words(&text(previous), &text(self)),
+        });
+    }
+
+    /// Content provided by the feed itself, e.g. through `content:encoded`.
+    pub fn feed_content(&self) -> Option<&str> {
+        self.item.content()
+    }
+
+    pub fn image_url(&self) -> Option<String> {
+        self.item.image_url()
+    }
+
+    /// Drop the full content of the item, keeping its description. Returns roughly the number of
+    /// bytes freed.
+    pub fn drop_body(&mut self) -> u64 {
+        let extracted = self.content.take().map_or(0, |content| content.len());
+        let provided = self.item.content().map_or(0, str::len);
+        self.item.set_content(None);
+        (extracted + provided) as u64
+    }
+}
+
+impl Hash for NewsItem {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.item.title().hash(state);
+        self.item.description().hash(state);
+        self.item.pub_date().hash(state);
+    }
+}
+
+impl Eq for NewsItem {}
+
+/// Items are ordered by date, then by identity so that items sharing a date are kept apart.
+impl Ord for NewsItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.pub_date()
+            .cmp(&other.pub_date())
+            .then_with(|| self.digest().as_bytes().cmp(other.digest().as_bytes()))
+    }
+}
+
+impl PartialOrd for NewsItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Return the publish date for the item.
+pub fn publish_date(item: &rss::Item) -> Option<DateTime<FixedOffset>> {
+    let pub_date = item.pub_date();
+    if let Some(date) = pub_date {
+        return DateTime::parse_from_str(date, "%a, %d %b %Y %H:%M:%S %z").ok();
+    }
+
+    if let Some(dc) = item.dublin_core_ext() {
+        let dates = dc.dates();
+        if !dates.is_empty() {
+            //println!("Dates: {:#?}", dates[0].parse::<DateTime<Utc>>());
+            return dates[0].parse::<DateTime<FixedOffset>>().ok();
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_util;
+
+    fn item(feed: &str, title: &str, link: &str, hour: u32) -> NewsItem {
+        let date = Utc.ymd(2020, 5, 9).and_hms(hour, 0, 0);
+        test_util::item(title)
+            .feed(feed)
+            .link(link)
+            .date(date)
+            .build()
+    }
+
+    #[test]
+    fn rewrites_fields() {
+        let mut news = item("https://example.com/feed", "Story", "https://example.com/story", 8);
+        let mut fields = news.fields();
+        assert_eq!(fields.title.as_deref(), Some("Story"));
+        fields.title = Some("Rewritten".to_string());
+        fields.image_url = Some("https://example.com/a.png".to_string());
+        news.set_fields(fields.clone(), Path::new("/cache"));
+
+        assert_eq!(news.title(), Some("Rewritten"));
+        assert_eq!(news.image_url().as_deref(), Some("https://example.com/a.png"));
+        assert_eq!(news.image_path.as_deref(), Some(Path::new("/cache/example.com/a.png")));
+        assert_eq!(news.fields(), fields);
+        let built = NewsItem::from_fields(fields, "https://example.com/feed", Path::new("/cache"));
+        assert_eq!(built, news);
+    }
+
+    #[test]
+    fn merges_copies_from_other_feeds() {
+        let own = item("https://example.com/feed", "Story", "https://example.com/story", 8);
+        let mut stored = BTreeSet::new();
+        stored.insert(own.clone());
+        let aggregator = "https://aggregator.example.org/rss";
+        let copy = "http://www.example.com/story/?utm_source=aggregator";
+        let mut items = vec![
+            item(aggregator, "Story (example.com)", copy, 9),
+            item(aggregator, "Other story", "https://example.com/other", 10),
+        ];
+
+        let merged = merge_duplicates(&stored, &mut items);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title(), Some("Other story"));
+        let source = Source {
+            feed: aggregator.to_string(),
+            title: None,
+        };
+        assert_eq!(merged.get(&own.id()), Some(&vec![source]));
+    }
+
+    #[test]
+    fn keeps_items_of_the_same_feed() {
+        let feed = "https://example.com/feed";
+        let mut stored = BTreeSet::new();
+        stored.insert(item(feed, "Story", "https://example.com/story", 8));
+        let mut items = vec![item(feed, "Story, updated", "https://example.com/story", 9)];
+        assert!(merge_duplicates(&stored, &mut items).is_empty());
+        assert_eq!(items.len(), 1);
+    }
+
+    #[test]
+    fn merges_known_copies_silently() {
+        let mut own = item("https://example.com/feed", "Story", "https://example.com/story", 8);
+        let aggregator = "https://aggregator.example.org/rss";
+        own.also_in.push(Source {
+            feed: aggregator.to_string(),
+            title: Some("Aggregator".to_string()),
+        });
+        let mut stored = BTreeSet::new();
+        stored.insert(own);
+        let mut items = vec![item(aggregator, "Story", "https://example.com/story", 9)];
+        assert!(merge_duplicates(&stored, &mut items).is_empty());
+        assert!(items.is_empty());
+    }
+
+    #[test]
+    fn keeps_items_sharing_a_date() {
+        let feed = "https://example.com/feed";
+        let mut items = BTreeSet::new();
+        items.insert(item(feed, "Story", "https://example.com/story", 8));
+        items.insert(item(feed, "Other story", "https://example.com/other", 8));
+        assert_eq!(items.len(), 2);
+    }
+}
@@ -0,0 +1,16 @@
+//! Normalization of feeds into news items, shared by the backend and the frontend.
+//!
+//! Nothing here touches the network, so the crate compiles to WebAssembly and feeds added in the
+//! browser are parsed, dated, deduplicated and sanitized exactly like the ones the backend fetches.
+
+pub mod charset;
+pub mod diff;
+pub mod item;
+pub mod sanitize;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+pub mod timezone;
+pub mod typography;
+pub mod urls;
+
+pub use rss;
@@ -0,0 +1,78 @@
+//! Sanitization of HTML provided by feeds before it is sent to the frontend.
+
+use scraper::Html;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Number of user-perceived characters of the excerpts listed in place of whole descriptions.
+pub const EXCERPT_LEN: usize = 160;
+
+/// Strip scripts, styles, event handlers, and other unsafe markup out of `html`.
+pub fn html(html: &str) -> String {
+    ammonia::Builder::default()
+        .link_rel(Some("noopener noreferrer"))
+        .clean(html)
+        .to_string()
+}
+
+/// Return the text of an HTML fragment.
+pub fn text(html: &str) -> String {
+    Html::parse_fragment(html)
+        .root_element()
+        .text()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Return the text of an HTML fragment, its whitespace collapsed, cut after `max` user-perceived
+/// characters so that emoji, combining accents and CJK characters are never split. Cut text ends
+/// with an ellipsis, at the last word boundary when there is one.
+pub fn excerpt(html: &str, max: usize) -> String {
+    let text = text(html).split_whitespace().collect::<Vec<_>>().join(" ");
+    let cut = match text.grapheme_indices(true).nth(max) {
+        Some((cut, _)) => cut,
+        None => return text,
+    };
+    let kept = &text[..cut];
+    // Words are kept whole unless a single one fills the excerpt.
+    let kept = match kept.rfind(' ') {
+        Some(space) if !text[cut..].starts_with(' ') => &kept[..space],
+        _ => kept,
+    };
+    format!("{}…", kept.trim_end())
+}
+
+/// Escape `text` so it can be embedded in HTML as is.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cuts_excerpts_between_graphemes() {
+        assert_eq!(excerpt("<p>Short  <b>text</b></p>", 20), "Short text");
+        assert_eq!(excerpt("<p>Fish and chips</p>", 11), "Fish and…");
+        assert_eq!(excerpt("Fish and chips", 8), "Fish and…");
+        let emoji =
+            "\u{1F469}\u{200D}\u{1F469}\u{200D}\u{1F467}\u{200D}\u{1F466}\u{1F44D}\u{1F3FD}";
+        assert_eq!(
+            excerpt(&format!("{} family", emoji), 2),
+            format!("{}…", emoji)
+        );
+        assert_eq!(excerpt("東京都の天気予報", 3), "東京都…");
+        assert_eq!(excerpt("Cafe\u{301} crème", 4), "Cafe\u{301}…");
+    }
+}
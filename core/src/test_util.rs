@@ -0,0 +1,56 @@
+//! Items for tests, of this crate and of the crates enabling its `test-util` feature.
+
+use chrono::prelude::*;
+
+use std::fmt::Display;
+use std::path::Path;
+
+use crate::item::NewsItem;
+
+/// Feed of the items unless another is given.
+pub const FEED: &str = "https://example.com/feed";
+
+/// Builder of an item as parsed from a feed, its image cached under `/cache`.
+pub struct ItemBuilder {
+    item: rss::Item,
+    feed: String,
+}
+
+/// Start building an item titled `title`.
+pub fn item(title: &str) -> ItemBuilder {
+    let mut item = rss::Item::default();
+    item.set_title(title.to_string());
+    ItemBuilder {
+        item,
+        feed: FEED.to_string(),
+    }
+}
+
+impl ItemBuilder {
+    pub fn feed(mut self, feed: &str) -> Self {
+        self.feed = feed.to_string();
+        self
+    }
+
+    pub fn link(mut self, link: &str) -> Self {
+        self.item.set_link(link.to_string());
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.item.set_description(description.to_string());
+        self
+    }
+
+    pub fn date<Tz: TimeZone>(mut self, date: DateTime<Tz>) -> Self
+    where
+        Tz::Offset: Display,
+    {
+        self.item.set_pub_date(date.to_rfc2822());
+        self
+    }
+
+    pub fn build(self) -> NewsItem {
+        NewsItem::new(self.item, &self.feed, Path::new("/cache"))
+    }
+}
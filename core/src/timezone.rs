@@ -0,0 +1,44 @@
+//! Time zones picked by name in the settings, so that days, digests and quiet hours follow the
+//! reader rather than the machine the backend or the browser runs on. Dates are kept in UTC and
+//! only converted for display and grouping.
+
+use chrono::{DateTime, FixedOffset, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Return the offset from UTC of the time zone with the IANA `name`, e.g. `Europe/Paris`, at
+/// `date`, or `None` when there is no such time zone.
+pub fn offset(name: &str, date: DateTime<Utc>) -> Option<FixedOffset> {
+    let tz: Tz = name.parse().ok()?;
+    Some(tz.offset_from_utc_datetime(&date.naive_utc()).fix())
+}
+
+/// Whether `name` is the IANA name of a time zone.
+pub fn is_valid(name: &str) -> bool {
+    name.parse::<Tz>().is_ok()
+}
+
+/// Return the IANA names of every time zone, sorted.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    chrono_tz::TZ_VARIANTS.iter().map(|tz| tz.name())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_daylight_saving_time() {
+        let winter = Utc.ymd(2020, 1, 15).and_hms(12, 0, 0);
+        let summer = Utc.ymd(2020, 7, 15).and_hms(12, 0, 0);
+        let paris = |date| offset("Europe/Paris", date).map(|offset| offset.local_minus_utc());
+        assert_eq!(paris(winter), Some(60 * 60));
+        assert_eq!(paris(summer), Some(2 * 60 * 60));
+        assert_eq!(offset("Mars/Olympus_Mons", winter), None);
+    }
+
+    #[test]
+    fn lists_valid_names() {
+        assert!(names().all(is_valid));
+        assert!(names().any(|name| name == "America/New_York"));
+    }
+}
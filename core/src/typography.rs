@@ -0,0 +1,309 @@
+//! Cleanup of the text of items: entities feeds escaped once too often, stray whitespace, and
+//! optionally straight quotes and dashes turned into typographic ones.
+
+/// Length of the longest entity decoded, e.g. `&#x1F600;`.
+const MAX_ENTITY_LEN: usize = 10;
+/// Number of times titles are decoded, undoing entities escaped that many times over.
+const MAX_DECODE_ROUNDS: usize = 3;
+
+/// Named entities feeds commonly leave in titles.
+const NAMED: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("nbsp", '\u{a0}'),
+    ("hellip", '…'),
+    ("mdash", '—'),
+    ("ndash", '–'),
+    ("lsquo", '‘'),
+    ("rsquo", '’'),
+    ("sbquo", '‚'),
+    ("ldquo", '“'),
+    ("rdquo", '”'),
+    ("bdquo", '„'),
+    ("laquo", '«'),
+    ("raquo", '»'),
+    ("bull", '•'),
+    ("middot", '·'),
+    ("copy", '©'),
+    ("reg", '®'),
+    ("trade", '™'),
+    ("deg", '°'),
+    ("times", '×'),
+    ("euro", '€'),
+    ("pound", '£'),
+    ("yen", '¥'),
+    ("cent", '¢'),
+    ("sect", '§'),
+    ("para", '¶'),
+    ("frac12", '½'),
+    ("agrave", 'à'),
+    ("aacute", 'á'),
+    ("acirc", 'â'),
+    ("auml", 'ä'),
+    ("ccedil", 'ç'),
+    ("egrave", 'è'),
+    ("eacute", 'é'),
+    ("ecirc", 'ê'),
+    ("euml", 'ë'),
+    ("iacute", 'í'),
+    ("iuml", 'ï'),
+    ("ntilde", 'ñ'),
+    ("oacute", 'ó'),
+    ("ocirc", 'ô'),
+    ("ouml", 'ö'),
+    ("uacute", 'ú'),
+    ("uuml", 'ü'),
+    ("szlig", 'ß'),
+    ("Eacute", 'É'),
+    ("Auml", 'Ä'),
+    ("Ouml", 'Ö'),
+    ("Uuml", 'Ü'),
+];
+
+/// Clean up the title, description and content of `item` as parsed from a feed.
+pub fn normalize_item(item: &mut rss::Item) {
+    if let Some(title) = item.title().map(clean_text) {
+        item.set_title(title);
+    }
+    if let Some(description) = item.description().map(clean_html) {
+        item.set_description(description);
+    }
+    if let Some(content) = item.content().map(clean_html) {
+        item.set_content(content);
+    }
+}
+
+/// Decode the entities left in plain `text`, even escaped several times over, and collapse its
+/// whitespace.
+pub fn clean_text(text: &str) -> String {
+    let mut text = text.to_string();
+    for _ in 0..MAX_DECODE_ROUNDS {
+        let decoded = decode_entities(&text);
+        if decoded == text {
+            break;
+        }
+        text = decoded;
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Undo the escaping of the entities of `html` escaped more than once, e.g. `&amp;amp;` or
+/// `&amp;#8217;`, and trim it. Entities escaped once are left for the browser to decode.
+pub fn clean_html(html: &str) -> String {
+    let mut html = html.trim().to_string();
+    loop {
+        let unescaped = unescape_entities(&html);
+        if unescaped == html {
+            return html;
+        }
+        html = unescaped;
+    }
+}
+
+/// Decode the entities of `text`, leaving the ampersands that don't start one as they are.
+pub fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        match entity(rest) {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => decoded.push('&'),
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Turn the `&amp;` followed by the rest of an entity into a plain `&`, once.
+fn unescape_entities(html: &str) -> String {
+    const AMP: &str = "&amp;";
+    let mut unescaped = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(AMP) {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start + AMP.len()..];
+        unescaped.push_str(if entity(rest).is_some() { "&" } else { AMP });
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Decode the entity `text` starts with, after its ampersand, returning its character and its
+/// length up to and including the semicolon.
+fn entity(text: &str) -> Option<(char, usize)> {
+    let end = text
+        .find(';')
+        .filter(|&end| end > 0 && end <= MAX_ENTITY_LEN)?;
+    let name = &text[..end];
+    let c = match name.strip_prefix('#') {
+        Some(number) => {
+            let code = match number
+                .strip_prefix('x')
+                .or_else(|| number.strip_prefix('X'))
+            {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code).filter(|&c| c != '\0')?
+        }
+        None => NAMED.iter().find(|(named, _)| *named == name)?.1,
+    };
+    Some((c, end + 1))
+}
+
+/// Turn the straight quotes of plain `text` into curly ones, double hyphens into em dashes,
+/// hyphens between spaces into en dashes and triple dots into ellipses.
+pub fn smarten(text: &str) -> String {
+    let mut smartened = String::with_capacity(text.len());
+    Smartener::default().push(&mut smartened, text);
+    smartened
+}
+
+/// Smarten the text of `html` like `smarten`, leaving its tags, entities, and the content of
+/// code, preformatted text, scripts and styles as they are.
+pub fn smarten_html(html: &str) -> String {
+    let mut smartened = String::with_capacity(html.len());
+    let mut smartener = Smartener::default();
+    // Depth of the elements whose content is left as is.
+    let mut verbatim = 0usize;
+    let mut rest = html;
+    while !rest.is_empty() {
+        let special = rest
+            .find(|c: char| c == '<' || c == '&')
+            .unwrap_or(rest.len());
+        let (text, tail) = rest.split_at(special);
+        if verbatim > 0 {
+            smartened.push_str(text);
+        } else {
+            smartener.push(&mut smartened, text);
+        }
+        rest = tail;
+        let end = match rest.chars().next() {
+            Some('<') => rest.find('>').map_or(rest.len(), |end| end + 1),
+            Some('&') => entity(&rest[1..]).map_or(1, |(_, len)| len + 1),
+            _ => break,
+        };
+        let (markup, tail) = rest.split_at(end);
+        if markup.starts_with('<') {
+            match tag(markup) {
+                Some((name, closing)) if is_verbatim(&name) && closing => {
+                    verbatim = verbatim.saturating_sub(1)
+                }
+                Some((name, _)) if is_verbatim(&name) => verbatim += 1,
+                _ => {}
+            }
+        }
+        smartened.push_str(markup);
+        rest = tail;
+    }
+    smartened
+}
+
+/// Return the lowercase name of the tag `markup` and whether it closes an element.
+fn tag(markup: &str) -> Option<(String, bool)> {
+    let inner = markup.strip_prefix('<')?;
+    let (inner, closing) = match inner.strip_prefix('/') {
+        Some(inner) => (inner, true),
+        None => (inner, false),
+    };
+    let name: String = inner
+        .chars()
+        .take_while(char::is_ascii_alphanumeric)
+        .collect();
+    Some((name.to_ascii_lowercase(), closing)).filter(|(name, _)| !name.is_empty())
+}
+
+fn is_verbatim(name: &str) -> bool {
+    matches!(name, "code" | "pre" | "script" | "style" | "kbd" | "samp")
+}
+
+/// Smartens text pushed in pieces, remembering the character before each.
+#[derive(Default)]
+struct Smartener {
+    previous: Option<char>,
+}
+
+impl Smartener {
+    fn push(&mut self, out: &mut String, text: &str) {
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let next = chars.get(i + 1).copied();
+            let (smart, len) = match chars[i] {
+                '"' if self.opens() => ('“', 1),
+                '"' => ('”', 1),
+                '\'' if self.opens() => ('‘', 1),
+                '\'' => ('’', 1),
+                '.' if next == Some('.') && chars.get(i + 2) == Some(&'.') => ('…', 3),
+                '-' if next == Some('-') => {
+                    let len = if chars.get(i + 2) == Some(&'-') { 3 } else { 2 };
+                    ('—', len)
+                }
+                '-' if self.previous == Some(' ') && next == Some(' ') => ('–', 1),
+                c => (c, 1),
+            };
+            out.push(smart);
+            self.previous = Some(smart);
+            i += len;
+        }
+    }
+
+    /// Whether a quote at this point opens a quotation rather than closing one or standing for
+    /// an apostrophe.
+    fn opens(&self) -> bool {
+        match self.previous {
+            None => true,
+            Some(c) => c.is_whitespace() || "([{<“‘—–-/".contains(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_titles() {
+        assert_eq!(clean_text("It&#8217;s &amp;amp; &#x1F600;"), "It’s & 😀");
+        assert_eq!(
+            clean_text("  Fish &amp;#038;\u{a0}Chips\n<3 "),
+            "Fish & Chips <3"
+        );
+        assert_eq!(
+            clean_text("AT&T; R&D &unknown; &#0;"),
+            "AT&T; R&D &unknown; &#0;"
+        );
+    }
+
+    #[test]
+    fn unescapes_html_once() {
+        assert_eq!(
+            clean_html(" <p>Fish &amp;amp;amp; chips &amp;#8212; &amp; more</p>\n"),
+            "<p>Fish &amp; chips &#8212; &amp; more</p>"
+        );
+        assert_eq!(
+            clean_html("<p>&lt;b&gt; is bold</p>"),
+            "<p>&lt;b&gt; is bold</p>"
+        );
+    }
+
+    #[test]
+    fn smartens_punctuation() {
+        assert_eq!(
+            smarten("\"Don't\" -- she said... 1990 - 2000 ('quoted')"),
+            "“Don’t” — she said… 1990 – 2000 (‘quoted’)"
+        );
+        assert_eq!(
+            smarten_html("<p class=\"x\">\"Hi\" &amp; <code>x = \"y\"</code> it's</p>"),
+            "<p class=\"x\">“Hi” &amp; <code>x = \"y\"</code> it’s</p>"
+        );
+    }
+}
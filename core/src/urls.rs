@@ -0,0 +1,188 @@
+//! Normalization of the URLs items link to.
+//!
+//! Some feeds give relative links and image URLs, which are resolved against the link of their
+//! channel, itself resolved against the URL of the feed. Tracking parameters are stripped from
+//! links so that the same page shared through different campaigns is stored once.
+
+pub use url::Url;
+
+/// Query parameters only used to track where visitors come from.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "msclkid", "yclid", "igshid", "mc_cid", "mc_eid", "_hsenc",
+    "_hsmi", "mkt_tok",
+];
+/// Prefixes of tracking query parameters, e.g. `utm_source`.
+const TRACKING_PREFIXES: &[&str] = &["utm_"];
+
+/// Return the URL relative links of the feed at `feed` whose channel links to `channel_link` are
+/// resolved against.
+pub fn base(feed: &str, channel_link: &str) -> Option<Url> {
+    let feed = Url::parse(feed).ok();
+    let link = match &feed {
+        Some(feed) => feed.join(channel_link.trim()).ok(),
+        None => Url::parse(channel_link.trim()).ok(),
+    };
+    link.filter(|link| link.scheme() == "http" || link.scheme() == "https")
+        .or(feed)
+}
+
+/// Return `url` resolved against `base` if it is relative.
+pub fn resolve(base: &Url, url: &str) -> Option<String> {
+    if Url::parse(url).is_ok() {
+        return None;
+    }
+    base.join(url.trim()).ok().map(String::from)
+}
+
+/// Return `url` without its tracking parameters, if it has any.
+pub fn strip_tracking(url: &str) -> Option<String> {
+    let mut url = Url::parse(url).ok()?;
+    let query = url.query()?;
+    let params: Vec<&str> = query.split('&').collect();
+    let kept: Vec<&str> = params
+        .iter()
+        .copied()
+        .filter(|param| !param.split('=').next().map_or(false, is_tracking))
+        .collect();
+    if kept.len() == params.len() {
+        return None;
+    }
+    let query = kept.join("&");
+    url.set_query(Some(query.as_str()).filter(|query| !query.is_empty()));
+    Some(url.into())
+}
+
+fn is_tracking(param: &str) -> bool {
+    let param = param.to_ascii_lowercase();
+    TRACKING_PARAMS.contains(&param.as_str())
+        || TRACKING_PREFIXES
+            .iter()
+            .any(|prefix| param.starts_with(prefix))
+}
+
+/// Return the key of the page `url` points to, ignoring its scheme, `www.` prefix, trailing slash,
+/// fragment and tracking parameters, so that the same article linked from several feeds matches.
+pub fn canonical(url: &str) -> Option<String> {
+    let stripped = strip_tracking(url);
+    let url = Url::parse(stripped.as_deref().unwrap_or(url)).ok()?;
+    let host = url.host_str()?;
+    let mut key = host.strip_prefix("www.").unwrap_or(host).to_string();
+    if let Some(port) = url.port() {
+        key.push_str(&format!(":{}", port));
+    }
+    key.push_str(url.path().trim_end_matches('/'));
+    if let Some(query) = url.query() {
+        key.push('?');
+        key.push_str(query);
+    }
+    Some(key)
+}
+
+/// Resolve the relative link, enclosure and thumbnail of `item` against `base`, and strip the
+/// tracking parameters of its link.
+pub fn normalize_item(item: &mut rss::Item, base: Option<&Url>) {
+    if let Some(base) = base {
+        resolve_item(item, base);
+    }
+    if let Some(link) = item.link().and_then(strip_tracking) {
+        item.set_link(link);
+    }
+}
+
+fn resolve_item(item: &mut rss::Item, base: &Url) {
+    if let Some(link) = item.link().and_then(|link| resolve(base, link)) {
+        item.set_link(link);
+    }
+
+    let enclosure = item.enclosure().cloned();
+    if let Some(mut enclosure) = enclosure {
+        if let Some(url) = resolve(base, enclosure.url()) {
+            enclosure.set_url(url);
+            item.set_enclosure(enclosure);
+        }
+    }
+
+    let mut extensions = item.extensions().clone();
+    let thumbnails = extensions
+        .get_mut("media")
+        .and_then(|media| media.get_mut("thumbnail"));
+    for thumbnail in thumbnails.into_iter().flatten() {
+        if let Some(url) = thumbnail.attrs.get("url").and_then(|url| resolve(base, url)) {
+            thumbnail.attrs.insert("url".to_string(), url);
+        }
+    }
+    item.set_extensions(extensions);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_is_the_channel_link() {
+        let base = base("https://example.com/feeds/all.xml", "https://blog.example.com/");
+        assert_eq!(base.unwrap().as_str(), "https://blog.example.com/");
+    }
+
+    #[test]
+    fn relative_channel_link_is_resolved_against_the_feed() {
+        let base = base("https://example.com/feeds/all.xml", "/blog/");
+        assert_eq!(base.unwrap().as_str(), "https://example.com/blog/");
+    }
+
+    #[test]
+    fn base_falls_back_to_the_feed() {
+        let feed = "https://example.com/feeds/all.xml";
+        assert_eq!(base(feed, "").unwrap().as_str(), feed);
+        assert_eq!(base(feed, "mailto:news@example.com").unwrap().as_str(), feed);
+    }
+
+    #[test]
+    fn resolves_relative_urls_only() {
+        let base = Url::parse("https://example.com/blog/").unwrap();
+        assert_eq!(
+            resolve(&base, "posts/1").as_deref(),
+            Some("https://example.com/blog/posts/1")
+        );
+        assert_eq!(
+            resolve(&base, "/about").as_deref(),
+            Some("https://example.com/about")
+        );
+        assert_eq!(
+            resolve(&base, "//cdn.example.com/a.png").as_deref(),
+            Some("https://cdn.example.com/a.png")
+        );
+        assert_eq!(resolve(&base, "https://other.example.com/"), None);
+    }
+
+    #[test]
+    fn strips_tracking_parameters() {
+        assert_eq!(
+            strip_tracking("https://example.com/a?utm_source=feed&id=3&fbclid=x#top").as_deref(),
+            Some("https://example.com/a?id=3#top")
+        );
+        assert_eq!(
+            strip_tracking("https://example.com/a?UTM_Medium=rss&utm_campaign").as_deref(),
+            Some("https://example.com/a")
+        );
+    }
+
+    #[test]
+    fn keeps_other_parameters_untouched() {
+        assert_eq!(strip_tracking("https://example.com/a?q=a%20b&page=2"), None);
+        assert_eq!(strip_tracking("https://example.com/a"), None);
+        assert_eq!(strip_tracking("posts/1?utm_source=feed"), None);
+    }
+
+    #[test]
+    fn canonical_links_match_across_feeds() {
+        let canonical = |url| canonical(url).unwrap();
+        let expected = "example.com/2020/05/story";
+        assert_eq!(canonical("https://www.example.com/2020/05/story/"), expected);
+        assert_eq!(canonical("http://EXAMPLE.com/2020/05/story#comments"), expected);
+        assert_eq!(canonical("https://example.com/2020/05/story?utm_source=rss"), expected);
+        assert_ne!(canonical("https://example.com/2020/05/story?page=2"), expected);
+        assert_ne!(canonical("https://example.com:8080/2020/05/story"), expected);
+        assert_eq!(super::canonical("/2020/05/story"), None);
+    }
+}
@@ -0,0 +1,166 @@
+## Navigation
+
+nav-discover = Entdecken
+nav-digest = Übersicht
+nav-queue = Leseliste ({ $count })
+nav-mutes = Stummschaltungen
+nav-settings = Einstellungen
+nav-label = Hauptnavigation
+
+## Footer
+
+items-left = { $count ->
+    [one] { $count } Eintrag übrig
+   *[other] { $count } Einträge übrig
+}
+fetch-news = Nachrichten abrufen
+loading-connecting = Verbinde…
+loading-refreshing = Feeds werden aktualisiert…
+loading-progress = { $done }/{ $total } Feeds aktualisiert…
+cancel-refresh = Abbrechen
+loading-failed = Offline
+filter-all = Alle
+filter-read = Gelesen
+filter-unread = Ungelesen
+filter-low-quality = Geringe Qualität
+filter-priority = Priorität
+catch-up = Bring mich auf den Stand in
+catch-up-minutes = { $minutes } Min.
+
+## Entries
+
+entries-label = Einträge
+new-items = { $count ->
+    [one] { $count } neuer Eintrag
+   *[other] { $count } neue Einträge
+}
+show-more = Mehr anzeigen
+only-showing = Nur { $name }
+only-showing-folder = Nur der Ordner { $name }
+show-every-feed = Alle Feeds anzeigen
+starred = Markiert
+dead-link = Der Link ist tot
+updated = Aktualisiert
+listen = Vorlesen
+share = Teilen
+star = Markieren
+unstar = Markierung entfernen
+junk = Unerwünscht
+translate = Übersetzen
+show-original = Original anzeigen
+export-pdf = Als PDF exportieren
+open-original = Original öffnen
+more-from-feed = Mehr aus diesem Feed
+snooze = Zurückstellen:
+snooze-tonight = Heute Abend
+snooze-tomorrow = Morgen
+snooze-next-week = Nächste Woche
+
+## Notes and highlights
+
+notes-title = Notizen
+highlight-selection = Auswahl markieren
+remove-highlight = Entfernen
+note-placeholder = Private Notiz hinzufügen…
+
+## Reading aloud
+
+listening = Vorlesen:
+pause = Pause
+resume = Fortsetzen
+skip = Überspringen
+stop = Beenden
+auto-advance = Automatisch weiter
+
+## Catch-up sessions
+
+session-empty = Nichts Ungelesenes passt, du bist auf dem Laufenden.
+session-progress = { $read } von { $total } gelesen, etwa { $minutes } Min.
+end-session = Sitzung beenden
+
+## Reading queue
+
+queue-title = Als Nächstes lesen
+queue-empty = Die Leseliste ist leer. Füge Einträge mit „Als Nächstes lesen“ hinzu.
+read-next = Als Nächstes lesen
+queue-previous = Zurück
+queue-next = Weiter
+queue-done = Fertig
+queue-remove = Aus der Leseliste entfernen
+close = Schließen
+
+## Errors
+
+retry = Erneut versuchen
+dismiss = Schließen
+connection-lost = Die Verbindung zum Server wurde unterbrochen.
+not-connected = Nicht mit dem Server verbunden, die Änderung wurde nicht gesendet.
+failed-connect = Verbindung zum Server fehlgeschlagen
+bad-message = Eine Nachricht des Servers war unlesbar
+failed-storage = Die Einträge konnten nicht im Browser gespeichert werden, der Speicher ist womöglich voll.
+failed-settings = Die Einstellungen konnten nicht geladen werden
+failed-save-settings = Die Einstellungen konnten nicht gespeichert werden
+failed-feeds = Die Feeds konnten nicht geladen werden
+failed-add-feed = Der Feed konnte nicht hinzugefügt werden
+failed-feed-order = Die Ordner konnten nicht geladen werden
+failed-save-feed-order = Die Ordner konnten nicht gespeichert werden
+failed-queue = Die Leseliste konnte nicht geladen werden
+failed-save-queue = Die Leseliste konnte nicht gespeichert werden
+failed-session = Die Lesesitzung konnte nicht gestartet werden
+failed-translate = Der Eintrag konnte nicht übersetzt werden
+failed-share = Der Eintrag konnte nicht geteilt werden
+failed-copy-link = Der Link konnte nicht kopiert werden
+failed-annotate = Die Notizen konnten nicht gespeichert werden
+
+## Settings
+
+language = Sprache
+language-browser = Wie der Browser
+timezone = Zeitzone
+timezone-browser = Wie der Browser
+
+## Dates
+
+just-now = gerade eben
+minutes-ago = { $count ->
+    [one] vor 1 Minute
+   *[other] vor { $count } Minuten
+}
+hours-ago = { $count ->
+    [one] vor 1 Stunde
+   *[other] vor { $count } Stunden
+}
+days-ago = { $count ->
+    [one] vor 1 Tag
+   *[other] vor { $count } Tagen
+}
+yesterday-relative = gestern
+today = Heute
+yesterday = Gestern
+day = { $day }. { $month }
+day-of-year = { $day }. { $month } { $year }
+date = { $day }. { $month } { $year }
+month-1 = Januar
+month-2 = Februar
+month-3 = März
+month-4 = April
+month-5 = Mai
+month-6 = Juni
+month-7 = Juli
+month-8 = August
+month-9 = September
+month-10 = Oktober
+month-11 = November
+month-12 = Dezember
+month-short-1 = Jan.
+month-short-2 = Feb.
+month-short-3 = März
+month-short-4 = Apr.
+month-short-5 = Mai
+month-short-6 = Juni
+month-short-7 = Juli
+month-short-8 = Aug.
+month-short-9 = Sept.
+month-short-10 = Okt.
+month-short-11 = Nov.
+month-short-12 = Dez.
@@ -0,0 +1,166 @@
+## Navigation
+
+nav-discover = Discover
+nav-digest = Digest
+nav-queue = Queue ({ $count })
+nav-mutes = Mutes
+nav-settings = Settings
+nav-label = Main navigation
+
+## Footer
+
+items-left = { $count ->
+    [one] { $count } item left
+   *[other] { $count } items left
+}
+fetch-news = Fetch News
+loading-connecting = Connecting…
+loading-refreshing = Refreshing feeds…
+loading-progress = Refreshing { $done }/{ $total } feeds…
+cancel-refresh = Cancel
+loading-failed = Offline
+filter-all = All
+filter-read = Read
+filter-unread = Unread
+filter-low-quality = Low quality
+filter-priority = Priority
+catch-up = Catch me up in
+catch-up-minutes = { $minutes } min
+
+## Entries
+
+entries-label = Entries
+new-items = { $count ->
+    [one] { $count } new item
+   *[other] { $count } new items
+}
+show-more = Show more
+only-showing = Only showing { $name }
+only-showing-folder = Only showing the folder { $name }
+show-every-feed = Show every feed
+starred = Starred
+dead-link = The link is dead
+updated = Updated
+listen = Listen
+share = Share
+star = Star
+unstar = Unstar
+junk = Junk
+translate = Translate
+show-original = Show original
+export-pdf = Export PDF
+open-original = Open original
+more-from-feed = More from this feed
+snooze = Snooze:
+snooze-tonight = Tonight
+snooze-tomorrow = Tomorrow
+snooze-next-week = Next week
+
+## Notes and highlights
+
+notes-title = Notes
+highlight-selection = Highlight selection
+remove-highlight = Remove
+note-placeholder = Add a private note…
+
+## Reading aloud
+
+listening = Listening:
+pause = Pause
+resume = Resume
+skip = Skip
+stop = Stop
+auto-advance = Auto-advance
+
+## Catch-up sessions
+
+session-empty = Nothing unread fits, you are caught up.
+session-progress = { $read } of { $total } read, about { $minutes } min
+end-session = End session
+
+## Reading queue
+
+queue-title = Read next
+queue-empty = Nothing queued. Add entries with "Read next".
+read-next = Read next
+queue-previous = Previous
+queue-next = Next
+queue-done = Done
+queue-remove = Remove from queue
+close = Close
+
+## Errors
+
+retry = Retry
+dismiss = Dismiss
+connection-lost = Lost the connection to the server.
+not-connected = Not connected to the server, the change wasn't sent.
+failed-connect = Couldn't connect to the server
+bad-message = Couldn't read a message of the server
+failed-storage = Couldn't keep the entries in the browser, its storage may be full.
+failed-settings = Couldn't load the settings
+failed-save-settings = Couldn't save the settings
+failed-feeds = Couldn't load the feeds
+failed-add-feed = Couldn't add the feed
+failed-feed-order = Couldn't load the folders
+failed-save-feed-order = Couldn't save the folders
+failed-queue = Couldn't load the reading queue
+failed-save-queue = Couldn't save the reading queue
+failed-session = Couldn't start a catch-up session
+failed-translate = Couldn't translate the entry
+failed-share = Couldn't share the entry
+failed-copy-link = Couldn't copy the link
+failed-annotate = Couldn't save the notes
+
+## Settings
+
+language = Language
+language-browser = Same as the browser
+timezone = Time zone
+timezone-browser = Same as the browser
+
+## Dates
+
+just-now = just now
+minutes-ago = { $count ->
+    [one] 1 minute ago
+   *[other] { $count } minutes ago
+}
+hours-ago = { $count ->
+    [one] 1 hour ago
+   *[other] { $count } hours ago
+}
+days-ago = { $count ->
+    [one] 1 day ago
+   *[other] { $count } days ago
+}
+yesterday-relative = yesterday
+today = Today
+yesterday = Yesterday
+day = { $month } { $day }
+day-of-year = { $month } { $day }, { $year }
+date = { $month } { $day }, { $year }
+month-1 = January
+month-2 = February
+month-3 = March
+month-4 = April
+month-5 = May
+month-6 = June
+month-7 = July
+month-8 = August
+month-9 = September
+month-10 = October
+month-11 = November
+month-12 = December
+month-short-1 = Jan
+month-short-2 = Feb
+month-short-3 = Mar
+month-short-4 = Apr
+month-short-5 = May
+month-short-6 = Jun
+month-short-7 = Jul
+month-short-8 = Aug
+month-short-9 = Sep
+month-short-10 = Oct
+month-short-11 = Nov
+month-short-12 = Dec
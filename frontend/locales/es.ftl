@@ -0,0 +1,166 @@
+## Navigation
+
+nav-discover = Descubrir
+nav-digest = Resumen
+nav-queue = Cola ({ $count })
+nav-mutes = Silenciados
+nav-settings = Ajustes
+nav-label = Navegación principal
+
+## Footer
+
+items-left = { $count ->
+    [one] Queda { $count } artículo
+   *[other] Quedan { $count } artículos
+}
+fetch-news = Obtener noticias
+loading-connecting = Conectando…
+loading-refreshing = Actualizando fuentes…
+loading-progress = Actualizando { $done }/{ $total } fuentes…
+cancel-refresh = Cancelar
+loading-failed = Sin conexión
+filter-all = Todos
+filter-read = Leídos
+filter-unread = Sin leer
+filter-low-quality = Baja calidad
+filter-priority = Prioridad
+catch-up = Ponme al día en
+catch-up-minutes = { $minutes } min
+
+## Entries
+
+entries-label = Entradas
+new-items = { $count ->
+    [one] { $count } entrada nueva
+   *[other] { $count } entradas nuevas
+}
+show-more = Mostrar más
+only-showing = Mostrando solo { $name }
+only-showing-folder = Mostrando solo la carpeta { $name }
+show-every-feed = Mostrar todas las fuentes
+starred = Destacado
+dead-link = El enlace está roto
+updated = Actualizado
+listen = Escuchar
+share = Compartir
+star = Destacar
+unstar = Quitar de destacados
+junk = Basura
+translate = Traducir
+show-original = Mostrar original
+export-pdf = Exportar PDF
+open-original = Abrir original
+more-from-feed = Más de esta fuente
+snooze = Posponer:
+snooze-tonight = Esta noche
+snooze-tomorrow = Mañana
+snooze-next-week = La próxima semana
+
+## Notes and highlights
+
+notes-title = Notas
+highlight-selection = Resaltar la selección
+remove-highlight = Quitar
+note-placeholder = Añadir una nota privada…
+
+## Reading aloud
+
+listening = Escuchando:
+pause = Pausar
+resume = Reanudar
+skip = Saltar
+stop = Detener
+auto-advance = Avanzar automáticamente
+
+## Catch-up sessions
+
+session-empty = No cabe nada sin leer, estás al día.
+session-progress = { $read } de { $total } leídos, unos { $minutes } min
+end-session = Terminar sesión
+
+## Reading queue
+
+queue-title = Leer después
+queue-empty = La cola está vacía. Añade artículos con «Leer después».
+read-next = Leer después
+queue-previous = Anterior
+queue-next = Siguiente
+queue-done = Hecho
+queue-remove = Quitar de la cola
+close = Cerrar
+
+## Errors
+
+retry = Reintentar
+dismiss = Cerrar
+connection-lost = Se perdió la conexión con el servidor.
+not-connected = Sin conexión con el servidor, el cambio no se envió.
+failed-connect = No se pudo conectar con el servidor
+bad-message = No se pudo leer un mensaje del servidor
+failed-storage = No se pudieron guardar las entradas en el navegador, puede que su almacenamiento esté lleno.
+failed-settings = No se pudieron cargar los ajustes
+failed-save-settings = No se pudieron guardar los ajustes
+failed-feeds = No se pudieron cargar las fuentes
+failed-add-feed = No se pudo añadir la fuente
+failed-feed-order = No se pudieron cargar las carpetas
+failed-save-feed-order = No se pudieron guardar las carpetas
+failed-queue = No se pudo cargar la cola de lectura
+failed-save-queue = No se pudo guardar la cola de lectura
+failed-session = No se pudo empezar una sesión de lectura
+failed-translate = No se pudo traducir la entrada
+failed-share = No se pudo compartir la entrada
+failed-copy-link = No se pudo copiar el enlace
+failed-annotate = No se pudieron guardar las notas
+
+## Settings
+
+language = Idioma
+language-browser = El del navegador
+timezone = Zona horaria
+timezone-browser = La del navegador
+
+## Dates
+
+just-now = ahora mismo
+minutes-ago = { $count ->
+    [one] hace 1 minuto
+   *[other] hace { $count } minutos
+}
+hours-ago = { $count ->
+    [one] hace 1 hora
+   *[other] hace { $count } horas
+}
+days-ago = { $count ->
+    [one] hace 1 día
+   *[other] hace { $count } días
+}
+yesterday-relative = ayer
+today = Hoy
+yesterday = Ayer
+day = { $day } de { $month }
+day-of-year = { $day } de { $month } de { $year }
+date = { $day } { $month } { $year }
+month-1 = enero
+month-2 = febrero
+month-3 = marzo
+month-4 = abril
+month-5 = mayo
+month-6 = junio
+month-7 = julio
+month-8 = agosto
+month-9 = septiembre
+month-10 = octubre
+month-11 = noviembre
+month-12 = diciembre
+month-short-1 = ene
+month-short-2 = feb
+month-short-3 = mar
+month-short-4 = abr
+month-short-5 = may
+month-short-6 = jun
+month-short-7 = jul
+month-short-8 = ago
+month-short-9 = sept
+month-short-10 = oct
+month-short-11 = nov
+month-short-12 = dic
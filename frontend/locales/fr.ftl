@@ -0,0 +1,166 @@
+## Navigation
+
+nav-discover = Découvrir
+nav-digest = Résumé
+nav-queue = File ({ $count })
+nav-mutes = Sourdines
+nav-settings = Réglages
+nav-label = Navigation principale
+
+## Footer
+
+items-left = { $count ->
+    [one] { $count } article restant
+   *[other] { $count } articles restants
+}
+fetch-news = Récupérer les actualités
+loading-connecting = Connexion…
+loading-refreshing = Actualisation des flux…
+loading-progress = Actualisation de { $done }/{ $total } flux…
+cancel-refresh = Annuler
+loading-failed = Hors ligne
+filter-all = Tous
+filter-read = Lus
+filter-unread = Non lus
+filter-low-quality = Faible qualité
+filter-priority = Priorité
+catch-up = Me mettre à jour en
+catch-up-minutes = { $minutes } min
+
+## Entries
+
+entries-label = Articles
+new-items = { $count ->
+    [one] { $count } nouvel article
+   *[other] { $count } nouveaux articles
+}
+show-more = Afficher plus
+only-showing = Seulement { $name }
+only-showing-folder = Seulement le dossier { $name }
+show-every-feed = Afficher tous les flux
+starred = Favori
+dead-link = Le lien est mort
+updated = Modifié
+listen = Écouter
+share = Partager
+star = Favori
+unstar = Retirer des favoris
+junk = Indésirable
+translate = Traduire
+show-original = Afficher l’original
+export-pdf = Exporter en PDF
+open-original = Ouvrir l’original
+more-from-feed = Plus de ce flux
+snooze = Reporter :
+snooze-tonight = Ce soir
+snooze-tomorrow = Demain
+snooze-next-week = La semaine prochaine
+
+## Notes and highlights
+
+notes-title = Notes
+highlight-selection = Surligner la sélection
+remove-highlight = Retirer
+note-placeholder = Ajouter une note privée…
+
+## Reading aloud
+
+listening = Lecture :
+pause = Pause
+resume = Reprendre
+skip = Passer
+stop = Arrêter
+auto-advance = Enchaîner
+
+## Catch-up sessions
+
+session-empty = Aucun article non lu ne tient, vous êtes à jour.
+session-progress = { $read } sur { $total } lus, environ { $minutes } min
+end-session = Terminer la session
+
+## Reading queue
+
+queue-title = À lire ensuite
+queue-empty = La file est vide. Ajoutez des articles avec « À lire ensuite ».
+read-next = À lire ensuite
+queue-previous = Précédent
+queue-next = Suivant
+queue-done = Terminé
+queue-remove = Retirer de la file
+close = Fermer
+
+## Errors
+
+retry = Réessayer
+dismiss = Fermer
+connection-lost = La connexion au serveur a été perdue.
+not-connected = Pas de connexion au serveur, la modification n’a pas été envoyée.
+failed-connect = Impossible de se connecter au serveur
+bad-message = Impossible de lire un message du serveur
+failed-storage = Impossible de garder les articles dans le navigateur, son stockage est peut-être plein.
+failed-settings = Impossible de charger les réglages
+failed-save-settings = Impossible d’enregistrer les réglages
+failed-feeds = Impossible de charger les flux
+failed-add-feed = Impossible d’ajouter le flux
+failed-feed-order = Impossible de charger les dossiers
+failed-save-feed-order = Impossible d’enregistrer les dossiers
+failed-queue = Impossible de charger la file de lecture
+failed-save-queue = Impossible d’enregistrer la file de lecture
+failed-session = Impossible de commencer une session de lecture
+failed-translate = Impossible de traduire l’article
+failed-share = Impossible de partager l’article
+failed-copy-link = Impossible de copier le lien
+failed-annotate = Impossible d’enregistrer les notes
+
+## Settings
+
+language = Langue
+language-browser = Celle du navigateur
+timezone = Fuseau horaire
+timezone-browser = Celui du navigateur
+
+## Dates
+
+just-now = à l’instant
+minutes-ago = { $count ->
+    [one] il y a 1 minute
+   *[other] il y a { $count } minutes
+}
+hours-ago = { $count ->
+    [one] il y a 1 heure
+   *[other] il y a { $count } heures
+}
+days-ago = { $count ->
+    [one] il y a 1 jour
+   *[other] il y a { $count } jours
+}
+yesterday-relative = hier
+today = Aujourd’hui
+yesterday = Hier
+day = { $day } { $month }
+day-of-year = { $day } { $month } { $year }
+date = { $day } { $month } { $year }
+month-1 = janvier
+month-2 = février
+month-3 = mars
+month-4 = avril
+month-5 = mai
+month-6 = juin
+month-7 = juillet
+month-8 = août
+month-9 = septembre
+month-10 = octobre
+month-11 = novembre
+month-12 = décembre
+month-short-1 = janv.
+month-short-2 = févr.
+month-short-3 = mars
+month-short-4 = avr.
+month-short-5 = mai
+month-short-6 = juin
+month-short-7 = juil.
+month-short-8 = août
+month-short-9 = sept.
+month-short-10 = oct.
+month-short-11 = nov.
+month-short-12 = déc.
@@ -0,0 +1,82 @@
+//! Highlights the user made in the text of entries.
+//!
+//! Highlights are ranges of characters of the text of the body shown in the reading pane, which
+//! the server stores as is.
+
+use crate::protocol::Highlight;
+
+/// Opening tag of a highlight.
+const MARK: &str = "<mark class=\"annotation\">";
+/// Longest entity counted as a single character, e.g. `&thetasym;`.
+const MAX_ENTITY_LEN: usize = 10;
+
+/// Wrap the highlighted characters of the text of sanitized HTML in `<mark>`, closing and reopening
+/// the marks around tags so that the markup stays balanced. Entities count as one character, like
+/// in the text of the rendered document.
+pub fn markup(html: &str, highlights: &[Highlight]) -> String {
+    let mut marked = String::with_capacity(html.len() + highlights.len() * 2 * MARK.len());
+    let mut highlights = highlights.iter().peekable();
+    let (mut pos, mut offset, mut open) = (0, 0, false);
+    while pos < html.len() {
+        let rest = &html[pos..];
+        if rest.starts_with('<') {
+            let len = rest.find('>').map_or(rest.len(), |end| end + 1);
+            if open {
+                marked.push_str("</mark>");
+            }
+            marked.push_str(&rest[..len]);
+            if open {
+                marked.push_str(MARK);
+            }
+            pos += len;
+            continue;
+        }
+
+        let len = match rest.find(';') {
+            Some(end) if rest.starts_with('&') && end <= MAX_ENTITY_LEN => end + 1,
+            _ => rest.chars().next().map_or(1, char::len_utf8),
+        };
+        while highlights.peek().map_or(false, |highlight| highlight.end <= offset) {
+            highlights.next();
+        }
+        let inside = highlights
+            .peek()
+            .map_or(false, |highlight| highlight.start <= offset);
+        if inside != open {
+            marked.push_str(if inside { MARK } else { "</mark>" });
+            open = inside;
+        }
+        marked.push_str(&rest[..len]);
+        pos += len;
+        offset += 1;
+    }
+    if open {
+        marked.push_str("</mark>");
+    }
+    marked
+}
+
+/// Return the range of characters of the text of the element `container` the user selected,
+/// along with the text selected, if the selection lies within it.
+pub fn selection(container: &str) -> Option<(usize, usize, String)> {
+    let document = yew::utils::document();
+    let container = document.get_element_by_id(container)?;
+    let selection = web_sys::window()?.get_selection().ok()??;
+    if selection.range_count() == 0 || selection.is_collapsed() {
+        return None;
+    }
+    let range = selection.get_range_at(0).ok()?;
+    if !container.contains(Some(&range.common_ancestor_container().ok()?)) {
+        return None;
+    }
+    // The text from the start of the container to the selection gives its offset.
+    let before = document.create_range().ok()?;
+    before.select_node_contents(&container).ok()?;
+    before
+        .set_end(&range.start_container().ok()?, range.start_offset().ok()?)
+        .ok()?;
+    let start = String::from(before.to_string()).chars().count();
+    let text = String::from(range.to_string());
+    let end = start + text.chars().count();
+    Some((start, end, text))
+}
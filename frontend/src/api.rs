@@ -0,0 +1,81 @@
+//! Helpers for calling the backend REST API.
+
+use anyhow::Error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use yew::callback::Callback;
+use yew::format::{Json, Nothing, Text};
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+
+/// Address of the backend.
+pub const SERVER_URL: &str = "http://localhost:9001";
+/// Address of the backend REST API.
+pub const API_URL: &str = "http://localhost:9001/api";
+
+/// `GET` a JSON resource from the backend.
+pub fn get<T>(path: &str, callback: Callback<Result<T, Error>>) -> Result<FetchTask, Error>
+where
+    T: DeserializeOwned + 'static,
+{
+    let request = Request::get(format!("{}{}", API_URL, path)).body(Nothing)?;
+    FetchService::new().fetch(request, handler(callback))
+}
+
+/// `POST` to the backend without a body and decode the JSON response.
+pub fn post<T>(path: &str, callback: Callback<Result<T, Error>>) -> Result<FetchTask, Error>
+where
+    T: DeserializeOwned + 'static,
+{
+    let request = Request::post(format!("{}{}", API_URL, path)).body(Nothing)?;
+    FetchService::new().fetch(request, handler(callback))
+}
+
+/// `POST` a JSON body to the backend and decode the JSON response.
+pub fn post_json<B, T>(
+    path: &str,
+    body: &B,
+    callback: Callback<Result<T, Error>>,
+) -> Result<FetchTask, Error>
+where
+    B: Serialize,
+    T: DeserializeOwned + 'static,
+{
+    let request = Request::post(format!("{}{}", API_URL, path))
+        .header("Content-Type", "application/json")
+        .body(Json(body))?;
+    FetchService::new().fetch(request, handler(callback))
+}
+
+/// `PUT` a JSON body to the backend and decode the JSON response.
+pub fn put<B, T>(
+    path: &str,
+    body: &B,
+    callback: Callback<Result<T, Error>>,
+) -> Result<FetchTask, Error>
+where
+    B: Serialize,
+    T: DeserializeOwned + 'static,
+{
+    let request = Request::put(format!("{}{}", API_URL, path))
+        .header("Content-Type", "application/json")
+        .body(Json(body))?;
+    FetchService::new().fetch(request, handler(callback))
+}
+
+/// Turn a response into the decoded body, treating non-success statuses as errors explained by
+/// the body, e.g. why the backend rejected a request.
+fn handler<T>(callback: Callback<Result<T, Error>>) -> Callback<Response<Text>>
+where
+    T: DeserializeOwned + 'static,
+{
+    Callback::from(move |response: Response<Text>| {
+        let (meta, body) = response.into_parts();
+        if meta.status.is_success() {
+            callback.emit(body.and_then(|body| Ok(serde_json::from_str(&body)?)));
+        } else {
+            let reason = body.ok().filter(|body| !body.trim().is_empty());
+            let reason = reason.unwrap_or_else(|| format!("request failed: {}", meta.status));
+            callback.emit(Err(Error::msg(reason)));
+        }
+    })
+}
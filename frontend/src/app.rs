@@ -1,45 +1,269 @@
 use anyhow::Error;
 use log::*;
+use news_core::timezone;
 use serde_derive::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 use strum_macros::{EnumIter, ToString};
+use wasm_bindgen::JsCast;
+use web_sys::{DragEvent, HtmlElement};
 use yew::format::Json;
 use yew::prelude::*;
+use yew::services::fetch::FetchTask;
+use yew::services::interval::{IntervalService, IntervalTask};
+use yew::services::keyboard::{KeyListenerHandle, KeyboardService};
 use yew::services::storage::{Area, StorageService};
 use yew::services::websocket::{WebSocketService, WebSocketStatus, WebSocketTask};
 
-use std::path::PathBuf;
+use crate::annotations;
+use crate::api;
+use crate::digest::DigestView;
+use crate::direct::{self, Unsynced};
+use crate::discover::DiscoverView;
+use crate::highlight;
+use crate::i18n::{self, tr, tr_args};
+use crate::lazy_image::LazyImage;
+use crate::mutes::MutesView;
+use crate::palette::{Command, Palette, Target};
+use crate::protocol::{
+    self, Annotations, Change, ChangeKind, ClientMessage, Encoding, Frame, Highlight, ItemDto,
+    ItemPatch, RefreshProgress, ServerMessage, Session, Translation,
+};
+use crate::settings::{select_value, Settings, SettingsView, Theme};
+use crate::share;
+use crate::sidebar::{FeedOrder, Folder, Sidebar};
+use crate::speech::{self, Speaker};
+use crate::theme;
+use crate::time::{self, Snooze};
+use crate::toast::{Retry, Toast, Toasts};
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::time::Duration;
 
 const KEY: &str = "be4k.news.self";
+/// Key of the changes waiting to be confirmed by the server in local storage.
+const PENDING_KEY: &str = "be4k.news.pending";
+/// Key of the items fetched from the browser that the server wasn't sent yet in local storage.
+const UNSYNCED_KEY: &str = "be4k.news.unsynced";
+/// Key of the subscribed feeds in local storage, fetched from the browser when the server is
+/// unreachable.
+const FEEDS_KEY: &str = "be4k.news.feeds";
+/// Key of the token authorizing the websocket in local storage.
+const TOKEN_KEY: &str = "be4k.news.token";
 
-type AsBinary = bool;
+/// Address of the backend websocket.
+const WS_URL: &str = "ws://localhost:9001/ws/";
+/// Encoding requested from the backend for pushed messages.
+const WS_ENCODING: Encoding = Encoding::Msgpack;
+/// How often relative timestamps are recomputed.
+const CLOCK_INTERVAL: Duration = Duration::from_secs(60);
+/// Lengths of the catch-up sessions offered, in minutes.
+const CATCH_UP_MINUTES: [u32; 3] = [5, 10, 20];
+/// Most toasts shown at once, older ones make room for new ones.
+const MAX_TOASTS: usize = 5;
+/// Placeholder entries shown while the list is loading.
+const SKELETON_ENTRIES: usize = 5;
 
 pub struct App {
     link: ComponentLink<Self>,
-    storage: StorageService,
     state: State,
     ws_service: WebSocketService,
     ws: Option<WebSocketTask>,
+    /// Whether the websocket finished opening and can be sent messages.
+    ws_open: bool,
+    /// Token authorizing the websocket, if the server requires one.
+    token: Option<String>,
+    settings: Settings,
+    settings_task: Option<FetchTask>,
+    /// URLs of the configured feeds.
+    feeds: Vec<String>,
+    feeds_task: Option<FetchTask>,
+    /// Fetches of the feeds from the browser while the server is unreachable.
+    direct_tasks: Vec<FetchTask>,
+    /// Number of those fetches still running.
+    direct_running: usize,
+    /// Folders and order of the feeds in the sidebar, synced with the server.
+    feed_order: FeedOrder,
+    feed_order_task: Option<FetchTask>,
+    /// Time relative timestamps are computed against.
+    now: chrono::DateTime<chrono::Utc>,
+    _clock: IntervalTask,
+    /// Whether the quick switcher is open.
+    palette: bool,
+    _keys: KeyListenerHandle,
+    /// Reads articles aloud, `None` when the browser can't.
+    speaker: Option<Speaker>,
+    translate_task: Option<FetchTask>,
+    annotations_task: Option<FetchTask>,
+    session_task: Option<FetchTask>,
+    /// Ids of the entries to read next, in the order the user chose, synced with the server.
+    queue: Vec<String>,
+    queue_task: Option<FetchTask>,
+    /// Position in the queue of the entry being dragged.
+    dragging: Option<usize>,
+    /// Id of the entry to resume reading from once it is received.
+    resume: Option<String>,
+    /// Id of the entry to scroll into view after the next render.
+    scroll_to: Option<String>,
+    /// Id of the entry last focused, the one reached with Tab in the list.
+    focused: Option<String>,
+    /// Id of the entry to focus after the next render.
+    focus_to: Option<String>,
+    /// Latest message read out by screen readers.
+    announcement: String,
+    /// Failures shown to the user, oldest first.
+    toasts: Vec<Toast>,
+    /// Id of the latest toast.
+    toast_id: usize,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct State {
     entries: Vec<Entry>,
     filter: Filter,
+    /// Feed whose entries only are listed, including the ones left out of the river.
+    feed: Option<String>,
+    /// Folder whose feeds' entries only are listed.
+    folder: Option<Folder>,
+    view: View,
+    /// Number of pages of entries shown.
+    pages: usize,
+    /// Ids of the entries expanded in the list.
+    expanded: HashSet<String>,
+    /// Id of the entry whose share menu is open.
+    sharing: Option<String>,
+    /// The entry being read aloud.
+    listening: Option<Listening>,
+    /// Continue with the next unread entry once one has been read aloud.
+    auto_advance: bool,
+    /// Translations shown in place of the original text, keyed by entry id.
+    translations: HashMap<String, Translation>,
+    /// Ids of the edited entries whose changes are shown.
+    diffing: HashSet<String>,
+    /// Changes shown right away that the server didn't confirm yet, oldest first.
+    pending: Vec<PendingChange>,
+    /// Items fetched from the browser while the server was unreachable, sent to it once it is
+    /// back.
+    unsynced: Vec<Unsynced>,
+    /// Catch-up session listed in place of the entries until it is ended.
+    session: Option<Session>,
+    /// What the list is waiting for from the server.
+    #[serde(skip)]
+    loading: Loading,
+}
+
+/// Progress of the websocket towards fresh entries.
+#[derive(Debug, Clone, PartialEq)]
+enum Loading {
+    Idle,
+    /// The websocket is opening.
+    Connecting,
+    /// The websocket is open and the feeds are being fetched, how far once the server told.
+    Refreshing(Option<RefreshProgress>),
+    /// The websocket failed to open or was lost.
+    Failed,
+}
+
+impl Default for Loading {
+    fn default() -> Self {
+        Loading::Idle
+    }
+}
+
+impl Loading {
+    fn busy(&self) -> bool {
+        match self {
+            Loading::Connecting | Loading::Refreshing(_) => true,
+            Loading::Idle | Loading::Failed => false,
+        }
+    }
+}
+
+/// Feed subscribed to through `POST /api/feeds`.
+#[derive(Debug, Serialize)]
+struct NewFeed {
+    url: String,
+}
+
+/// Answer of `POST /api/feeds`.
+#[derive(Debug, Deserialize)]
+pub struct FeedAdded {
+    /// Whether the feed wasn't subscribed to already.
+    added: bool,
+}
+
+/// Read or starred state changed locally, sent again on every connection until the server
+/// reflects it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PendingChange {
+    id: String,
+    /// Whether the entry was marked read.
+    read: bool,
+    starred: Option<bool>,
+    /// Time of the latest change, which the server keeps unless the entry changed later
+    /// elsewhere.
+    at: chrono::DateTime<chrono::Utc>,
+    /// Whether the change was sent on the current connection.
+    #[serde(skip)]
+    sent: bool,
+}
+
+impl PendingChange {
+    /// Whether the server state of the entry already reflects the change.
+    fn settled_by(&self, read: bool, starred: bool) -> bool {
+        (!self.read || read) && self.starred.map_or(true, |s| s == starred)
+    }
+
+    /// Messages telling the server about the change, sent at `now`.
+    fn messages(&self, now: chrono::DateTime<chrono::Utc>) -> Vec<ClientMessage> {
+        let mut messages = Vec::new();
+        if self.read {
+            messages.push(ClientMessage::MarkRead {
+                id: self.id.clone(),
+                at: self.at,
+                sent_at: now,
+            });
+        }
+        if let Some(starred) = self.starred {
+            messages.push(ClientMessage::Star {
+                id: self.id.clone(),
+                starred,
+                at: self.at,
+                sent_at: now,
+            });
+        }
+        messages
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Listening {
+    id: String,
+    /// Fraction of the entry read so far.
+    progress: f64,
+    paused: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum View {
+    News,
+    Settings,
+    Discover,
+    Mutes,
+    Digest,
+    Queue,
 }
 
 #[derive(Serialize, Deserialize)]
 struct Entry {
-    item: rss::Item,
-    pub image_path: Option<PathBuf>,
-    pub_date: Option<chrono::DateTime<chrono::FixedOffset>>,
+    item: ItemDto,
     read: bool,
 }
 
 #[derive(Debug)]
 pub enum WsAction {
     Connect,
-    SendData(AsBinary),
     Disconnect,
     Lost,
 }
@@ -47,9 +271,62 @@ pub enum WsAction {
 #[derive(Debug)]
 pub enum Msg {
     Read(usize),
+    ToggleStar(usize),
+    Toggle(usize),
+    FocusEntry(usize),
+    EntryKey(usize, KeyboardEvent),
+    ToggleShare(usize),
+    ShareNative(usize),
+    CopyLink(usize),
+    Snooze(usize, Snooze),
+    Translate(usize),
+    ToggleChanges(usize),
+    MarkJunk(usize),
+    Highlight(usize),
+    RemoveHighlight(usize, usize),
+    SetNote(usize, String),
+    AnnotationsSaved(String, Result<Annotations, Error>),
+    Translated(String, Result<Translation, Error>),
+    Listen(usize),
+    PauseListening,
+    SkipListening,
+    StopListening,
+    ToggleAutoAdvance,
+    SpeechProgress(f64),
+    SpeechEnded,
     SetFilter(Filter),
+    SetFeed(Option<String>),
+    Key(KeyboardEvent),
+    Jump(Target),
+    ClosePalette,
+    SetView(View),
+    ShowMore,
+    QueueLoaded(Result<Vec<String>, Error>),
+    QueueSaved(Result<Vec<String>, Error>),
+    ToggleQueued(usize),
+    QueuePrevious(usize),
+    QueueNext(usize),
+    DragQueued(usize),
+    DropQueued(usize),
+    CatchUp(u32),
+    SessionLoaded(Result<Session, Error>),
+    EndSession,
+    SettingsLoaded(Result<Settings, Error>),
+    SettingsSaved(Settings, Result<Settings, Error>),
+    FeedsLoaded(Result<Vec<String>, Error>),
+    FetchedDirectly(Result<Vec<news_core::item::NewsItem>, Error>),
+    FeedAdded(Result<FeedAdded, Error>),
+    FeedOrderLoaded(Result<FeedOrder, Error>),
+    SetFeedOrder(FeedOrder),
+    FeedOrderSaved(FeedOrder, Result<FeedOrder, Error>),
+    DismissToast(usize),
+    RetryToast(usize),
+    Tick,
+    SaveSettings(Settings),
+    CancelRefresh,
     WsAction(WsAction),
-    WsReady(Result<WsResponse, Error>),
+    WsOpened,
+    WsReady(Result<ServerMessage, Error>),
     Ignore,
 }
 
@@ -59,24 +336,13 @@ impl From<WsAction> for Msg {
     }
 }
 
-/// This type is used as a request which sent to websocket connection.
-#[derive(Serialize, Debug)]
-struct WsRequest {
-    value: u32,
-}
-
-/// This type is an expected response from a websocket connection.
-#[derive(Deserialize, Debug)]
-pub struct WsResponse {
-    value: u32,
-}
-
 impl Component for App {
     type Message = Msg;
     type Properties = ();
 
     fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let storage = StorageService::new(Area::Local).unwrap();
+        let mut storage = StorageService::new(Area::Local).unwrap();
+        let token = ws_token(&mut storage);
         let entries = {
             if let Json(Ok(restored_entries)) = storage.restore(KEY) {
                 restored_entries
@@ -84,114 +350,773 @@ impl Component for App {
                 Vec::new()
             }
         };
+        let pending = match storage.restore(PENDING_KEY) {
+            Json(Ok(pending)) => pending,
+            _ => Vec::new(),
+        };
+        let unsynced = match storage.restore(UNSYNCED_KEY) {
+            Json(Ok(unsynced)) => unsynced,
+            _ => Vec::new(),
+        };
+        // Replaced by the feeds the server lists, if it is reachable.
+        let feeds = match storage.restore(FEEDS_KEY) {
+            Json(Ok(feeds)) => feeds,
+            _ => Vec::new(),
+        };
         let state = State {
             entries,
             filter: Filter::All,
+            feed: None,
+            folder: None,
+            view: View::News,
+            pages: 1,
+            expanded: HashSet::new(),
+            sharing: None,
+            listening: None,
+            auto_advance: false,
+            translations: HashMap::new(),
+            diffing: HashSet::new(),
+            pending,
+            unsynced,
+            session: None,
+            loading: Loading::Idle,
         };
+        let settings_task = api::get("/settings", link.callback(Msg::SettingsLoaded))
+            .map_err(|e| log::error!("failed to request settings: {}", e))
+            .ok();
+        let feeds_task = api::get("/feeds", link.callback(Msg::FeedsLoaded))
+            .map_err(|e| log::error!("failed to request feeds: {}", e))
+            .ok();
+        let feed_order_task = api::get("/feeds/order", link.callback(Msg::FeedOrderLoaded))
+            .map_err(|e| log::error!("failed to request the feed order: {}", e))
+            .ok();
+        let queue_task = api::get("/queue", link.callback(Msg::QueueLoaded))
+            .map_err(|e| log::error!("failed to request the queue: {}", e))
+            .ok();
+        let clock = IntervalService::new().spawn(CLOCK_INTERVAL, link.callback(|_| Msg::Tick));
+        let document = yew::utils::document();
+        let keys = KeyboardService::register_key_down(&document, link.callback(Msg::Key));
+        let speaker = Speaker::new(
+            link.callback(Msg::SpeechProgress),
+            link.callback(|_| Msg::SpeechEnded),
+        );
         App {
             link,
-            storage,
             state,
             ws_service: WebSocketService::new(),
             ws: None,
+            ws_open: false,
+            token,
+            settings: Settings::default(),
+            settings_task,
+            feeds,
+            feeds_task,
+            direct_tasks: Vec::new(),
+            direct_running: 0,
+            feed_order: FeedOrder::default(),
+            feed_order_task,
+            now: time::now(),
+            _clock: clock,
+            palette: false,
+            _keys: keys,
+            speaker,
+            translate_task: None,
+            annotations_task: None,
+            session_task: None,
+            queue: Vec::new(),
+            queue_task,
+            dragging: None,
+            resume: None,
+            scroll_to: None,
+            focused: None,
+            focus_to: None,
+            announcement: String::new(),
+            toasts: Vec::new(),
+            toast_id: 0,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
             Msg::Read(idx) => {
-                self.state.read(idx);
+                let listed = listed_ids();
+                self.mark_read(idx);
+                self.restore_focus(idx, &listed);
+            }
+            Msg::ToggleStar(idx) => {
+                let starred = !self.state.entries[idx].item.starred;
+                self.change(idx, false, Some(starred));
+            }
+            Msg::Toggle(idx) => {
+                let expanded = self.state.toggle(idx);
+                self.focused = Some(self.state.entries[idx].item.id.clone());
+                if expanded {
+                    let id = self.state.entries[idx].item.id.clone();
+                    self.ws_send(&ClientMessage::Position { id });
+                    if self.settings.mark_read_on_expand {
+                        self.mark_read(idx);
+                    }
+                }
+            }
+            Msg::FocusEntry(idx) => {
+                let id = &self.state.entries[idx].item.id;
+                if self.focused.as_ref() == Some(id) {
+                    return false;
+                }
+                self.focused = Some(id.clone());
+            }
+            Msg::EntryKey(idx, e) => {
+                // Keys pressed on the buttons and links of an entry keep their usual meaning.
+                if e.target() != e.current_target() {
+                    return false;
+                }
+                let id = &self.state.entries[idx].item.id;
+                let ids = listed_ids();
+                let pos = ids.iter().position(|listed| listed == id);
+                let target = match e.key().as_str() {
+                    "ArrowDown" => pos.and_then(|pos| ids.get(pos + 1)),
+                    "ArrowUp" => pos.filter(|&pos| pos > 0).and_then(|pos| ids.get(pos - 1)),
+                    "Home" => ids.first(),
+                    "End" => ids.last(),
+                    "Enter" | " " => {
+                        e.prevent_default();
+                        self.focus_to = Some(id.clone());
+                        return self.update(Msg::Toggle(idx));
+                    }
+                    _ => return false,
+                };
+                e.prevent_default();
+                match target {
+                    Some(target) => self.focus(target.clone()),
+                    None => return false,
+                }
+            }
+            Msg::ToggleShare(idx) => {
+                let id = self.state.entries[idx].item.id.clone();
+                if self.state.sharing.as_ref() == Some(&id) {
+                    self.state.sharing = None;
+                } else {
+                    self.state.sharing = Some(id);
+                }
+            }
+            Msg::ShareNative(idx) => {
+                let item = &self.state.entries[idx].item;
+                let title = item.title.clone().unwrap_or_default();
+                let link = item.link.clone().unwrap_or_default();
+                if let Err(e) = share::native(&title, &link) {
+                    self.fail("failed-share", format!("{:?}", e), None);
+                }
+                self.state.sharing = None;
+                self.focus(self.state.entries[idx].item.id.clone());
+            }
+            Msg::CopyLink(idx) => {
+                let link = self.state.entries[idx].item.link.clone().unwrap_or_default();
+                if let Err(e) = share::copy(&link) {
+                    self.fail("failed-copy-link", format!("{:?}", e), None);
+                }
+                self.state.sharing = None;
+                self.focus(self.state.entries[idx].item.id.clone());
+            }
+            Msg::Snooze(idx, snooze) => {
+                let listed = listed_ids();
+                let entry = &mut self.state.entries[idx];
+                let until = snooze.until(time::now(), time::local_offset());
+                entry.item.snoozed_until = Some(until);
+                let id = entry.item.id.clone();
+                self.state.expanded.remove(&id);
+                self.ws_send(&ClientMessage::Snooze { id, until });
+                self.restore_focus(idx, &listed);
+            }
+            Msg::ToggleChanges(idx) => {
+                let id = self.state.entries[idx].item.id.clone();
+                if !self.state.diffing.remove(&id) {
+                    self.state.diffing.insert(id);
+                }
+            }
+            Msg::Translate(idx) => {
+                let id = self.state.entries[idx].item.id.clone();
+                // Translating an entry again shows the original.
+                if self.state.translations.remove(&id).is_none() {
+                    let path = format!("/items/{}/translate?to={}", id, browser_language());
+                    let callback = self
+                        .link
+                        .callback(move |result| Msg::Translated(id.clone(), result));
+                    self.translate_task = api::post(&path, callback)
+                        .map_err(|e| log::error!("failed to request translation: {}", e))
+                        .ok();
+                }
+            }
+            Msg::Translated(id, result) => {
+                self.translate_task = None;
+                match result {
+                    Ok(translation) => {
+                        self.state.translations.insert(id, translation);
+                    }
+                    Err(e) => self.fail("failed-translate", e, None),
+                }
+            }
+            Msg::MarkJunk(idx) => {
+                let listed = listed_ids();
+                let item = &mut self.state.entries[idx].item;
+                item.low_quality = true;
+                let id = item.id.clone();
+                self.state.expanded.remove(&id);
+                self.ws_send(&ClientMessage::MarkJunk { id });
+                self.restore_focus(idx, &listed);
+            }
+            Msg::Highlight(idx) => {
+                let body = body_anchor(&self.state.entries[idx].item.id);
+                let (start, end, text) = match annotations::selection(&body) {
+                    Some(selected) => selected,
+                    None => return false,
+                };
+                let mut annotations = self.annotations(idx);
+                // Highlighting over an earlier highlight replaces it.
+                annotations
+                    .highlights
+                    .retain(|highlight| highlight.end <= start || highlight.start >= end);
+                annotations.highlights.push(Highlight {
+                    start,
+                    end,
+                    text,
+                    note: None,
+                    created_at: time::now(),
+                });
+                annotations.highlights.sort_by_key(|highlight| highlight.start);
+                self.save_annotations(idx, annotations);
+            }
+            Msg::RemoveHighlight(idx, pos) => {
+                let mut annotations = self.annotations(idx);
+                annotations.highlights.remove(pos);
+                self.save_annotations(idx, annotations);
+            }
+            Msg::SetNote(idx, note) => {
+                let mut annotations = self.annotations(idx);
+                annotations.note = Some(note).filter(|note| !note.trim().is_empty());
+                self.save_annotations(idx, annotations);
+            }
+            Msg::AnnotationsSaved(id, result) => {
+                self.annotations_task = None;
+                match result {
+                    Ok(annotations) => {
+                        let entry = self.state.entries.iter_mut().find(|e| e.item.id == id);
+                        if let Some(entry) = entry {
+                            entry.item.annotations = Some(annotations).filter(|a| !a.is_empty());
+                        }
+                    }
+                    Err(e) => self.fail("failed-annotate", e, None),
+                }
+            }
+            Msg::Listen(idx) => {
+                self.listen(idx);
+            }
+            Msg::PauseListening => {
+                if let (Some(speaker), Some(listening)) =
+                    (self.speaker.as_ref(), self.state.listening.as_mut())
+                {
+                    if listening.paused {
+                        speaker.resume();
+                    } else {
+                        speaker.pause();
+                    }
+                    listening.paused = !listening.paused;
+                }
+            }
+            Msg::SkipListening => {
+                self.listen_next();
+            }
+            Msg::StopListening => {
+                if let Some(speaker) = self.speaker.as_mut() {
+                    speaker.cancel();
+                }
+                self.state.listening = None;
+            }
+            Msg::ToggleAutoAdvance => {
+                self.state.auto_advance = !self.state.auto_advance;
+            }
+            Msg::SpeechProgress(progress) => {
+                if let Some(listening) = self.state.listening.as_mut() {
+                    listening.progress = progress;
+                }
+            }
+            Msg::SpeechEnded => {
+                if self.state.auto_advance {
+                    self.listen_next();
+                } else {
+                    self.state.listening = None;
+                }
             }
             Msg::SetFilter(filter) => {
                 self.state.filter = filter;
+                self.state.pages = 1;
+            }
+            Msg::SetFeed(feed) => {
+                self.state.feed = feed;
+                self.state.folder = None;
+                self.state.pages = 1;
+            }
+            Msg::Key(e) => {
+                let shortcut = (e.ctrl_key() || e.meta_key()) && e.key().eq_ignore_ascii_case("k");
+                if !shortcut {
+                    return false;
+                }
+                // Browsers focus their address bar on Ctrl+K otherwise.
+                e.prevent_default();
+                self.palette = !self.palette;
+                return true;
+            }
+            Msg::Jump(target) => {
+                self.palette = false;
+                if let Target::Command(command) = target {
+                    return self.run(command);
+                }
+                self.state.view = View::News;
+                self.state.pages = 1;
+                match target {
+                    Target::Command(_) => {}
+                    Target::Feed(feed) => {
+                        self.state.feed = Some(feed);
+                        self.state.folder = None;
+                    }
+                    Target::Folder(name) => {
+                        let folders = &self.feed_order.folders;
+                        self.state.folder = folders.iter().find(|f| f.name == name).cloned();
+                        self.state.feed = None;
+                    }
+                }
+            }
+            Msg::ClosePalette => {
+                self.palette = false;
+                return true;
+            }
+            Msg::SetView(view) => {
+                self.state.view = view;
+            }
+            Msg::ShowMore => {
+                self.state.pages += 1;
+            }
+            Msg::QueueLoaded(result) => {
+                self.queue_task = None;
+                match result {
+                    Ok(queue) => self.queue = queue,
+                    Err(e) => self.fail("failed-queue", e, Some(Retry::LoadQueue)),
+                }
+            }
+            Msg::QueueSaved(result) => {
+                self.queue_task = None;
+                match result {
+                    Ok(queue) => self.queue = queue,
+                    Err(e) => self.fail("failed-save-queue", e, Some(Retry::SaveQueue)),
+                }
+            }
+            Msg::ToggleQueued(idx) => {
+                let id = &self.state.entries[idx].item.id;
+                match self.queue.iter().position(|queued| queued == id) {
+                    Some(pos) => {
+                        self.queue.remove(pos);
+                    }
+                    None => self.queue.push(id.clone()),
+                }
+                self.save_queue();
+            }
+            Msg::QueuePrevious(idx) => {
+                let id = &self.state.entries[idx].item.id;
+                let pos = self.queue.iter().position(|queued| queued == id);
+                if let Some(previous) = pos.filter(|pos| *pos > 0).map(|pos| pos - 1) {
+                    let previous = self.queue[previous].clone();
+                    self.read_queued(idx, previous);
+                }
+            }
+            Msg::QueueNext(idx) => {
+                // Reading on consumes the queue.
+                let id = self.state.entries[idx].item.id.clone();
+                self.mark_read(idx);
+                if let Some(pos) = self.queue.iter().position(|queued| *queued == id) {
+                    self.queue.remove(pos);
+                    self.save_queue();
+                    if let Some(next) = self.queue.get(pos).cloned() {
+                        self.read_queued(idx, next);
+                    }
+                }
+            }
+            Msg::DragQueued(pos) => {
+                self.dragging = Some(pos);
+                return false;
+            }
+            Msg::DropQueued(to) => {
+                let from = match self.dragging.take() {
+                    Some(from) if from != to && from < self.queue.len() => from,
+                    _ => return false,
+                };
+                let id = self.queue.remove(from);
+                self.queue.insert(to.min(self.queue.len()), id);
+                self.save_queue();
+            }
+            Msg::CatchUp(minutes) => {
+                let path = format!("/catch_up?minutes={}", minutes);
+                self.session_task = api::get(&path, self.link.callback(Msg::SessionLoaded))
+                    .map_err(|e| log::error!("failed to request a catch-up session: {}", e))
+                    .ok();
+            }
+            Msg::SessionLoaded(result) => {
+                self.session_task = None;
+                match result {
+                    Ok(session) => {
+                        self.state.view = View::News;
+                        self.state.session = Some(session);
+                    }
+                    Err(e) => self.fail("failed-session", e, None),
+                }
+            }
+            Msg::EndSession => {
+                self.state.session = None;
+            }
+            Msg::SettingsLoaded(result) => {
+                self.settings_task = None;
+                match result {
+                    Ok(settings) => {
+                        self.state.filter = settings.default_filter.clone();
+                        self.settings = settings;
+                        self.seed_timezone();
+                    }
+                    Err(e) => self.fail("failed-settings", e, Some(Retry::LoadSettings)),
+                }
+            }
+            Msg::SettingsSaved(settings, result) => {
+                self.settings_task = None;
+                match result {
+                    Ok(saved) => self.settings = saved,
+                    Err(e) => {
+                        self.fail("failed-save-settings", e, Some(Retry::SaveSettings(settings)))
+                    }
+                }
+            }
+            Msg::Tick => {
+                self.now = time::now();
+                return true;
+            }
+            // The refresh ends when the server reports its progress as finished.
+            Msg::CancelRefresh => {
+                self.ws_send(&ClientMessage::CancelRefresh);
+                return false;
+            }
+            Msg::FeedsLoaded(result) => {
+                self.feeds_task = None;
+                match result {
+                    Ok(feeds) => {
+                        if let Err(e) = store(FEEDS_KEY, &feeds) {
+                            log::error!("failed to store feeds: {}", e);
+                        }
+                        self.feeds = feeds;
+                    }
+                    Err(e) => self.fail("failed-feeds", e, Some(Retry::LoadFeeds)),
+                }
+            }
+            Msg::FetchedDirectly(result) => {
+                self.direct_running -= 1;
+                if self.direct_running == 0 {
+                    self.direct_tasks.clear();
+                }
+                let mut items = match result {
+                    Ok(items) => items,
+                    // Most feeds don't allow other origins, which is no news.
+                    Err(e) => {
+                        log::warn!("failed to fetch a feed directly: {}", e);
+                        return false;
+                    }
+                };
+                direct::retain_new(&mut items, self.state.entries.iter().map(|e| &e.item));
+                let first = match items.first() {
+                    Some(first) => first,
+                    None => return false,
+                };
+                log::debug!("fetched {} new item(s) of {} directly", items.len(), first.feed);
+                self.state.unsynced.push(Unsynced {
+                    feed: first.feed.clone(),
+                    title: first.feed_title.clone(),
+                    items: items.iter().map(|item| item.rss_item().clone()).collect(),
+                });
+                let added = self.state.merge(items.iter().map(direct::dto).collect());
+                self.announcement = tr_args("new-items", &[("count", added.into())]);
+            }
+            Msg::FeedAdded(result) => {
+                self.feeds_task = None;
+                match result {
+                    // The new feed goes in the sidebar, outside any folder.
+                    Ok(FeedAdded { added: true }) => self.load_feeds(),
+                    Ok(_) => log::info!("already subscribed to the feed"),
+                    Err(e) => self.fail("failed-add-feed", e, None),
+                }
+            }
+            Msg::FeedOrderLoaded(result) => {
+                self.feed_order_task = None;
+                match result {
+                    Ok(order) => self.feed_order = order,
+                    Err(e) => self.fail("failed-feed-order", e, Some(Retry::LoadFeeds)),
+                }
+            }
+            Msg::SetFeedOrder(order) => {
+                // Shown right away, then replaced by the order the server kept.
+                let saved = order.clone();
+                let callback = self
+                    .link
+                    .callback(move |result| Msg::FeedOrderSaved(saved.clone(), result));
+                self.feed_order_task = api::put("/feeds/order", &order, callback)
+                    .map_err(|e| log::error!("failed to save the feed order: {}", e))
+                    .ok();
+                self.feed_order = order;
+            }
+            Msg::FeedOrderSaved(order, result) => {
+                self.feed_order_task = None;
+                match result {
+                    Ok(saved) => self.feed_order = saved,
+                    Err(e) => {
+                        let retry = Some(Retry::SaveFeedOrder(order));
+                        self.fail("failed-save-feed-order", e, retry);
+                    }
+                }
+            }
+            Msg::DismissToast(id) => {
+                self.toasts.retain(|toast| toast.id != id);
+            }
+            Msg::RetryToast(id) => {
+                let pos = match self.toasts.iter().position(|toast| toast.id == id) {
+                    Some(pos) => pos,
+                    None => return false,
+                };
+                match self.toasts.remove(pos).retry {
+                    Some(Retry::Connect) => return self.update(WsAction::Connect.into()),
+                    Some(Retry::LoadSettings) => self.load_settings(),
+                    Some(Retry::LoadFeeds) => self.load_feeds(),
+                    Some(Retry::LoadQueue) => self.load_queue(),
+                    Some(Retry::SaveSettings(settings)) => {
+                        return self.update(Msg::SaveSettings(settings))
+                    }
+                    Some(Retry::SaveFeedOrder(order)) => {
+                        return self.update(Msg::SetFeedOrder(order))
+                    }
+                    Some(Retry::SaveQueue) => self.save_queue(),
+                    None => {}
+                }
+            }
+            Msg::SaveSettings(settings) => {
+                if settings.notifications.new_items {
+                    let _ = web_sys::Notification::request_permission();
+                }
+                let saved = settings.clone();
+                let callback = self
+                    .link
+                    .callback(move |result| Msg::SettingsSaved(saved.clone(), result));
+                self.settings_task = api::put("/settings", &settings, callback)
+                    .map_err(|e| log::error!("failed to save settings: {}", e))
+                    .ok();
+                self.settings = settings;
+                self.state.view = View::News;
             }
             Msg::WsAction(action) => match action {
                 WsAction::Connect => {
                     log::debug!("websocket connect: {:#?}", action);
-                    let callback = self.link.callback(|Json(data)| Msg::WsReady(data));
+                    let callback = self
+                        .link
+                        .callback(|frame: Frame| Msg::WsReady(frame.decode(WS_ENCODING)));
                     let notification = self.link.callback(|status| match status {
-                        WebSocketStatus::Opened => Msg::Ignore,
+                        WebSocketStatus::Opened => Msg::WsOpened,
                         WebSocketStatus::Closed | WebSocketStatus::Error => WsAction::Lost.into(),
                     });
-                    let task = self
-                        .ws_service
-                        .connect("ws://localhost:9001/ws/", callback, notification)
-                        .unwrap();
-                    self.ws = Some(task);
-                }
-                WsAction::SendData(binary) => {
-                    log::debug!("websocket send_data: {:#?}", action);
-                    let request = WsRequest { value: 321 };
-                    if binary {
-                        self.ws.as_mut().unwrap().send_binary(Json(&request));
-                    } else {
-                        self.ws.as_mut().unwrap().send(Json(&request));
-                    }
+                    let url = protocol::connect_url(WS_URL, WS_ENCODING, self.token.as_deref());
+                    self.ws = match self.ws_service.connect(&url, callback, notification) {
+                        Ok(task) => {
+                            self.state.loading = Loading::Connecting;
+                            Some(task)
+                        }
+                        Err(e) => {
+                            let e = e.to_string();
+                            self.fail("failed-connect", e, Some(Retry::Connect));
+                            self.state.loading = Loading::Failed;
+                            self.fetch_directly();
+                            None
+                        }
+                    };
+                    self.ws_open = false;
+                    self.state.unsend();
                 }
                 WsAction::Disconnect => {
                     log::debug!("websocket disconnect: {:#?}", action);
                     self.ws.take();
+                    self.state.loading = Loading::Idle;
+                    self.ws_open = false;
+                    self.state.unsend();
                 }
                 WsAction::Lost => {
                     log::debug!("websocket lost: {:#?}", action);
+                    // Closing the websocket on purpose drops it first.
+                    if self.ws.is_some() {
+                        self.toast(tr("connection-lost"), Some(Retry::Connect));
+                        self.state.loading = Loading::Failed;
+                        self.fetch_directly();
+                    }
                     self.ws = None;
+                    self.ws_open = false;
+                    self.state.unsend();
                 }
             },
-            Msg::WsReady(response) => {
-                log::debug!("websocket ready resp: {:#?}", response);
-                //self.data = response.map(|data| data.value).ok();
+            Msg::WsOpened => {
+                self.ws_open = true;
+                self.state.loading = Loading::Refreshing(None);
+                // The server fetches the feeds itself from now on, and stores the items fetched
+                // meanwhile before the changes made to them are sent.
+                self.direct_tasks.clear();
+                self.direct_running = 0;
+                for unsynced in std::mem::take(&mut self.state.unsynced) {
+                    log::debug!("syncing {} item(s) of {}", unsynced.items.len(), unsynced.feed);
+                    self.ws_send(&unsynced.message());
+                }
+                // Changes made while disconnected, or sent just before the connection dropped,
+                // are sent again; the server ignores the ones it already applied.
+                let entries = &self.state.entries;
+                self.state
+                    .pending
+                    .retain(|change| entries.iter().any(|e| e.item.id == change.id));
+                let pending = self.state.pending.clone();
+                log::debug!("websocket opened, sending {} pending change(s)", pending.len());
+                for change in &pending {
+                    self.send_change(change);
+                }
             }
+            Msg::WsReady(response) => match response {
+                Ok(ServerMessage::Items(batch)) => {
+                    log::debug!(
+                        "websocket received {} items and {} patches",
+                        batch.items.len(),
+                        batch.patches.len()
+                    );
+                    let added = self.state.merge(batch.items);
+                    self.state.patch(batch.patches);
+                    // Stored items are shown while the refresh runs, they are not news.
+                    if batch.stale {
+                        if let Loading::Idle | Loading::Connecting = self.state.loading {
+                            self.state.loading = Loading::Refreshing(None);
+                        }
+                    } else if added > 0 {
+                        self.announcement = tr_args("new-items", &[("count", added.into())]);
+                    }
+                    let quiet = self
+                        .settings
+                        .quiet_hours
+                        .contains(time::now().with_timezone(&time::local_offset()).time());
+                    if added > 0
+                        && !batch.stale
+                        && self.settings.notifications.new_items
+                        && !quiet
+                    {
+                        notify(&self.announcement);
+                    }
+                    self.ws_send(&ClientMessage::Ack { up_to: batch.seq });
+                    self.resume();
+                }
+                Ok(ServerMessage::Position(position)) => {
+                    log::debug!("resuming at {} from {}", position.id, position.at);
+                    self.resume = Some(position.id);
+                    self.resume();
+                }
+                Ok(ServerMessage::Progress(progress)) => {
+                    self.state.loading = if progress.feeds_done == progress.feeds_total {
+                        Loading::Idle
+                    } else {
+                        Loading::Refreshing(Some(progress))
+                    };
+                }
+                Ok(ServerMessage::ResyncNeeded) => {
+                    log::warn!("websocket fell behind, resyncing");
+                    self.ws_send(&ClientMessage::Resync);
+                    return false;
+                }
+                Err(e) => {
+                    self.fail("bad-message", e, None);
+                }
+            },
             Msg::Ignore => {
                 return false;
             }
         }
-        self.storage.store(KEY, Json(&self.state.entries));
+        let stored = store(KEY, &self.state.entries)
+            .and_then(|_| store(PENDING_KEY, &self.state.pending))
+            .and_then(|_| store(UNSYNCED_KEY, &self.state.unsynced));
+        if let Err(e) = stored {
+            // Usually the storage being full, which the error of the browser doesn't put simply.
+            log::error!("failed to store entries: {}", e);
+            self.toast(tr("failed-storage"), None);
+        }
         true
     }
 
+    fn rendered(&mut self, _first_render: bool) {
+        let document = yew::utils::document();
+        if let Some(id) = self.scroll_to.take() {
+            if let Some(element) = document.get_element_by_id(&entry_anchor(&id)) {
+                element.scroll_into_view();
+            }
+        }
+        if let Some(id) = self.focus_to.take() {
+            let element = document
+                .get_element_by_id(&entry_anchor(&id))
+                .and_then(|element| element.dyn_into::<HtmlElement>().ok());
+            if let Some(element) = element {
+                let _ = element.focus();
+            }
+        }
+    }
+
     fn view(&self) -> Html {
         info!("rendered!");
 
-        // Inspect the prefer colors scheme and possibly enable the tailwindcss dark plugin.
-        let dark = web_sys::window()
-            .and_then(|window| window.match_media("(prefers-color-scheme: dark)").ok())
-            .flatten()
-            .and_then(|query_list| Some(query_list.matches()))
-            .unwrap_or(false);
-        yew::utils::document()
-            .document_element()
-            .and_then(|element| {
-                if dark {
-                    Some(element.class_list().add_1("mode-dark"))
-                } else {
-                    Some(element.class_list().remove_1("mode-dark"))
-                }
-            });
+        theme::apply(&self.settings);
+        i18n::set_language(self.settings.language.as_deref());
+        time::set_timezone(self.settings.timezone.as_deref());
 
+        // The priority inbox is opt-in.
+        let filters = Filter::iter()
+            .filter(|flt| *flt != Filter::Priority || self.settings.priority_inbox);
+        let left = tr_args("items-left", &[("count", self.state.total_unread().into())]);
+        let queue = tr_args("nav-queue", &[("count", self.queue.len().into())]);
         html! {
             <div class="text-gray-800 dark:text-gray-200">
                 <section class="newsapp">
                     <header class="header">
                         <h1>{ "news" }</h1>
+                        <nav aria-label=tr("nav-label")>
+                            { self.view_nav_link(View::Discover, tr("nav-discover")) }
+                            { self.view_nav_link(View::Digest, tr("nav-digest")) }
+                            { self.view_nav_link(View::Queue, queue) }
+                            { self.view_nav_link(View::Mutes, tr("nav-mutes")) }
+                            { self.view_nav_link(View::Settings, tr("nav-settings")) }
+                        </nav>
                     </header>
-                    <section class="main">
-                        <ul class="news-list">
-                            { for self.state.entries.iter().filter(|e| self.state.filter.fit(e))
-                                .enumerate()
-                                .map(|val| self.view_entry(val)) }
-                        </ul>
-                    </section>
+                    <div class="sr-only" role="status" aria-live="polite">
+                        { &self.announcement }
+                    </div>
+                    { self.view_palette() }
+                    <Toasts toasts=self.toasts.clone()
+                        ondismiss=self.link.callback(Msg::DismissToast)
+                        onretry=self.link.callback(Msg::RetryToast) />
+                    { self.view_sidebar() }
+                    { self.view_main() }
                     <footer class="footer">
                         <span class="unread">
-                            <strong>{ self.state.total_unread() }</strong>
-                            { " item(s) left" }
+                            { left }
                         </span>
                         <ul class="filters">
-                            { for Filter::iter().map(|flt| self.view_filter(flt)) }
+                            { for filters.map(|flt| self.view_filter(flt)) }
                         </ul>
+                        { self.view_player() }
+                        { self.view_catch_up() }
                         <div class="mt-4">
                             <a onclick=self.link.callback(|_| WsAction::Connect.into())
-                             href="#" class="inline-block px-5 py-3 rounded-lg shadow-lg bg-indigo-500 text-white uppercase tracking-wider">{"Fetch News"}</a>
+                             aria-busy=self.state.loading.busy().to_string()
+                             href="#" class="inline-block px-5 py-3 rounded-lg shadow-lg bg-indigo-500 text-white uppercase tracking-wider">
+                                { self.view_spinner() }{ tr("fetch-news") }
+                            </a>
+                            { self.view_loading() }
                         </div>
                     </footer>
                 </section>
@@ -204,6 +1129,722 @@ impl Component for App {
 }
 
 impl App {
+    /// Focus the entry `id`, making it the one reached with Tab in the list.
+    fn focus(&mut self, id: String) {
+        self.focused = Some(id.clone());
+        self.focus_to = Some(id);
+    }
+
+    /// Keep focus in the list once the entry at `idx` changed, moving it to the next entry, or the
+    /// previous one, when the entry left the list. `listed` are the ids listed before the change.
+    fn restore_focus(&mut self, idx: usize, listed: &[String]) {
+        let entry = &self.state.entries[idx];
+        let id = entry.item.id.clone();
+        if self.state.shows(entry) {
+            return self.focus(id);
+        }
+        let pos = match listed.iter().position(|listed| listed == &id) {
+            Some(pos) => pos,
+            None => return,
+        };
+        let neighbour = listed
+            .get(pos + 1)
+            .or_else(|| pos.checked_sub(1).and_then(|pos| listed.get(pos)));
+        if let Some(neighbour) = neighbour {
+            self.focus(neighbour.clone());
+        }
+    }
+
+    /// Id of the entry reached with Tab among `listed`: the one focused last, else the first one.
+    fn tabbable<'a>(&'a self, listed: &[(usize, &'a Entry)]) -> Option<&'a str> {
+        let ids = || listed.iter().map(|(_, entry)| entry.item.id.as_str());
+        let focused = self.focused.as_deref();
+        ids().find(|id| Some(*id) == focused).or_else(|| ids().next())
+    }
+
+    /// Start reading the entry at `idx` aloud, marking it read.
+    fn listen(&mut self, idx: usize) {
+        let speaker = match self.speaker.as_mut() {
+            Some(speaker) => speaker,
+            None => return,
+        };
+        let entry = &self.state.entries[idx];
+        let body = entry
+            .item
+            .content
+            .as_ref()
+            .or_else(|| entry.item.description.as_ref())
+            .map(|body| speech::text_of(body))
+            .unwrap_or_default();
+        let title = entry.item.title.clone().unwrap_or_default();
+        speaker.speak(&format!("{}. {}", title, body));
+        self.state.listening = Some(Listening {
+            id: entry.item.id.clone(),
+            progress: 0.0,
+            paused: false,
+        });
+        self.mark_read(idx);
+    }
+
+    /// Expand the entry the user was last reading and scroll to it, once it was received.
+    fn resume(&mut self) {
+        let id = match &self.resume {
+            Some(id) => id,
+            None => return,
+        };
+        if !self.state.entries.iter().any(|e| &e.item.id == id) {
+            return;
+        }
+        self.state.expanded.insert(id.clone());
+        self.scroll_to = self.resume.take();
+    }
+
+    /// Run a command picked in the palette.
+    fn run(&mut self, command: Command) -> ShouldRender {
+        match command {
+            Command::MarkAllRead => {
+                let unread: Vec<usize> = (0..self.state.entries.len())
+                    .filter(|idx| {
+                        let entry = &self.state.entries[*idx];
+                        !entry.read && self.state.shows(entry)
+                    })
+                    .collect();
+                for idx in unread {
+                    self.mark_read(idx);
+                }
+            }
+            Command::FetchNews => return self.update(WsAction::Connect.into()),
+            Command::ToggleTheme => {
+                let mut settings = self.settings.clone();
+                settings.theme = match settings.theme {
+                    Theme::Dark => Theme::Light,
+                    _ => Theme::Dark,
+                };
+                return self.update(Msg::SaveSettings(settings));
+            }
+            Command::AddFeed(url) => {
+                let body = NewFeed { url };
+                let callback = self.link.callback(Msg::FeedAdded);
+                self.feeds_task = api::post_json("/feeds", &body, callback)
+                    .map_err(|e| log::error!("failed to request adding a feed: {}", e))
+                    .ok();
+            }
+            Command::OpenSettings => self.state.view = View::Settings,
+            Command::OpenDigest => self.state.view = View::Digest,
+            Command::OpenQueue => self.state.view = View::Queue,
+            Command::OpenMutes => self.state.view = View::Mutes,
+            Command::Discover => self.state.view = View::Discover,
+            Command::ExportStarred => {
+                let url = format!("{}/feeds/out/starred.xml", api::API_URL);
+                if let Some(window) = web_sys::window() {
+                    let _ = window.open_with_url(&url);
+                }
+            }
+        }
+        true
+    }
+
+    /// Show a toast about a failure, offering to `retry` if it may succeed on a second try. The
+    /// same message isn't shown twice at once.
+    fn toast(&mut self, message: String, retry: Option<Retry>) {
+        log::error!("{}", message);
+        if self.toasts.iter().any(|toast| toast.message == message) {
+            return;
+        }
+        self.toast_id += 1;
+        self.toasts.push(Toast {
+            id: self.toast_id,
+            message,
+            retry,
+        });
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+    }
+
+    /// Show a toast with the translated message `id` followed by the `error` that caused it.
+    fn fail(&mut self, id: &str, error: impl Display, retry: Option<Retry>) {
+        self.toast(format!("{}: {}", tr(id), error), retry);
+    }
+
+    /// Request the settings again.
+    fn load_settings(&mut self) {
+        self.settings_task = api::get("/settings", self.link.callback(Msg::SettingsLoaded))
+            .map_err(|e| log::error!("failed to request settings: {}", e))
+            .ok();
+    }
+
+    /// Save the browser's timezone as the one of the settings when they name none, so the server
+    /// groups days as the browser does.
+    fn seed_timezone(&mut self) {
+        if self.settings.timezone.is_some() {
+            return;
+        }
+        let browser = time::browser_timezone().filter(|name| timezone::is_valid(name));
+        let settings = match browser {
+            Some(name) => Settings {
+                timezone: Some(name),
+                ..self.settings.clone()
+            },
+            None => return,
+        };
+        let saved = settings.clone();
+        let callback = self
+            .link
+            .callback(move |result| Msg::SettingsSaved(saved.clone(), result));
+        self.settings_task = api::put("/settings", &settings, callback)
+            .map_err(|e| log::error!("failed to save the timezone: {}", e))
+            .ok();
+        self.settings = settings;
+    }
+
+    /// Fetch the subscribed feeds from the browser, as the server can't be reached.
+    fn fetch_directly(&mut self) {
+        if self.direct_running > 0 {
+            return;
+        }
+        let callback = self.link.callback(Msg::FetchedDirectly);
+        self.direct_tasks = self
+            .feeds
+            .iter()
+            .filter_map(|feed| {
+                direct::fetch(feed, callback.clone())
+                    .map_err(|e| log::error!("failed to request {}: {}", feed, e))
+                    .ok()
+            })
+            .collect();
+        self.direct_running = self.direct_tasks.len();
+    }
+
+    /// Request the queue again.
+    fn load_queue(&mut self) {
+        self.queue_task = api::get("/queue", self.link.callback(Msg::QueueLoaded))
+            .map_err(|e| log::error!("failed to request the queue: {}", e))
+            .ok();
+    }
+
+    /// Request the subscribed feeds and their order in the sidebar again.
+    fn load_feeds(&mut self) {
+        self.feeds_task = api::get("/feeds", self.link.callback(Msg::FeedsLoaded))
+            .map_err(|e| log::error!("failed to request feeds: {}", e))
+            .ok();
+        let callback = self.link.callback(Msg::FeedOrderLoaded);
+        self.feed_order_task = api::get("/feeds/order", callback)
+            .map_err(|e| log::error!("failed to request the feed order: {}", e))
+            .ok();
+    }
+
+    /// Leave the queued entry at `idx` for the queued entry `id`, expanding it in the queue.
+    fn read_queued(&mut self, idx: usize, id: String) {
+        let current = self.state.entries[idx].item.id.clone();
+        self.state.expanded.remove(&current);
+        self.state.view = View::Queue;
+        self.state.expanded.insert(id.clone());
+        self.ws_send(&ClientMessage::Position { id: id.clone() });
+        self.scroll_to = Some(id.clone());
+        self.focus(id);
+    }
+
+    /// Store the order of the queue on the server, which syncs it with the other devices.
+    fn save_queue(&mut self) {
+        self.queue_task = api::put("/queue", &self.queue, self.link.callback(Msg::QueueSaved))
+            .map_err(|e| log::error!("failed to save the queue: {}", e))
+            .ok();
+    }
+
+    /// Return a copy of the annotations of the entry at `idx`, to change and save.
+    fn annotations(&self, idx: usize) -> Annotations {
+        self.state.entries[idx]
+            .item
+            .annotations
+            .clone()
+            .unwrap_or_default()
+    }
+
+    /// Show the annotations of the entry at `idx` right away and store them on the server, which
+    /// replaces them with the annotations it kept.
+    fn save_annotations(&mut self, idx: usize, annotations: Annotations) {
+        let id = self.state.entries[idx].item.id.clone();
+        let path = format!("/items/{}/annotations", id);
+        let callback = self
+            .link
+            .callback(move |result| Msg::AnnotationsSaved(id.clone(), result));
+        self.annotations_task = api::put(&path, &annotations, callback)
+            .map_err(|e| log::error!("failed to save annotations: {}", e))
+            .ok();
+        self.state.entries[idx].item.annotations = Some(annotations).filter(|a| !a.is_empty());
+    }
+
+    /// Mark the entry at `idx` read, letting the server know the first time.
+    fn mark_read(&mut self, idx: usize) {
+        if self.state.entries[idx].read {
+            return;
+        }
+        self.change(idx, true, None);
+    }
+
+    /// Show a change to the entry at `idx` right away and send it to the server, or keep it
+    /// until the websocket is open.
+    fn change(&mut self, idx: usize, read: bool, starred: Option<bool>) {
+        let change = self.state.change(idx, read, starred);
+        self.send_change(&change);
+    }
+
+    fn send_change(&mut self, change: &PendingChange) {
+        if !self.ws_open {
+            return;
+        }
+        for msg in change.messages(time::now()) {
+            self.ws_send(&msg);
+        }
+        if let Some(pending) = self.state.pending.iter_mut().find(|p| p.id == change.id) {
+            pending.sent = true;
+        }
+    }
+
+    /// Move on to the next unread entry after the one being read aloud.
+    fn listen_next(&mut self) {
+        let current = self.state.listening.as_ref().map(|l| l.id.clone());
+        let start = current
+            .and_then(|id| self.state.entries.iter().position(|e| e.item.id == id))
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+        let next = (start..self.state.entries.len()).find(|&idx| !self.state.entries[idx].read);
+        match next {
+            Some(idx) => self.listen(idx),
+            None => {
+                if let Some(speaker) = self.speaker.as_mut() {
+                    speaker.cancel();
+                }
+                self.state.listening = None;
+            }
+        }
+    }
+
+    fn view_player(&self) -> Html {
+        let listening = match &self.state.listening {
+            Some(listening) => listening,
+            None => return html! {},
+        };
+        let title = self
+            .state
+            .entries
+            .iter()
+            .find(|e| e.item.id == listening.id)
+            .and_then(|e| e.item.title.clone())
+            .unwrap_or_default();
+        html! {
+            <div class="player mt-4">
+                <span>{ tr("listening") }{ " " }{ title }</span>
+                <progress max="1" value=listening.progress.to_string() />
+                <button onclick=self.link.callback(|_| Msg::PauseListening)>
+                    { tr(if listening.paused { "resume" } else { "pause" }) }
+                </button>
+                <button onclick=self.link.callback(|_| Msg::SkipListening)>{ tr("skip") }</button>
+                <button onclick=self.link.callback(|_| Msg::StopListening)>{ tr("stop") }</button>
+                <label>
+                    <input type="checkbox" checked=self.state.auto_advance
+                        onclick=self.link.callback(|_| Msg::ToggleAutoAdvance) />
+                    { " " }{ tr("auto-advance") }
+                </label>
+            </div>
+        }
+    }
+
+    fn view_main(&self) -> Html {
+        if self.state.view == View::Settings {
+            return html! {
+                <SettingsView settings=self.settings.clone() feeds=self.feeds.clone()
+                    onsave=self.link.callback(Msg::SaveSettings)
+                    oncancel=self.link.callback(|_| Msg::SetView(View::News)) />
+            };
+        }
+        if self.state.view == View::Discover {
+            return html! {
+                <DiscoverView onclose=self.link.callback(|_| Msg::SetView(View::News)) />
+            };
+        }
+        if self.state.view == View::Digest {
+            return html! {
+                <DigestView onclose=self.link.callback(|_| Msg::SetView(View::News)) />
+            };
+        }
+        if self.state.view == View::Mutes {
+            return html! {
+                <MutesView feeds=self.feeds.clone()
+                    onclose=self.link.callback(|_| Msg::SetView(View::News)) />
+            };
+        }
+        if self.state.view == View::Queue {
+            return self.view_queue();
+        }
+        if let Some(session) = &self.state.session {
+            return self.view_session(session);
+        }
+
+        // Only the newest entry of a story is listed, with the other coverage grouped under it.
+        let mut clusters: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (idx, entry) in self.state.entries.iter().enumerate() {
+            if let Some(cluster) = &entry.item.cluster {
+                if self.state.shows(entry) {
+                    clusters.entry(cluster).or_default().push(idx);
+                }
+            }
+        }
+
+        let shown = self.settings.items_per_page * self.state.pages;
+        let mut listed: Vec<(usize, &Entry)> = self
+            .state
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| self.state.shows(e))
+            .filter(|(idx, e)| match &e.item.cluster {
+                Some(cluster) => clusters[cluster.as_str()][0] == *idx,
+                None => true,
+            })
+            .collect();
+        let prioritized = self.state.filter == Filter::Priority;
+        if prioritized {
+            listed.sort_by(|(_, a), (_, b)| {
+                b.item
+                    .priority
+                    .partial_cmp(&a.item.priority)
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+        let tabbable = self.tabbable(&listed[..shown.min(listed.len())]);
+        let mut entries = listed.into_iter();
+        // Entries are sorted newest first, so a header starts each run of entries from the same
+        // local day. Entries without a date are grouped under their own header. Prioritized
+        // entries aren't in date order, so they go without headers.
+        let offset = time::local_offset();
+        let mut current_day = None;
+        let mut visible: Vec<Html> = Vec::new();
+        for (idx, entry) in entries.by_ref().take(shown) {
+            let day = entry.item.pub_date.map(|date| time::day(date, offset));
+            if !prioritized && (visible.is_empty() || day != current_day) {
+                visible.push(self.view_day_header(day, offset));
+                current_day = day;
+            }
+            let tabbable = tabbable == Some(entry.item.id.as_str());
+            visible.push(self.view_entry((idx, entry), tabbable));
+            if let Some(cluster) = &entry.item.cluster {
+                visible.push(self.view_coverage(&clusters[cluster.as_str()][1..]));
+            }
+        }
+        if visible.is_empty() && self.state.loading.busy() {
+            visible.extend((0..SKELETON_ENTRIES).map(|_| view_skeleton()));
+        }
+        let more = if entries.next().is_some() {
+            html! {
+                <a href="#" onclick=self.link.callback(|_| Msg::ShowMore)>{ tr("show-more") }</a>
+            }
+        } else {
+            html! {}
+        };
+        html! {
+            <section class="main">
+                { self.view_feed_banner() }
+                <ul class="news-list" role="feed" aria-label=tr("entries-label")
+                    aria-busy=self.state.loading.busy().to_string()>
+                    { for visible }
+                </ul>
+                { more }
+            </section>
+        }
+    }
+
+    /// Titles of the feeds of the received entries, keyed by URL.
+    fn feed_titles(&self) -> HashMap<String, String> {
+        self.state
+            .entries
+            .iter()
+            .filter_map(|e| Some((e.item.feed.clone(), e.item.feed_title.clone()?)))
+            .collect()
+    }
+
+    fn view_palette(&self) -> Html {
+        if !self.palette {
+            return html! {};
+        }
+        html! {
+            <Palette feeds=self.feeds.clone() titles=self.feed_titles()
+                folders=self.feed_order.folders.clone()
+                onjump=self.link.callback(Msg::Jump)
+                onclose=self.link.callback(|_| Msg::ClosePalette) />
+        }
+    }
+
+    fn view_sidebar(&self) -> Html {
+        let titles = self.feed_titles();
+        html! {
+            <Sidebar order=self.feed_order.clone() titles=titles selected=self.state.feed.clone()
+                onselect=self.link.callback(Msg::SetFeed)
+                onchange=self.link.callback(Msg::SetFeedOrder) />
+        }
+    }
+
+    /// List the entries of a catch-up session in reading order, with the progress made.
+    fn view_session(&self, session: &Session) -> Html {
+        let entries: Vec<(usize, &Entry)> = session
+            .ids
+            .iter()
+            .filter_map(|id| self.state.entries.iter().position(|e| &e.item.id == id))
+            .map(|idx| (idx, &self.state.entries[idx]))
+            .collect();
+        let tabbable = self.tabbable(&entries);
+        let read = entries.iter().filter(|(_, entry)| entry.read).count();
+        let progress = if entries.is_empty() {
+            html! { <p>{ tr("session-empty") }</p> }
+        } else {
+            let total = entries.len();
+            let args = [
+                ("read", read.into()),
+                ("total", total.into()),
+                ("minutes", session.minutes.into()),
+            ];
+            let label = tr_args("session-progress", &args);
+            html! {
+                <p class="session-progress my-2">
+                    <progress max=total.to_string() value=read.to_string()
+                        aria-label=label.clone() />
+                    { " " }{ label }
+                </p>
+            }
+        };
+        html! {
+            <section class="main">
+                { progress }
+                <ul class="news-list" role="feed" aria-label=tr("entries-label")>
+                    { for entries.into_iter().map(|entry| {
+                        let tabbable = tabbable == Some(entry.1.item.id.as_str());
+                        self.view_entry(entry, tabbable)
+                    }) }
+                </ul>
+                <button onclick=self.link.callback(|_| Msg::EndSession)>
+                    { tr("end-session") }
+                </button>
+            </section>
+        }
+    }
+
+    /// List the queued entries in reading order, reordered by dragging them.
+    fn view_queue(&self) -> Html {
+        let queued: Vec<(usize, usize, &Entry)> = self
+            .queue
+            .iter()
+            .enumerate()
+            .filter_map(|(pos, id)| {
+                let idx = self.state.entries.iter().position(|e| &e.item.id == id)?;
+                Some((pos, idx, &self.state.entries[idx]))
+            })
+            .collect();
+        let entries: Vec<(usize, &Entry)> =
+            queued.iter().map(|&(_, idx, entry)| (idx, entry)).collect();
+        let tabbable = self.tabbable(&entries);
+        let rows: Vec<Html> = queued
+            .into_iter()
+            .map(|(pos, idx, entry)| {
+                let id = entry.item.id.as_str();
+                let ondragover = self.link.callback(|e: DragEvent| {
+                    // Dropping is only allowed where the default is prevented.
+                    e.prevent_default();
+                    Msg::Ignore
+                });
+                let ondrop = self.link.callback(move |e: DragEvent| {
+                    e.prevent_default();
+                    Msg::DropQueued(pos)
+                });
+                html! {
+                    <div class="queued flex" draggable="true"
+                        ondragstart=self.link.callback(move |_| Msg::DragQueued(pos))
+                        ondragover=ondragover ondrop=ondrop>
+                        <span class="handle mr-2 cursor-move" title="Drag to reorder"
+                            aria-hidden="true">
+                            { "☰" }
+                        </span>
+                        <ul class="news-list" role="none">
+                            { self.view_entry((idx, entry), tabbable == Some(id)) }
+                        </ul>
+                    </div>
+                }
+            })
+            .collect();
+        let empty = if rows.is_empty() {
+            html! { <p>{ tr("queue-empty") }</p> }
+        } else {
+            html! {}
+        };
+        html! {
+            <section class="main queue">
+                <h2>{ tr("queue-title") }</h2>
+                { empty }
+                <div role="feed" aria-label=tr("queue-title")>
+                    { for rows }
+                </div>
+                <button class="mt-4" onclick=self.link.callback(|_| Msg::SetView(View::News))>
+                    { tr("close") }
+                </button>
+            </section>
+        }
+    }
+
+    /// Offer to queue an entry, or to move through the queue from a queued one.
+    fn view_queue_controls(&self, idx: usize, entry: &Entry) -> Html {
+        let pos = match self.queue.iter().position(|id| id == &entry.item.id) {
+            Some(pos) => pos,
+            None => {
+                return html! {
+                    <button onclick=self.link.callback(move |_| Msg::ToggleQueued(idx))>
+                        { tr("read-next") }
+                    </button>
+                };
+            }
+        };
+        let previous = if pos > 0 {
+            html! {
+                <button onclick=self.link.callback(move |_| Msg::QueuePrevious(idx))>
+                    { tr("queue-previous") }
+                </button>
+            }
+        } else {
+            html! {}
+        };
+        html! {
+            <>
+                { previous }
+                <button onclick=self.link.callback(move |_| Msg::QueueNext(idx))>
+                    { tr(if pos + 1 < self.queue.len() { "queue-next" } else { "queue-done" }) }
+                </button>
+                <button onclick=self.link.callback(move |_| Msg::ToggleQueued(idx))>
+                    { tr("queue-remove") }
+                </button>
+            </>
+        }
+    }
+
+    /// Offer sessions reading the most likely read unread entries in a bounded time.
+    fn view_catch_up(&self) -> Html {
+        let button = |minutes: u32| {
+            html! {
+                <button class="mr-2" onclick=self.link.callback(move |_| Msg::CatchUp(minutes))>
+                    { tr_args("catch-up-minutes", &[("minutes", minutes.into())]) }
+                </button>
+            }
+        };
+        html! {
+            <div class="catch-up mt-4">
+                { tr("catch-up") }{ " " }
+                { for CATCH_UP_MINUTES.iter().map(|minutes| button(*minutes)) }
+            </div>
+        }
+    }
+
+    fn ws_send(&mut self, msg: &ClientMessage) {
+        if self.ws_open {
+            if let Some(ws) = self.ws.as_mut() {
+                ws.send(Json(msg));
+                return;
+            }
+        }
+        // Where the user is reading only matters to their other devices, it is dropped quietly.
+        if let ClientMessage::Position { .. } = msg {
+            return;
+        }
+        self.toast(tr("not-connected"), Some(Retry::Connect));
+    }
+
+    /// Name the feed whose entries only are listed, with a way back to the river.
+    fn view_feed_banner(&self) -> Html {
+        if let Some(folder) = &self.state.folder {
+            return html! {
+                <p class="feed-view my-2">
+                    { tr_args("only-showing-folder", &[("name", folder.name.as_str().into())]) }
+                    { " " }
+                    <button onclick=self.link.callback(|_| Msg::SetFeed(None))>
+                        { tr("show-every-feed") }
+                    </button>
+                </p>
+            };
+        }
+        let feed = match &self.state.feed {
+            Some(feed) => feed,
+            None => return html! {},
+        };
+        let title = self
+            .state
+            .entries
+            .iter()
+            .find(|entry| &entry.item.feed == feed)
+            .and_then(|entry| entry.item.feed_title.as_deref());
+        html! {
+            <p class="feed-view my-2">
+                { tr_args("only-showing", &[("name", protocol::feed_name(feed, title).into())]) }
+                { " " }
+                <button onclick=self.link.callback(|_| Msg::SetFeed(None))>
+                    { tr("show-every-feed") }
+                </button>
+            </p>
+        }
+    }
+
+    /// Spin while waiting for the server.
+    fn view_spinner(&self) -> Html {
+        if self.state.loading.busy() {
+            html! { <span class="spinner mr-2" aria-hidden="true"></span> }
+        } else {
+            html! {}
+        }
+    }
+
+    /// Tell what the list is waiting for from the server, if anything.
+    fn view_loading(&self) -> Html {
+        let label = match &self.state.loading {
+            Loading::Idle => return html! {},
+            Loading::Connecting => tr("loading-connecting"),
+            Loading::Refreshing(None) => tr("loading-refreshing"),
+            Loading::Refreshing(Some(progress)) => {
+                let args = [
+                    ("done", progress.feeds_done.into()),
+                    ("total", progress.feeds_total.into()),
+                ];
+                tr_args("loading-progress", &args)
+            }
+            Loading::Failed => tr("loading-failed"),
+        };
+        // The feed being fetched is only named on hover, it changes too often to read.
+        let current = match &self.state.loading {
+            Loading::Refreshing(Some(progress)) => progress.current_feed.clone(),
+            _ => None,
+        };
+        let cancel = if let Loading::Refreshing(_) = self.state.loading {
+            html! {
+                <button class="ml-2 underline" onclick=self.link.callback(|_| Msg::CancelRefresh)>
+                    { tr("cancel-refresh") }
+                </button>
+            }
+        } else {
+            html! {}
+        };
+        html! {
+            <span class="loading ml-2 text-sm" title=current.unwrap_or_default()>
+                { label }
+                { cancel }
+            </span>
+        }
+    }
+
+    fn view_nav_link(&self, view: View, label: String) -> Html {
+        let current = if self.state.view == view { "page" } else { "false" };
+        html! {
+            <a href="#" aria-current=current
+                onclick=self.link.callback(move |_| Msg::SetView(view))>
+                { label }
+            </a>
+        }
+    }
+
     fn view_filter(&self, filter: Filter) -> Html {
         let flt = filter.clone();
 
@@ -212,27 +1853,362 @@ impl App {
                 <a class=if self.state.filter == flt { "selected" } else { "not-selected" }
                    href=&flt
                    onclick=self.link.callback(move |_| Msg::SetFilter(flt.clone()))>
-                    { filter }
+                    { filter.label() }
                 </a>
             </li>
         }
     }
 
-    fn view_entry(&self, (idx, entry): (usize, &Entry)) -> Html {
+    /// Show an entry of the list, the one reached with Tab when `tabbable`. Arrow keys move to
+    /// the other entries.
+    fn view_entry(&self, (idx, entry): (usize, &Entry), tabbable: bool) -> Html {
         let mut class = "news".to_string();
+        if entry.read {
+            class.push_str(" read");
+        }
+        if !entry.item.title_highlights.is_empty() || !entry.item.description_highlights.is_empty()
+        {
+            class.push_str(" highlighted");
+        }
+        let title = match self.translation(entry).and_then(|t| t.title.as_deref()) {
+            Some(title) => html! { { title } },
+            None => highlight::text(
+                entry.item.title.as_deref().unwrap_or_default(),
+                &entry.item.title_highlights,
+            ),
+        };
+        let expanded = self.state.expanded.contains(&entry.item.id);
+        let title_id = format!("{}-title", entry_anchor(&entry.item.id));
         html! {
-            <li class=class>
-                { self.view_entry((idx, &entry)) }
+            <li class=class id=entry_anchor(&entry.item.id) role="article"
+                aria-labelledby=title_id.clone() tabindex=if tabbable { "0" } else { "-1" }
+                onfocus=self.link.callback(move |_| Msg::FocusEntry(idx))
+                onkeydown=self.link.callback(move |e: KeyboardEvent| Msg::EntryKey(idx, e))>
+                { if entry.item.starred {
+                    html! {
+                        <span class="starred mr-1" role="img" aria-label=tr("starred")
+                            title=tr("starred")>
+                            { "★" }
+                        </span>
+                    }
+                } else {
+                    html! {}
+                } }
+                { if entry.item.revisions > 0 {
+                    html! {
+                        <span class="updated mr-1 text-sm" title="Edited by the feed">
+                            { tr("updated") }
+                        </span>
+                    }
+                } else {
+                    html! {}
+                } }
+                { if entry.item.dead_link {
+                    html! {
+                        <span class="dead-link mr-1" role="img" aria-label=tr("dead-link")
+                            title=tr("dead-link")>
+                            { "⚠" }
+                        </span>
+                    }
+                } else {
+                    html! {}
+                } }
+                // The entry itself takes focus, so its title is left out of the tab order.
+                <a href="#" id=title_id role="button" tabindex="-1"
+                    aria-expanded=expanded.to_string()
+                    onclick=self.link.callback(move |_| Msg::Toggle(idx))>
+                    { title }
+                </a>
+                { self.view_date(entry) }
+                { if expanded {
+                    self.view_preview(idx, entry)
+                } else {
+                    view_excerpt(entry)
+                } }
             </li>
         }
     }
+
+    /// Show the image and the sanitized content of an expanded entry.
+    fn view_preview(&self, idx: usize, entry: &Entry) -> Html {
+        let translated = self.translation(entry).and_then(|t| t.body.as_ref());
+        let translate = if self.translation(entry).is_some() {
+            "show-original"
+        } else {
+            "translate"
+        };
+        let highlights: &[Highlight] = match &entry.item.annotations {
+            Some(annotations) => &annotations.highlights[..],
+            None => &[],
+        };
+        let body = match (translated, &entry.item.content, &entry.item.description) {
+            (Some(translated), _, _) => raw_html(translated),
+            (None, Some(content), _) => raw_html(&annotations::markup(content, highlights)),
+            (None, None, Some(description)) => {
+                let marked = highlight::markup(description, &entry.item.description_highlights);
+                raw_html(&annotations::markup(&marked, highlights))
+            }
+            (None, None, None) => html! {},
+        };
+        let image = match &entry.item.image_path {
+            Some(path) => html! {
+                <LazyImage class="max-w-full my-2" src=format!("{}{}", api::SERVER_URL, path)
+                    placeholder=entry.item.image_placeholder.clone() />
+            },
+            None => html! {},
+        };
+        let link = entry.item.link.clone().unwrap_or_else(|| "#".to_string());
+        let pdf = format!("{}/items/{}/pdf", api::API_URL, entry.item.id);
+        let listen = if self.speaker.is_some() {
+            html! { <button onclick=self.link.callback(move |_| Msg::Listen(idx))>{ tr("listen") }</button> }
+        } else {
+            html! {}
+        };
+        let summary = match &entry.item.summary {
+            Some(summary) => html! {
+                <p class="summary my-2 p-2 bg-gray-100 dark:bg-gray-900">
+                    <strong>{ "TL;DR: " }</strong>{ summary }
+                </p>
+            },
+            None => html! {},
+        };
+        html! {
+            <div class="preview my-2">
+                { image }
+                { summary }
+                { view_sources(entry) }
+                { self.view_changes(idx, entry) }
+                <div class="reader" id=body_anchor(&entry.item.id)
+                    style=self.settings.typography.style()>{ body }</div>
+                { self.view_annotations(idx, entry, translated.is_some()) }
+                { listen }
+                { self.view_queue_controls(idx, entry) }
+                <button onclick=self.link.callback(move |_| Msg::ToggleShare(idx))>{ tr("share") }</button>
+                { self.view_share_menu(idx, entry) }
+                { self.view_snooze(idx) }
+                <button onclick=self.link.callback(move |_| Msg::ToggleStar(idx))>
+                    { tr(if entry.item.starred { "unstar" } else { "star" }) }
+                </button>
+                <button onclick=self.link.callback(move |_| Msg::MarkJunk(idx))>{ tr("junk") }</button>
+                { self.view_more_from_feed(entry) }
+                <button onclick=self.link.callback(move |_| Msg::Translate(idx))>
+                    { tr(translate) }
+                </button>
+                <a href=pdf download="">{ tr("export-pdf") }</a>
+                { self.view_copies(entry) }
+                <a href=link target="_blank" onclick=self.link.callback(move |_| Msg::Read(idx))>
+                    { tr("open-original") }
+                </a>
+            </div>
+        }
+    }
+
+    /// Show the notes and highlights of an expanded entry, with ways to change them. Highlights
+    /// are ranges of the original text, so none are made in a translation.
+    fn view_annotations(&self, idx: usize, entry: &Entry, translated: bool) -> Html {
+        let annotations = entry.item.annotations.clone().unwrap_or_default();
+        let highlight = if translated {
+            html! {}
+        } else {
+            html! {
+                <button onclick=self.link.callback(move |_| Msg::Highlight(idx))>
+                    { tr("highlight-selection") }
+                </button>
+            }
+        };
+        let highlights = annotations.highlights.iter().enumerate().map(|(pos, highlight)| {
+            html! {
+                <li class="my-1">
+                    <blockquote class="inline border-l-4 pl-2">{ &highlight.text }</blockquote>
+                    <button onclick=self.link.callback(move |_| Msg::RemoveHighlight(idx, pos))>
+                        { tr("remove-highlight") }
+                    </button>
+                </li>
+            }
+        });
+        html! {
+            <section class="annotations my-2" aria-label=tr("notes-title")>
+                { highlight }
+                <ul>{ for highlights }</ul>
+                <textarea class="block w-full" rows="2" placeholder=tr("note-placeholder")
+                    value=annotations.note.unwrap_or_default()
+                    onchange=self.link.callback(move |e| Msg::SetNote(idx, select_value(e))) />
+            </section>
+        }
+    }
+
+    /// Offer to show what changed in an edited entry, and show it when asked.
+    fn view_changes(&self, idx: usize, entry: &Entry) -> Html {
+        let changes = match &entry.item.changes {
+            Some(changes) => changes,
+            None => return html! {},
+        };
+        let toggle = self.link.callback(move |_| Msg::ToggleChanges(idx));
+        if !self.state.diffing.contains(&entry.item.id) {
+            return html! { <button onclick=toggle>{ "Show changes" }</button> };
+        }
+        html! {
+            <div class="changes my-2 p-2 border">
+                <p class="font-bold">{ view_diff(&changes.title) }</p>
+                <p>{ view_diff(&changes.description) }</p>
+                <button onclick=toggle>{ "Hide changes" }</button>
+            </div>
+        }
+    }
+
+    /// Link the archived copies of an entry.
+    fn view_copies(&self, entry: &Entry) -> Html {
+        let snapshot = match &entry.item.snapshot_path {
+            Some(path) => html! {
+                <a href=format!("{}{}", api::SERVER_URL, path) target="_blank">{ "Snapshot" }</a>
+            },
+            None => html! {},
+        };
+        let archived = match &entry.item.archived_url {
+            Some(url) => html! { <a href=url target="_blank">{ "Archived copy" }</a> },
+            None => html! {},
+        };
+        let wayback = match (entry.item.dead_link, &entry.item.wayback_url) {
+            (true, Some(url)) => html! {
+                <a href=url target="_blank">{ "Dead link, open Wayback Machine copy" }</a>
+            },
+            (true, None) => html! { <span class="dead-link">{ "Dead link" }</span> },
+            (false, _) => html! {},
+        };
+        html! { <>{ snapshot }{ archived }{ wayback }</> }
+    }
+
+    fn translation(&self, entry: &Entry) -> Option<&Translation> {
+        self.state.translations.get(&entry.item.id)
+    }
+
+    fn view_snooze(&self, idx: usize) -> Html {
+        let options = [Snooze::Tonight, Snooze::Tomorrow, Snooze::NextWeek];
+        html! {
+            <span class="snooze">
+                { tr("snooze") }{ " " }
+                { for options.iter().map(|&snooze| html! {
+                    <button onclick=self.link.callback(move |_| Msg::Snooze(idx, snooze))>
+                        { snooze.label() }
+                    </button>
+                }) }
+            </span>
+        }
+    }
+
+    /// Offer the view of the feed of an entry, unless it is shown already.
+    fn view_more_from_feed(&self, entry: &Entry) -> Html {
+        if self.state.feed.as_ref() == Some(&entry.item.feed) {
+            return html! {};
+        }
+        let feed = entry.item.feed.clone();
+        html! {
+            <button onclick=self.link.callback(move |_| Msg::SetFeed(Some(feed.clone())))>
+                { tr("more-from-feed") }
+            </button>
+        }
+    }
+
+    fn view_share_menu(&self, idx: usize, entry: &Entry) -> Html {
+        if self.state.sharing.as_ref() != Some(&entry.item.id) {
+            return html! {};
+        }
+
+        let title = entry.item.title.clone().unwrap_or_default();
+        let link = entry.item.link.clone().unwrap_or_default();
+        let native = if share::native_available() {
+            html! { <li><a href="#" onclick=self.link.callback(move |_| Msg::ShareNative(idx))>{ "Share…" }</a></li> }
+        } else {
+            html! {}
+        };
+        html! {
+            <ul class="share-menu">
+                { native }
+                <li><a href="#" onclick=self.link.callback(move |_| Msg::CopyLink(idx))>{ "Copy link" }</a></li>
+                <li><a href=share::mailto(&title, &link)>{ "Email" }</a></li>
+            </ul>
+        }
+    }
+
+    /// List the other entries covering the same story as the entry above.
+    fn view_coverage(&self, related: &[usize]) -> Html {
+        if related.is_empty() {
+            return html! {};
+        }
+
+        let sources = related
+            .iter()
+            .map(|&idx| &self.state.entries[idx].item.feed)
+            .collect::<HashSet<_>>()
+            .len()
+            + 1;
+        html! {
+            <li class="coverage ml-4 text-sm" role="none">
+                <details>
+                    <summary>{ format!("{} sources covering this story", sources) }</summary>
+                    <ul>
+                        { for related.iter().map(|&idx| {
+                            let item = &self.state.entries[idx].item;
+                            html! {
+                                <li>
+                                    <a href=item.link.clone().unwrap_or_else(|| "#".to_string())
+                                        target="_blank"
+                                        onclick=self.link.callback(move |_| Msg::Read(idx))>
+                                        { item.title.clone().unwrap_or_default() }
+                                    </a>
+                                    <span class="ml-2 text-gray-600">{ &item.feed }</span>
+                                </li>
+                            }
+                        }) }
+                    </ul>
+                </details>
+            </li>
+        }
+    }
+
+    fn view_day_header(
+        &self,
+        day: Option<chrono::Date<chrono::FixedOffset>>,
+        offset: chrono::FixedOffset,
+    ) -> Html {
+        let label = match day {
+            Some(day) => time::day_label(day, self.now, offset),
+            None => "Undated".to_string(),
+        };
+        html! {
+            <li class="day-header sticky top-0 py-1 font-bold bg-white dark:bg-black"
+                role="heading" aria-level="3">
+                { label }
+            </li>
+        }
+    }
+
+    fn view_date(&self, entry: &Entry) -> Html {
+        match entry.item.pub_date {
+            Some(date) => {
+                let offset = time::local_offset();
+                html! {
+                    <time class="ml-2 text-sm text-gray-600" datetime=date.to_rfc3339()
+                        title=time::absolute(date, offset)>
+                        { time::relative(date, self.now, offset) }
+                    </time>
+                }
+            }
+            None => html! {},
+        }
+    }
 }
 
 #[derive(Debug, EnumIter, ToString, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Filter {
     All,
     Read,
     Unread,
+    #[strum(serialize = "Low quality")]
+    LowQuality,
+    /// Unread entries, most likely to be read first.
+    Priority,
 }
 
 impl<'a> Into<Href> for &'a Filter {
@@ -241,23 +2217,132 @@ impl<'a> Into<Href> for &'a Filter {
             Filter::All => "#/".into(),
             Filter::Read => "#/read".into(),
             Filter::Unread => "#/unread".into(),
+            Filter::LowQuality => "#/low-quality".into(),
+            Filter::Priority => "#/priority".into(),
         }
     }
 }
 
 impl Filter {
+    fn label(&self) -> String {
+        tr(match self {
+            Filter::All => "filter-all",
+            Filter::Read => "filter-read",
+            Filter::Unread => "filter-unread",
+            Filter::LowQuality => "filter-low-quality",
+            Filter::Priority => "filter-priority",
+        })
+    }
+
     fn fit(&self, entry: &Entry) -> bool {
+        // Snoozed entries stay hidden until the server wakes them.
+        if entry.item.snoozed_until.is_some() {
+            return false;
+        }
+        // Low quality entries are kept out of every list but their own.
         match *self {
+            Filter::LowQuality => entry.item.low_quality,
+            _ if entry.item.low_quality => false,
             Filter::All => true,
-            Filter::Unread => !entry.read,
+            // Muted entries keep their history but stay out of the unread count.
+            Filter::Unread | Filter::Priority => !entry.read && !entry.item.muted,
             Filter::Read => entry.read,
         }
     }
 }
 
 impl State {
-    fn read(&mut self, idx: usize) {
-        self.entries[idx].read = true;
+    /// Apply a change to the entry at `idx` and queue it until the server confirms it,
+    /// returning the change queued for the entry.
+    fn change(&mut self, idx: usize, read: bool, starred: Option<bool>) -> PendingChange {
+        let entry = &mut self.entries[idx];
+        entry.read |= read;
+        if let Some(starred) = starred {
+            entry.item.starred = starred;
+        }
+
+        let id = &entry.item.id;
+        let at = time::now();
+        match self.pending.iter_mut().find(|change| &change.id == id) {
+            Some(change) => {
+                change.read |= read;
+                change.starred = starred.or(change.starred);
+                change.at = at;
+                change.clone()
+            }
+            None => {
+                let change = PendingChange {
+                    id: id.clone(),
+                    read,
+                    starred,
+                    at,
+                    sent: false,
+                };
+                self.pending.push(change.clone());
+                change
+            }
+        }
+    }
+
+    /// Expand or collapse an entry, returning whether it is now expanded.
+    fn toggle(&mut self, idx: usize) -> bool {
+        let id = &self.entries[idx].item.id;
+        if self.expanded.remove(id) {
+            false
+        } else {
+            self.expanded.insert(id.clone());
+            true
+        }
+    }
+
+    /// Add newly received items, keeping the read state of ones already known. Returns the
+    /// number of items that weren't known before.
+    fn merge(&mut self, items: Vec<ItemDto>) -> usize {
+        let mut added = 0;
+        for item in items {
+            match self.entries.iter_mut().find(|e| e.item.id == item.id) {
+                Some(entry) => {
+                    let woken = entry.item.snoozed_until.is_some() && item.snoozed_until.is_none();
+                    if woken {
+                        entry.read = item.read;
+                    } else {
+                        entry.read |= item.read;
+                    }
+                    entry.item = item;
+                    reconcile(&mut self.pending, entry);
+                }
+                None => {
+                    let read = item.read;
+                    self.entries.push(Entry { item, read });
+                    added += 1;
+                }
+            }
+        }
+        self.entries.sort_by(|a, b| b.item.pub_date.cmp(&a.item.pub_date));
+        added
+    }
+
+    /// Forget which pending changes were sent, as the connection they were sent on is gone.
+    fn unsend(&mut self) {
+        for change in self.pending.iter_mut() {
+            change.sent = false;
+        }
+    }
+
+    /// Apply changes to the read or starred state of known items.
+    fn patch(&mut self, patches: Vec<ItemPatch>) {
+        for patch in patches {
+            if let Some(entry) = self.entries.iter_mut().find(|e| e.item.id == patch.id) {
+                if let Some(read) = patch.read {
+                    entry.read = read;
+                    entry.item.read = read;
+                }
+                if let Some(starred) = patch.starred {
+                    entry.item.starred = starred;
+                }
+                reconcile(&mut self.pending, entry);
+            }
+        }
     }
 
     fn total(&self) -> usize {
@@ -275,11 +2360,22 @@ impl State {
             .count()
     }
 
+    /// Whether the entry is listed, given the filter and the feed or folder shown.
+    fn shows(&self, entry: &Entry) -> bool {
+        let listed = match (&self.feed, &self.folder) {
+            (Some(feed), _) => &entry.item.feed == feed,
+            (None, Some(folder)) => folder.feeds.contains(&entry.item.feed),
+            // Entries left out of the river only show in the view of their feed.
+            (None, None) => !entry.item.overflow,
+        };
+        listed && self.filter.fit(entry)
+    }
+
     fn is_all_read(&self) -> bool {
         let mut filtered_iter = self
             .entries
             .iter()
-            .filter(|e| self.filter.fit(e))
+            .filter(|e| self.shows(e))
             .peekable();
 
         if filtered_iter.peek().is_none() {
@@ -289,3 +2385,155 @@ impl State {
         filtered_iter.all(|e| e.read)
     }
 }
+
+/// Drop the pending change of an entry the server state of which was just received if the server
+/// reflects it, or keep showing the change until the server catches up with it.
+fn reconcile(pending: &mut Vec<PendingChange>, entry: &mut Entry) {
+    let idx = match pending.iter().position(|change| change.id == entry.item.id) {
+        Some(idx) => idx,
+        None => return,
+    };
+    let change = &pending[idx];
+    // Once sent, the server resolves the change against the ones made elsewhere and pushes the
+    // outcome, which stands even if the change lost.
+    if change.sent || change.settled_by(entry.item.read, entry.item.starred) {
+        pending.remove(idx);
+        return;
+    }
+    entry.read |= change.read;
+    if let Some(starred) = change.starred {
+        entry.item.starred = starred;
+    }
+}
+
+/// Render HTML the backend already sanitized.
+fn raw_html(html: &str) -> Html {
+    let element = yew::utils::document().create_element("div").unwrap();
+    element.set_inner_html(html);
+    Html::VRef(element.into())
+}
+
+/// Return the id of the element listing the entry `id`.
+fn entry_anchor(id: &str) -> String {
+    format!("entry-{}", id)
+}
+
+/// Return the id of the element showing the body of the entry `id`, whose text highlights are
+/// ranges of.
+fn body_anchor(id: &str) -> String {
+    format!("{}-body", entry_anchor(id))
+}
+
+/// Placeholder of an entry, shown while the list is loading.
+fn view_skeleton() -> Html {
+    html! {
+        <li class="skeleton my-2" aria-hidden="true">
+            <span class="skeleton-line block h-4 w-3/4 rounded bg-gray-300 dark:bg-gray-700">
+            </span>
+            <span class="skeleton-line block h-3 w-1/4 mt-1 rounded bg-gray-200 dark:bg-gray-800">
+            </span>
+        </li>
+    }
+}
+
+/// Store `value` in local storage as JSON, which fails when the storage is full.
+fn store<T: serde::Serialize>(key: &str, value: &T) -> Result<(), Error> {
+    let storage = web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| Error::msg("local storage is unavailable"))?;
+    let json = serde_json::to_string(value)?;
+    storage
+        .set_item(key, &json)
+        .map_err(|e| Error::msg(format!("{:?}", e)))
+}
+
+/// Ids of the entries shown, in the order they are listed.
+fn listed_ids() -> Vec<String> {
+    let nodes = match yew::utils::document().query_selector_all("li.news") {
+        Ok(nodes) => nodes,
+        Err(_) => return Vec::new(),
+    };
+    (0..nodes.length())
+        .filter_map(|i| nodes.item(i)?.dyn_into::<web_sys::Element>().ok())
+        .filter_map(|element| Some(element.id().strip_prefix("entry-")?.to_string()))
+        .collect()
+}
+
+/// Return the primary language of the browser, e.g. `en` for `en-US`.
+fn browser_language() -> String {
+    web_sys::window()
+        .and_then(|window| window.navigator().language())
+        .and_then(|language| language.split('-').next().map(str::to_owned))
+        .unwrap_or_else(|| "en".to_string())
+}
+
+/// Credit the feeds an entry was published in when several feeds carried it, e.g. "via Ars
+/// Technica, also on Slashdot".
+fn view_sources(entry: &Entry) -> Html {
+    if entry.item.also_in.is_empty() {
+        return html! {};
+    }
+    let via = protocol::feed_name(&entry.item.feed, entry.item.feed_title.as_deref());
+    let also: Vec<&str> = entry.item.also_in.iter().map(|source| source.name()).collect();
+    html! {
+        <p class="sources text-sm text-gray-600">
+            { format!("via {}, also on {}", via, also.join(", ")) }
+        </p>
+    }
+}
+
+/// Show the start of the description of a collapsed entry.
+fn view_excerpt(entry: &Entry) -> Html {
+    match &entry.item.excerpt {
+        Some(excerpt) => html! {
+            <p class="excerpt text-sm text-gray-600">{ excerpt }</p>
+        },
+        None => html! {},
+    }
+}
+
+/// Render a word diff, striking removed words and underlining added ones.
+fn view_diff(changes: &[Change]) -> Html {
+    html! {
+        { for changes.iter().map(|change| match change.kind {
+            ChangeKind::Same => html! { <span>{ &change.text }{ " " }</span> },
+            ChangeKind::Added => html! {
+                <><ins class="bg-green-200 dark:bg-green-900">{ &change.text }</ins>{ " " }</>
+            },
+            ChangeKind::Removed => html! {
+                <><del class="bg-red-200 dark:bg-red-900">{ &change.text }</del>{ " " }</>
+            },
+        }) }
+    }
+}
+
+/// Return the websocket token given as the `token` query parameter of the page, remembering it, or
+/// the one remembered earlier.
+fn ws_token(storage: &mut StorageService) -> Option<String> {
+    let search = web_sys::window()
+        .and_then(|window| window.location().search().ok())
+        .unwrap_or_default();
+    let given = search
+        .trim_start_matches('?')
+        .split('&')
+        .find_map(|param| param.strip_prefix("token="))
+        .and_then(|token| js_sys::decode_uri_component(token).ok())
+        .map(String::from);
+    match given {
+        Some(token) => {
+            storage.store(TOKEN_KEY, Json(&token));
+            Some(token)
+        }
+        None => match storage.restore(TOKEN_KEY) {
+            Json(Ok(token)) => Some(token),
+            _ => None,
+        },
+    }
+}
+
+/// Show a desktop notification through the browser, if permitted.
+fn notify(message: &str) {
+    if let Err(e) = web_sys::Notification::new(message) {
+        log::warn!("failed to show notification: {:?}", e);
+    }
+}
@@ -0,0 +1,159 @@
+//! The digest view, collapsing each feed to one card of headlines per day.
+
+use anyhow::Error;
+use chrono::{Date, NaiveDate};
+use serde_derive::Deserialize;
+use yew::prelude::*;
+use yew::services::fetch::FetchTask;
+
+use crate::api;
+use crate::protocol;
+use crate::time;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DigestDay {
+    pub day: NaiveDate,
+    pub cards: Vec<Card>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Card {
+    pub feed: String,
+    #[serde(default)]
+    pub feed_title: Option<String>,
+    pub unread: usize,
+    pub headlines: Vec<Headline>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Headline {
+    pub id: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub link: Option<String>,
+    pub read: bool,
+}
+
+pub struct DigestView {
+    link: ComponentLink<Self>,
+    props: Props,
+    /// Digests of the last days, newest first, `None` until loaded.
+    days: Option<Vec<DigestDay>>,
+    task: Option<FetchTask>,
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub onclose: Callback<()>,
+}
+
+pub enum Msg {
+    Loaded(Result<Vec<DigestDay>, Error>),
+    Close,
+}
+
+impl Component for DigestView {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        // Days start at the reader's midnight rather than the server's.
+        let offset = time::local_offset().local_minus_utc() / 60;
+        let path = format!("/digest?offset_minutes={}", offset);
+        let task = api::get(&path, link.callback(Msg::Loaded))
+            .map_err(|e| log::error!("failed to request the digest: {}", e))
+            .ok();
+        DigestView {
+            link,
+            props,
+            days: None,
+            task,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Loaded(result) => {
+                self.task = None;
+                match result {
+                    Ok(days) => self.days = Some(days),
+                    Err(e) => log::error!("failed to load the digest: {}", e),
+                }
+            }
+            Msg::Close => {
+                self.props.onclose.emit(());
+                return false;
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let days = match &self.days {
+            None => html! { <p>{ "Loading…" }</p> },
+            Some(days) if days.is_empty() => html! { <p>{ "Nothing was published lately." }</p> },
+            Some(days) => html! { { for days.iter().map(view_day) } },
+        };
+        html! {
+            <section class="digest">
+                <h2>{ "Digest" }</h2>
+                { days }
+                <button class="mt-4" onclick=self.link.callback(|_| Msg::Close)>
+                    { "Close" }
+                </button>
+            </section>
+        }
+    }
+}
+
+fn view_day(digest: &DigestDay) -> Html {
+    let offset = time::local_offset();
+    let label = time::day_label(Date::from_utc(digest.day, offset), time::now(), offset);
+    html! {
+        <div class="digest-day mt-4">
+            <h3 class="font-bold">{ label }</h3>
+            { for digest.cards.iter().map(view_card) }
+        </div>
+    }
+}
+
+/// Render the headlines of a feed on one day, collapsed to its name and counts.
+fn view_card(card: &Card) -> Html {
+    let name = protocol::feed_name(&card.feed, card.feed_title.as_deref());
+    let count = match card.headlines.len() {
+        1 => "1 headline".to_string(),
+        count => format!("{} headlines", count),
+    };
+    html! {
+        <details class="card my-2 p-2 border">
+            <summary>
+                { name }
+                <span class="ml-2 text-sm text-gray-600">
+                    { format!("{}, {} unread", count, card.unread) }
+                </span>
+            </summary>
+            <ul>
+                { for card.headlines.iter().map(view_headline) }
+            </ul>
+        </details>
+    }
+}
+
+fn view_headline(headline: &Headline) -> Html {
+    let class = if headline.read { "read" } else { "" };
+    let title = headline.title.as_deref().unwrap_or("Untitled");
+    match &headline.link {
+        Some(link) => html! {
+            <li class=class>
+                <a href=link.clone() target="_blank" rel="noopener noreferrer">{ title }</a>
+            </li>
+        },
+        None => html! { <li class=class>{ title }</li> },
+    }
+}
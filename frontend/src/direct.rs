@@ -0,0 +1,123 @@
+//! Fetching feeds straight from the browser while the backend is unreachable.
+//!
+//! Only feeds served with CORS headers can be fetched this way. They are parsed by the same
+//! `news-core` code as on the server, so their items get the ids the server gives them, and the
+//! changes made to them meanwhile apply once they are synced back.
+
+use anyhow::Error;
+use news_core::item::{self, NewsItem};
+use news_core::{sanitize, urls};
+use serde_derive::{Deserialize, Serialize};
+use yew::callback::Callback;
+use yew::format::{Binary, Nothing};
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::protocol::{ClientMessage, ItemDto};
+
+/// Items of a feed fetched from the browser, kept until the server is sent them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Unsynced {
+    pub feed: String,
+    /// Title of the channel of the feed.
+    pub title: Option<String>,
+    pub items: Vec<rss::Item>,
+}
+
+impl Unsynced {
+    /// Message handing the items over to the server.
+    pub fn message(self) -> ClientMessage {
+        ClientMessage::Fetched {
+            feed: self.feed,
+            title: self.title,
+            items: self.items,
+        }
+    }
+}
+
+/// Fetch the feed at `feed` and parse its items.
+pub fn fetch(
+    feed: &str,
+    callback: Callback<Result<Vec<NewsItem>, Error>>,
+) -> Result<FetchTask, Error> {
+    let request = Request::get(feed).body(Nothing)?;
+    let feed = feed.to_string();
+    let handler = move |response: Response<Binary>| {
+        let (meta, body) = response.into_parts();
+        if !meta.status.is_success() {
+            let e = Error::msg(format!("request failed: {}", meta.status));
+            return callback.emit(Err(e));
+        }
+        let content_type = meta
+            .headers
+            .get("content-type")
+            .and_then(|value| value.to_str().ok());
+        // Images aren't cached in the browser, the directory only names them.
+        let items = body.and_then(|body| {
+            item::parse_feed(&body, content_type, &feed, Path::new(""))
+        });
+        callback.emit(items);
+    };
+    FetchService::new().fetch_binary(request, handler.into())
+}
+
+/// Drop the items already listed, by id or by the page they link to, as the copies the server
+/// sent know more about them.
+pub fn retain_new<'a, I>(items: &mut Vec<NewsItem>, listed: I)
+where
+    I: IntoIterator<Item = &'a ItemDto>,
+{
+    let mut ids = HashSet::new();
+    let mut links = HashSet::new();
+    for dto in listed {
+        ids.insert(dto.id.clone());
+        links.extend(dto.link.as_deref().and_then(urls::canonical));
+    }
+    items.retain(|item| {
+        !ids.contains(&item.id())
+            && item
+                .canonical_link()
+                .map_or(true, |link| !links.contains(&link))
+    });
+}
+
+/// Build the entry of an item fetched from the browser, which the server knows nothing about yet.
+pub fn dto(item: &NewsItem) -> ItemDto {
+    ItemDto {
+        id: item.id(),
+        feed: item.feed.clone(),
+        feed_title: item.feed_title.clone(),
+        also_in: Vec::new(),
+        title: item.title().map(str::to_owned),
+        link: item.link().map(str::to_owned),
+        description: item.description().map(sanitize::html),
+        excerpt: item
+            .description()
+            .map(|html| sanitize::excerpt(html, sanitize::EXCERPT_LEN)),
+        content: None,
+        summary: None,
+        image_path: None,
+        image_placeholder: None,
+        snapshot_path: None,
+        archived_url: None,
+        revisions: 0,
+        changes: None,
+        dead_link: false,
+        wayback_url: None,
+        title_highlights: Vec::new(),
+        description_highlights: Vec::new(),
+        read: false,
+        starred: false,
+        low_quality: false,
+        priority: 0.0,
+        muted: false,
+        snoozed_until: None,
+        cluster: None,
+        overflow: false,
+        reading_minutes: item.reading_minutes(),
+        annotations: None,
+        pub_date: item.pub_date().map(|date| date.with_timezone(&chrono::Utc)),
+    }
+}
@@ -0,0 +1,108 @@
+//! Feeds recommended by the backend based on what the user reads.
+
+use anyhow::Error;
+use serde_derive::Deserialize;
+use yew::prelude::*;
+use yew::services::fetch::FetchTask;
+
+use crate::api;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Recommendation {
+    pub title: String,
+    pub url: String,
+    pub score: usize,
+    /// Keywords from read items that matched the feed's topics.
+    pub reasons: Vec<String>,
+}
+
+pub struct DiscoverView {
+    link: ComponentLink<Self>,
+    props: Props,
+    recommendations: Option<Vec<Recommendation>>,
+    task: Option<FetchTask>,
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub onclose: Callback<()>,
+}
+
+pub enum Msg {
+    Loaded(Result<Vec<Recommendation>, Error>),
+    Close,
+}
+
+impl Component for DiscoverView {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let task = api::get("/recommendations", link.callback(Msg::Loaded))
+            .map_err(|e| log::error!("failed to request recommendations: {}", e))
+            .ok();
+        DiscoverView {
+            link,
+            props,
+            recommendations: None,
+            task,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Loaded(result) => {
+                self.task = None;
+                match result {
+                    Ok(recommendations) => self.recommendations = Some(recommendations),
+                    Err(e) => {
+                        log::error!("failed to load recommendations: {}", e);
+                        self.recommendations = Some(Vec::new());
+                    }
+                }
+            }
+            Msg::Close => {
+                self.props.onclose.emit(());
+                return false;
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        false
+    }
+
+    fn view(&self) -> Html {
+        let body = match &self.recommendations {
+            None => html! { <p>{ "Loading…" }</p> },
+            Some(recommendations) if recommendations.is_empty() => html! {
+                <p>{ "Read a few more items to get recommendations." }</p>
+            },
+            Some(recommendations) => html! {
+                <ul>
+                    { for recommendations.iter().map(view_recommendation) }
+                </ul>
+            },
+        };
+        html! {
+            <section class="discover">
+                <h2>{ "Discover" }</h2>
+                { body }
+                <button onclick=self.link.callback(|_| Msg::Close)>{ "Close" }</button>
+            </section>
+        }
+    }
+}
+
+fn view_recommendation(recommendation: &Recommendation) -> Html {
+    html! {
+        <li class="mt-2">
+            <a href=recommendation.url.clone() target="_blank">{ &recommendation.title }</a>
+            <div class="text-sm text-gray-600">
+                { "Because you read about " }{ recommendation.reasons.join(", ") }
+            </div>
+        </li>
+    }
+}
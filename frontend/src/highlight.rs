@@ -0,0 +1,48 @@
+//! Emphasis of the highlight terms the backend matched in an item.
+
+use yew::prelude::*;
+
+use crate::protocol::Span;
+
+/// Render plain text with the spans wrapped in `<mark>`.
+pub fn text(text: &str, spans: &[Span]) -> Html {
+    let mut parts: Vec<Html> = Vec::new();
+    let mut last = 0;
+    for span in valid(text, spans) {
+        parts.push(html! { { &text[last..span.start] } });
+        parts.push(html! { <mark>{ &text[span.start..span.end] }</mark> });
+        last = span.end;
+    }
+    parts.push(html! { { &text[last..] } });
+    html! { <>{ for parts }</> }
+}
+
+/// Wrap the spans of sanitized HTML in `<mark>`; spans never cross tags.
+pub fn markup(html: &str, spans: &[Span]) -> String {
+    let mut marked = String::with_capacity(html.len() + spans.len() * 13);
+    let mut last = 0;
+    for span in valid(html, spans) {
+        marked.push_str(&html[last..span.start]);
+        marked.push_str("<mark>");
+        marked.push_str(&html[span.start..span.end]);
+        marked.push_str("</mark>");
+        last = span.end;
+    }
+    marked.push_str(&html[last..]);
+    marked
+}
+
+/// Skip spans that don't fit the text, which happens if it changed since they were computed.
+fn valid<'a>(text: &'a str, spans: &'a [Span]) -> impl Iterator<Item = &'a Span> {
+    let mut last = 0;
+    spans.iter().filter(move |span| {
+        let fits = span.start >= last
+            && span.start <= span.end
+            && text.is_char_boundary(span.start)
+            && text.is_char_boundary(span.end);
+        if fits {
+            last = span.end;
+        }
+        fits
+    })
+}
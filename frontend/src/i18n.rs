@@ -0,0 +1,121 @@
+//! Translations of the interface, written as Fluent files in `locales/`.
+//!
+//! The language is picked once the settings are known, from the setting or else the browser, and
+//! kept for the thread so that views can translate without it being passed around. Messages
+//! missing from a translation fall back to English.
+
+use fluent::{FluentArgs, FluentBundle, FluentResource, FluentValue};
+use unic_langid::LanguageIdentifier;
+
+use std::cell::RefCell;
+
+/// Languages with a translation, with their names in their own language.
+pub const LANGUAGES: [(&str, &str); 4] = [
+    ("en", "English"),
+    ("de", "Deutsch"),
+    ("es", "Español"),
+    ("fr", "Français"),
+];
+
+const FALLBACK: &str = "en";
+
+struct Translations {
+    language: String,
+    bundle: FluentBundle<FluentResource>,
+    fallback: FluentBundle<FluentResource>,
+}
+
+thread_local! {
+    static TRANSLATIONS: RefCell<Translations> = RefCell::new(Translations {
+        language: FALLBACK.to_string(),
+        bundle: bundle(FALLBACK),
+        fallback: bundle(FALLBACK),
+    });
+}
+
+fn source(language: &str) -> &'static str {
+    match language {
+        "de" => include_str!("../locales/de.ftl"),
+        "es" => include_str!("../locales/es.ftl"),
+        "fr" => include_str!("../locales/fr.ftl"),
+        _ => include_str!("../locales/en.ftl"),
+    }
+}
+
+fn bundle(language: &str) -> FluentBundle<FluentResource> {
+    let id: LanguageIdentifier = language.parse().expect("invalid language identifier");
+    let resource = FluentResource::try_new(source(language).to_string())
+        .unwrap_or_else(|(resource, errors)| {
+            log::error!("failed to parse the {} translation: {:?}", language, errors);
+            resource
+        });
+    let mut bundle = FluentBundle::new(&[id]);
+    // Isolation marks around arguments show up in attributes and titles.
+    bundle.set_use_isolating(false);
+    if let Err(errors) = bundle.add_resource(resource) {
+        log::error!("failed to load the {} translation: {:?}", language, errors);
+    }
+    bundle
+}
+
+/// Translate the interface to `language`, or to the language of the browser when it is `None` or
+/// has no translation.
+pub fn set_language(language: Option<&str>) {
+    let language = language
+        .filter(|language| is_supported(language))
+        .map(str::to_owned)
+        .unwrap_or_else(browser_language);
+    TRANSLATIONS.with(|translations| {
+        let mut translations = translations.borrow_mut();
+        if translations.language != language {
+            translations.bundle = bundle(&language);
+            translations.language = language;
+        }
+    });
+}
+
+fn is_supported(language: &str) -> bool {
+    LANGUAGES.iter().any(|(code, _)| *code == language)
+}
+
+/// Return the primary language of the browser if there is a translation to it, English otherwise.
+fn browser_language() -> String {
+    web_sys::window()
+        .and_then(|window| window.navigator().language())
+        .and_then(|language| language.split('-').next().map(str::to_owned))
+        .filter(|language| is_supported(language))
+        .unwrap_or_else(|| FALLBACK.to_string())
+}
+
+/// Return the message `id` in the language of the interface.
+pub fn tr(id: &str) -> String {
+    format(id, None)
+}
+
+/// Return the message `id` in the language of the interface, with its arguments set to `args`.
+pub fn tr_args(id: &str, args: &[(&str, FluentValue)]) -> String {
+    let args: FluentArgs = args.iter().cloned().collect();
+    format(id, Some(&args))
+}
+
+fn format(id: &str, args: Option<&FluentArgs>) -> String {
+    TRANSLATIONS.with(|translations| {
+        let translations = translations.borrow();
+        let message = [&translations.bundle, &translations.fallback]
+            .iter()
+            .find_map(|bundle| Some((*bundle, bundle.get_message(id)?.value?)));
+        let (bundle, pattern) = match message {
+            Some(message) => message,
+            None => {
+                log::warn!("missing translation of {}", id);
+                return id.to_string();
+            }
+        };
+        let mut errors = Vec::new();
+        let text = bundle.format_pattern(pattern, args, &mut errors).into_owned();
+        if !errors.is_empty() {
+            log::warn!("failed to format {}: {:?}", id, errors);
+        }
+        text
+    })
+}
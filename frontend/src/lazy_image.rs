@@ -0,0 +1,129 @@
+//! Images that only load once they scroll into view, showing a blurred placeholder until then.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::{Clamped, JsCast};
+use web_sys::{
+    CanvasRenderingContext2d, Element, HtmlCanvasElement, ImageData, IntersectionObserver,
+    IntersectionObserverEntry,
+};
+use yew::prelude::*;
+
+/// Width and height the placeholder is decoded at before being stretched over the image.
+const PLACEHOLDER_SIZE: u32 = 32;
+
+thread_local! {
+    /// Observer shared by every lazy image, loading each one as it becomes visible.
+    static OBSERVER: IntersectionObserver = create_observer();
+}
+
+pub struct LazyImage {
+    props: Props,
+    node: NodeRef,
+    /// Data URL of the decoded placeholder.
+    placeholder: Option<String>,
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub src: String,
+    /// Blurhash of the image.
+    #[prop_or_default]
+    pub placeholder: Option<String>,
+    #[prop_or_default]
+    pub class: String,
+}
+
+impl Component for LazyImage {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+        let placeholder = props.placeholder.as_deref().and_then(decode_placeholder);
+        LazyImage {
+            props,
+            node: NodeRef::default(),
+            placeholder,
+        }
+    }
+
+    fn mounted(&mut self) -> ShouldRender {
+        if let Some(element) = self.node.cast::<Element>() {
+            OBSERVER.with(|observer| observer.observe(&element));
+        }
+        false
+    }
+
+    fn update(&mut self, _: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if props.src == self.props.src {
+            return false;
+        }
+
+        self.placeholder = props.placeholder.as_deref().and_then(decode_placeholder);
+        self.props = props;
+        if let Some(element) = self.node.cast::<Element>() {
+            OBSERVER.with(|observer| observer.observe(&element));
+        }
+        true
+    }
+
+    fn view(&self) -> Html {
+        let style = match &self.placeholder {
+            Some(url) => format!("background-image: url({}); background-size: cover;", url),
+            None => String::new(),
+        };
+        html! {
+            <img ref=self.node.clone() class=&self.props.class style=style
+                data-src=&self.props.src />
+        }
+    }
+}
+
+fn create_observer() -> IntersectionObserver {
+    let callback = Closure::wrap(Box::new(
+        |entries: js_sys::Array, observer: IntersectionObserver| {
+            for entry in entries.iter() {
+                let entry: IntersectionObserverEntry = entry.unchecked_into();
+                if !entry.is_intersecting() {
+                    continue;
+                }
+
+                let target = entry.target();
+                if let Some(src) = target.get_attribute("data-src") {
+                    let _ = target.set_attribute("src", &src);
+                }
+                observer.unobserve(&target);
+            }
+        },
+    ) as Box<dyn FnMut(js_sys::Array, IntersectionObserver)>);
+
+    let observer = IntersectionObserver::new(callback.as_ref().unchecked_ref())
+        .expect("failed to create intersection observer");
+    // The observer lives for the whole page, so its callback does too.
+    callback.forget();
+    observer
+}
+
+/// Decode a blurhash into a data URL by drawing it onto a small canvas.
+fn decode_placeholder(hash: &str) -> Option<String> {
+    let mut pixels = blurhash::decode(hash, PLACEHOLDER_SIZE, PLACEHOLDER_SIZE, 1.0);
+    let canvas: HtmlCanvasElement = yew::utils::document()
+        .create_element("canvas")
+        .ok()?
+        .dyn_into()
+        .ok()?;
+    canvas.set_width(PLACEHOLDER_SIZE);
+    canvas.set_height(PLACEHOLDER_SIZE);
+    let context: CanvasRenderingContext2d = canvas.get_context("2d").ok()??.dyn_into().ok()?;
+    let data = ImageData::new_with_u8_clamped_array_and_sh(
+        Clamped(&mut pixels),
+        PLACEHOLDER_SIZE,
+        PLACEHOLDER_SIZE,
+    )
+    .ok()?;
+    context.put_image_data(&data, 0.0, 0.0).ok()?;
+    canvas.to_data_url().ok()
+}
@@ -1,6 +1,24 @@
 #![recursion_limit = "512"]
 
+mod annotations;
+mod api;
 mod app;
+mod digest;
+mod direct;
+mod discover;
+mod highlight;
+mod i18n;
+mod lazy_image;
+mod mutes;
+mod palette;
+mod protocol;
+mod settings;
+mod share;
+mod sidebar;
+mod speech;
+mod theme;
+mod time;
+mod toast;
 mod utils;
 
 use wasm_bindgen::prelude::*;
@@ -0,0 +1,223 @@
+//! Temporary mutes of feeds and keywords, lifted by the backend once they expire.
+
+use anyhow::Error;
+use chrono::{DateTime, Duration, Utc};
+use serde_derive::{Deserialize, Serialize};
+use yew::prelude::*;
+use yew::services::fetch::FetchTask;
+
+use crate::api;
+use crate::time;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mute {
+    #[serde(flatten)]
+    pub target: MuteTarget,
+    pub until: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum MuteTarget {
+    Feed(String),
+    Keyword(String),
+}
+
+/// How long a mute lasts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MuteFor {
+    Day,
+    Week,
+    Month,
+}
+
+impl MuteFor {
+    pub fn label(self) -> &'static str {
+        match self {
+            MuteFor::Day => "1 day",
+            MuteFor::Week => "1 week",
+            MuteFor::Month => "1 month",
+        }
+    }
+
+    pub fn duration(self) -> Duration {
+        match self {
+            MuteFor::Day => Duration::days(1),
+            MuteFor::Week => Duration::weeks(1),
+            MuteFor::Month => Duration::days(30),
+        }
+    }
+}
+
+pub struct MutesView {
+    link: ComponentLink<Self>,
+    props: Props,
+    /// Active mutes, `None` until loaded.
+    mutes: Option<Vec<Mute>>,
+    /// Keyword being typed.
+    keyword: String,
+    task: Option<FetchTask>,
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    /// URLs of the configured feeds.
+    pub feeds: Vec<String>,
+    pub onclose: Callback<()>,
+}
+
+pub enum Msg {
+    Loaded(Result<Vec<Mute>, Error>),
+    Keyword(String),
+    MuteKeyword(MuteFor),
+    MuteFeed(String, MuteFor),
+    Unmute(usize),
+    Close,
+}
+
+impl Component for MutesView {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let task = api::get("/mutes", link.callback(Msg::Loaded))
+            .map_err(|e| log::error!("failed to request mutes: {}", e))
+            .ok();
+        MutesView {
+            link,
+            props,
+            mutes: None,
+            keyword: String::new(),
+            task,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Loaded(result) => {
+                self.task = None;
+                match result {
+                    Ok(mutes) => self.mutes = Some(mutes),
+                    Err(e) => log::error!("failed to load mutes: {}", e),
+                }
+            }
+            Msg::Keyword(keyword) => self.keyword = keyword,
+            Msg::MuteKeyword(duration) => {
+                let keyword = self.keyword.trim().to_string();
+                if keyword.is_empty() {
+                    return false;
+                }
+                self.keyword.clear();
+                self.add(MuteTarget::Keyword(keyword), duration);
+            }
+            Msg::MuteFeed(feed, duration) => self.add(MuteTarget::Feed(feed), duration),
+            Msg::Unmute(idx) => {
+                let mut mutes = self.mutes.clone().unwrap_or_default();
+                if idx < mutes.len() {
+                    mutes.remove(idx);
+                    self.save(mutes);
+                }
+            }
+            Msg::Close => {
+                self.props.onclose.emit(());
+                return false;
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let mutes = match &self.mutes {
+            None => html! { <p>{ "Loading…" }</p> },
+            Some(mutes) if mutes.is_empty() => html! { <p>{ "Nothing is muted." }</p> },
+            Some(mutes) => html! {
+                <ul>
+                    { for mutes.iter().enumerate().map(|(idx, mute)| self.view_mute(idx, mute)) }
+                </ul>
+            },
+        };
+        let durations = [MuteFor::Day, MuteFor::Week, MuteFor::Month];
+        html! {
+            <section class="mutes">
+                <h2>{ "Mutes" }</h2>
+                { mutes }
+                <h3 class="mt-4">{ "Mute a keyword" }</h3>
+                <input type="text" placeholder="election" value=self.keyword.clone()
+                    oninput=self.link.callback(|e: InputData| Msg::Keyword(e.value)) />
+                { for durations.iter().map(|&duration| html! {
+                    <button onclick=self.link.callback(move |_| Msg::MuteKeyword(duration))>
+                        { duration.label() }
+                    </button>
+                }) }
+                <h3 class="mt-4">{ "Mute a feed" }</h3>
+                <ul>
+                    { for self.props.feeds.iter().map(|feed| self.view_feed(feed)) }
+                </ul>
+                <button class="mt-4" onclick=self.link.callback(|_| Msg::Close)>
+                    { "Close" }
+                </button>
+            </section>
+        }
+    }
+}
+
+impl MutesView {
+    /// Mute `target` for `duration` from now, replacing any mute of it.
+    fn add(&mut self, target: MuteTarget, duration: MuteFor) {
+        let mut mutes = self.mutes.clone().unwrap_or_default();
+        mutes.retain(|mute| mute.target != target);
+        mutes.push(Mute {
+            target,
+            until: time::now() + duration.duration(),
+        });
+        self.save(mutes);
+    }
+
+    fn save(&mut self, mutes: Vec<Mute>) {
+        self.task = api::put("/mutes", &mutes, self.link.callback(Msg::Loaded))
+            .map_err(|e| log::error!("failed to save mutes: {}", e))
+            .ok();
+    }
+
+    fn view_mute(&self, idx: usize, mute: &Mute) -> Html {
+        let target = match &mute.target {
+            MuteTarget::Feed(feed) => format!("Feed {}", feed),
+            MuteTarget::Keyword(keyword) => format!("Keyword \"{}\"", keyword),
+        };
+        let until = mute.until.with_timezone(&time::local_offset());
+        html! {
+            <li class="mt-2">
+                { target }
+                <span class="ml-2 text-sm text-gray-600">
+                    { format!("until {}", until.format("%b %-d, %H:%M")) }
+                </span>
+                <button class="ml-2" onclick=self.link.callback(move |_| Msg::Unmute(idx))>
+                    { "Unmute" }
+                </button>
+            </li>
+        }
+    }
+
+    fn view_feed(&self, feed: &str) -> Html {
+        let durations = [MuteFor::Day, MuteFor::Week, MuteFor::Month];
+        html! {
+            <li class="mt-2">
+                { feed }
+                { for durations.iter().map(|&duration| {
+                    let feed = feed.to_string();
+                    let onclick = self
+                        .link
+                        .callback(move |_| Msg::MuteFeed(feed.clone(), duration));
+                    html! {
+                        <button class="ml-2" onclick=onclick>{ duration.label() }</button>
+                    }
+                }) }
+            </li>
+        }
+    }
+}
@@ -0,0 +1,272 @@
+//! The command palette, opened with Ctrl+K, running an action or jumping to a feed or folder by
+//! typing part of its name.
+
+use web_sys::HtmlElement;
+use yew::prelude::*;
+
+use std::collections::HashMap;
+
+use crate::protocol;
+use crate::sidebar::Folder;
+
+/// Maximum number of matches listed.
+const MAX_MATCHES: usize = 10;
+
+/// What the user picked in the palette.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Feed(String),
+    /// Name of a folder.
+    Folder(String),
+    Command(Command),
+}
+
+/// Actions run from the palette.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    MarkAllRead,
+    FetchNews,
+    ToggleTheme,
+    /// Subscribe to the feed at the URL typed.
+    AddFeed(String),
+    OpenSettings,
+    OpenDigest,
+    OpenQueue,
+    OpenMutes,
+    Discover,
+    ExportStarred,
+}
+
+impl Command {
+    /// Commands that don't take an argument.
+    const ALL: [Command; 9] = [
+        Command::MarkAllRead,
+        Command::FetchNews,
+        Command::ToggleTheme,
+        Command::OpenSettings,
+        Command::OpenDigest,
+        Command::OpenQueue,
+        Command::OpenMutes,
+        Command::Discover,
+        Command::ExportStarred,
+    ];
+
+    pub fn label(&self) -> String {
+        let label = match self {
+            Command::MarkAllRead => "Mark all read",
+            Command::FetchNews => "Fetch news",
+            Command::ToggleTheme => "Toggle dark theme",
+            Command::AddFeed(url) => return format!("Add feed {}", url),
+            Command::OpenSettings => "Open settings",
+            Command::OpenDigest => "Open digest",
+            Command::OpenQueue => "Open reading queue",
+            Command::OpenMutes => "Open mutes",
+            Command::Discover => "Discover feeds",
+            Command::ExportStarred => "Export starred items as RSS",
+        };
+        label.to_string()
+    }
+}
+
+pub struct Palette {
+    link: ComponentLink<Self>,
+    props: Props,
+    input: NodeRef,
+    query: String,
+    /// Position of the highlighted match.
+    selected: usize,
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    /// URLs of the subscribed feeds.
+    pub feeds: Vec<String>,
+    /// Titles of the feeds, keyed by URL.
+    pub titles: HashMap<String, String>,
+    pub folders: Vec<Folder>,
+    pub onjump: Callback<Target>,
+    pub onclose: Callback<()>,
+}
+
+pub enum Msg {
+    Query(String),
+    Key(KeyboardEvent),
+    Jump(Target),
+}
+
+impl Component for Palette {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Palette {
+            link,
+            props,
+            input: NodeRef::default(),
+            query: String::new(),
+            selected: 0,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Query(query) => {
+                self.query = query;
+                self.selected = 0;
+            }
+            Msg::Key(e) => match e.key().as_str() {
+                "ArrowDown" => {
+                    e.prevent_default();
+                    let count = self.matches().len();
+                    if self.selected + 1 < count {
+                        self.selected += 1;
+                    }
+                }
+                "ArrowUp" => {
+                    e.prevent_default();
+                    self.selected = self.selected.saturating_sub(1);
+                }
+                "Enter" => {
+                    if let Some((target, _)) = self.matches().into_iter().nth(self.selected) {
+                        self.props.onjump.emit(target);
+                    }
+                    return false;
+                }
+                "Escape" => {
+                    self.props.onclose.emit(());
+                    return false;
+                }
+                _ => return false,
+            },
+            Msg::Jump(target) => {
+                self.props.onjump.emit(target);
+                return false;
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn rendered(&mut self, first_render: bool) {
+        if !first_render {
+            return;
+        }
+        if let Some(input) = self.input.cast::<HtmlElement>() {
+            let _ = input.focus();
+        }
+    }
+
+    fn view(&self) -> Html {
+        let matches = self.matches();
+        let empty = if matches.is_empty() {
+            html! { <li class="text-gray-600">{ "Nothing matches." }</li> }
+        } else {
+            html! {}
+        };
+        html! {
+            <div class="palette fixed inset-x-0 top-0 mx-auto mt-16 max-w-lg p-2 shadow-lg border"
+                role="dialog" aria-modal="true" aria-label="Command palette">
+                <input type="text" class="w-full" placeholder="Run a command or jump to a feed"
+                    role="combobox" aria-expanded="true" aria-controls="palette-matches"
+                    aria-autocomplete="list" aria-activedescendant=match_id(self.selected)
+                    ref=self.input.clone() value=self.query.clone()
+                    oninput=self.link.callback(|e: InputData| Msg::Query(e.value))
+                    onkeydown=self.link.callback(Msg::Key) />
+                <ul id="palette-matches" role="listbox">
+                    { empty }
+                    { for matches.into_iter().enumerate().map(|(idx, (target, label))| {
+                        self.view_match(idx, target, label)
+                    }) }
+                </ul>
+            </div>
+        }
+    }
+}
+
+impl Palette {
+    /// Commands, feeds and folders matching the query, best first, with their labels.
+    fn matches(&self) -> Vec<(Target, String)> {
+        let query = self.query.trim();
+        // Typing a URL offers to subscribe to it, whatever else matches.
+        let add = if query.starts_with("http://") || query.starts_with("https://") {
+            Some(Command::AddFeed(query.to_string()))
+        } else {
+            None
+        };
+        let commands = Command::ALL.iter().map(|command| {
+            let label = command.label();
+            (Target::Command(command.clone()), label.clone(), label)
+        });
+        let folders = self.props.folders.iter().map(|folder| {
+            let label = format!("Folder {}", folder.name);
+            (Target::Folder(folder.name.clone()), folder.name.clone(), label)
+        });
+        let feeds = self.props.feeds.iter().map(|feed| {
+            let title = self.props.titles.get(feed).map(String::as_str);
+            let name = protocol::feed_name(feed, title);
+            (Target::Feed(feed.clone()), name.to_string(), name.to_string())
+        });
+        let mut scored: Vec<(u32, Target, String)> = commands
+            .chain(folders)
+            .chain(feeds)
+            .filter_map(|(target, name, label)| Some((score(query, &name)?, target, label)))
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.2.cmp(&b.2)));
+        add.map(|command| {
+            let label = command.label();
+            (Target::Command(command), label)
+        })
+        .into_iter()
+        .chain(scored.into_iter().map(|(_, target, label)| (target, label)))
+        .take(MAX_MATCHES)
+        .collect()
+    }
+
+    fn view_match(&self, idx: usize, target: Target, label: String) -> Html {
+        let selected = idx == self.selected;
+        html! {
+            <li class=if selected { "selected" } else { "" } id=match_id(idx) role="option"
+                aria-selected=selected.to_string()>
+                <a href="#" tabindex="-1"
+                    onclick=self.link.callback(move |_| Msg::Jump(target.clone()))>
+                    { label }
+                </a>
+            </li>
+        }
+    }
+}
+
+/// Id of the element listing the match at `idx`.
+fn match_id(idx: usize) -> String {
+    format!("palette-match-{}", idx)
+}
+
+/// Score how well `name` matches `query`, whose characters must all appear in it in order.
+/// Characters following each other or starting a word score higher.
+fn score(query: &str, name: &str) -> Option<u32> {
+    let mut score = 0;
+    let mut chars = name.chars().flat_map(char::to_lowercase);
+    let mut previous: Option<char> = None;
+    let mut consecutive = false;
+    for wanted in query.chars().flat_map(char::to_lowercase) {
+        if wanted.is_whitespace() {
+            continue;
+        }
+        loop {
+            let c = chars.next()?;
+            let word_start = previous.map_or(true, |p| !p.is_alphanumeric());
+            previous = Some(c);
+            if c == wanted {
+                score += 1 + if consecutive { 2 } else { 0 } + if word_start { 3 } else { 0 };
+                consecutive = true;
+                break;
+            }
+            consecutive = false;
+        }
+    }
+    Some(score)
+}
@@ -0,0 +1,309 @@
+//! Messages exchanged with the backend over the websocket.
+
+use anyhow::Error;
+use serde_derive::{Deserialize, Serialize};
+use yew::format::{Binary, Text};
+
+/// Message sent from the server to the client.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Items(ItemBatch),
+    Position(ReadPosition),
+    ResyncNeeded,
+    Progress(RefreshProgress),
+}
+
+/// Message sent from the client to the server.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Ack { up_to: u64 },
+    Resync,
+    /// Changes are dated `at` and sent at `sent_at` on this device, which lets the server make up
+    /// for its clock skew when resolving conflicting changes.
+    MarkRead {
+        id: String,
+        at: chrono::DateTime<chrono::Utc>,
+        sent_at: chrono::DateTime<chrono::Utc>,
+    },
+    MarkJunk { id: String },
+    Star {
+        id: String,
+        starred: bool,
+        at: chrono::DateTime<chrono::Utc>,
+        sent_at: chrono::DateTime<chrono::Utc>,
+    },
+    Snooze {
+        id: String,
+        until: chrono::DateTime<chrono::Utc>,
+    },
+    Position { id: String },
+    CancelRefresh,
+    /// Items of a feed fetched from the browser while the server was unreachable.
+    Fetched {
+        feed: String,
+        title: Option<String>,
+        items: Vec<rss::Item>,
+    },
+}
+
+/// Where the user left off reading, possibly on another device.
+#[derive(Debug, Deserialize)]
+pub struct ReadPosition {
+    pub id: String,
+    pub at: chrono::DateTime<chrono::Utc>,
+}
+
+/// How far the feeds being refreshed are fetched.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RefreshProgress {
+    pub feeds_done: usize,
+    pub feeds_total: usize,
+    /// Feed whose fetch started last, `None` once every feed was fetched.
+    pub current_feed: Option<String>,
+}
+
+/// A batch of news items.
+#[derive(Debug, Deserialize)]
+pub struct ItemBatch {
+    pub seq: u64,
+    pub items: Vec<ItemDto>,
+    /// The items were stored before a refresh still running, whose fetched items follow.
+    #[serde(default)]
+    pub stale: bool,
+    #[serde(default)]
+    pub patches: Vec<ItemPatch>,
+}
+
+/// Change to the read or starred state of an item already received.
+#[derive(Debug, Deserialize)]
+pub struct ItemPatch {
+    pub id: String,
+    #[serde(default)]
+    pub read: Option<bool>,
+    #[serde(default)]
+    pub starred: Option<bool>,
+}
+
+/// Word diffs of an edited item against its previous revision.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Changes {
+    pub title: Vec<Change>,
+    pub description: Vec<Change>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Same,
+    Added,
+    Removed,
+}
+
+/// A feed an item was published in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Source {
+    pub feed: String,
+    pub title: Option<String>,
+}
+
+impl Source {
+    /// Title of the feed, falling back to its host.
+    pub fn name(&self) -> &str {
+        feed_name(&self.feed, self.title.as_deref())
+    }
+}
+
+/// Return `title`, falling back to the host of `feed`.
+pub fn feed_name<'a>(feed: &'a str, title: Option<&'a str>) -> &'a str {
+    title.unwrap_or_else(|| {
+        feed.split("://")
+            .nth(1)
+            .and_then(|rest| rest.split('/').next())
+            .unwrap_or(feed)
+    })
+}
+
+/// The subset of a news item needed to render it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemDto {
+    pub id: String,
+    pub feed: String,
+    #[serde(default)]
+    pub feed_title: Option<String>,
+    /// Other feeds the item was also published in.
+    #[serde(default)]
+    pub also_in: Vec<Source>,
+    pub title: Option<String>,
+    pub link: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub excerpt: Option<String>,
+    pub content: Option<String>,
+    #[serde(default)]
+    pub summary: Option<String>,
+    pub image_path: Option<String>,
+    pub image_placeholder: Option<String>,
+    #[serde(default)]
+    pub snapshot_path: Option<String>,
+    #[serde(default)]
+    pub archived_url: Option<String>,
+    /// Number of times the feed edited the item.
+    #[serde(default)]
+    pub revisions: usize,
+    #[serde(default)]
+    pub changes: Option<Changes>,
+    #[serde(default)]
+    pub dead_link: bool,
+    #[serde(default)]
+    pub wayback_url: Option<String>,
+    #[serde(default)]
+    pub title_highlights: Vec<Span>,
+    #[serde(default)]
+    pub description_highlights: Vec<Span>,
+    /// Whether the item was read on any device.
+    #[serde(default)]
+    pub read: bool,
+    #[serde(default)]
+    pub starred: bool,
+    #[serde(default)]
+    pub low_quality: bool,
+    /// Likelihood that the user reads the item, ranking the priority inbox.
+    #[serde(default)]
+    pub priority: f64,
+    /// Whether the item is kept out of the unread list, e.g. because its feed is paused.
+    #[serde(default)]
+    pub muted: bool,
+    #[serde(default)]
+    pub snoozed_until: Option<chrono::DateTime<chrono::Utc>>,
+    /// Id shared by the items covering the same story.
+    #[serde(default)]
+    pub cluster: Option<String>,
+    /// Whether the item is left out of the river, only showing in the view of its feed.
+    #[serde(default)]
+    pub overflow: bool,
+    /// Estimated time it takes to read the item.
+    #[serde(default)]
+    pub reading_minutes: u32,
+    /// The user's notes and highlights.
+    #[serde(default)]
+    pub annotations: Option<Annotations>,
+    pub pub_date: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Notes and highlights of an item, stored through `PUT /api/items/{id}/annotations`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Annotations {
+    /// Note on the item as a whole.
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Highlights in the order they appear in the text.
+    #[serde(default)]
+    pub highlights: Vec<Highlight>,
+}
+
+impl Annotations {
+    pub fn is_empty(&self) -> bool {
+        self.note.is_none() && self.highlights.is_empty()
+    }
+}
+
+/// A range of characters of the text of the body of an item the user highlighted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Highlight {
+    pub start: usize,
+    /// Offset of the character after the last one highlighted.
+    pub end: usize,
+    /// Text highlighted.
+    pub text: String,
+    #[serde(default)]
+    pub note: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Items to read in a time-boxed session, returned by `GET /api/catch_up`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// Ids of the items, in reading order.
+    pub ids: Vec<String>,
+    /// Estimated minutes it takes to read every item.
+    pub minutes: u32,
+}
+
+/// Translated text of an item, returned by `POST /api/items/{id}/translate`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Translation {
+    pub to: String,
+    pub title: Option<String>,
+    pub body: Option<String>,
+}
+
+/// Byte range of a highlight term match.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Serialization format requested from the server.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Json,
+    Msgpack,
+}
+
+/// Build the websocket url asking the server for the given encoding, authorized by `token` if the
+/// server requires one.
+pub fn connect_url(base: &str, encoding: Encoding, token: Option<&str>) -> String {
+    let encoding = match encoding {
+        Encoding::Json => "json",
+        Encoding::Msgpack => "msgpack",
+    };
+    let mut url = format!("{}?encoding={}", base, encoding);
+    if let Some(token) = token {
+        url.push_str("&token=");
+        url.push_str(&String::from(js_sys::encode_uri_component(token)));
+    }
+    url
+}
+
+/// A raw frame received from the websocket.
+pub enum Frame {
+    Text(Text),
+    Binary(Binary),
+}
+
+impl From<Text> for Frame {
+    fn from(text: Text) -> Self {
+        Frame::Text(text)
+    }
+}
+
+impl From<Binary> for Frame {
+    fn from(binary: Binary) -> Self {
+        Frame::Binary(binary)
+    }
+}
+
+impl Frame {
+    /// Decode the frame as a server message given what was requested at connect time.
+    pub fn decode(self, encoding: Encoding) -> Result<ServerMessage, Error> {
+        match self {
+            Frame::Text(text) => Ok(serde_json::from_str(&text?)?),
+            Frame::Binary(bytes) => {
+                let bytes = bytes?;
+                match encoding {
+                    Encoding::Json => Ok(serde_json::from_slice(&bytes)?),
+                    Encoding::Msgpack => Ok(rmp_serde::from_read_ref(&bytes)?),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,993 @@
+//! The settings view and the settings shared with the backend.
+
+use chrono::NaiveTime;
+use news_core::timezone;
+use serde_derive::{Deserialize, Serialize};
+use yew::prelude::*;
+
+use std::collections::HashMap;
+
+use crate::app::Filter;
+use crate::i18n::{self, tr};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub refresh_interval_minutes: u32,
+    pub items_per_page: usize,
+    pub theme: Theme,
+    pub high_contrast: bool,
+    /// Stylesheet applied over the theme.
+    pub custom_css: String,
+    /// Language of the interface, following the browser when unset.
+    pub language: Option<String>,
+    /// IANA name of the timezone of dates and days, following the browser when unset.
+    pub timezone: Option<String>,
+    pub default_filter: Filter,
+    pub mark_read_on_expand: bool,
+    pub priority_inbox: bool,
+    pub typography: Typography,
+    pub archive_starred: bool,
+    pub wayback_starred: bool,
+    pub unshorten_links: bool,
+    pub images: ImagePolicy,
+    pub notifications: NotificationSettings,
+    pub quiet_hours: QuietHours,
+    pub anomalies: AnomalySettings,
+    pub quality_threshold: f64,
+    pub highlights: Vec<String>,
+    /// Number of items of each feed surfacing in the river per day, if limited.
+    pub river_max_per_day: Option<usize>,
+    /// What becomes of items dated in the future.
+    pub future_items: FutureItems,
+    pub feeds: HashMap<String, FeedSettings>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeedSettings {
+    pub content: ContentMode,
+    pub keep_latest: Option<usize>,
+    pub paused: bool,
+    pub alias_of: Option<String>,
+    pub max_per_day: Option<usize>,
+    pub transform: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentMode {
+    Summary,
+    FullContent,
+}
+
+impl Default for ContentMode {
+    fn default() -> Self {
+        ContentMode::Summary
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FutureItems {
+    /// Date them when they are first fetched.
+    Clamp,
+    /// Keep them out of the lists until their date passes.
+    Hold,
+}
+
+impl Default for FutureItems {
+    fn default() -> Self {
+        FutureItems::Clamp
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImagePolicy {
+    pub caching: ImageCaching,
+    pub max_size_kb: Option<u32>,
+    pub schedule: ImageSchedule,
+    pub transcode: Transcode,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transcode {
+    pub format: TranscodeFormat,
+    pub quality: u8,
+}
+
+impl Default for Transcode {
+    fn default() -> Self {
+        Transcode {
+            format: TranscodeFormat::Off,
+            quality: 75,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscodeFormat {
+    Off,
+    Webp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageCaching {
+    All,
+    Starred,
+    Off,
+}
+
+impl Default for ImageCaching {
+    fn default() -> Self {
+        ImageCaching::All
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageSchedule {
+    pub enabled: bool,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl Default for ImageSchedule {
+    fn default() -> Self {
+        ImageSchedule {
+            enabled: false,
+            start: NaiveTime::from_hms(1, 0, 0),
+            end: NaiveTime::from_hms(6, 0, 0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    System,
+    Light,
+    Dark,
+    Sepia,
+    Nord,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Typography {
+    pub font: Font,
+    pub font_size_px: u32,
+    pub line_height: f64,
+    pub max_width_ch: u32,
+    pub smart_punctuation: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Font {
+    Sans,
+    Serif,
+}
+
+impl Default for Typography {
+    fn default() -> Self {
+        Typography {
+            font: Font::Sans,
+            font_size_px: 16,
+            line_height: 1.6,
+            max_width_ch: 70,
+            smart_punctuation: false,
+        }
+    }
+}
+
+impl Typography {
+    /// Inline style of the reading pane.
+    pub fn style(&self) -> String {
+        let family = match self.font {
+            Font::Sans => "ui-sans-serif, system-ui, sans-serif",
+            Font::Serif => "Georgia, Cambria, 'Times New Roman', serif",
+        };
+        format!(
+            "font-family: {}; font-size: {}px; line-height: {}; max-width: {}ch;",
+            family, self.font_size_px, self.line_height, self.max_width_ch
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub new_items: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuietHours {
+    pub enabled: bool,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub refresh_interval_minutes: u32,
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        QuietHours {
+            enabled: false,
+            start: NaiveTime::from_hms(22, 0, 0),
+            end: NaiveTime::from_hms(7, 0, 0),
+            refresh_interval_minutes: 180,
+        }
+    }
+}
+
+impl QuietHours {
+    /// Whether `time` falls within quiet hours.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnomalySettings {
+    pub enabled: bool,
+    pub silence_factor: f64,
+    pub flood_factor: f64,
+    pub notify: bool,
+}
+
+impl Default for AnomalySettings {
+    fn default() -> Self {
+        AnomalySettings {
+            enabled: true,
+            silence_factor: 3.0,
+            flood_factor: 10.0,
+            notify: false,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            refresh_interval_minutes: 30,
+            items_per_page: 50,
+            theme: Theme::System,
+            high_contrast: false,
+            custom_css: String::new(),
+            language: None,
+            timezone: None,
+            default_filter: Filter::All,
+            mark_read_on_expand: true,
+            priority_inbox: false,
+            typography: Typography::default(),
+            archive_starred: false,
+            wayback_starred: false,
+            unshorten_links: false,
+            images: ImagePolicy::default(),
+            notifications: NotificationSettings::default(),
+            quiet_hours: QuietHours::default(),
+            anomalies: AnomalySettings::default(),
+            quality_threshold: 0.5,
+            highlights: Vec::new(),
+            river_max_per_day: None,
+            future_items: FutureItems::Clamp,
+            feeds: HashMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Return the settings of a feed, using defaults for feeds without any.
+    pub fn feed(&self, url: &str) -> FeedSettings {
+        self.feeds.get(url).cloned().unwrap_or_default()
+    }
+}
+
+pub struct SettingsView {
+    link: ComponentLink<Self>,
+    props: Props,
+    /// Settings being edited, only sent to `onsave` when saved.
+    draft: Settings,
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub settings: Settings,
+    /// URLs of the configured feeds.
+    pub feeds: Vec<String>,
+    pub onsave: Callback<Settings>,
+    pub oncancel: Callback<()>,
+}
+
+pub enum Msg {
+    RefreshInterval(String),
+    ItemsPerPage(String),
+    Theme(String),
+    ToggleHighContrast,
+    Language(String),
+    Timezone(String),
+    CustomCss(String),
+    DefaultFilter(String),
+    ToggleNewItemNotifications,
+    ToggleMarkReadOnExpand,
+    TogglePriorityInbox,
+    ToggleArchiveStarred,
+    ToggleWaybackStarred,
+    ToggleUnshortenLinks,
+    ToggleSmartPunctuation,
+    ImageCaching(String),
+    ImageMaxSize(String),
+    ToggleImageSchedule,
+    ImageScheduleStart(String),
+    ImageScheduleEnd(String),
+    TranscodeFormat(String),
+    TranscodeQuality(String),
+    Font(String),
+    FontSize(String),
+    LineHeight(String),
+    ContentWidth(String),
+    Highlights(String),
+    RiverMaxPerDay(String),
+    FutureItems(String),
+    QualityThreshold(String),
+    ToggleQuietHours,
+    QuietStart(String),
+    QuietEnd(String),
+    QuietRefreshInterval(String),
+    ToggleAnomalies,
+    SilenceFactor(String),
+    FloodFactor(String),
+    ToggleAnomalyNotifications,
+    FeedContent(String, String),
+    FeedKeepLatest(String, String),
+    FeedMaxPerDay(String, String),
+    ToggleFeedPaused(String),
+    FeedAliasOf(String, String),
+    FeedTransform(String, String),
+    Save,
+    Cancel,
+}
+
+impl Component for SettingsView {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let draft = props.settings.clone();
+        SettingsView { link, props, draft }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::RefreshInterval(value) => {
+                if let Ok(minutes) = value.parse() {
+                    self.draft.refresh_interval_minutes = minutes;
+                }
+            }
+            Msg::ItemsPerPage(value) => {
+                if let Ok(count) = value.parse() {
+                    self.draft.items_per_page = count;
+                }
+            }
+            Msg::Theme(value) => {
+                self.draft.theme = match value.as_str() {
+                    "light" => Theme::Light,
+                    "dark" => Theme::Dark,
+                    "sepia" => Theme::Sepia,
+                    "nord" => Theme::Nord,
+                    _ => Theme::System,
+                };
+            }
+            Msg::ToggleHighContrast => {
+                self.draft.high_contrast = !self.draft.high_contrast;
+            }
+            Msg::Language(value) => {
+                self.draft.language = Some(value).filter(|language| !language.is_empty());
+            }
+            Msg::Timezone(value) => {
+                self.draft.timezone = Some(value).filter(|timezone| !timezone.is_empty());
+            }
+            Msg::CustomCss(value) => {
+                self.draft.custom_css = value;
+            }
+            Msg::DefaultFilter(value) => {
+                self.draft.default_filter = match value.as_str() {
+                    "read" => Filter::Read,
+                    "unread" => Filter::Unread,
+                    "low_quality" => Filter::LowQuality,
+                    "priority" => Filter::Priority,
+                    _ => Filter::All,
+                };
+            }
+            Msg::ToggleNewItemNotifications => {
+                self.draft.notifications.new_items = !self.draft.notifications.new_items;
+            }
+            Msg::ToggleMarkReadOnExpand => {
+                self.draft.mark_read_on_expand = !self.draft.mark_read_on_expand;
+            }
+            Msg::TogglePriorityInbox => {
+                self.draft.priority_inbox = !self.draft.priority_inbox;
+            }
+            Msg::ToggleArchiveStarred => {
+                self.draft.archive_starred = !self.draft.archive_starred;
+            }
+            Msg::ToggleWaybackStarred => {
+                self.draft.wayback_starred = !self.draft.wayback_starred;
+            }
+            Msg::ToggleUnshortenLinks => {
+                self.draft.unshorten_links = !self.draft.unshorten_links;
+            }
+            Msg::ToggleSmartPunctuation => {
+                let typography = &mut self.draft.typography;
+                typography.smart_punctuation = !typography.smart_punctuation;
+            }
+            Msg::ImageCaching(value) => {
+                self.draft.images.caching = match value.as_str() {
+                    "starred" => ImageCaching::Starred,
+                    "off" => ImageCaching::Off,
+                    _ => ImageCaching::All,
+                };
+            }
+            Msg::ImageMaxSize(value) => {
+                // An empty field lifts the limit.
+                self.draft.images.max_size_kb = value.parse().ok().filter(|&kb: &u32| kb > 0);
+            }
+            Msg::ToggleImageSchedule => {
+                self.draft.images.schedule.enabled = !self.draft.images.schedule.enabled;
+            }
+            Msg::ImageScheduleStart(value) => {
+                if let Ok(time) = NaiveTime::parse_from_str(&value, "%H:%M") {
+                    self.draft.images.schedule.start = time;
+                }
+            }
+            Msg::ImageScheduleEnd(value) => {
+                if let Ok(time) = NaiveTime::parse_from_str(&value, "%H:%M") {
+                    self.draft.images.schedule.end = time;
+                }
+            }
+            Msg::TranscodeFormat(value) => {
+                self.draft.images.transcode.format = match value.as_str() {
+                    "webp" => TranscodeFormat::Webp,
+                    _ => TranscodeFormat::Off,
+                };
+            }
+            Msg::TranscodeQuality(value) => {
+                if let Ok(quality) = value.parse() {
+                    self.draft.images.transcode.quality = quality;
+                }
+            }
+            Msg::Font(value) => {
+                self.draft.typography.font = match value.as_str() {
+                    "serif" => Font::Serif,
+                    _ => Font::Sans,
+                };
+            }
+            Msg::FontSize(value) => {
+                if let Ok(size) = value.parse() {
+                    self.draft.typography.font_size_px = size;
+                }
+            }
+            Msg::LineHeight(value) => {
+                if let Ok(height) = value.parse() {
+                    self.draft.typography.line_height = height;
+                }
+            }
+            Msg::ContentWidth(value) => {
+                if let Ok(width) = value.parse() {
+                    self.draft.typography.max_width_ch = width;
+                }
+            }
+            Msg::Highlights(value) => {
+                self.draft.highlights = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|term| !term.is_empty())
+                    .map(str::to_owned)
+                    .collect();
+            }
+            Msg::RiverMaxPerDay(value) => {
+                // An empty field surfaces every item.
+                self.draft.river_max_per_day = value.parse().ok().filter(|&max: &usize| max > 0);
+            }
+            Msg::FutureItems(value) => {
+                self.draft.future_items = match value.as_str() {
+                    "hold" => FutureItems::Hold,
+                    _ => FutureItems::Clamp,
+                };
+            }
+            Msg::QualityThreshold(value) => {
+                if let Ok(threshold) = value.parse() {
+                    self.draft.quality_threshold = threshold;
+                }
+            }
+            Msg::ToggleQuietHours => {
+                self.draft.quiet_hours.enabled = !self.draft.quiet_hours.enabled;
+            }
+            Msg::QuietStart(value) => {
+                if let Ok(time) = NaiveTime::parse_from_str(&value, "%H:%M") {
+                    self.draft.quiet_hours.start = time;
+                }
+            }
+            Msg::QuietEnd(value) => {
+                if let Ok(time) = NaiveTime::parse_from_str(&value, "%H:%M") {
+                    self.draft.quiet_hours.end = time;
+                }
+            }
+            Msg::QuietRefreshInterval(value) => {
+                if let Ok(minutes) = value.parse() {
+                    self.draft.quiet_hours.refresh_interval_minutes = minutes;
+                }
+            }
+            Msg::ToggleAnomalies => {
+                self.draft.anomalies.enabled = !self.draft.anomalies.enabled;
+            }
+            Msg::SilenceFactor(value) => {
+                if let Ok(factor) = value.parse() {
+                    self.draft.anomalies.silence_factor = factor;
+                }
+            }
+            Msg::FloodFactor(value) => {
+                if let Ok(factor) = value.parse() {
+                    self.draft.anomalies.flood_factor = factor;
+                }
+            }
+            Msg::ToggleAnomalyNotifications => {
+                self.draft.anomalies.notify = !self.draft.anomalies.notify;
+            }
+            Msg::FeedContent(feed, value) => {
+                let content = match value.as_str() {
+                    "full_content" => ContentMode::FullContent,
+                    _ => ContentMode::Summary,
+                };
+                self.draft.feeds.entry(feed).or_default().content = content;
+            }
+            Msg::FeedKeepLatest(feed, value) => {
+                // An empty field keeps every unread item.
+                let keep_latest = value.parse().ok().filter(|&count: &usize| count > 0);
+                self.draft.feeds.entry(feed).or_default().keep_latest = keep_latest;
+            }
+            Msg::FeedMaxPerDay(feed, value) => {
+                // An empty field falls back to the limit of every feed.
+                let max_per_day = value.parse().ok().filter(|&max: &usize| max > 0);
+                self.draft.feeds.entry(feed).or_default().max_per_day = max_per_day;
+            }
+            Msg::FeedAliasOf(feed, value) => {
+                // An empty value makes the feed stand on its own again.
+                self.draft.feeds.entry(feed).or_default().alias_of =
+                    Some(value).filter(|value| !value.is_empty());
+            }
+            Msg::FeedTransform(feed, value) => {
+                // An empty script leaves the items as fetched.
+                self.draft.feeds.entry(feed).or_default().transform =
+                    Some(value).filter(|value| !value.trim().is_empty());
+            }
+            Msg::ToggleFeedPaused(feed) => {
+                let settings = self.draft.feeds.entry(feed).or_default();
+                settings.paused = !settings.paused;
+            }
+            Msg::Save => {
+                self.props.onsave.emit(self.draft.clone());
+                return false;
+            }
+            Msg::Cancel => {
+                self.draft = self.props.settings.clone();
+                self.props.oncancel.emit(());
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.draft = props.settings.clone();
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let language = self.draft.language.as_deref();
+        let timezone = self.draft.timezone.as_deref();
+        let theme = match self.draft.theme {
+            Theme::System => "system",
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::Sepia => "sepia",
+            Theme::Nord => "nord",
+        };
+        let filter = match self.draft.default_filter {
+            Filter::All => "all",
+            Filter::Read => "read",
+            Filter::Unread => "unread",
+            Filter::LowQuality => "low_quality",
+            Filter::Priority => "priority",
+        };
+        let river_max = self.draft.river_max_per_day.map(|n| n.to_string());
+        let hold = self.draft.future_items == FutureItems::Hold;
+
+        html! {
+            <section class="settings">
+                <h2>{ "Settings" }</h2>
+                <label class="block mt-2">
+                    { "Refresh interval (minutes)" }
+                    <input type="number" min="1"
+                        value=self.draft.refresh_interval_minutes.to_string()
+                        oninput=self.link.callback(|e: InputData| Msg::RefreshInterval(e.value)) />
+                </label>
+                <label class="block mt-2">
+                    { "Items per page" }
+                    <input type="number" min="1" max="500"
+                        value=self.draft.items_per_page.to_string()
+                        oninput=self.link.callback(|e: InputData| Msg::ItemsPerPage(e.value)) />
+                </label>
+                <label class="block mt-2">
+                    { "Theme" }
+                    <select onchange=self.link.callback(|e| Msg::Theme(select_value(e)))>
+                        <option value="system" selected=theme == "system">{ "System" }</option>
+                        <option value="light" selected=theme == "light">{ "Light" }</option>
+                        <option value="dark" selected=theme == "dark">{ "Dark" }</option>
+                        <option value="sepia" selected=theme == "sepia">{ "Sepia" }</option>
+                        <option value="nord" selected=theme == "nord">{ "Nord" }</option>
+                    </select>
+                </label>
+                <label class="block mt-2">
+                    <input type="checkbox" checked=self.draft.high_contrast
+                        onclick=self.link.callback(|_| Msg::ToggleHighContrast) />
+                    { " High contrast" }
+                </label>
+                <label class="block mt-2">
+                    { tr("language") }
+                    <select onchange=self.link.callback(|e| Msg::Language(select_value(e)))>
+                        <option value="" selected=language.is_none()>
+                            { tr("language-browser") }
+                        </option>
+                        { for i18n::LANGUAGES.iter().map(|&(code, name)| html! {
+                            <option value=code selected=language == Some(code)>{ name }</option>
+                        }) }
+                    </select>
+                </label>
+                <label class="block mt-2">
+                    { tr("timezone") }
+                    <select onchange=self.link.callback(|e| Msg::Timezone(select_value(e)))>
+                        <option value="" selected=timezone.is_none()>
+                            { tr("timezone-browser") }
+                        </option>
+                        { for timezone::names().map(|name| html! {
+                            <option value=name selected=timezone == Some(name)>{ name }</option>
+                        }) }
+                    </select>
+                </label>
+                <label class="block mt-2">
+                    { "Custom CSS" }
+                    <textarea class="block w-full font-mono" rows="6"
+                        placeholder=".newsapp { font-size: 1.1rem; }"
+                        value=self.draft.custom_css.clone()
+                        oninput=self.link.callback(|e: InputData| Msg::CustomCss(e.value)) />
+                </label>
+                <label class="block mt-2">
+                    { "Default filter" }
+                    <select onchange=self.link.callback(|e| Msg::DefaultFilter(select_value(e)))>
+                        <option value="all" selected=filter == "all">{ "All" }</option>
+                        <option value="unread" selected=filter == "unread">{ "Unread" }</option>
+                        <option value="read" selected=filter == "read">{ "Read" }</option>
+                        <option value="low_quality" selected=filter == "low_quality">
+                            { "Low quality" }
+                        </option>
+                        { if self.draft.priority_inbox {
+                            html! {
+                                <option value="priority" selected=filter == "priority">
+                                    { "Priority" }
+                                </option>
+                            }
+                        } else {
+                            html! {}
+                        } }
+                    </select>
+                </label>
+                <label class="block mt-2">
+                    <input type="checkbox" checked=self.draft.mark_read_on_expand
+                        onclick=self.link.callback(|_| Msg::ToggleMarkReadOnExpand) />
+                    { " Mark items read when expanded" }
+                </label>
+                <label class="block mt-2">
+                    <input type="checkbox" checked=self.draft.priority_inbox
+                        onclick=self.link.callback(|_| Msg::TogglePriorityInbox) />
+                    { " Show a Priority tab ranking unread items by how likely I am to read them" }
+                </label>
+                <label class="block mt-2">
+                    <input type="checkbox" checked=self.draft.archive_starred
+                        onclick=self.link.callback(|_| Msg::ToggleArchiveStarred) />
+                    { " Archive a snapshot of starred articles" }
+                </label>
+                <label class="block mt-2">
+                    <input type="checkbox" checked=self.draft.wayback_starred
+                        onclick=self.link.callback(|_| Msg::ToggleWaybackStarred) />
+                    { " Save starred articles to the Wayback Machine" }
+                </label>
+                <label class="block mt-2">
+                    <input type="checkbox" checked=self.draft.unshorten_links
+                        onclick=self.link.callback(|_| Msg::ToggleUnshortenLinks) />
+                    { " Follow shortened links to the articles they point to" }
+                </label>
+                { self.view_images() }
+                { self.view_typography() }
+                <label class="block mt-2">
+                    <input type="checkbox" checked=self.draft.notifications.new_items
+                        onclick=self.link.callback(|_| Msg::ToggleNewItemNotifications) />
+                    { " Notify me about new items" }
+                </label>
+                <label class="block mt-2">
+                    <input type="checkbox" checked=self.draft.quiet_hours.enabled
+                        onclick=self.link.callback(|_| Msg::ToggleQuietHours) />
+                    { " Quiet hours from " }
+                    <input type="time" value=self.draft.quiet_hours.start.format("%H:%M").to_string()
+                        onchange=self.link.callback(|e| Msg::QuietStart(select_value(e))) />
+                    { " to " }
+                    <input type="time" value=self.draft.quiet_hours.end.format("%H:%M").to_string()
+                        onchange=self.link.callback(|e| Msg::QuietEnd(select_value(e))) />
+                </label>
+                <label class="block mt-2">
+                    { "Refresh interval during quiet hours (minutes)" }
+                    <input type="number" min="1"
+                        value=self.draft.quiet_hours.refresh_interval_minutes.to_string()
+                        oninput=self.link.callback(|e: InputData| Msg::QuietRefreshInterval(e.value)) />
+                </label>
+                { self.view_anomalies() }
+                <label class="block mt-2">
+                    { "Move items scoring below this quality to Low quality" }
+                    <input type="number" min="0" max="1" step="0.05"
+                        value=self.draft.quality_threshold.to_string()
+                        oninput=self.link.callback(|e: InputData| Msg::QualityThreshold(e.value)) />
+                </label>
+                <label class="block mt-2">
+                    { "Highlight terms (comma separated)" }
+                    <input type="text" placeholder="rust, RISC-V"
+                        value=self.draft.highlights.join(", ")
+                        onchange=self.link.callback(|e| Msg::Highlights(select_value(e))) />
+                </label>
+                <label class="block mt-2">
+                    { "Show at most " }
+                    <input type="number" min="1" placeholder="all"
+                        value=river_max.unwrap_or_default()
+                        oninput=self.link.callback(|e: InputData| Msg::RiverMaxPerDay(e.value)) />
+                    { " items of each feed per day, the rest staying in the feed's view" }
+                </label>
+                <label class="block mt-2">
+                    { "Items dated in the future" }
+                    <select onchange=self.link.callback(|e| Msg::FutureItems(select_value(e)))>
+                        <option value="clamp" selected=!hold>{ "Date them when fetched" }</option>
+                        <option value="hold" selected=hold>{ "Hold them until their date" }</option>
+                    </select>
+                </label>
+                <h3 class="mt-4">{ "Feeds" }</h3>
+                { for self.props.feeds.iter().map(|feed| self.view_feed(feed)) }
+                <div class="mt-4">
+                    <button onclick=self.link.callback(|_| Msg::Save)>{ "Save" }</button>
+                    <button onclick=self.link.callback(|_| Msg::Cancel)>{ "Cancel" }</button>
+                </div>
+            </section>
+        }
+    }
+}
+
+impl SettingsView {
+    fn view_images(&self) -> Html {
+        let images = &self.draft.images;
+        let caching = match images.caching {
+            ImageCaching::All => "all",
+            ImageCaching::Starred => "starred",
+            ImageCaching::Off => "off",
+        };
+        let format = match images.transcode.format {
+            TranscodeFormat::Off => "off",
+            TranscodeFormat::Webp => "webp",
+        };
+        let max_size = images
+            .max_size_kb
+            .map(|kb| kb.to_string())
+            .unwrap_or_default();
+        html! {
+            <>
+                <label class="block mt-2">
+                    { "Cache images of" }
+                    <select onchange=self.link.callback(|e| Msg::ImageCaching(select_value(e)))>
+                        <option value="all" selected=caching == "all">{ "Every item" }</option>
+                        <option value="starred" selected=caching == "starred">
+                            { "Starred items" }
+                        </option>
+                        <option value="off" selected=caching == "off">{ "No item" }</option>
+                    </select>
+                </label>
+                <label class="block mt-2">
+                    { "Skip images larger than (KiB)" }
+                    <input type="number" min="1" placeholder="No limit" value=max_size
+                        oninput=self.link.callback(|e: InputData| Msg::ImageMaxSize(e.value)) />
+                </label>
+                <label class="block mt-2">
+                    <input type="checkbox" checked=images.schedule.enabled
+                        onclick=self.link.callback(|_| Msg::ToggleImageSchedule) />
+                    { " Only download images from " }
+                    <input type="time" value=images.schedule.start.format("%H:%M").to_string()
+                        onchange=self.link.callback(|e| Msg::ImageScheduleStart(select_value(e))) />
+                    { " to " }
+                    <input type="time" value=images.schedule.end.format("%H:%M").to_string()
+                        onchange=self.link.callback(|e| Msg::ImageScheduleEnd(select_value(e))) />
+                </label>
+                <label class="block mt-2">
+                    { "Convert cached images to" }
+                    <select onchange=self.link.callback(|e| Msg::TranscodeFormat(select_value(e)))>
+                        <option value="off" selected=format == "off">{ "Keep as is" }</option>
+                        <option value="webp" selected=format == "webp">{ "WebP" }</option>
+                    </select>
+                    { " at quality " }
+                    <input type="number" min="1" max="100" disabled=format == "off"
+                        value=images.transcode.quality.to_string()
+                        oninput=self.link.callback(|e: InputData| Msg::TranscodeQuality(e.value)) />
+                </label>
+            </>
+        }
+    }
+
+    fn view_anomalies(&self) -> Html {
+        let anomalies = &self.draft.anomalies;
+        html! {
+            <>
+                <label class="block mt-2">
+                    <input type="checkbox" checked=anomalies.enabled
+                        onclick=self.link.callback(|_| Msg::ToggleAnomalies) />
+                    { " Watch for feeds posting at an unusual rate" }
+                </label>
+                <label class="block mt-2">
+                    { "Feeds are silent after this many times their usual interval" }
+                    <input type="number" min="1.5" step="0.5" disabled=!anomalies.enabled
+                        value=anomalies.silence_factor.to_string()
+                        oninput=self.link.callback(|e: InputData| Msg::SilenceFactor(e.value)) />
+                </label>
+                <label class="block mt-2">
+                    { "Feeds flood at this many times their usual daily volume" }
+                    <input type="number" min="2" step="1" disabled=!anomalies.enabled
+                        value=anomalies.flood_factor.to_string()
+                        oninput=self.link.callback(|e: InputData| Msg::FloodFactor(e.value)) />
+                </label>
+                <label class="block mt-2">
+                    <input type="checkbox" checked=anomalies.notify disabled=!anomalies.enabled
+                        onclick=self.link.callback(|_| Msg::ToggleAnomalyNotifications) />
+                    { " Show a desktop notification about them" }
+                </label>
+            </>
+        }
+    }
+
+    fn view_typography(&self) -> Html {
+        let typography = &self.draft.typography;
+        let serif = typography.font == Font::Serif;
+        html! {
+            <fieldset class="mt-4">
+                <legend>{ "Reader" }</legend>
+                <label class="block mt-2">
+                    { "Font" }
+                    <select onchange=self.link.callback(|e| Msg::Font(select_value(e)))>
+                        <option value="sans" selected=!serif>{ "Sans serif" }</option>
+                        <option value="serif" selected=serif>{ "Serif" }</option>
+                    </select>
+                </label>
+                <label class="block mt-2">
+                    { "Font size (pixels)" }
+                    <input type="number" min="10" max="32"
+                        value=typography.font_size_px.to_string()
+                        oninput=self.link.callback(|e: InputData| Msg::FontSize(e.value)) />
+                </label>
+                <label class="block mt-2">
+                    { "Line height" }
+                    <input type="number" min="1" max="3" step="0.1"
+                        value=typography.line_height.to_string()
+                        oninput=self.link.callback(|e: InputData| Msg::LineHeight(e.value)) />
+                </label>
+                <label class="block mt-2">
+                    { "Content width (characters)" }
+                    <input type="number" min="30" max="200"
+                        value=typography.max_width_ch.to_string()
+                        oninput=self.link.callback(|e: InputData| Msg::ContentWidth(e.value)) />
+                </label>
+                <label class="block mt-2">
+                    <input type="checkbox" checked=typography.smart_punctuation
+                        onclick=self.link.callback(|_| Msg::ToggleSmartPunctuation) />
+                    { " Curly quotes and dashes" }
+                </label>
+                <p class="mt-2 p-2 border" style=typography.style()>
+                    { "The quick brown fox jumps over the lazy dog." }
+                </p>
+            </fieldset>
+        }
+    }
+
+    fn view_feed(&self, feed: &str) -> Html {
+        let settings = self.draft.feed(feed);
+        let full = settings.content == ContentMode::FullContent;
+        let url = feed.to_string();
+        let onchange = self
+            .link
+            .callback(move |e| Msg::FeedContent(url.clone(), select_value(e)));
+        let url = feed.to_string();
+        let oninput = self
+            .link
+            .callback(move |e: InputData| Msg::FeedKeepLatest(url.clone(), e.value));
+        let url = feed.to_string();
+        let onmax = self
+            .link
+            .callback(move |e: InputData| Msg::FeedMaxPerDay(url.clone(), e.value));
+        let url = feed.to_string();
+        let onpause = self
+            .link
+            .callback(move |_| Msg::ToggleFeedPaused(url.clone()));
+        let url = feed.to_string();
+        let onalias = self
+            .link
+            .callback(move |e| Msg::FeedAliasOf(url.clone(), select_value(e)));
+        let url = feed.to_string();
+        let ontransform = self
+            .link
+            .callback(move |e: InputData| Msg::FeedTransform(url.clone(), e.value));
+        let alias_of = settings.alias_of.clone().unwrap_or_default();
+        let river_max = self.draft.river_max_per_day.map(|n| n.to_string());
+        // Aliases of aliases aren't allowed, so only feeds standing on their own are offered.
+        let targets = self
+            .props
+            .feeds
+            .iter()
+            .filter(|other| other.as_str() != feed && self.draft.feed(other).alias_of.is_none());
+        html! {
+            <label class="block mt-2">
+                { feed }
+                <select onchange=onchange>
+                    <option value="summary" selected=!full>{ "Summary" }</option>
+                    <option value="full_content" selected=full>{ "Full content" }</option>
+                </select>
+                { " Only keep latest " }
+                <input type="number" min="1" placeholder="all"
+                    value=settings.keep_latest.map(|n| n.to_string()).unwrap_or_default()
+                    oninput=oninput />
+                { " unread " }
+                { " Show at most " }
+                <input type="number" min="1"
+                    placeholder=river_max.unwrap_or_else(|| "all".to_string())
+                    value=settings.max_per_day.map(|n| n.to_string()).unwrap_or_default()
+                    oninput=onmax />
+                { " a day " }
+                <input type="checkbox" checked=settings.paused onclick=onpause />
+                { " Paused " }
+                { " Alias of " }
+                <select onchange=onalias>
+                    <option value="" selected=alias_of.is_empty()>{ "None" }</option>
+                    { for targets.map(|other| html! {
+                        <option value=other.clone() selected=*other == alias_of>{ other }</option>
+                    }) }
+                </select>
+                <textarea class="block w-full font-mono" rows="3"
+                    placeholder="Lua script run on every item, e.g. return item.title ~= nil"
+                    value=settings.transform.clone().unwrap_or_default()
+                    oninput=ontransform />
+            </label>
+        }
+    }
+}
+
+/// Return the value a select or text field changed to.
+pub fn select_value(data: ChangeData) -> String {
+    match data {
+        ChangeData::Select(select) => select.value(),
+        ChangeData::Value(value) => value,
+        ChangeData::Files(_) => String::new(),
+    }
+}
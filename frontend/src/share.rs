@@ -0,0 +1,66 @@
+//! Sharing entries through the Web Share API, the clipboard, or email.
+//!
+//! `navigator.share` and `navigator.clipboard` are still unstable in web-sys, so they are called
+//! through reflection.
+
+use js_sys::{Function, Object, Promise, Reflect};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+fn navigator() -> Option<JsValue> {
+    let window = web_sys::window()?;
+    Some(window.navigator().into())
+}
+
+/// Look up `name` on `target` as a function, if it is one.
+fn method(target: &JsValue, name: &str) -> Option<Function> {
+    Reflect::get(target, &JsValue::from_str(name))
+        .ok()?
+        .dyn_into::<Function>()
+        .ok()
+}
+
+/// Whether the browser supports the native share sheet.
+pub fn native_available() -> bool {
+    navigator()
+        .and_then(|navigator| method(&navigator, "share"))
+        .is_some()
+}
+
+/// Open the native share sheet.
+pub fn native(title: &str, url: &str) -> Result<(), JsValue> {
+    let navigator = navigator().ok_or_else(|| JsValue::from_str("no navigator"))?;
+    let share = method(&navigator, "share").ok_or_else(|| JsValue::from_str("no share"))?;
+    let data = Object::new();
+    Reflect::set(&data, &"title".into(), &title.into())?;
+    Reflect::set(&data, &"url".into(), &url.into())?;
+    let promise: Promise = share.call1(&navigator, &data)?.dyn_into()?;
+    ignore_rejection(promise);
+    Ok(())
+}
+
+/// Copy `text` to the clipboard.
+pub fn copy(text: &str) -> Result<(), JsValue> {
+    let navigator = navigator().ok_or_else(|| JsValue::from_str("no navigator"))?;
+    let clipboard = Reflect::get(&navigator, &"clipboard".into())?;
+    let write = method(&clipboard, "writeText").ok_or_else(|| JsValue::from_str("no clipboard"))?;
+    let promise: Promise = write.call1(&clipboard, &text.into())?.dyn_into()?;
+    ignore_rejection(promise);
+    Ok(())
+}
+
+/// Return a `mailto:` link pre-filled with the title and URL of an entry.
+pub fn mailto(title: &str, url: &str) -> String {
+    format!(
+        "mailto:?subject={}&body={}",
+        js_sys::encode_uri_component(title),
+        js_sys::encode_uri_component(url)
+    )
+}
+
+/// Log instead of raising when the user dismisses the share sheet or denies the clipboard.
+fn ignore_rejection(promise: Promise) {
+    let on_rejected = Closure::once(|e: JsValue| log::debug!("share rejected: {:?}", e));
+    let _ = promise.catch(&on_rejected);
+    on_rejected.forget();
+}
@@ -0,0 +1,266 @@
+//! The sidebar listing the subscribed feeds in folders, organized by dragging them.
+
+use serde_derive::{Deserialize, Serialize};
+use web_sys::DragEvent;
+use yew::prelude::*;
+
+use std::collections::HashMap;
+
+use crate::protocol;
+
+/// Order of the feeds in the sidebar, stored by the backend.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeedOrder {
+    pub folders: Vec<Folder>,
+    /// URLs of the feeds outside any folder, listed after the folders.
+    pub feeds: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Folder {
+    pub name: String,
+    pub feeds: Vec<String>,
+}
+
+impl FeedOrder {
+    /// Feeds of the folder at `folder`, or the ones outside any folder.
+    fn feeds_mut(&mut self, folder: Option<usize>) -> &mut Vec<String> {
+        match folder {
+            Some(folder) => &mut self.folders[folder].feeds,
+            None => &mut self.feeds,
+        }
+    }
+
+    /// Move `feed` to position `pos` of `folder`, at its end when `pos` is `None`.
+    fn move_feed(&mut self, feed: &str, folder: Option<usize>, pos: Option<usize>) {
+        let mut pos = pos;
+        for from in (0..self.folders.len()).map(Some).chain(Some(None)) {
+            let feeds = self.feeds_mut(from);
+            if let Some(idx) = feeds.iter().position(|f| f == feed) {
+                feeds.remove(idx);
+                // Later feeds moved up.
+                if from == folder {
+                    pos = pos.map(|pos| if pos > idx { pos - 1 } else { pos });
+                }
+            }
+        }
+        let feeds = self.feeds_mut(folder);
+        let pos = pos.unwrap_or(feeds.len()).min(feeds.len());
+        feeds.insert(pos, feed.to_string());
+    }
+}
+
+/// What is being dragged.
+#[derive(Debug, Clone, PartialEq)]
+enum Dragged {
+    Feed(String),
+    Folder(usize),
+}
+
+pub struct Sidebar {
+    link: ComponentLink<Self>,
+    props: Props,
+    dragging: Option<Dragged>,
+    /// Name of the folder being created.
+    name: String,
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub order: FeedOrder,
+    /// Titles of the feeds, keyed by URL.
+    pub titles: HashMap<String, String>,
+    /// Feed whose entries only are listed.
+    pub selected: Option<String>,
+    pub onselect: Callback<Option<String>>,
+    pub onchange: Callback<FeedOrder>,
+}
+
+pub enum Msg {
+    Select(Option<String>),
+    DragFeed(String),
+    DragFolder(usize),
+    /// Drop on the folder at the index, or outside any folder, before the feed at the position.
+    Drop(Option<usize>, Option<usize>),
+    Name(String),
+    AddFolder,
+    RemoveFolder(usize),
+    Ignore,
+}
+
+impl Component for Sidebar {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Sidebar {
+            link,
+            props,
+            dragging: None,
+            name: String::new(),
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Select(feed) => {
+                self.props.onselect.emit(feed);
+                return false;
+            }
+            Msg::DragFeed(feed) => {
+                self.dragging = Some(Dragged::Feed(feed));
+                return false;
+            }
+            Msg::DragFolder(idx) => {
+                self.dragging = Some(Dragged::Folder(idx));
+                return false;
+            }
+            Msg::Drop(folder, pos) => {
+                let mut order = self.props.order.clone();
+                match self.dragging.take() {
+                    Some(Dragged::Feed(feed)) => order.move_feed(&feed, folder, pos),
+                    // Folders are reordered by dropping them on another folder.
+                    Some(Dragged::Folder(from)) => match folder {
+                        Some(to) if from != to && from < order.folders.len() => {
+                            let moved = order.folders.remove(from);
+                            order.folders.insert(to, moved);
+                        }
+                        _ => return false,
+                    },
+                    None => return false,
+                }
+                self.props.onchange.emit(order);
+                return false;
+            }
+            Msg::Name(name) => self.name = name,
+            Msg::AddFolder => {
+                let name = self.name.trim().to_string();
+                let order = &self.props.order;
+                if name.is_empty() || order.folders.iter().any(|folder| folder.name == name) {
+                    return false;
+                }
+                self.name.clear();
+                let mut order = order.clone();
+                order.folders.push(Folder {
+                    name,
+                    feeds: Vec::new(),
+                });
+                self.props.onchange.emit(order);
+            }
+            Msg::RemoveFolder(idx) => {
+                // The feeds of the folder move out of it rather than being unsubscribed.
+                let mut order = self.props.order.clone();
+                let folder = order.folders.remove(idx);
+                order.feeds.extend(folder.feeds);
+                self.props.onchange.emit(order);
+                return false;
+            }
+            Msg::Ignore => return false,
+        }
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let order = &self.props.order;
+        html! {
+            <nav class="sidebar" aria-label="Feeds">
+                <a href="#" class=self.class(None) aria-current=self.current(None)
+                    onclick=self.link.callback(|_| Msg::Select(None))>
+                    { "Every feed" }
+                </a>
+                { for order.folders.iter().enumerate().map(|(idx, folder)| {
+                    self.view_folder(idx, folder)
+                }) }
+                <ul ondragover=self.allow_drop() ondrop=self.on_drop(None, None)>
+                    { for order.feeds.iter().enumerate().map(|(pos, feed)| {
+                        self.view_feed(None, pos, feed)
+                    }) }
+                </ul>
+                <input type="text" placeholder="New folder" aria-label="New folder"
+                    value=self.name.clone()
+                    oninput=self.link.callback(|e: InputData| Msg::Name(e.value)) />
+                <button onclick=self.link.callback(|_| Msg::AddFolder)>{ "Add" }</button>
+            </nav>
+        }
+    }
+}
+
+impl Sidebar {
+    fn view_folder(&self, idx: usize, folder: &Folder) -> Html {
+        html! {
+            <div class="folder mt-2">
+                <div class="folder-name font-bold" draggable="true"
+                    ondragstart=self.link.callback(move |_| Msg::DragFolder(idx))
+                    ondragover=self.allow_drop() ondrop=self.on_drop(Some(idx), None)>
+                    { &folder.name }
+                    <button class="ml-2 text-sm" title="Remove the folder, keeping its feeds"
+                        aria-label=format!("Remove the folder {}", folder.name)
+                        onclick=self.link.callback(move |_| Msg::RemoveFolder(idx))>
+                        { "×" }
+                    </button>
+                </div>
+                <ul class="ml-2">
+                    { for folder.feeds.iter().enumerate().map(|(pos, feed)| {
+                        self.view_feed(Some(idx), pos, feed)
+                    }) }
+                </ul>
+            </div>
+        }
+    }
+
+    fn view_feed(&self, folder: Option<usize>, pos: usize, feed: &str) -> Html {
+        let title = self.props.titles.get(feed).map(String::as_str);
+        let dragged = feed.to_string();
+        let selected = feed.to_string();
+        html! {
+            <li draggable="true"
+                ondragstart=self.link.callback(move |_| Msg::DragFeed(dragged.clone()))
+                ondragover=self.allow_drop() ondrop=self.on_drop(folder, Some(pos))>
+                <a href="#" class=self.class(Some(feed)) aria-current=self.current(Some(feed))
+                    onclick=self.link.callback(move |_| Msg::Select(Some(selected.clone())))>
+                    { protocol::feed_name(feed, title) }
+                </a>
+            </li>
+        }
+    }
+
+    fn class(&self, feed: Option<&str>) -> &'static str {
+        if self.props.selected.as_deref() == feed {
+            "selected"
+        } else {
+            "not-selected"
+        }
+    }
+
+    /// Value of `aria-current` for the link to `feed`.
+    fn current(&self, feed: Option<&str>) -> &'static str {
+        if self.props.selected.as_deref() == feed {
+            "page"
+        } else {
+            "false"
+        }
+    }
+
+    /// Allow dropping on an element, which is only allowed where the default is prevented.
+    fn allow_drop(&self) -> Callback<DragEvent> {
+        self.link.callback(|e: DragEvent| {
+            e.prevent_default();
+            Msg::Ignore
+        })
+    }
+
+    fn on_drop(&self, folder: Option<usize>, pos: Option<usize>) -> Callback<DragEvent> {
+        self.link.callback(move |e: DragEvent| {
+            e.prevent_default();
+            // Drops bubble up to the list of the feed dropped on.
+            e.stop_propagation();
+            Msg::Drop(folder, pos)
+        })
+    }
+}
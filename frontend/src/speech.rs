@@ -0,0 +1,93 @@
+//! Reading articles aloud with the Web Speech API.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{SpeechSynthesis, SpeechSynthesisEvent, SpeechSynthesisUtterance};
+use yew::callback::Callback;
+
+type EventClosure = Closure<dyn FnMut(SpeechSynthesisEvent)>;
+
+pub struct Speaker {
+    synth: SpeechSynthesis,
+    /// Called with the fraction of the text spoken so far.
+    on_progress: Callback<f64>,
+    /// Called once the text has been spoken completely.
+    on_end: Callback<()>,
+    current: Option<Utterance>,
+}
+
+/// An utterance being spoken along with the closures handling its events.
+struct Utterance {
+    utterance: SpeechSynthesisUtterance,
+    _on_boundary: EventClosure,
+    _on_end: EventClosure,
+}
+
+impl Speaker {
+    /// Create a speaker, or `None` if the browser doesn't support speech synthesis.
+    pub fn new(on_progress: Callback<f64>, on_end: Callback<()>) -> Option<Self> {
+        let synth = web_sys::window()?.speech_synthesis().ok()?;
+        Some(Speaker {
+            synth,
+            on_progress,
+            on_end,
+            current: None,
+        })
+    }
+
+    /// Start speaking `text`, replacing whatever was being spoken.
+    pub fn speak(&mut self, text: &str) {
+        self.cancel();
+
+        let utterance = match SpeechSynthesisUtterance::new_with_text(text) {
+            Ok(utterance) => utterance,
+            Err(e) => {
+                log::error!("failed to create utterance: {:?}", e);
+                return;
+            }
+        };
+
+        let length = text.chars().count().max(1) as f64;
+        let on_progress = self.on_progress.clone();
+        let on_boundary = Closure::wrap(Box::new(move |event: SpeechSynthesisEvent| {
+            on_progress.emit(f64::from(event.char_index()) / length);
+        }) as Box<dyn FnMut(SpeechSynthesisEvent)>);
+        let on_end = self.on_end.clone();
+        let on_end = Closure::wrap(Box::new(move |_: SpeechSynthesisEvent| {
+            on_end.emit(());
+        }) as Box<dyn FnMut(SpeechSynthesisEvent)>);
+
+        utterance.set_onboundary(Some(on_boundary.as_ref().unchecked_ref()));
+        utterance.set_onend(Some(on_end.as_ref().unchecked_ref()));
+        self.synth.speak(&utterance);
+        self.current = Some(Utterance {
+            utterance,
+            _on_boundary: on_boundary,
+            _on_end: on_end,
+        });
+    }
+
+    pub fn pause(&self) {
+        self.synth.pause();
+    }
+
+    pub fn resume(&self) {
+        self.synth.resume();
+    }
+
+    /// Stop speaking without reporting the end of the text.
+    pub fn cancel(&mut self) {
+        if let Some(current) = self.current.take() {
+            current.utterance.set_onboundary(None);
+            current.utterance.set_onend(None);
+        }
+        self.synth.cancel();
+    }
+}
+
+/// Return the text of a HTML fragment.
+pub fn text_of(html: &str) -> String {
+    let element = yew::utils::document().create_element("div").unwrap();
+    element.set_inner_html(html);
+    element.text_content().unwrap_or_default()
+}
@@ -0,0 +1,133 @@
+//! Applying the theme and the user stylesheet to the document.
+//!
+//! Bundled themes are stylesheets layered over the light or dark tailwind styles. Both they and the
+//! user stylesheet are injected as `<style>` elements of the document head. High contrast and
+//! reduced motion are classes of the document element, like dark mode, which the accessibility
+//! stylesheet styles.
+
+use crate::settings::{Settings, Theme};
+
+/// Id of the style element holding the stylesheet of the bundled theme.
+const THEME_STYLE_ID: &str = "theme-style";
+/// Id of the style element holding the high contrast and reduced motion styles.
+const ACCESSIBILITY_STYLE_ID: &str = "accessibility-style";
+/// Id of the style element holding the user stylesheet.
+const USER_STYLE_ID: &str = "user-style";
+
+const SEPIA: &str = "
+body { background-color: #f4ecd8; }
+.newsapp, .settings { color: #5b4636; }
+.newsapp a { color: #8a4b08; }
+.newsapp .day-header { background-color: #f4ecd8; }
+.newsapp .summary { background-color: #ebe0c5; }
+";
+
+const NORD: &str = "
+body { background-color: #2e3440; }
+.newsapp, .settings { color: #d8dee9; }
+.newsapp a { color: #88c0d0; }
+.newsapp .day-header { background-color: #2e3440; }
+.newsapp .summary { background-color: #3b4252; }
+";
+
+/// Black on white, or white on black in dark mode, with underlined links and visible focus.
+const HIGH_CONTRAST: &str = "
+.high-contrast body, .high-contrast .newsapp .day-header { background-color: #fff; }
+.high-contrast .newsapp, .high-contrast .settings,
+.high-contrast [class*=text-gray] { color: #000; }
+.high-contrast .newsapp a { color: #00e; text-decoration: underline; }
+.high-contrast .newsapp .summary { background-color: #fff; border: 1px solid #000; }
+.high-contrast :focus { outline: 3px solid #f80; outline-offset: 2px; }
+.high-contrast.mode-dark body, .high-contrast.mode-dark .newsapp .day-header {
+  background-color: #000;
+}
+.high-contrast.mode-dark .newsapp, .high-contrast.mode-dark .settings,
+.high-contrast.mode-dark [class*=text-gray] { color: #fff; }
+.high-contrast.mode-dark .newsapp a { color: #ff0; }
+.high-contrast.mode-dark .newsapp .summary { background-color: #000; border-color: #fff; }
+";
+
+const REDUCED_MOTION: &str = "
+.reduce-motion *, .reduce-motion *::before, .reduce-motion *::after {
+  animation: none !important;
+  transition: none !important;
+  scroll-behavior: auto !important;
+}
+";
+
+/// Apply the theme and user stylesheet of `settings`.
+pub fn apply(settings: &Settings) {
+    // Inspect the preferred color scheme when following the system, and possibly enable the
+    // tailwindcss dark plugin.
+    let dark = match settings.theme {
+        Theme::Light | Theme::Sepia => false,
+        Theme::Dark | Theme::Nord => true,
+        Theme::System => media_matches("(prefers-color-scheme: dark)"),
+    };
+    set_class("mode-dark", dark);
+    // The user may also ask every site for more contrast through their system settings.
+    let high_contrast = settings.high_contrast || media_matches("(prefers-contrast: more)");
+    set_class("high-contrast", high_contrast);
+    set_class("reduce-motion", media_matches("(prefers-reduced-motion: reduce)"));
+
+    let theme = match settings.theme {
+        Theme::Sepia => SEPIA,
+        Theme::Nord => NORD,
+        Theme::System | Theme::Light | Theme::Dark => "",
+    };
+    set_style(THEME_STYLE_ID, theme);
+    set_style(ACCESSIBILITY_STYLE_ID, &[HIGH_CONTRAST, REDUCED_MOTION].concat());
+    set_style(USER_STYLE_ID, &settings.custom_css);
+}
+
+/// Whether the media query `query` matches, e.g. a preference of the user.
+fn media_matches(query: &str) -> bool {
+    web_sys::window()
+        .and_then(|window| window.match_media(query).ok())
+        .flatten()
+        .map(|query_list| query_list.matches())
+        .unwrap_or(false)
+}
+
+/// Add or remove the class `name` of the document element.
+fn set_class(name: &str, enabled: bool) {
+    if let Some(element) = yew::utils::document().document_element() {
+        let classes = element.class_list();
+        let _ = if enabled {
+            classes.add_1(name)
+        } else {
+            classes.remove_1(name)
+        };
+    }
+}
+
+/// Set the content of the style element `id`, creating it after the existing stylesheets.
+fn set_style(id: &str, css: &str) {
+    let document = yew::utils::document();
+    let element = match document.get_element_by_id(id) {
+        Some(element) => element,
+        None => {
+            let head = match document.head() {
+                Some(head) => head,
+                None => return,
+            };
+            let element = match document.create_element("style") {
+                Ok(element) => element,
+                Err(e) => {
+                    log::warn!("failed to create style element: {:?}", e);
+                    return;
+                }
+            };
+            element.set_id(id);
+            if let Err(e) = head.append_child(&element) {
+                log::warn!("failed to add style element: {:?}", e);
+                return;
+            }
+            element
+        }
+    };
+    // Only touch the element on changes, rendering happens far more often than settings change.
+    if element.text_content().as_deref() != Some(css) {
+        element.set_text_content(Some(css));
+    }
+}
@@ -0,0 +1,154 @@
+//! Date helpers for the browser.
+//!
+//! `chrono::Utc::now()` isn't available in the browser, so the current time and the local
+//! timezone are taken from javascript's `Date`, unless the settings pick another timezone.
+
+use chrono::prelude::*;
+use fluent::FluentValue;
+use news_core::timezone;
+
+use std::cell::RefCell;
+
+use crate::i18n::{tr, tr_args};
+
+thread_local! {
+    static TIMEZONE: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// Return the current time.
+pub fn now() -> DateTime<Utc> {
+    Utc.timestamp_millis(js_sys::Date::now() as i64)
+}
+
+/// Show dates in the timezone with the IANA name `name`, or in the browser's when it is `None`.
+pub fn set_timezone(name: Option<&str>) {
+    TIMEZONE.with(|timezone| *timezone.borrow_mut() = name.map(str::to_owned));
+}
+
+/// Return the current offset of the local timezone, the one of the settings or else the
+/// browser's.
+pub fn local_offset() -> FixedOffset {
+    let named = TIMEZONE.with(|timezone| {
+        let timezone = timezone.borrow();
+        timezone.as_deref().and_then(|name| timezone::offset(name, now()))
+    });
+    named.unwrap_or_else(browser_offset)
+}
+
+/// Return the IANA name of the browser's timezone, e.g. `Europe/Paris`.
+pub fn browser_timezone() -> Option<String> {
+    let format = js_sys::Intl::DateTimeFormat::new(&js_sys::Array::new(), &js_sys::Object::new());
+    js_sys::Reflect::get(&format.resolved_options(), &"timeZone".into())
+        .ok()
+        .and_then(|name| name.as_string())
+}
+
+/// Return the current offset of the browser's timezone.
+fn browser_offset() -> FixedOffset {
+    // `getTimezoneOffset` is the number of minutes to add to local time to get UTC.
+    let minutes = js_sys::Date::new_0().get_timezone_offset() as i32;
+    FixedOffset::west(minutes * 60)
+}
+
+/// Describe `date` relative to `now`, e.g. "3 minutes ago" or "yesterday".
+pub fn relative(date: DateTime<Utc>, now: DateTime<Utc>, offset: FixedOffset) -> String {
+    let elapsed = now.signed_duration_since(date);
+    if elapsed.num_minutes() < 1 {
+        return tr("just-now");
+    }
+    if elapsed.num_hours() < 1 {
+        return ago("minutes-ago", elapsed.num_minutes());
+    }
+
+    let days = now.with_timezone(&offset).date().num_days_from_ce()
+        - date.with_timezone(&offset).date().num_days_from_ce();
+    match days {
+        0 => ago("hours-ago", elapsed.num_hours()),
+        1 => tr("yesterday-relative"),
+        2..=6 => ago("days-ago", i64::from(days)),
+        _ => absolute(date, offset),
+    }
+}
+
+/// Format `date` in the local timezone, e.g. "Mar 3, 2020".
+pub fn absolute(date: DateTime<Utc>, offset: FixedOffset) -> String {
+    let date = date.with_timezone(&offset);
+    format_date("date", &tr(&format!("month-short-{}", date.month())), date.day(), date.year())
+}
+
+/// Return the local calendar day of `date`.
+pub fn day(date: DateTime<Utc>, offset: FixedOffset) -> Date<FixedOffset> {
+    date.with_timezone(&offset).date()
+}
+
+/// Label the day `date` falls on: "Today", "Yesterday", or e.g. "March 3".
+pub fn day_label(date: Date<FixedOffset>, now: DateTime<Utc>, offset: FixedOffset) -> String {
+    let today = day(now, offset);
+    let month = tr(&format!("month-{}", date.month()));
+    if date == today {
+        tr("today")
+    } else if date == today.pred() {
+        tr("yesterday")
+    } else if date.year() == today.year() {
+        format_date("day", &month, date.day(), date.year())
+    } else {
+        format_date("day-of-year", &month, date.day(), date.year())
+    }
+}
+
+fn ago(id: &str, count: i64) -> String {
+    tr_args(id, &[("count", FluentValue::from(count))])
+}
+
+/// Format a date with the message `id` of the locale, which orders its parts.
+fn format_date(id: &str, month: &str, day: u32, year: i32) -> String {
+    tr_args(
+        id,
+        &[
+            ("month", FluentValue::from(month)),
+            ("day", FluentValue::from(day)),
+            // Years aren't numbers to be grouped by thousands.
+            ("year", FluentValue::from(year.to_string())),
+        ],
+    )
+}
+
+/// When a snoozed item comes back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Snooze {
+    Tonight,
+    Tomorrow,
+    NextWeek,
+}
+
+impl Snooze {
+    pub fn label(self) -> String {
+        tr(match self {
+            Snooze::Tonight => "snooze-tonight",
+            Snooze::Tomorrow => "snooze-tomorrow",
+            Snooze::NextWeek => "snooze-next-week",
+        })
+    }
+
+    /// Return the time the item comes back: 7pm today (or in three hours when already past), 8am
+    /// tomorrow, or 8am next Monday, in the local timezone.
+    pub fn until(self, now: DateTime<Utc>, offset: FixedOffset) -> DateTime<Utc> {
+        let today = day(now, offset);
+        let local = match self {
+            Snooze::Tonight => {
+                let evening = today.and_hms(19, 0, 0);
+                if evening.with_timezone(&Utc) > now {
+                    evening
+                } else {
+                    now.with_timezone(&offset) + chrono::Duration::hours(3)
+                }
+            }
+            Snooze::Tomorrow => today.succ().and_hms(8, 0, 0),
+            Snooze::NextWeek => {
+                let days = 7 - i64::from(today.weekday().num_days_from_monday());
+                (today + chrono::Duration::days(days)).and_hms(8, 0, 0)
+            }
+        };
+        local.with_timezone(&Utc)
+    }
+}
@@ -0,0 +1,105 @@
+//! Toasts telling the user about failures, e.g. a lost connection or an error of the backend,
+//! until they are dismissed.
+
+use yew::prelude::*;
+
+use crate::i18n::tr;
+use crate::settings::Settings;
+use crate::sidebar::FeedOrder;
+
+/// A failure shown to the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Toast {
+    pub id: usize,
+    pub message: String,
+    /// What to try again, if it may succeed on a second try.
+    pub retry: Option<Retry>,
+}
+
+/// Actions retried from a toast.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Retry {
+    Connect,
+    LoadSettings,
+    /// Load the subscribed feeds and their order in the sidebar.
+    LoadFeeds,
+    LoadQueue,
+    SaveSettings(Settings),
+    SaveFeedOrder(FeedOrder),
+    SaveQueue,
+}
+
+pub struct Toasts {
+    link: ComponentLink<Self>,
+    props: Props,
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub toasts: Vec<Toast>,
+    pub ondismiss: Callback<usize>,
+    pub onretry: Callback<usize>,
+}
+
+pub enum Msg {
+    Dismiss(usize),
+    Retry(usize),
+}
+
+impl Component for Toasts {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Toasts { link, props }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Dismiss(id) => self.props.ondismiss.emit(id),
+            Msg::Retry(id) => self.props.onretry.emit(id),
+        }
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        if self.props.toasts.is_empty() {
+            return html! {};
+        }
+        html! {
+            <div class="toasts fixed bottom-0 right-0 m-4 max-w-sm">
+                { for self.props.toasts.iter().map(|toast| self.view_toast(toast)) }
+            </div>
+        }
+    }
+}
+
+impl Toasts {
+    fn view_toast(&self, toast: &Toast) -> Html {
+        let id = toast.id;
+        let retry = if toast.retry.is_some() {
+            html! {
+                <button class="ml-2 underline" onclick=self.link.callback(move |_| Msg::Retry(id))>
+                    { tr("retry") }
+                </button>
+            }
+        } else {
+            html! {}
+        };
+        html! {
+            <div class="toast mt-2 p-2 shadow-lg border bg-white dark:bg-black" role="alert">
+                { &toast.message }
+                { retry }
+                <button class="ml-2" aria-label=tr("dismiss") title=tr("dismiss")
+                    onclick=self.link.callback(move |_| Msg::Dismiss(id))>
+                    { "×" }
+                </button>
+            </div>
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::panic;
+
 pub fn set_panic_hook() {
     // When the `console_error_panic_hook` feature is enabled, we can call the
     // `set_panic_hook` function at least once during initialization, and then
@@ -7,4 +9,34 @@ pub fn set_panic_hook() {
     // https://github.com/rustwasm/console_error_panic_hook#readme
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
+
+    // The app stops responding after a panic, so rather than leave it looking alive, tell the
+    // user and offer to reload it.
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        hook(info);
+        show_crash();
+    }));
+}
+
+/// Cover the app with a message about it having crashed, built without yew which stopped.
+fn show_crash() {
+    let document = match web_sys::window().and_then(|window| window.document()) {
+        Some(document) => document,
+        None => return,
+    };
+    let (body, element) = match (document.body(), document.create_element("div")) {
+        (Some(body), Ok(element)) => (body, element),
+        _ => return,
+    };
+    element.set_class_name(
+        "crash fixed inset-0 flex items-center justify-center bg-white dark:bg-black",
+    );
+    let _ = element.set_attribute("role", "alert");
+    // Not translated, the translations might be what panicked.
+    element.set_inner_html(concat!(
+        "<p>Something went wrong and the app stopped. ",
+        "<a href=\"\" class=\"underline\">Reload</a></p>",
+    ));
+    let _ = body.append_child(&element);
 }
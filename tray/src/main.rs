@@ -0,0 +1,171 @@
+//! System tray companion of the news backend.
+//!
+//! Shows the number of unread items in the tray and the latest headlines in its menu, polling the
+//! REST API of the backend. Clicking the icon opens the web UI, clicking a headline opens its
+//! article.
+//!
+//! Usage: `news-tray [SERVER]`, where `SERVER` defaults to `http://localhost:9001`.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use ksni::menu::{MenuItem, StandardItem};
+use serde::Deserialize;
+use url::Url;
+
+use std::thread;
+use std::time::Duration;
+
+/// How often the unread items are fetched.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// Number of headlines listed in the menu.
+const HEADLINES: usize = 10;
+/// Headlines are cut to this many characters.
+const MAX_HEADLINE_CHARS: usize = 60;
+
+/// The fields of the items served by `/api/items` the tray needs.
+#[derive(Debug, Clone, Deserialize)]
+struct Item {
+    title: Option<String>,
+    link: Option<String>,
+    #[serde(default)]
+    low_quality: bool,
+    snoozed_until: Option<DateTime<Utc>>,
+}
+
+struct NewsTray {
+    server: Url,
+    unread: usize,
+    headlines: Vec<Item>,
+    /// Whether the last poll of the backend failed.
+    offline: bool,
+}
+
+impl ksni::Tray for NewsTray {
+    fn icon_name(&self) -> String {
+        if self.unread > 0 {
+            "mail-unread".to_string()
+        } else {
+            "mail-read".to_string()
+        }
+    }
+
+    fn title(&self) -> String {
+        "News".to_string()
+    }
+
+    fn tool_tip(&self) -> ksni::ToolTip {
+        let description = if self.offline {
+            "Backend unreachable".to_string()
+        } else {
+            format!("{} unread item(s)", self.unread)
+        };
+        ksni::ToolTip {
+            title: "News".to_string(),
+            description,
+            ..Default::default()
+        }
+    }
+
+    fn activate(&mut self, _x: i32, _y: i32) {
+        open(self.server.as_str());
+    }
+
+    fn menu(&self) -> Vec<MenuItem<Self>> {
+        let mut menu: Vec<MenuItem<Self>> = self
+            .headlines
+            .iter()
+            .map(|item| {
+                let link = item.link.clone();
+                StandardItem {
+                    label: headline(item.title.as_deref().unwrap_or("Untitled")),
+                    enabled: link.is_some(),
+                    activate: Box::new(move |_: &mut Self| {
+                        if let Some(link) = &link {
+                            open(link);
+                        }
+                    }),
+                    ..Default::default()
+                }
+                .into()
+            })
+            .collect();
+        if !menu.is_empty() {
+            menu.push(MenuItem::Separator);
+        }
+        menu.push(
+            StandardItem {
+                label: "Open News".to_string(),
+                activate: Box::new(|tray: &mut Self| open(tray.server.as_str())),
+                ..Default::default()
+            }
+            .into(),
+        );
+        menu.push(
+            StandardItem {
+                label: "Quit".to_string(),
+                activate: Box::new(|_: &mut Self| std::process::exit(0)),
+                ..Default::default()
+            }
+            .into(),
+        );
+        menu
+    }
+}
+
+fn main() -> Result<()> {
+    let server = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "http://localhost:9001".to_string());
+    let server = Url::parse(&server)?;
+
+    let service = ksni::TrayService::new(NewsTray {
+        server: server.clone(),
+        unread: 0,
+        headlines: Vec::new(),
+        offline: false,
+    });
+    let handle = service.handle();
+    service.spawn();
+
+    loop {
+        let result = unread_items(&server);
+        handle.update(|tray| match result {
+            Ok(items) => {
+                tray.unread = items.len();
+                tray.headlines = items.into_iter().take(HEADLINES).collect();
+                tray.offline = false;
+            }
+            Err(e) => {
+                eprintln!("failed to fetch items: {}", e);
+                tray.offline = true;
+            }
+        });
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Fetch the unread items the web UI would show, newest first.
+fn unread_items(server: &Url) -> Result<Vec<Item>> {
+    let mut url = server.join("/api/items")?;
+    url.set_query(Some("unread_only=true"));
+    let items: Vec<Item> = reqwest::blocking::get(url)?.error_for_status()?.json()?;
+    Ok(items
+        .into_iter()
+        .filter(|item| !item.low_quality && item.snoozed_until.is_none())
+        .collect())
+}
+
+fn headline(title: &str) -> String {
+    if title.chars().count() <= MAX_HEADLINE_CHARS {
+        return title.to_string();
+    }
+    let mut headline: String = title.chars().take(MAX_HEADLINE_CHARS - 1).collect();
+    headline.push('…');
+    headline
+}
+
+fn open(url: &str) {
+    if let Err(e) = webbrowser::open(url) {
+        eprintln!("failed to open {}: {}", url, e);
+    }
+}
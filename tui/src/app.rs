@@ -0,0 +1,205 @@
+//! State of the terminal client and its keyboard handling.
+
+use crossterm::event::KeyCode;
+
+use crate::protocol::{ClientMessage, ItemDto, ItemPatch};
+
+/// Pane receiving the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Focus {
+    Feeds,
+    Items,
+    Reader,
+}
+
+pub struct App {
+    /// URLs of the subscribed feeds.
+    pub feeds: Vec<String>,
+    /// Every item, newest first.
+    pub items: Vec<ItemDto>,
+    /// Selected entry of the sidebar, 0 being every feed.
+    pub feed: usize,
+    /// Selected entry of the item list.
+    pub item: usize,
+    pub focus: Focus,
+    /// Lines the reader pane is scrolled by.
+    pub scroll: u16,
+    pub quit: bool,
+    /// Id of the item to resume reading from once it is received.
+    resume: Option<String>,
+}
+
+impl App {
+    pub fn new(feeds: Vec<String>) -> Self {
+        App {
+            feeds,
+            items: Vec::new(),
+            feed: 0,
+            item: 0,
+            focus: Focus::Items,
+            scroll: 0,
+            quit: false,
+            resume: None,
+        }
+    }
+
+    /// Add or replace items pushed by the server.
+    pub fn merge(&mut self, items: Vec<ItemDto>) {
+        let selected = self.selected().map(|item| item.id.clone());
+        for item in items {
+            match self.items.iter_mut().find(|known| known.id == item.id) {
+                Some(known) => *known = item,
+                None => self.items.push(item),
+            }
+        }
+        self.items.sort_by(|a, b| b.pub_date.cmp(&a.pub_date));
+
+        // Keep the same item selected as the list grows.
+        if let Some(id) = selected {
+            if let Some(idx) = self.visible().iter().position(|item| item.id == id) {
+                self.item = idx;
+            }
+        }
+        self.apply_resume();
+    }
+
+    /// Apply changes to the read or starred state of known items.
+    pub fn patch(&mut self, patches: Vec<ItemPatch>) {
+        for patch in patches {
+            if let Some(item) = self.items.iter_mut().find(|item| item.id == patch.id) {
+                item.read = patch.read.unwrap_or(item.read);
+                item.starred = patch.starred.unwrap_or(item.starred);
+            }
+        }
+    }
+
+    /// Select the item the user was last reading, possibly on another device.
+    pub fn resume_at(&mut self, id: String) {
+        self.resume = Some(id);
+        self.apply_resume();
+    }
+
+    fn apply_resume(&mut self) {
+        let id = match &self.resume {
+            Some(id) => id,
+            None => return,
+        };
+        if !self.items.iter().any(|item| &item.id == id) {
+            return;
+        }
+        // Show every feed so that the item is visible whichever it belongs to.
+        self.feed = 0;
+        if let Some(idx) = self.visible().iter().position(|item| &item.id == id) {
+            self.item = idx;
+            self.scroll = 0;
+        }
+        self.resume = None;
+    }
+
+    /// Items of the selected feed, hiding snoozed and low quality ones.
+    pub fn visible(&self) -> Vec<&ItemDto> {
+        let feed = self.feed.checked_sub(1).and_then(|idx| self.feeds.get(idx));
+        self.items
+            .iter()
+            .filter(|item| feed.map_or(true, |feed| &item.feed == feed))
+            .filter(|item| item.snoozed_until.is_none() && !item.low_quality)
+            .collect()
+    }
+
+    pub fn selected(&self) -> Option<&ItemDto> {
+        self.visible().get(self.item).copied()
+    }
+
+    pub fn unread(&self, feed: Option<&str>) -> usize {
+        self.items
+            .iter()
+            .filter(|item| feed.map_or(true, |feed| item.feed == feed) && !item.read)
+            .count()
+    }
+
+    /// Handle a key press, returning the messages to send to the server.
+    pub fn on_key(&mut self, key: KeyCode) -> Vec<ClientMessage> {
+        match key {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Feeds => Focus::Items,
+                    Focus::Items => Focus::Reader,
+                    Focus::Reader => Focus::Feeds,
+                }
+            }
+            KeyCode::Esc | KeyCode::Left | KeyCode::Char('h') => {
+                self.focus = match self.focus {
+                    Focus::Reader => Focus::Items,
+                    _ => Focus::Feeds,
+                }
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => match self.focus {
+                Focus::Feeds => self.focus = Focus::Items,
+                Focus::Items | Focus::Reader => {
+                    self.focus = Focus::Reader;
+                    let position = self
+                        .selected()
+                        .map(|item| ClientMessage::Position { id: item.id.clone() });
+                    return position.into_iter().chain(self.mark_read()).collect();
+                }
+            },
+            KeyCode::Char('r') => return self.mark_read().into_iter().collect(),
+            KeyCode::Char('s') => return self.toggle_star().into_iter().collect(),
+            _ => {}
+        }
+        Vec::new()
+    }
+
+    fn toggle_star(&mut self) -> Option<ClientMessage> {
+        let id = self.selected()?.id.clone();
+        let item = self.items.iter_mut().find(|item| item.id == id)?;
+        item.starred = !item.starred;
+        Some(ClientMessage::Star {
+            id,
+            starred: item.starred,
+        })
+    }
+
+    fn move_by(&mut self, delta: i32) {
+        match self.focus {
+            Focus::Feeds => {
+                self.feed = step(self.feed, delta, self.feeds.len() + 1);
+                self.item = 0;
+                self.scroll = 0;
+            }
+            Focus::Items => {
+                self.item = step(self.item, delta, self.visible().len());
+                self.scroll = 0;
+            }
+            Focus::Reader => {
+                self.scroll = if delta > 0 {
+                    self.scroll.saturating_add(1)
+                } else {
+                    self.scroll.saturating_sub(1)
+                }
+            }
+        }
+    }
+
+    fn mark_read(&mut self) -> Option<ClientMessage> {
+        let id = self.selected()?.id.clone();
+        let item = self.items.iter_mut().find(|item| item.id == id)?;
+        if item.read {
+            return None;
+        }
+        item.read = true;
+        Some(ClientMessage::MarkRead { id })
+    }
+}
+
+/// Move `idx` by `delta` within `0..len`.
+fn step(idx: usize, delta: i32, len: usize) -> usize {
+    if len == 0 {
+        return 0;
+    }
+    let idx = idx as i64 + i64::from(delta);
+    idx.max(0).min(len as i64 - 1) as usize
+}
@@ -0,0 +1,65 @@
+//! Connection to the backend: the websocket pushing items and the REST API.
+
+use anyhow::Result;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+use url::Url;
+
+use std::io;
+use std::net::TcpStream;
+use std::time::Duration;
+
+use crate::protocol::{ClientMessage, ServerMessage};
+
+/// How long polling the websocket waits for a message.
+const POLL_TIMEOUT: Duration = Duration::from_millis(10);
+
+pub struct Client {
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+}
+
+impl Client {
+    /// Open the websocket of the backend at `server`, e.g. `http://localhost:9001`, authorized by
+    /// `token` if the server requires one.
+    pub fn connect(server: &Url, token: Option<&str>) -> Result<Client> {
+        let mut url = server.join("/ws/")?;
+        url.set_scheme(if server.scheme() == "https" { "wss" } else { "ws" })
+            .expect("ws is a valid scheme");
+        url.set_query(Some("encoding=json"));
+        if let Some(token) = token {
+            url.query_pairs_mut().append_pair("token", token);
+        }
+
+        // Without TLS support compiled into tungstenite, every stream is a plain one.
+        let (socket, _) = tungstenite::connect(url)?;
+        if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+            stream.set_read_timeout(Some(POLL_TIMEOUT))?;
+        }
+        Ok(Client { socket })
+    }
+
+    /// Return the next message pushed by the server, or `None` if there is none yet.
+    pub fn poll(&mut self) -> Result<Option<ServerMessage>> {
+        match self.socket.read() {
+            Ok(Message::Text(text)) => Ok(Some(serde_json::from_str(&text)?)),
+            Ok(_) => Ok(None),
+            Err(tungstenite::Error::Io(e))
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn send(&mut self, msg: &ClientMessage) -> Result<()> {
+        self.socket.send(Message::Text(serde_json::to_string(msg)?))?;
+        Ok(())
+    }
+}
+
+/// List the URLs of the subscribed feeds.
+pub fn feeds(server: &Url) -> Result<Vec<String>> {
+    let url = server.join("/api/feeds")?;
+    Ok(reqwest::blocking::get(url)?.error_for_status()?.json()?)
+}
@@ -0,0 +1,85 @@
+//! Terminal client of the news backend.
+//!
+//! Usage: `news-tui [SERVER]`, where `SERVER` defaults to `http://localhost:9001`. The token of
+//! servers requiring one to open the websocket is read from `NEWS_TOKEN`.
+
+mod app;
+mod client;
+mod protocol;
+mod text;
+mod ui;
+
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::Terminal;
+use url::Url;
+
+use std::io;
+use std::time::Duration;
+
+use app::App;
+use client::Client;
+use protocol::{ClientMessage, ServerMessage};
+
+/// How long to wait for a key press before checking the websocket again.
+const TICK: Duration = Duration::from_millis(50);
+
+fn main() -> Result<()> {
+    let server = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "http://localhost:9001".to_string());
+    let server = Url::parse(&server)?;
+    let feeds = client::feeds(&server)?;
+    let token = std::env::var("NEWS_TOKEN").ok();
+    let mut client = Client::connect(&server, token.as_deref())?;
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = run(&mut terminal, &mut client, App::new(feeds));
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+fn run(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    client: &mut Client,
+    mut app: App,
+) -> Result<()> {
+    while !app.quit {
+        terminal.draw(|f| ui::draw(f, &app))?;
+
+        if event::poll(TICK)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    for msg in app.on_key(key.code) {
+                        client.send(&msg)?;
+                    }
+                }
+            }
+        }
+
+        while let Some(msg) = client.poll()? {
+            match msg {
+                ServerMessage::Items(batch) => {
+                    app.merge(batch.items);
+                    app.patch(batch.patches);
+                    client.send(&ClientMessage::Ack { up_to: batch.seq })?;
+                }
+                ServerMessage::Position(position) => app.resume_at(position.id),
+                ServerMessage::ResyncNeeded => client.send(&ClientMessage::Resync)?,
+            }
+        }
+    }
+    Ok(())
+}
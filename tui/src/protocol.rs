@@ -0,0 +1,70 @@
+//! Messages exchanged with the backend over the websocket, requested as uncompressed JSON.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Message sent from the server to the client.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Items(ItemBatch),
+    Position(ReadPosition),
+    ResyncNeeded,
+}
+
+/// Message sent from the client to the server.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Ack { up_to: u64 },
+    Resync,
+    MarkRead { id: String },
+    Star { id: String, starred: bool },
+    Position { id: String },
+}
+
+/// Where the user left off reading, possibly on another device.
+#[derive(Debug, Deserialize)]
+pub struct ReadPosition {
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ItemBatch {
+    pub seq: u64,
+    pub items: Vec<ItemDto>,
+    #[serde(default)]
+    pub patches: Vec<ItemPatch>,
+}
+
+/// Change to the read or starred state of an item already received.
+#[derive(Debug, Deserialize)]
+pub struct ItemPatch {
+    pub id: String,
+    #[serde(default)]
+    pub read: Option<bool>,
+    #[serde(default)]
+    pub starred: Option<bool>,
+}
+
+/// The subset of a news item needed to render it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ItemDto {
+    pub id: String,
+    pub feed: String,
+    pub title: Option<String>,
+    pub link: Option<String>,
+    pub description: Option<String>,
+    pub content: Option<String>,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub read: bool,
+    #[serde(default)]
+    pub starred: bool,
+    #[serde(default)]
+    pub low_quality: bool,
+    #[serde(default)]
+    pub snoozed_until: Option<DateTime<Utc>>,
+    pub pub_date: Option<DateTime<Utc>>,
+}
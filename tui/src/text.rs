@@ -0,0 +1,44 @@
+//! Conversion of the sanitized HTML sent by the backend to plain text for the terminal.
+
+/// Elements that start a new line.
+const BLOCKS: &[&str] = &[
+    "p", "br", "div", "li", "ul", "ol", "h1", "h2", "h3", "h4", "h5", "h6", "blockquote", "pre",
+    "tr",
+];
+
+/// Strip the tags of `html`, breaking lines at block elements and decoding common entities.
+pub fn plain(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let name = rest[start + 1..end]
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        if BLOCKS.contains(&name.as_str()) && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        rest = &rest[end + 1..];
+    }
+    if !rest.contains('<') {
+        text.push_str(rest);
+    }
+
+    decode_entities(&text)
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,124 @@
+//! Drawing of the feed sidebar, the item list, and the reader pane.
+
+use ratatui::backend::Backend;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::Frame;
+
+use crate::app::{App, Focus};
+use crate::text;
+
+pub fn draw<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(1)])
+        .split(f.size());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(20),
+            Constraint::Percentage(35),
+            Constraint::Percentage(45),
+        ])
+        .split(rows[0]);
+
+    draw_feeds(f, app, columns[0]);
+    draw_items(f, app, columns[1]);
+    draw_reader(f, app, columns[2]);
+    f.render_widget(
+        Paragraph::new(
+            "tab/h/l: switch pane  j/k: move  enter: read  r: mark read  s: star  q: quit",
+        ),
+        rows[1],
+    );
+}
+
+fn block(title: &str, focused: bool) -> Block<'_> {
+    let style = if focused {
+        Style::default().add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+    };
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(style)
+        .title(title)
+}
+
+fn draw_feeds<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let mut entries = vec![ListItem::new(format!("All ({})", app.unread(None)))];
+    entries.extend(app.feeds.iter().map(|feed| {
+        let name = feed
+            .split("://")
+            .nth(1)
+            .and_then(|rest| rest.split('/').next())
+            .unwrap_or(feed);
+        ListItem::new(format!("{} ({})", name, app.unread(Some(feed))))
+    }));
+
+    let mut state = ListState::default();
+    state.select(Some(app.feed));
+    let list = List::new(entries)
+        .block(block("Feeds", app.focus == Focus::Feeds))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_items<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let entries: Vec<ListItem> = app
+        .visible()
+        .into_iter()
+        .map(|item| {
+            let marker = if item.starred { "★ " } else { "  " };
+            let style = if item.read {
+                Style::default().add_modifier(Modifier::DIM)
+            } else {
+                Style::default().add_modifier(Modifier::BOLD)
+            };
+            let title = item.title.clone().unwrap_or_default();
+            ListItem::new(Line::from(vec![Span::raw(marker), Span::styled(title, style)]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(app.item));
+    let list = List::new(entries)
+        .block(block("Items", app.focus == Focus::Items))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_reader<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let mut lines: Vec<Line> = Vec::new();
+    if let Some(item) = app.selected() {
+        let title = item.title.clone().unwrap_or_default();
+        lines.push(Line::styled(title, Style::default().add_modifier(Modifier::BOLD)));
+        if let Some(date) = item.pub_date {
+            lines.push(Line::from(date.format("%b %-d, %Y %H:%M UTC").to_string()));
+        }
+        if let Some(link) = &item.link {
+            let style = Style::default().add_modifier(Modifier::UNDERLINED);
+            lines.push(Line::styled(link.clone(), style));
+        }
+        lines.push(Line::from(""));
+        if let Some(summary) = &item.summary {
+            lines.push(Line::from(format!("TL;DR: {}", summary)));
+            lines.push(Line::from(""));
+        }
+        let body = item
+            .content
+            .as_ref()
+            .or_else(|| item.description.as_ref())
+            .map(|body| text::plain(body))
+            .unwrap_or_default();
+        lines.extend(body.lines().map(|line| Line::from(line.to_string())));
+    }
+
+    let reader = Paragraph::new(lines)
+        .block(block("Reader", app.focus == Focus::Reader))
+        .wrap(Wrap { trim: true })
+        .scroll((app.scroll, 0));
+    f.render_widget(reader, area);
+}